@@ -1,7 +1,8 @@
 pub mod llm {
-    use futures::Stream;
+    use futures::{future::BoxFuture, Stream};
     use serde::{Deserialize, Serialize};
     use thiserror::Error;
+    pub use tokio_util::sync::CancellationToken;
 
     #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
     pub enum Role {
@@ -10,18 +11,84 @@ pub mod llm {
         System,
     }
 
+    #[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+    pub enum ContentPart {
+        Text(String),
+        // `url` may be an https URL or a `data:image/...;base64,` URL
+        ImageUrl { url: String, detail: Option<String> },
+    }
+
     #[derive(Clone, Debug, Serialize, Deserialize)]
     pub struct Message {
         pub role: Role,
-        pub content: String,
+        // A bare string (older format) loads as a single Text part
+        #[serde(deserialize_with = "content_compat")]
+        pub content: Vec<ContentPart>,
     }
 
-    #[derive(Clone, Debug)]
+    impl Message {
+        pub fn text<S: Into<String>>(role: Role, s: S) -> Self {
+            Self {
+                role,
+                content: vec![ContentPart::Text(s.into())],
+            }
+        }
+
+        // Concatenated text parts.
+        pub fn text_content(&self) -> String {
+            self.content
+                .iter()
+                .filter_map(|p| match p {
+                    ContentPart::Text(t) => Some(t.as_str()),
+                    ContentPart::ImageUrl { .. } => None,
+                })
+                .collect()
+        }
+
+        // Rough prompt size: ~4 characters per token, a flat cost per image.
+        pub fn estimated_tokens(&self) -> usize {
+            const IMAGE_TOKENS: usize = 765;
+            self.content
+                .iter()
+                .map(|p| match p {
+                    ContentPart::Text(t) => t.chars().count().div_ceil(4),
+                    ContentPart::ImageUrl { .. } => IMAGE_TOKENS,
+                })
+                .sum::<usize>()
+                + 4
+        }
+
+        pub fn has_images(&self) -> bool {
+            self.content
+                .iter()
+                .any(|p| matches!(p, ContentPart::ImageUrl { .. }))
+        }
+    }
+
+    fn content_compat<'de, D>(d: D) -> Result<Vec<ContentPart>, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Compat {
+            Text(String),
+            Parts(Vec<ContentPart>),
+        }
+        Ok(match Compat::deserialize(d)? {
+            Compat::Text(t) => vec![ContentPart::Text(t)],
+            Compat::Parts(p) => p,
+        })
+    }
+
+    #[derive(Clone, Debug, Serialize, Deserialize)]
     pub struct ChatOpts {
         pub model: String,
         pub temperature: Option<f32>,
         pub top_p: Option<f32>,
         pub max_tokens: Option<u32>,
+        // Ask reasoning models to stream a summary of their thinking
+        pub reasoning_summary: bool,
     }
 
     #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -31,15 +98,32 @@ pub mod llm {
         Auto,
     }
 
-    #[derive(Clone, Debug)]
+    // Rate-limit headroom reported by the provider (response headers).
+    #[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+    pub struct RateInfo {
+        pub remaining_requests: Option<u32>,
+        pub remaining_tokens: Option<u32>,
+    }
+
+    #[derive(Clone, Debug, Serialize, Deserialize)]
     pub enum ChatDelta {
         RoleStart(Role),
         Text(String),
+        // Thinking tokens streamed ahead of the answer (DeepSeek `reasoning_content`,
+        // Responses API reasoning summaries)
+        Reasoning(String),
         Finish(Option<String>),
         Usage {
             prompt_tokens: Option<u32>,
             completion_tokens: Option<u32>,
         },
+        RateInfo(RateInfo),
+        // The request failed before any output and is sent again after a
+        // backoff; `attempt` is the try about to start, of `max_attempts`
+        Retrying {
+            attempt: u32,
+            max_attempts: u32,
+        },
     }
 
     #[derive(Clone, Debug)]
@@ -48,9 +132,10 @@ pub mod llm {
         pub finish_reason: Option<String>,
         pub prompt_tokens: Option<u32>,
         pub completion_tokens: Option<u32>,
+        pub rate_info: Option<RateInfo>,
     }
 
-    #[derive(Error, Debug)]
+    #[derive(Error, Clone, Debug, Serialize, Deserialize)]
     pub enum ChatError {
         #[error("auth error: {0}")]
         Auth(String),
@@ -74,19 +159,70 @@ pub mod llm {
 
     use std::pin::Pin;
 
-    #[allow(async_fn_in_trait)]
+    // Boxed futures keep the trait object-safe, so clients can be held as
+    // `Box<dyn ModelClient>` (see `registry`).
     pub trait ModelClient: Send + Sync {
-        async fn send_chat(
-            &self,
-            msgs: &[Message],
-            opts: &ChatOpts,
-        ) -> Result<ChatResult, ChatError>;
-        async fn stream_chat<'a>(
+        fn send_chat<'a>(
+            &'a self,
+            msgs: &'a [Message],
+            opts: &'a ChatOpts,
+        ) -> BoxFuture<'a, Result<ChatResult, ChatError>>;
+        fn stream_chat<'a>(
             &'a self,
             msgs: Vec<Message>,
             opts: ChatOpts,
             wire: ChatWire,
-        ) -> Result<ChatStream<'a>, ChatError>;
+            // Cancelling aborts the request; the stream then ends with `ChatError::Canceled`
+            cancel: CancellationToken,
+        ) -> BoxFuture<'a, Result<ChatStream<'a>, ChatError>>;
+    }
+
+    #[allow(async_fn_in_trait)]
+    pub trait EmbeddingClient: Send + Sync {
+        // One vector per input, in input order.
+        async fn embed(&self, inputs: &[String], model: &str) -> Result<Vec<Vec<f32>>, ChatError>;
+    }
+
+    pub mod testing;
+
+    pub mod registry {
+        use std::collections::BTreeMap;
+
+        use super::{ChatError, ModelClient};
+
+        pub type ClientFactory =
+            Box<dyn Fn() -> Result<Box<dyn ModelClient>, ChatError> + Send + Sync>;
+
+        // Provider id (e.g. "openai", "deepseek") -> client factory.
+        #[derive(Default)]
+        pub struct Registry {
+            factories: BTreeMap<String, ClientFactory>,
+        }
+
+        impl Registry {
+            pub fn new() -> Self {
+                Self::default()
+            }
+
+            // A later registration under the same id replaces the earlier one.
+            pub fn register<F>(&mut self, id: impl Into<String>, factory: F)
+            where
+                F: Fn() -> Result<Box<dyn ModelClient>, ChatError> + Send + Sync + 'static,
+            {
+                self.factories.insert(id.into(), Box::new(factory));
+            }
+
+            pub fn create(&self, id: &str) -> Result<Box<dyn ModelClient>, ChatError> {
+                match self.factories.get(id) {
+                    Some(f) => f(),
+                    None => Err(ChatError::Other(format!("unknown provider '{}'", id))),
+                }
+            }
+
+            pub fn ids(&self) -> impl Iterator<Item = &str> {
+                self.factories.keys().map(String::as_str)
+            }
+        }
     }
 }
 
@@ -0,0 +1,432 @@
+// Scripted `ModelClient` for running without an API key (tests, demos).
+
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use futures::future::BoxFuture;
+use serde::Deserialize;
+
+use super::{
+    CancellationToken, ChatDelta, ChatError, ChatOpts, ChatResult, ChatStream, ChatWire, Message,
+    ModelClient,
+};
+
+// One scripted delta, emitted after `delay`.
+#[derive(Clone, Debug)]
+pub struct ScriptStep {
+    pub delta: Result<ChatDelta, ChatError>,
+    pub delay: Duration,
+}
+
+impl From<Result<ChatDelta, ChatError>> for ScriptStep {
+    fn from(delta: Result<ChatDelta, ChatError>) -> Self {
+        Self {
+            delta,
+            delay: Duration::ZERO,
+        }
+    }
+}
+
+// What the client was asked to do, for assertions.
+#[derive(Clone, Debug)]
+pub struct RecordedRequest {
+    pub messages: Vec<Message>,
+    pub opts: ChatOpts,
+    pub wire: Option<ChatWire>,
+}
+
+// Serves one script per request, in order. Clones share the script cursor
+// and the request log.
+#[derive(Clone)]
+pub struct MockClient {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    scripts: Vec<Vec<ScriptStep>>,
+    // Start over after the last script instead of failing
+    repeat: bool,
+    state: Mutex<State>,
+}
+
+#[derive(Default)]
+struct State {
+    next: usize,
+    requests: Vec<RecordedRequest>,
+}
+
+impl MockClient {
+    pub fn new(scripts: Vec<Vec<Result<ChatDelta, ChatError>>>) -> Self {
+        Self::with_steps(
+            scripts
+                .into_iter()
+                .map(|s| s.into_iter().map(ScriptStep::from).collect())
+                .collect(),
+            false,
+        )
+    }
+
+    pub fn with_steps(scripts: Vec<Vec<ScriptStep>>, repeat: bool) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                scripts,
+                repeat,
+                state: Mutex::new(State::default()),
+            }),
+        }
+    }
+
+    // Parse a JSON script (see docs/mock/*.json):
+    // {"repeat": bool, "requests": [[{"text": "Hi", "delay_ms": 20}, {"error": {"kind": "rate_limit", "message": "..."}}]]}
+    pub fn from_json(text: &str) -> Result<Self, ChatError> {
+        let file: ScriptFile = serde_json::from_str(text)
+            .map_err(|e| ChatError::Decode(format!("mock script: {}", e)))?;
+        let scripts = file
+            .requests
+            .into_iter()
+            .map(|r| r.into_iter().map(ScriptStep::from).collect())
+            .collect();
+        Ok(Self::with_steps(scripts, file.repeat))
+    }
+
+    pub fn requests(&self) -> Vec<RecordedRequest> {
+        self.lock().requests.clone()
+    }
+
+    pub fn assert_request_count(&self, n: usize) {
+        let got = self.lock().requests.len();
+        assert_eq!(got, n, "mock client: expected {} requests, got {}", n, got);
+    }
+
+    // Asserts the last message of the latest request contains `needle`.
+    pub fn assert_last_message_contains(&self, needle: &str) {
+        let state = self.lock();
+        let last = state
+            .requests
+            .last()
+            .and_then(|r| r.messages.last())
+            .map(|m| m.text_content())
+            .unwrap_or_default();
+        assert!(
+            last.contains(needle),
+            "mock client: last message {:?} does not contain {:?}",
+            last,
+            needle
+        );
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, State> {
+        self.inner.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    // Record the request and take the next script.
+    fn next_script(
+        &self,
+        messages: &[Message],
+        opts: &ChatOpts,
+        wire: Option<ChatWire>,
+    ) -> Result<Vec<ScriptStep>, ChatError> {
+        let mut state = self.lock();
+        state.requests.push(RecordedRequest {
+            messages: messages.to_vec(),
+            opts: opts.clone(),
+            wire,
+        });
+        let total = self.inner.scripts.len();
+        let idx = if self.inner.repeat && total > 0 {
+            state.next % total
+        } else {
+            state.next
+        };
+        state.next += 1;
+        self.inner
+            .scripts
+            .get(idx)
+            .cloned()
+            .ok_or_else(|| ChatError::Other(format!("mock: no script for request {}", idx + 1)))
+    }
+}
+
+impl ModelClient for MockClient {
+    fn send_chat<'a>(
+        &'a self,
+        msgs: &'a [Message],
+        opts: &'a ChatOpts,
+    ) -> BoxFuture<'a, Result<ChatResult, ChatError>> {
+        Box::pin(async move {
+            let script = self.next_script(msgs, opts, None)?;
+            let mut result = ChatResult {
+                text: String::new(),
+                finish_reason: None,
+                prompt_tokens: None,
+                completion_tokens: None,
+                rate_info: None,
+            };
+            for step in script {
+                if !step.delay.is_zero() {
+                    tokio::time::sleep(step.delay).await;
+                }
+                match step.delta? {
+                    ChatDelta::Text(t) => result.text.push_str(&t),
+                    ChatDelta::Finish(r) => result.finish_reason = r,
+                    ChatDelta::Usage {
+                        prompt_tokens,
+                        completion_tokens,
+                    } => {
+                        result.prompt_tokens = prompt_tokens;
+                        result.completion_tokens = completion_tokens;
+                    }
+                    ChatDelta::RateInfo(r) => result.rate_info = Some(r),
+                    ChatDelta::RoleStart(_)
+                    | ChatDelta::Reasoning(_)
+                    | ChatDelta::Retrying { .. } => {}
+                }
+            }
+            Ok(result)
+        })
+    }
+
+    fn stream_chat<'a>(
+        &'a self,
+        msgs: Vec<Message>,
+        opts: ChatOpts,
+        wire: ChatWire,
+        cancel: CancellationToken,
+    ) -> BoxFuture<'a, Result<ChatStream<'a>, ChatError>> {
+        Box::pin(async move {
+            let script = self.next_script(&msgs, &opts, Some(wire))?;
+            let stream =
+                futures::stream::unfold(Some((script.into_iter(), cancel)), |state| async move {
+                    let (mut steps, cancel) = state?;
+                    let step = steps.next()?;
+                    if !step.delay.is_zero() {
+                        tokio::select! {
+                            _ = tokio::time::sleep(step.delay) => {}
+                            _ = cancel.cancelled() => return Some((Err(ChatError::Canceled), None)),
+                        }
+                    } else if cancel.is_cancelled() {
+                        return Some((Err(ChatError::Canceled), None));
+                    }
+                    // An error ends the stream, as with a real provider
+                    let next = step.delta.is_ok().then_some((steps, cancel));
+                    Some((step.delta, next))
+                });
+            Ok(Box::pin(stream) as ChatStream<'a>)
+        })
+    }
+}
+
+#[derive(Deserialize)]
+struct ScriptFile {
+    #[serde(default)]
+    repeat: bool,
+    requests: Vec<Vec<JsonStep>>,
+}
+
+#[derive(Deserialize)]
+struct JsonStep {
+    #[serde(flatten)]
+    event: JsonEvent,
+    #[serde(default)]
+    delay_ms: u64,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum JsonEvent {
+    Text(String),
+    Reasoning(String),
+    Usage {
+        prompt_tokens: Option<u32>,
+        completion_tokens: Option<u32>,
+    },
+    Finish(Option<String>),
+    Error {
+        kind: String,
+        message: String,
+    },
+}
+
+impl From<JsonStep> for ScriptStep {
+    fn from(s: JsonStep) -> Self {
+        let delta = match s.event {
+            JsonEvent::Text(t) => Ok(ChatDelta::Text(t)),
+            JsonEvent::Reasoning(t) => Ok(ChatDelta::Reasoning(t)),
+            JsonEvent::Usage {
+                prompt_tokens,
+                completion_tokens,
+            } => Ok(ChatDelta::Usage {
+                prompt_tokens,
+                completion_tokens,
+            }),
+            JsonEvent::Finish(r) => Ok(ChatDelta::Finish(r)),
+            JsonEvent::Error { kind, message } => Err(match kind.as_str() {
+                "auth" => ChatError::Auth(message),
+                "rate_limit" => ChatError::RateLimit(message),
+                "timeout" => ChatError::Timeout(message),
+                "network" => ChatError::Network(message),
+                "decode" => ChatError::Decode(message),
+                "protocol" => ChatError::Protocol(message),
+                "canceled" => ChatError::Canceled,
+                _ => ChatError::Other(message),
+            }),
+        };
+        Self {
+            delta,
+            delay: Duration::from_millis(s.delay_ms),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::StreamExt;
+
+    use super::*;
+    use crate::llm::Role;
+
+    fn opts() -> ChatOpts {
+        ChatOpts {
+            model: "mock".into(),
+            temperature: None,
+            top_p: None,
+            max_tokens: None,
+            reasoning_summary: false,
+        }
+    }
+
+    async fn collect(client: &MockClient, prompt: &str) -> Vec<Result<ChatDelta, ChatError>> {
+        let msgs = vec![Message::text(Role::User, prompt)];
+        match client
+            .stream_chat(msgs, opts(), ChatWire::Chat, CancellationToken::new())
+            .await
+        {
+            Ok(stream) => stream.collect().await,
+            Err(e) => vec![Err(e)],
+        }
+    }
+
+    fn text(deltas: &[Result<ChatDelta, ChatError>]) -> String {
+        deltas
+            .iter()
+            .filter_map(|d| match d {
+                Ok(ChatDelta::Text(t)) => Some(t.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn scripts_are_served_in_order_and_then_run_out() {
+        let client = MockClient::new(vec![
+            vec![Ok(ChatDelta::Text("one".into()))],
+            vec![Ok(ChatDelta::Text("two".into()))],
+        ]);
+        assert_eq!(text(&collect(&client, "a").await), "one");
+        assert_eq!(text(&collect(&client, "b").await), "two");
+        let third = collect(&client, "c").await;
+        assert!(matches!(third.as_slice(), [Err(ChatError::Other(_))]));
+        client.assert_request_count(3);
+        client.assert_last_message_contains("c");
+        assert_eq!(client.requests()[0].wire, Some(ChatWire::Chat));
+    }
+
+    #[tokio::test]
+    async fn repeat_starts_over_and_clones_share_the_cursor() {
+        let client = MockClient::with_steps(
+            vec![
+                vec![Ok(ChatDelta::Text("one".into())).into()],
+                vec![Ok(ChatDelta::Text("two".into())).into()],
+            ],
+            true,
+        );
+        let other = client.clone();
+        assert_eq!(text(&collect(&client, "a").await), "one");
+        assert_eq!(text(&collect(&other, "b").await), "two");
+        assert_eq!(text(&collect(&client, "c").await), "one");
+        other.assert_request_count(3);
+    }
+
+    #[tokio::test]
+    async fn an_error_ends_the_stream() {
+        let client = MockClient::new(vec![vec![
+            Ok(ChatDelta::Text("partial".into())),
+            Err(ChatError::Network("reset".into())),
+            Ok(ChatDelta::Text("never sent".into())),
+        ]]);
+        let deltas = collect(&client, "a").await;
+        assert_eq!(deltas.len(), 2);
+        assert!(matches!(deltas[1], Err(ChatError::Network(_))));
+    }
+
+    #[tokio::test]
+    async fn cancel_stops_before_the_next_step() {
+        let client = MockClient::new(vec![vec![Ok(ChatDelta::Text("hi".into()))]]);
+        let cancel = CancellationToken::new();
+        cancel.cancel();
+        let stream = client
+            .stream_chat(vec![], opts(), ChatWire::Chat, cancel)
+            .await
+            .unwrap();
+        let deltas: Vec<_> = stream.collect().await;
+        assert!(matches!(deltas.as_slice(), [Err(ChatError::Canceled)]));
+    }
+
+    #[tokio::test]
+    async fn send_chat_folds_the_script() {
+        let client = MockClient::new(vec![vec![
+            Ok(ChatDelta::Reasoning("thinking".into())),
+            Ok(ChatDelta::Text("Hello, ".into())),
+            Ok(ChatDelta::Text("world".into())),
+            Ok(ChatDelta::Usage {
+                prompt_tokens: Some(3),
+                completion_tokens: Some(2),
+            }),
+            Ok(ChatDelta::Finish(Some("stop".into()))),
+        ]]);
+        let res = client.send_chat(&[], &opts()).await.unwrap();
+        assert_eq!(res.text, "Hello, world");
+        assert_eq!(res.finish_reason.as_deref(), Some("stop"));
+        assert_eq!(
+            (res.prompt_tokens, res.completion_tokens),
+            (Some(3), Some(2))
+        );
+    }
+
+    #[test]
+    fn from_json_maps_steps_and_errors() {
+        let client = MockClient::from_json(
+            r#"{"requests": [[
+                {"text": "Hi", "delay_ms": 20},
+                {"error": {"kind": "rate_limit", "message": "slow down"}}
+            ]]}"#,
+        )
+        .unwrap();
+        assert!(!client.inner.repeat);
+        let steps = &client.inner.scripts[0];
+        assert_eq!(steps[0].delay, Duration::from_millis(20));
+        assert!(matches!(&steps[0].delta, Ok(ChatDelta::Text(t)) if t == "Hi"));
+        assert!(matches!(&steps[1].delta, Err(ChatError::RateLimit(m)) if m == "slow down"));
+        assert!(matches!(
+            MockClient::from_json("{}"),
+            Err(ChatError::Decode(_))
+        ));
+    }
+
+    #[test]
+    fn bundled_scripts_parse() {
+        let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("../../docs/mock");
+        let mut seen = 0;
+        for entry in std::fs::read_dir(&dir).unwrap() {
+            let path = entry.unwrap().path();
+            let text = std::fs::read_to_string(&path).unwrap();
+            if let Err(e) = MockClient::from_json(&text) {
+                panic!("{}: {}", path.display(), e);
+            }
+            seen += 1;
+        }
+        assert!(seen >= 3);
+    }
+}
@@ -0,0 +1,229 @@
+// Opt-in response cache (`cache = true`): completed replies are stored per
+// request fingerprint under `<data dir>/fast/cache/` and served again as a
+// synthetic stream, ending in `Finish(Some(CACHED_FINISH_REASON))`.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::Context;
+use directories::BaseDirs;
+use fast_core::llm::{
+    CancellationToken, ChatDelta, ChatError, ChatOpts, ChatResult, ChatStream, ChatWire, Message,
+    ModelClient,
+};
+use futures::{future::BoxFuture, StreamExt};
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+// Finish reason of a reply served from the cache
+pub const CACHED_FINISH_REASON: &str = "cached";
+
+pub fn cache_dir() -> Option<PathBuf> {
+    let base = BaseDirs::new()?;
+    Some(base.data_dir().join("fast").join("cache"))
+}
+
+// Delete every cached reply; returns how many were removed.
+pub fn clear() -> anyhow::Result<usize> {
+    let Some(dir) = cache_dir() else {
+        return Ok(0);
+    };
+    let mut removed = 0;
+    for (path, _, _) in entries(&dir) {
+        fs::remove_file(&path).with_context(|| format!("remove {}", path.display()))?;
+        removed += 1;
+    }
+    Ok(removed)
+}
+
+#[derive(Serialize, Deserialize)]
+struct CachedReply {
+    // Unix seconds
+    created: u64,
+    model: String,
+    text: String,
+    prompt_tokens: Option<u32>,
+    completion_tokens: Option<u32>,
+}
+
+impl CachedReply {
+    fn deltas(self) -> Vec<Result<ChatDelta, ChatError>> {
+        let mut out = vec![Ok(ChatDelta::Text(self.text))];
+        if self.prompt_tokens.is_some() || self.completion_tokens.is_some() {
+            out.push(Ok(ChatDelta::Usage {
+                prompt_tokens: self.prompt_tokens,
+                completion_tokens: self.completion_tokens,
+            }));
+        }
+        out.push(Ok(ChatDelta::Finish(Some(CACHED_FINISH_REASON.into()))));
+        out
+    }
+}
+
+pub struct CachingClient {
+    inner: Box<dyn ModelClient>,
+    dir: PathBuf,
+    ttl: Duration,
+    max_bytes: u64,
+}
+
+impl CachingClient {
+    pub fn new(inner: Box<dyn ModelClient>, dir: PathBuf, ttl: Duration, max_bytes: u64) -> Self {
+        Self {
+            inner,
+            dir,
+            ttl,
+            max_bytes,
+        }
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", key))
+    }
+
+    fn lookup(&self, key: &str) -> Option<CachedReply> {
+        let data = fs::read(self.path(key)).ok()?;
+        let reply: CachedReply = serde_json::from_slice(&data).ok()?;
+        if now().saturating_sub(reply.created) > self.ttl.as_secs() {
+            let _ = fs::remove_file(self.path(key));
+            return None;
+        }
+        Some(reply)
+    }
+
+    fn store(&self, key: &str, reply: &CachedReply) {
+        let res = (|| -> anyhow::Result<()> {
+            fs::create_dir_all(&self.dir)?;
+            fs::write(self.path(key), serde_json::to_vec(reply)?)?;
+            Ok(())
+        })();
+        match res {
+            Ok(()) => {
+                info!(target:"providers::cache","stored reply for {} ({} chars)", key, reply.text.len());
+                self.evict();
+            }
+            Err(e) => warn!(target:"providers::cache","cache write failed: {:#}", e),
+        }
+    }
+
+    // Drop expired entries, then the oldest until the store fits `max_bytes`.
+    fn evict(&self) {
+        let mut items = entries(&self.dir);
+        items.sort_by_key(|(_, _, modified)| *modified);
+        let cutoff = SystemTime::now()
+            .checked_sub(self.ttl)
+            .unwrap_or(UNIX_EPOCH);
+        let mut total: u64 = items.iter().map(|(_, len, _)| len).sum();
+        for (path, len, modified) in items {
+            if modified >= cutoff && total <= self.max_bytes {
+                continue;
+            }
+            if fs::remove_file(&path).is_ok() {
+                total = total.saturating_sub(len);
+            }
+        }
+    }
+}
+
+impl ModelClient for CachingClient {
+    fn send_chat<'a>(
+        &'a self,
+        msgs: &'a [Message],
+        opts: &'a ChatOpts,
+    ) -> BoxFuture<'a, Result<ChatResult, ChatError>> {
+        Box::pin(async move {
+            let key = fingerprint(msgs, opts, None);
+            if let Some(hit) = self.lookup(&key) {
+                info!(target:"providers::cache","hit {}", key);
+                return Ok(ChatResult {
+                    text: hit.text,
+                    finish_reason: Some(CACHED_FINISH_REASON.into()),
+                    prompt_tokens: hit.prompt_tokens,
+                    completion_tokens: hit.completion_tokens,
+                    rate_info: None,
+                });
+            }
+            let res = self.inner.send_chat(msgs, opts).await?;
+            self.store(
+                &key,
+                &CachedReply {
+                    created: now(),
+                    model: opts.model.clone(),
+                    text: res.text.clone(),
+                    prompt_tokens: res.prompt_tokens,
+                    completion_tokens: res.completion_tokens,
+                },
+            );
+            Ok(res)
+        })
+    }
+
+    fn stream_chat<'a>(
+        &'a self,
+        msgs: Vec<Message>,
+        opts: ChatOpts,
+        wire: ChatWire,
+        cancel: CancellationToken,
+    ) -> BoxFuture<'a, Result<ChatStream<'a>, ChatError>> {
+        Box::pin(async move {
+            let key = fingerprint(&msgs, &opts, Some(wire));
+            if let Some(hit) = self.lookup(&key) {
+                info!(target:"providers::cache","hit {}", key);
+                return Ok(Box::pin(futures::stream::iter(hit.deltas())) as ChatStream<'a>);
+            }
+            let model = opts.model.clone();
+            let mut upstream = self.inner.stream_chat(msgs, opts, wire, cancel).await?;
+            let s = async_stream::stream! {
+                let mut reply = CachedReply { created: now(), model, text: String::new(), prompt_tokens: None, completion_tokens: None };
+                while let Some(d) = upstream.next().await {
+                    match &d {
+                        Ok(ChatDelta::Text(t)) => reply.text.push_str(t),
+                        Ok(ChatDelta::Usage { prompt_tokens, completion_tokens }) => {
+                            reply.prompt_tokens = *prompt_tokens;
+                            reply.completion_tokens = *completion_tokens;
+                        }
+                        // Only complete replies are stored; store before yielding
+                        // since consumers stop polling after Finish
+                        Ok(ChatDelta::Finish(_)) if !reply.text.is_empty() => self.store(&key, &reply),
+                        _ => {}
+                    }
+                    let last = matches!(d, Ok(ChatDelta::Finish(_)) | Err(_));
+                    yield d;
+                    if last {
+                        break;
+                    }
+                }
+            };
+            Ok(Box::pin(s) as ChatStream<'a>)
+        })
+    }
+}
+
+// Model, wire, messages and sampling options; `opts` carries the model.
+fn fingerprint(msgs: &[Message], opts: &ChatOpts, wire: Option<ChatWire>) -> String {
+    let json = serde_json::to_string(&(msgs, opts)).unwrap_or_default();
+    crate::vcr::fnv1a(format!("{:?}|{}", wire, json).as_bytes())
+}
+
+fn entries(dir: &Path) -> Vec<(PathBuf, u64, SystemTime)> {
+    let Ok(rd) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    rd.flatten()
+        .filter(|e| e.path().extension().is_some_and(|x| x == "json"))
+        .filter_map(|e| {
+            let meta = e.metadata().ok()?;
+            Some((e.path(), meta.len(), meta.modified().unwrap_or(UNIX_EPOCH)))
+        })
+        .collect()
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
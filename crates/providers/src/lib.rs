@@ -1 +1,82 @@
+pub mod cache;
 pub mod openai;
+pub mod vcr;
+
+use std::sync::OnceLock;
+
+use fast_core::llm::{registry::Registry, testing::MockClient, ChatError, ModelClient};
+
+// `FAST_MOCK=script.json` serves scripted replies instead of calling an API.
+pub const MOCK_ENV: &str = "FAST_MOCK";
+pub const MOCK_PROVIDER_ID: &str = "mock";
+
+// All built-in providers. Presets are OpenAI-compatible endpoints, so each id
+// builds an `OpenAiClient` from the config file, which resolves the endpoint
+// and key of the configured `model_provider`; asking for any other id fails.
+pub fn registry() -> Registry {
+    let mut reg = Registry::new();
+    let ids = std::iter::once(openai::config::DEFAULT_PROVIDER_ID)
+        .chain(openai::config::PRESETS.iter().map(|p| p.id));
+    for id in ids {
+        reg.register(id, move || openai_client(id));
+    }
+    if mock_enabled() {
+        reg.register(MOCK_PROVIDER_ID, mock_client);
+    }
+    reg
+}
+
+pub fn mock_enabled() -> bool {
+    std::env::var_os(MOCK_ENV).is_some()
+}
+
+// Wrapped in a `RecordingClient` when FAST_VCR is set; replay needs no config or key.
+// With `cache = true` the result is wrapped again in a `CachingClient`.
+fn openai_client(id: &str) -> Result<Box<dyn ModelClient>, ChatError> {
+    let vcr_mode = vcr::VcrMode::from_env();
+    let vcr_err = |e: anyhow::Error| ChatError::Other(format!("vcr: {:#}", e));
+    let cassette = || {
+        vcr::cassette_path().ok_or_else(|| ChatError::Other("vcr: no data dir for cassette".into()))
+    };
+    if vcr_mode == Some(vcr::VcrMode::Replay) {
+        let rc =
+            vcr::RecordingClient::<openai::OpenAiClient>::replay(cassette()?).map_err(vcr_err)?;
+        return Ok(Box::new(rc));
+    }
+    let cfg = openai::config::OpenAiConfig::from_env_and_file(None)
+        .and_then(|c| c.validate().map(|_| c))
+        .map_err(|e| ChatError::Other(format!("config: {:#}", e)))?;
+    if cfg.provider_id() != id {
+        return Err(ChatError::Other(format!(
+            "provider '{}' is not the configured model_provider '{}'",
+            id,
+            cfg.provider_id()
+        )));
+    }
+    let cache = cfg.cache.then_some((cfg.cache_ttl, cfg.cache_max_bytes));
+    let client =
+        openai::OpenAiClient::new(cfg).map_err(|e| ChatError::Other(format!("client: {}", e)))?;
+    let client: Box<dyn ModelClient> = if vcr_mode == Some(vcr::VcrMode::Record) {
+        Box::new(vcr::RecordingClient::record(client, cassette()?).map_err(vcr_err)?)
+    } else {
+        Box::new(client)
+    };
+    match (cache, cache::cache_dir()) {
+        (Some((ttl, max_bytes)), Some(dir)) => Ok(Box::new(cache::CachingClient::new(
+            client, dir, ttl, max_bytes,
+        ))),
+        _ => Ok(client),
+    }
+}
+
+// Loaded once so successive requests walk through the script.
+fn mock_client() -> Result<Box<dyn ModelClient>, ChatError> {
+    static MOCK: OnceLock<Result<MockClient, ChatError>> = OnceLock::new();
+    let mock = MOCK.get_or_init(|| {
+        let path = std::env::var(MOCK_ENV).unwrap_or_default();
+        let text = std::fs::read_to_string(&path)
+            .map_err(|e| ChatError::Other(format!("{} {}: {}", MOCK_ENV, path, e)))?;
+        MockClient::from_json(&text)
+    });
+    Ok(Box::new(mock.clone()?))
+}
@@ -1,19 +1,33 @@
 use crate::openai::config::OpenAiConfig;
+use crate::openai::httplog::{self, HttpLog};
 use bytes::Buf;
 use fast_core::llm::{
-    self, ChatDelta, ChatError, ChatOpts, ChatResult, ChatWire, Message, ModelClient, Role,
+    self, CancellationToken, ChatDelta, ChatError, ChatOpts, ChatResult, ChatWire, ContentPart,
+    EmbeddingClient, Message, ModelClient, RateInfo, Role,
 };
-use futures::{Stream, StreamExt};
+use futures::{future::BoxFuture, Stream, StreamExt};
 use reqwest::{header, Client, StatusCode};
-use std::result::Result as StdResult;
-use std::{pin::Pin, time::Instant};
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+use std::time::Instant;
 use tokio::time::{sleep, Duration};
-use tracing::{debug, error, info, warn};
+use tracing::{debug, error, info, trace, warn, Level};
+
+// Per-run id for server-side correlation (`client_session_header = true`)
+pub const CLIENT_SESSION_HEADER: &str = "x-client-session";
+
+// Random UUID, fixed for the life of the process.
+pub fn client_session_id() -> &'static str {
+    static ID: OnceLock<String> = OnceLock::new();
+    ID.get_or_init(|| uuid::Uuid::new_v4().to_string())
+}
 
 #[derive(Clone)]
 pub struct OpenAiClient {
     http: Client,
     cfg: OpenAiConfig,
+    // Set when `log_requests` is on
+    log: Option<HttpLog>,
 }
 
 impl OpenAiClient {
@@ -34,8 +48,15 @@ impl OpenAiClient {
             header::AUTHORIZATION,
             header::HeaderValue::from_str(&format!("Bearer {}", cfg.api_key))?,
         );
+        if cfg.client_session_header {
+            headers.insert(
+                CLIENT_SESSION_HEADER,
+                header::HeaderValue::from_str(client_session_id())?,
+            );
+        }
         let mut builder = Client::builder()
             .default_headers(headers)
+            .user_agent(cfg.user_agent.as_str())
             .use_rustls_tls()
             .pool_idle_timeout(Duration::from_secs(30))
             .pool_max_idle_per_host(2)
@@ -44,38 +65,216 @@ impl OpenAiClient {
             builder = builder.proxy(reqwest::Proxy::all(p)?);
         }
         let http = builder.build()?;
-        Ok(Self { http, cfg })
+        let log = HttpLog::from_config(&cfg);
+        Ok(Self { http, cfg, log })
     }
 
-    fn map_messages(&self, msgs: &[Message]) -> Vec<serde_json::Value> {
-        msgs.iter()
-            .map(|m| {
-                let role = match m.role {
-                    Role::User => "user",
-                    Role::Assistant => "assistant",
-                    Role::System => "system",
-                };
-                serde_json::json!({"role": role, "content": m.content})
-            })
-            .collect()
+    // Fail before sending when images go to a model without image input.
+    fn check_vision(model: &str, msgs: &[Message]) -> Result<(), ChatError> {
+        if msgs.iter().any(|m| m.has_images()) && !supports_vision(model) {
+            return Err(ChatError::Protocol(format!(
+                "model '{}' does not accept image input; switch to a vision model (e.g. gpt-4o) or remove the attachment",
+                model
+            )));
+        }
+        Ok(())
+    }
+}
+
+// Chat Completions `messages`.
+fn map_messages(msgs: &[Message]) -> Vec<serde_json::Value> {
+    msgs.iter()
+        .map(|m| {
+            let role = match m.role {
+                Role::User => "user",
+                Role::Assistant => "assistant",
+                Role::System => "system",
+            };
+            serde_json::json!({"role": role, "content": chat_content(m)})
+        })
+        .collect()
+}
+
+// Body of a /chat/completions request for `model`.
+fn chat_body(model: &str, msgs: &[Message], opts: &ChatOpts, stream: bool) -> serde_json::Value {
+    serde_json::json!({
+        "model": model,
+        "messages": map_messages(msgs),
+        "stream": stream,
+        "temperature": opts.temperature,
+        "top_p": opts.top_p,
+        "max_tokens": opts.max_tokens,
+    })
+}
+
+// Body of a streaming /responses request.
+fn responses_body(msgs: &[Message], opts: &ChatOpts) -> serde_json::Value {
+    let (model_slug, verbosity) = OpenAiClient::normalize_gpt5(&opts.model);
+    // Responses API expects input to be a list of role/content items.
+    let input_items: Vec<serde_json::Value> = msgs
+        .iter()
+        .filter_map(|m| {
+            let is_assistant = matches!(m.role, Role::Assistant);
+            if is_assistant && m.text_content().trim().is_empty() {
+                return None;
+            }
+            let role = match m.role {
+                Role::System => "system",
+                Role::User => "user",
+                Role::Assistant => "assistant",
+            };
+            Some(serde_json::json!({
+                "role": role,
+                "content": responses_content(m)
+            }))
+        })
+        .collect();
+    let mut body = serde_json::json!({ "model": model_slug, "input": input_items, "stream": true });
+    if let Some(v) = verbosity {
+        if let Some(map) = body.as_object_mut() {
+            map.insert("text".to_string(), serde_json::json!({ "verbosity": v }));
+        }
+    }
+    if opts.reasoning_summary && is_reasoning_model(&model_slug) {
+        if let Some(map) = body.as_object_mut() {
+            map.insert(
+                "reasoning".to_string(),
+                serde_json::json!({ "summary": "auto" }),
+            );
+        }
+    }
+    body
+}
+
+// URL and body of the streaming request `stream_chat` would send on `wire`,
+// for /dryrun. Auto shows the wire already probed for `base_url`, else
+// Responses, which it tries first.
+pub fn request_preview(
+    base_url: &str,
+    msgs: &[Message],
+    opts: &ChatOpts,
+    wire: ChatWire,
+) -> (String, serde_json::Value) {
+    let base = base_url.trim_end_matches('/');
+    let wire = match wire {
+        ChatWire::Auto => probed_wires()
+            .lock()
+            .ok()
+            .and_then(|m| m.get(base).copied())
+            .unwrap_or(ChatWire::Responses),
+        w => w,
+    };
+    match wire {
+        ChatWire::Chat => {
+            let (model_slug, _) = OpenAiClient::normalize_gpt5(&opts.model);
+            (
+                format!("{}/chat/completions", base),
+                chat_body(&model_slug, msgs, opts, true),
+            )
+        }
+        _ => (format!("{}/responses", base), responses_body(msgs, opts)),
+    }
+}
+
+// Reasoning model families that accept `reasoning.summary` on the Responses API.
+fn is_reasoning_model(model: &str) -> bool {
+    let m = model.trim().to_lowercase();
+    ["gpt-5", "o1", "o3", "o4"].iter().any(|p| m.starts_with(p))
+}
+
+// Best-effort list of OpenAI model families that accept image input.
+fn supports_vision(model: &str) -> bool {
+    let m = model.trim().to_lowercase();
+    [
+        "gpt-4o",
+        "gpt-4.1",
+        "gpt-4-turbo",
+        "gpt-5",
+        "o1",
+        "o3",
+        "o4",
+    ]
+    .iter()
+    .any(|p| m.starts_with(p))
+        || m.contains("vision")
+}
+
+// Chat Completions content: plain string for text-only messages, parts otherwise.
+fn chat_content(m: &Message) -> serde_json::Value {
+    if !m.has_images() {
+        return serde_json::Value::String(m.text_content());
     }
+    let parts: Vec<serde_json::Value> = m
+        .content
+        .iter()
+        .map(|p| match p {
+            ContentPart::Text(t) => serde_json::json!({ "type": "text", "text": t }),
+            ContentPart::ImageUrl { url, detail } => {
+                let mut image = serde_json::json!({ "url": url });
+                if let Some(d) = detail {
+                    image["detail"] = serde_json::json!(d);
+                }
+                serde_json::json!({ "type": "image_url", "image_url": image })
+            }
+        })
+        .collect();
+    serde_json::Value::Array(parts)
+}
+
+// Responses API content items for one message.
+fn responses_content(m: &Message) -> Vec<serde_json::Value> {
+    let text_type = match m.role {
+        Role::Assistant => "output_text",
+        _ => "input_text",
+    };
+    m.content
+        .iter()
+        .map(|p| match p {
+            ContentPart::Text(t) => serde_json::json!({ "type": text_type, "text": t }),
+            ContentPart::ImageUrl { url, detail } => serde_json::json!({
+                "type": "input_image",
+                "image_url": url,
+                "detail": detail.as_deref().unwrap_or("auto"),
+            }),
+        })
+        .collect()
 }
 
-#[allow(async_fn_in_trait)]
 impl ModelClient for OpenAiClient {
-    async fn send_chat(&self, msgs: &[Message], opts: &ChatOpts) -> Result<ChatResult, ChatError> {
+    fn send_chat<'a>(
+        &'a self,
+        msgs: &'a [Message],
+        opts: &'a ChatOpts,
+    ) -> BoxFuture<'a, Result<ChatResult, ChatError>> {
+        Box::pin(self.send_chat_inner(msgs, opts))
+    }
+
+    fn stream_chat<'a>(
+        &'a self,
+        msgs: Vec<Message>,
+        opts: ChatOpts,
+        wire: ChatWire,
+        cancel: CancellationToken,
+    ) -> BoxFuture<'a, Result<fast_core::llm::ChatStream<'a>, ChatError>> {
+        Box::pin(self.stream_chat_inner(msgs, opts, wire, cancel))
+    }
+}
+
+impl OpenAiClient {
+    async fn send_chat_inner(
+        &self,
+        msgs: &[Message],
+        opts: &ChatOpts,
+    ) -> Result<ChatResult, ChatError> {
+        Self::check_vision(&opts.model, msgs)?;
         let url = format!(
             "{}/chat/completions",
             self.cfg.base_url.trim_end_matches('/')
         );
-        let body = serde_json::json!({
-            "model": opts.model,
-            "messages": self.map_messages(msgs),
-            "stream": false,
-            "temperature": opts.temperature,
-            "top_p": opts.top_p,
-            "max_tokens": opts.max_tokens,
-        });
+        let body = chat_body(&opts.model, msgs, opts, false);
+        if let Some(log) = &self.log {
+            log.request(&url, &body);
+        }
         let resp = self
             .http
             .post(url)
@@ -84,12 +283,20 @@ impl ModelClient for OpenAiClient {
             .await
             .map_err(map_reqwest_err)?;
         if !resp.status().is_success() {
-            return Err(map_status_err(resp.status(), resp.text().await.ok()));
+            let status = resp.status();
+            let request_id = request_id_of(&resp);
+            return Err(map_status_err(status, request_id, resp.text().await.ok()));
         }
-        let v: serde_json::Value = resp
-            .json()
+        let rate_info = rate_info_of(&resp);
+        let raw = resp
+            .text()
             .await
             .map_err(|e| ChatError::Decode(e.to_string()))?;
+        if let Some(log) = &self.log {
+            log.event(raw.as_bytes());
+        }
+        let v: serde_json::Value =
+            serde_json::from_str(&raw).map_err(|e| ChatError::Decode(e.to_string()))?;
         let text = v["choices"][0]["message"]["content"]
             .as_str()
             .unwrap_or("")
@@ -99,52 +306,217 @@ impl ModelClient for OpenAiClient {
             finish_reason: None,
             prompt_tokens: None,
             completion_tokens: None,
+            rate_info,
         })
     }
 
-    async fn stream_chat<'a>(
+    async fn stream_chat_inner<'a>(
         &'a self,
         msgs: Vec<Message>,
         opts: ChatOpts,
         wire: ChatWire,
+        cancel: CancellationToken,
     ) -> Result<fast_core::llm::ChatStream<'a>, ChatError> {
-        let actual = match wire {
-            ChatWire::Chat => ChatWire::Chat,
-            ChatWire::Responses => ChatWire::Responses,
-            ChatWire::Auto => ChatWire::Responses,
-        };
-        match actual {
-            ChatWire::Chat => self.stream_chat_completions(msgs, opts).await,
-            ChatWire::Responses => self.stream_responses_or_fallback(msgs, opts).await,
-            ChatWire::Auto => unreachable!(),
+        Self::check_vision(&opts.model, &msgs)?;
+        match wire {
+            ChatWire::Chat => self.stream_chat_completions(msgs, opts, cancel).await,
+            ChatWire::Responses => self.stream_responses_or_fallback(msgs, opts, cancel).await,
+            ChatWire::Auto => self.stream_auto(msgs, opts, cancel).await,
         }
     }
 }
 
+// Consecutive unparseable chat SSE events tolerated before the stream fails
+const MAX_BAD_SSE_EVENTS: u32 = 3;
+
+// Max inputs per /embeddings request (API limit)
+const EMBED_BATCH_MAX: usize = 2048;
+
+impl EmbeddingClient for OpenAiClient {
+    async fn embed(&self, inputs: &[String], model: &str) -> Result<Vec<Vec<f32>>, ChatError> {
+        self.embed_batched(inputs, model, EMBED_BATCH_MAX).await
+    }
+}
+
 impl OpenAiClient {
-    async fn stream_responses_or_fallback<'a>(
+    // One /embeddings request per `batch_max` inputs; vectors come back in
+    // input order.
+    async fn embed_batched(
+        &self,
+        inputs: &[String],
+        model: &str,
+        batch_max: usize,
+    ) -> Result<Vec<Vec<f32>>, ChatError> {
+        let url = format!("{}/embeddings", self.cfg.base_url.trim_end_matches('/'));
+        let mut out: Vec<Vec<f32>> = Vec::with_capacity(inputs.len());
+        for batch in inputs.chunks(batch_max) {
+            let body = serde_json::json!({ "model": model, "input": batch });
+            let resp = self
+                .http
+                .post(&url)
+                .json(&body)
+                .send()
+                .await
+                .map_err(map_reqwest_err)?;
+            if !resp.status().is_success() {
+                let status = resp.status();
+                let request_id = request_id_of(&resp);
+                return Err(map_status_err(status, request_id, resp.text().await.ok()));
+            }
+            let v: serde_json::Value = resp
+                .json()
+                .await
+                .map_err(|e| ChatError::Decode(e.to_string()))?;
+            let (pt, _) = extract_usage_tokens(&v);
+            info!(target:"providers::openai","embeddings model={} inputs={} prompt_tokens={:?}", model, batch.len(), pt);
+            out.extend(parse_embeddings(&v, batch.len())?);
+        }
+        Ok(out)
+    }
+}
+
+// Vectors from an /embeddings response, ordered by each item's `index`.
+fn parse_embeddings(v: &serde_json::Value, expected: usize) -> Result<Vec<Vec<f32>>, ChatError> {
+    let data = v["data"]
+        .as_array()
+        .ok_or_else(|| ChatError::Decode("embeddings: missing data".into()))?;
+    let mut slots: Vec<Option<Vec<f32>>> = vec![None; expected];
+    for (pos, item) in data.iter().enumerate() {
+        let idx = item["index"].as_u64().map(|i| i as usize).unwrap_or(pos);
+        let vec = item["embedding"]
+            .as_array()
+            .ok_or_else(|| ChatError::Decode("embeddings: missing embedding".into()))?
+            .iter()
+            .map(|x| x.as_f64().unwrap_or(0.0) as f32)
+            .collect();
+        match slots.get_mut(idx) {
+            Some(slot) => *slot = Some(vec),
+            None => {
+                return Err(ChatError::Decode(format!(
+                    "embeddings: index {} out of range",
+                    idx
+                )))
+            }
+        }
+    }
+    slots
+        .into_iter()
+        .enumerate()
+        .map(|(i, s)| {
+            s.ok_or_else(|| ChatError::Decode(format!("embeddings: no vector for input {}", i)))
+        })
+        .collect()
+}
+
+// Wire found to work per base_url by `wire_api = "auto"`, for the life of
+// the process.
+fn probed_wires() -> &'static Mutex<HashMap<String, ChatWire>> {
+    static WIRES: OnceLock<Mutex<HashMap<String, ChatWire>>> = OnceLock::new();
+    WIRES.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+// Errors that mean the deployment has no Responses endpoint: 404/405, or
+// another 4xx whose body says the endpoint is not supported. Other 400s
+// are bad parameters and would fail the same way on Chat Completions.
+fn responses_unsupported(e: &ChatError) -> bool {
+    let (ChatError::Protocol(s) | ChatError::Other(s)) = e else {
+        return false;
+    };
+    if s.starts_with("404 ") || s.starts_with("405 ") {
+        return true;
+    }
+    let lower = s.to_lowercase();
+    s.starts_with('4')
+        && [
+            "unsupported endpoint",
+            "endpoint not supported",
+            "endpoint is not supported",
+        ]
+        .iter()
+        .any(|m| lower.contains(m))
+}
+
+// Streams send their request on first poll, so an HTTP error is their
+// first item. Waits past retry notices for the first real output and
+// returns the error as such, or a stream replaying what was read.
+async fn first_output(
+    stream: Result<llm::ChatStream<'_>, ChatError>,
+) -> Result<llm::ChatStream<'_>, ChatError> {
+    let mut stream = stream?;
+    let mut head = Vec::new();
+    while let Some(item) = stream.next().await {
+        let delta = item?;
+        let retry = matches!(delta, ChatDelta::Retrying { .. });
+        head.push(Ok(delta));
+        if !retry {
+            break;
+        }
+    }
+    Ok(Box::pin(futures::stream::iter(head).chain(stream)))
+}
+
+impl OpenAiClient {
+    // `wire_api = "auto"`: try Responses once per base_url and remember which
+    // wire answered, so chat-only gateways are not probed on every request.
+    async fn stream_auto<'a>(
         &'a self,
         msgs: Vec<Message>,
         opts: ChatOpts,
+        cancel: CancellationToken,
     ) -> Result<fast_core::llm::ChatStream<'a>, ChatError> {
-        match self.stream_responses(msgs.clone(), opts.clone()).await {
-            Ok(s) => Ok(s),
-            Err(ChatError::Protocol(e)) => {
-                // Fallback for Responses not available in this deployment
-                if e.contains("404") || e.contains("400") || e.to_lowercase().contains("responses")
-                {
-                    return self.stream_chat_completions(msgs, opts).await;
-                }
-                Err(ChatError::Protocol(e))
+        let base = self.cfg.base_url.trim_end_matches('/').to_string();
+        let known = probed_wires()
+            .lock()
+            .ok()
+            .and_then(|m| m.get(&base).copied());
+        match known {
+            Some(ChatWire::Chat) => return self.stream_chat_completions(msgs, opts, cancel).await,
+            Some(_) => {
+                let attempts = self.cfg.stream_max_retries;
+                return self.stream_responses(msgs, opts, cancel, attempts).await;
             }
-            Err(ChatError::Other(e)) => {
-                // Many providers return 400 for unsupported endpoints/params
-                if e.starts_with("400 ") || e.contains("404") {
-                    return self.stream_chat_completions(msgs, opts).await;
-                }
-                Err(ChatError::Other(e))
+            None => {}
+        }
+        // One attempt: a retried probe would only delay the fallback
+        let probe = self
+            .stream_responses(msgs.clone(), opts.clone(), cancel.clone(), 1)
+            .await;
+        let (res, wire) = match first_output(probe).await {
+            Ok(s) => (Ok(s), ChatWire::Responses),
+            Err(e) if responses_unsupported(&e) => {
+                warn!(target:"providers::openai","responses unavailable at {}, using chat: {}", base, e);
+                let fallback = self.stream_chat_completions(msgs, opts, cancel).await;
+                (first_output(fallback).await, ChatWire::Chat)
+            }
+            // Auth, network, canceled...: nothing learned about the endpoint
+            Err(e) => return Err(e),
+        };
+        // A fallback that fails too proves nothing about Chat Completions
+        if res.is_ok() {
+            info!(target:"providers::openai","wire_api auto: {} uses {:?}", base, wire);
+            if let Ok(mut m) = probed_wires().lock() {
+                m.insert(base, wire);
             }
-            Err(e) => Err(e),
+        }
+        res
+    }
+
+    async fn stream_responses_or_fallback<'a>(
+        &'a self,
+        msgs: Vec<Message>,
+        opts: ChatOpts,
+        cancel: CancellationToken,
+    ) -> Result<fast_core::llm::ChatStream<'a>, ChatError> {
+        let attempts = self.cfg.stream_max_retries;
+        let stream = self
+            .stream_responses(msgs.clone(), opts.clone(), cancel.clone(), attempts)
+            .await;
+        match first_output(stream).await {
+            // Fallback for Responses not available in this deployment
+            Err(e) if responses_unsupported(&e) => {
+                self.stream_chat_completions(msgs, opts, cancel).await
+            }
+            res => res,
         }
     }
 
@@ -152,6 +524,7 @@ impl OpenAiClient {
         &'a self,
         msgs: Vec<Message>,
         opts: ChatOpts,
+        cancel: CancellationToken,
     ) -> Result<fast_core::llm::ChatStream<'a>, ChatError> {
         let url = format!(
             "{}/chat/completions",
@@ -159,35 +532,45 @@ impl OpenAiClient {
         );
         info!(target:"providers::openai","start chat stream model={} url={}", opts.model, url);
         let (model_slug, _verbosity) = Self::normalize_gpt5(&opts.model);
-        let body = serde_json::json!({
-            "model": model_slug,
-            "messages": self.map_messages(&msgs),
-            "stream": true,
-            "temperature": opts.temperature,
-            "top_p": opts.top_p,
-            "max_tokens": opts.max_tokens,
-        });
+        let body = chat_body(&model_slug, &msgs, &opts, true);
+        debug_request(&url, &body, self.cfg.log_body_max_bytes);
         let mut attempt = 0u32;
         let max_attempts = self.cfg.stream_max_retries.max(1);
         let idle = self.cfg.stream_idle_timeout;
         let client = self.http.clone();
-        let req = move || client.post(&url).json(&body).send();
+        let log = self.log.clone();
+        let req_log = log.clone();
+        let req = move || {
+            if let Some(l) = &req_log {
+                l.request(&url, &body);
+            }
+            client.post(&url).json(&body).send()
+        };
 
         async fn sse_stream(
             send_fut: impl std::future::Future<Output = Result<reqwest::Response, reqwest::Error>>,
             idle: Duration,
+            cancel: CancellationToken,
+            log: Option<HttpLog>,
         ) -> Result<impl Stream<Item = Result<ChatDelta, ChatError>>, ChatError> {
-            let resp = send_fut.await.map_err(map_reqwest_err)?;
+            let resp = tokio::select! {
+                r = send_fut => r.map_err(map_reqwest_err)?,
+                _ = cancel.cancelled() => return Err(ChatError::Canceled),
+            };
             if !resp.status().is_success() {
                 let status = resp.status();
-                let body = resp.text().await.ok();
-                error!(target:"providers::openai","chat stream non-200 status={} body={:?}", status, body);
-                return Err(map_status_err(status, body));
+                let request_id = request_id_of(&resp);
+                let body = resp.text().await.ok().map(|b| httplog::scrub(&b));
+                error!(target:"providers::openai","chat stream non-200 status={} request_id={:?} body={:?}", status, request_id, body);
+                return Err(map_status_err(status, request_id, body));
             }
+            let rate_info = rate_info_of(&resp);
             let mut stream = resp.bytes_stream();
             let mut buf = bytes::BytesMut::new();
             let mut last = Instant::now();
+            let mut bad_events = 0u32;
             let s = async_stream::stream! {
+                if let Some(r) = rate_info { yield Ok(ChatDelta::RateInfo(r)); }
                 use futures::StreamExt;
                 'outer: loop {
                     tokio::select! {
@@ -196,22 +579,31 @@ impl OpenAiClient {
                                 Some(Ok(b)) => {
                                     buf.extend_from_slice(&b);
                                     last = Instant::now();
-                                    loop {
-                                        if let Some(pos) = find_event_boundary(&buf) {
-                                            let ev = buf.split_to(pos).freeze();
-                                            let _ = if buf.starts_with(b"\r\n\r\n") { buf.split_to(4) } else { buf.split_to(2) };
-                                            match parse_chat_sse_event(&ev) {
-                                                Ok(Some(delta)) => { yield Ok(delta); }
-                                                Ok(None) => {}
-                                                Err(e) => { yield Err(e); break 'outer; }
+                                    while let Some(pos) = find_event_boundary(&buf) {
+                                        let ev = buf.split_to(pos).freeze();
+                                        let _ = if buf.starts_with(b"\r\n\r\n") { buf.split_to(4) } else { buf.split_to(2) };
+                                        if let Some(l) = &log { l.event(&ev); }
+                                        match parse_chat_sse_event(&ev) {
+                                            Ok(Some(delta)) => {
+                                                trace!(target:"providers::openai","chat event {}", delta_kind(&delta));
+                                                bad_events = 0;
+                                                yield Ok(delta);
+                                            }
+                                            Ok(None) => { trace!(target:"providers::openai","chat event without delta ({} bytes)", ev.len()); }
+                                            // Proxies inject noise; only a run of bad events is fatal
+                                            Err(ChatError::Decode(e)) if bad_events + 1 < MAX_BAD_SSE_EVENTS => {
+                                                bad_events += 1;
+                                                warn!(target:"providers::openai","skipping malformed SSE event ({}/{}): {}", bad_events, MAX_BAD_SSE_EVENTS, e);
                                             }
-                                        } else { break; }
+                                            Err(e) => { yield Err(e); break 'outer; }
+                                        }
                                     }
                                 }
                                 Some(Err(e)) => { yield Err(map_reqwest_err(e)); break 'outer; }
                                 None => { break 'outer; }
                             }
                         }
+                        _ = cancel.cancelled() => { yield Err(ChatError::Canceled); break 'outer; }
                         _ = tokio::time::sleep(Duration::from_millis(500)) => {
                             if last.elapsed() > idle { yield Err(ChatError::Timeout("idle".into())); break 'outer; }
                         }
@@ -224,7 +616,7 @@ impl OpenAiClient {
         let merged = async_stream::try_stream! {
             let mut acc = String::new();
             loop {
-                let s = sse_stream(req(), idle).await;
+                let s = sse_stream(req(), idle, cancel.clone(), log.clone()).await;
                 match s {
                     Ok(st) => {
                         let mut st = Box::pin(st);
@@ -246,7 +638,9 @@ impl OpenAiClient {
                     }
                     Err(e) => {
                         attempt += 1;
-                        if attempt >= max_attempts { Err(e)? } else {
+                        if attempt >= max_attempts || matches!(e, ChatError::Canceled) { Err(e)? } else {
+                            warn!(target:"providers::openai","retrying {}/{} after: {}", attempt + 1, max_attempts, e);
+                            yield ChatDelta::Retrying { attempt: attempt + 1, max_attempts };
                             let backoff = Duration::from_millis(300 * attempt as u64);
                             sleep(backoff).await;
                             continue;
@@ -258,64 +652,49 @@ impl OpenAiClient {
         Ok(Box::pin(merged))
     }
 
+    // Up to `max_attempts` requests; a missing endpoint is never retried.
     async fn stream_responses<'a>(
         &'a self,
         msgs: Vec<Message>,
         opts: ChatOpts,
+        cancel: CancellationToken,
+        max_attempts: u32,
     ) -> Result<llm::ChatStream<'a>, ChatError> {
         let url = format!("{}/responses", self.cfg.base_url.trim_end_matches('/'));
         info!(target:"providers::openai","start responses stream model={} url={}", opts.model, url);
-        let (model_slug, verbosity) = Self::normalize_gpt5(&opts.model);
-        // Responses API expects input to be a list of role/content items.
-        let input_items: Vec<serde_json::Value> = msgs
-            .iter()
-            .filter_map(|m| {
-                let is_assistant = matches!(m.role, Role::Assistant);
-                if is_assistant && m.content.trim().is_empty() {
-                    return None;
-                }
-                let role = match m.role {
-                    Role::System => "system",
-                    Role::User => "user",
-                    Role::Assistant => "assistant",
-                };
-                let content_type = match m.role {
-                    Role::Assistant => "output_text",
-                    _ => "input_text",
-                };
-                Some(serde_json::json!({
-                    "role": role,
-                    "content": [ { "type": content_type, "text": m.content } ]
-                }))
-            })
-            .collect();
-        let mut body =
-            serde_json::json!({ "model": model_slug, "input": input_items, "stream": true });
-        if let Some(v) = verbosity {
-            if let Some(map) = body.as_object_mut() {
-                map.insert("text".to_string(), serde_json::json!({ "verbosity": v }));
-            }
-        }
+        let body = responses_body(&msgs, &opts);
+        debug_request(&url, &body, self.cfg.log_body_max_bytes);
         let client = self.http.clone();
+        let log = self.log.clone();
         let idle = self.cfg.stream_idle_timeout;
         let mut attempt = 0u32;
-        let max_attempts = self.cfg.stream_max_retries.max(1);
+        let max_attempts = max_attempts.max(1);
 
         async fn responses_sse_stream(
             send_fut: impl std::future::Future<Output = Result<reqwest::Response, reqwest::Error>>,
             idle: Duration,
+            cancel: CancellationToken,
+            log: Option<HttpLog>,
         ) -> Result<impl Stream<Item = Result<ChatDelta, ChatError>>, ChatError> {
-            let resp = send_fut.await.map_err(map_reqwest_err)?;
+            let resp = tokio::select! {
+                r = send_fut => r.map_err(map_reqwest_err)?,
+                _ = cancel.cancelled() => return Err(ChatError::Canceled),
+            };
             if !resp.status().is_success() {
                 let status = resp.status();
-                let body = resp.text().await.ok();
-                error!(target:"providers::openai","responses non-200 status={} body={:?}", status, body);
-                return Err(map_status_err(status, body));
+                let request_id = request_id_of(&resp);
+                let body = resp.text().await.ok().map(|b| httplog::scrub(&b));
+                error!(target:"providers::openai","responses non-200 status={} request_id={:?} body={:?}", status, request_id, body);
+                return Err(map_status_err(status, request_id, body));
             }
+            let rate_info = rate_info_of(&resp);
             let mut stream = resp.bytes_stream();
             let mut buf = bytes::BytesMut::new();
             let mut last = Instant::now();
+            // Text streamed for the current output part, checked against `.done`
+            let mut part_text = String::new();
             let s = async_stream::stream! {
+                if let Some(r) = rate_info { yield Ok(ChatDelta::RateInfo(r)); }
                 'outer: loop {
                     tokio::select! {
                         chunk = stream.next() => {
@@ -324,9 +703,22 @@ impl OpenAiClient {
                                     buf.extend_from_slice(&b);
                                     last = Instant::now();
                                     loop {
-                                        match parse_responses_event(&mut buf) {
+                                        match parse_responses_event(&mut buf, log.as_ref()) {
                                             Ok(Some((event, data))) => match event.as_str() {
-                                                "response.output_text.delta" => yield Ok(ChatDelta::Text(data)),
+                                                "response.output_text.delta" => {
+                                                    part_text.push_str(&data);
+                                                    yield Ok(ChatDelta::Text(data));
+                                                }
+                                                // Full text of the part; servers that skip deltas only send this
+                                                "response.output_text.done" => {
+                                                    if let Some(rest) = data.strip_prefix(part_text.as_str()).filter(|r| !r.is_empty()) {
+                                                        yield Ok(ChatDelta::Text(rest.to_string()));
+                                                    }
+                                                    part_text.clear();
+                                                }
+                                                "response.reasoning_summary_text.delta" => yield Ok(ChatDelta::Reasoning(data)),
+                                                // Separate summary parts with a blank line
+                                                "response.reasoning_summary_part.done" => yield Ok(ChatDelta::Reasoning("\n\n".into())),
                                                 "response.completed" => {
                                                     // Try to parse usage tokens if present
                                                     if data.trim().starts_with('{') {
@@ -352,6 +744,7 @@ impl OpenAiClient {
                                 None => { break 'outer; }
                             }
                         }
+                        _ = cancel.cancelled() => { yield Err(ChatError::Canceled); break 'outer; }
                         _ = tokio::time::sleep(Duration::from_millis(500)) => {
                             if last.elapsed() > idle { yield Err(ChatError::Timeout("idle".into())); break 'outer; }
                         }
@@ -364,8 +757,11 @@ impl OpenAiClient {
         let merged = async_stream::try_stream! {
             let mut acc = String::new();
             loop {
+                if let Some(l) = &log {
+                    l.request(&url, &body);
+                }
                 let req_fut = client.post(&url).json(&body).send();
-                let s = responses_sse_stream(req_fut, idle).await;
+                let s = responses_sse_stream(req_fut, idle, cancel.clone(), log.clone()).await;
                 match s {
                     Ok(st) => {
                         let mut st = Box::pin(st);
@@ -385,7 +781,9 @@ impl OpenAiClient {
                     }
                     Err(e) => {
                         attempt += 1;
-                        if attempt >= max_attempts { Err(e)? } else {
+                        if attempt >= max_attempts || matches!(e, ChatError::Canceled) || responses_unsupported(&e) { Err(e)? } else {
+                            warn!(target:"providers::openai","retrying {}/{} after: {}", attempt + 1, max_attempts, e);
+                            yield ChatDelta::Retrying { attempt: attempt + 1, max_attempts };
                             let backoff = Duration::from_millis(300 * attempt as u64);
                             sleep(backoff).await;
                             continue;
@@ -398,6 +796,32 @@ impl OpenAiClient {
     }
 }
 
+// `RUST_LOG=providers=debug`: the outgoing body with key fields and
+// key-shaped strings redacted, cut at `log_body_max_bytes`. Unlike the
+// TUI's own events this includes message text, whatever `log_content` says.
+fn debug_request(url: &str, body: &serde_json::Value, max_bytes: usize) {
+    if !tracing::enabled!(target: "providers::openai", Level::DEBUG) {
+        return;
+    }
+    let mut body = body.clone();
+    httplog::redact_json(&mut body);
+    let text = serde_json::to_string(&body).unwrap_or_default();
+    debug!(target:"providers::openai","request POST {} body={}", url, httplog::truncate(&httplog::scrub(&text), max_bytes));
+}
+
+// Variant name for trace logs; the text itself stays out.
+fn delta_kind(d: &ChatDelta) -> &'static str {
+    match d {
+        ChatDelta::RoleStart(_) => "role",
+        ChatDelta::Text(_) => "text",
+        ChatDelta::Reasoning(_) => "reasoning",
+        ChatDelta::Finish(_) => "finish",
+        ChatDelta::Usage { .. } => "usage",
+        ChatDelta::RateInfo(_) => "rate_info",
+        ChatDelta::Retrying { .. } => "retrying",
+    }
+}
+
 fn map_reqwest_err(e: reqwest::Error) -> ChatError {
     if e.is_timeout() {
         ChatError::Timeout(e.to_string())
@@ -408,8 +832,50 @@ fn map_reqwest_err(e: reqwest::Error) -> ChatError {
     }
 }
 
-fn map_status_err(status: StatusCode, body: Option<String>) -> ChatError {
-    let s = format!("{} {}", status.as_u16(), body.unwrap_or_default());
+fn request_id_of(resp: &reqwest::Response) -> Option<String> {
+    resp.headers()
+        .get("x-request-id")
+        .and_then(|v| v.to_str().ok())
+        .map(|s| s.to_string())
+}
+
+// Remaining request/token budget from x-ratelimit-* headers; unparsable values are ignored.
+fn rate_info_of(resp: &reqwest::Response) -> Option<RateInfo> {
+    let num = |name: &str| {
+        resp.headers()
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|s| s.trim().parse::<u32>().ok())
+    };
+    let info = RateInfo {
+        remaining_requests: num("x-ratelimit-remaining-requests"),
+        remaining_tokens: num("x-ratelimit-remaining-tokens"),
+    };
+    if info == RateInfo::default() {
+        None
+    } else {
+        Some(info)
+    }
+}
+
+fn map_status_err(
+    status: StatusCode,
+    request_id: Option<String>,
+    body: Option<String>,
+) -> ChatError {
+    // Error bodies can echo the request's key back
+    let body = httplog::scrub(&body.unwrap_or_default());
+    let mut detail = describe_error_body(&body);
+    if detail.is_empty() {
+        detail = status
+            .canonical_reason()
+            .unwrap_or("no error body")
+            .to_string();
+    }
+    let mut s = format!("{} {}", status.as_u16(), detail);
+    if let Some(id) = request_id {
+        s.push_str(&format!(" [request id: {}]", id));
+    }
     match status {
         StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => ChatError::Auth(s),
         StatusCode::TOO_MANY_REQUESTS => ChatError::RateLimit(s),
@@ -417,22 +883,61 @@ fn map_status_err(status: StatusCode, body: Option<String>) -> ChatError {
         | StatusCode::BAD_GATEWAY
         | StatusCode::SERVICE_UNAVAILABLE
         | StatusCode::GATEWAY_TIMEOUT => ChatError::Network(s),
-        StatusCode::NOT_FOUND => ChatError::Protocol("404".into()),
+        StatusCode::NOT_FOUND => ChatError::Protocol(s),
         _ => ChatError::Other(s),
     }
 }
 
+// Turn an OpenAI error envelope `{"error":{"message","type","code"}}` into a
+// readable line; anything else is returned trimmed as-is.
+fn describe_error_body(body: &str) -> String {
+    let raw = body.trim();
+    let Ok(v) = serde_json::from_str::<serde_json::Value>(raw) else {
+        return raw.to_string();
+    };
+    let err = &v["error"];
+    let Some(message) = err["message"].as_str().or_else(|| err.as_str()) else {
+        return raw.to_string();
+    };
+    let mut details = Vec::new();
+    if let Some(t) = err["type"].as_str() {
+        details.push(format!("type={}", t));
+    }
+    match &err["code"] {
+        serde_json::Value::String(c) => details.push(format!("code={}", c)),
+        serde_json::Value::Number(n) => details.push(format!("code={}", n)),
+        _ => {}
+    }
+    if details.is_empty() {
+        message.to_string()
+    } else {
+        format!("{} ({})", message, details.join(", "))
+    }
+}
+
+// End of the first event; servers may mix CRLF and LF framing, so the
+// earlier of the two blank lines wins.
 fn find_event_boundary(buf: &bytes::BytesMut) -> Option<usize> {
-    if let Some(p) = twoway::find_bytes(&buf, b"\r\n\r\n") {
-        return Some(p);
+    let crlf = twoway::find_bytes(buf, b"\r\n\r\n");
+    let lf = twoway::find_bytes(buf, b"\n\n");
+    match (crlf, lf) {
+        (Some(a), Some(b)) => Some(a.min(b)),
+        (a, b) => a.or(b),
     }
-    twoway::find_bytes(&buf, b"\n\n")
 }
 
 fn parse_chat_sse_event(ev: &bytes::Bytes) -> Result<Option<ChatDelta>, ChatError> {
     let s = std::str::from_utf8(ev).map_err(|e| ChatError::Decode(e.to_string()))?;
     let mut data_lines = Vec::new();
+    let mut is_error_event = false;
     for line in s.lines() {
+        // `: ping` and other comment lines are keep-alives
+        if line.starts_with(':') {
+            continue;
+        }
+        if let Some(rest) = line.strip_prefix("event:") {
+            is_error_event = rest.trim() == "error";
+        }
         if let Some(rest) = line.strip_prefix("data:") {
             data_lines.push(rest.trim_start());
         }
@@ -444,12 +949,27 @@ fn parse_chat_sse_event(ev: &bytes::Bytes) -> Result<Option<ChatDelta>, ChatErro
         return Ok(Some(ChatDelta::Finish(None)));
     }
     let json_text = data_lines.join("\n");
-    let v: serde_json::Value =
-        serde_json::from_str(&json_text).map_err(|e| ChatError::Decode(e.to_string()))?;
-    if let Some(content) = v["choices"][0]["delta"]["content"].as_str() {
+    let v = match serde_json::from_str::<serde_json::Value>(&json_text) {
+        Ok(v) => v,
+        // A plain-text payload under `event: error` is still an error
+        Err(_) if is_error_event => return Err(ChatError::Protocol(json_text.trim().to_string())),
+        Err(e) => return Err(ChatError::Decode(e.to_string())),
+    };
+    // Mid-stream failures arrive as `{"error": {...}}` instead of a chunk
+    if is_error_event || !v["error"].is_null() {
+        return Err(ChatError::Protocol(describe_error_body(&json_text)));
+    }
+    let delta = &v["choices"][0]["delta"];
+    if let Some(r) = delta["reasoning_content"]
+        .as_str()
+        .filter(|r| !r.is_empty())
+    {
+        return Ok(Some(ChatDelta::Reasoning(r.to_string())));
+    }
+    if let Some(content) = delta["content"].as_str() {
         return Ok(Some(ChatDelta::Text(content.to_string())));
     }
-    if let Some(role) = v["choices"][0]["delta"]["role"].as_str() {
+    if let Some(role) = delta["role"].as_str() {
         let r = match role {
             "user" => Role::User,
             "assistant" => Role::Assistant,
@@ -464,9 +984,12 @@ fn parse_chat_sse_event(ev: &bytes::Bytes) -> Result<Option<ChatDelta>, ChatErro
     Ok(None)
 }
 
-fn parse_responses_event(buf: &mut bytes::BytesMut) -> Result<Option<(String, String)>, ChatError> {
+fn parse_responses_event(
+    buf: &mut bytes::BytesMut,
+    log: Option<&HttpLog>,
+) -> Result<Option<(String, String)>, ChatError> {
     // Extract one SSE block (terminated by a blank line), parse event+data.
-    let content = match std::str::from_utf8(&buf) {
+    let content = match std::str::from_utf8(buf) {
         Ok(s) => s,
         Err(_) => return Ok(None),
     };
@@ -478,6 +1001,9 @@ fn parse_responses_event(buf: &mut bytes::BytesMut) -> Result<Option<(String, St
         return Ok(None);
     };
     let block = &content[..block_end];
+    if let Some(l) = log {
+        l.event(block.as_bytes());
+    }
 
     let mut event: Option<String> = None;
     let mut data_lines: Vec<&str> = Vec::new();
@@ -504,7 +1030,8 @@ fn parse_responses_event(buf: &mut bytes::BytesMut) -> Result<Option<(String, St
     };
 
     // Prepare returned `data` based on the event kind for convenience.
-    let ret = if ev == "response.output_text.delta" {
+    let ret = if ev == "response.output_text.delta" || ev == "response.reasoning_summary_text.delta"
+    {
         if data_text.trim().starts_with('{') {
             if let Ok(v) = serde_json::from_str::<serde_json::Value>(&data_text) {
                 v["delta"].as_str().unwrap_or("").to_string()
@@ -514,6 +1041,11 @@ fn parse_responses_event(buf: &mut bytes::BytesMut) -> Result<Option<(String, St
         } else {
             data_text.clone()
         }
+    } else if ev == "response.output_text.done" {
+        match serde_json::from_str::<serde_json::Value>(&data_text) {
+            Ok(v) => v["text"].as_str().unwrap_or("").to_string(),
+            Err(_) => data_text.clone(),
+        }
     } else if ev == "response.error" {
         if data_text.trim().starts_with('{') {
             if let Ok(v) = serde_json::from_str::<serde_json::Value>(&data_text) {
@@ -537,6 +1069,7 @@ fn parse_responses_event(buf: &mut bytes::BytesMut) -> Result<Option<(String, St
     if ev.is_empty() {
         return Ok(None);
     }
+    trace!(target:"providers::openai","responses event {} ({} bytes)", ev, ret.len());
     Ok(Some((ev, ret)))
 }
 
@@ -548,10 +1081,8 @@ fn dedup_delta(acc: &str, delta: &str) -> Option<String> {
     let max_k = std::cmp::min(acc.len(), delta.len());
     // Iterate over valid char boundaries of delta prefix to avoid UTF‑8 slicing issues
     let mut best = 0usize;
-    let mut idx = 0usize;
     for (i, _) in delta.char_indices() {
         // i is a char boundary
-        idx = i;
         if i > max_k {
             break;
         }
@@ -590,3 +1121,389 @@ fn extract_usage_tokens(v: &serde_json::Value) -> (Option<u32>, Option<u32>) {
     }
     (pt, ct)
 }
+
+#[cfg(test)]
+mod tests {
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::sync::mpsc;
+
+    use super::*;
+    use crate::openai::config::DEFAULT_USER_AGENT;
+
+    // Local HTTP server answering each connection with the next canned
+    // `(status line, content type, body)`; sends back the request head
+    // (request line and headers, lowercased) of each one.
+    fn serve(
+        replies: Vec<(&'static str, &'static str, &'static str)>,
+    ) -> (String, mpsc::Receiver<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base = format!("http://{}/v1", listener.local_addr().unwrap());
+        let (tx, rx) = mpsc::channel();
+        std::thread::spawn(move || {
+            for (status, content_type, body) in replies {
+                let Ok((stream, _)) = listener.accept() else {
+                    return;
+                };
+                let mut reader = BufReader::new(stream);
+                let mut head = String::new();
+                let mut len = 0usize;
+                loop {
+                    let mut line = String::new();
+                    if reader.read_line(&mut line).unwrap_or(0) == 0 || line == "\r\n" {
+                        break;
+                    }
+                    let line = line.to_lowercase();
+                    if let Some(v) = line.strip_prefix("content-length:") {
+                        len = v.trim().parse().unwrap_or(0);
+                    }
+                    head.push_str(&line);
+                }
+                let mut request_body = vec![0; len];
+                let _ = reader.read_exact(&mut request_body);
+                let _ = tx.send(head);
+                let mut stream = reader.into_inner();
+                let _ = write!(
+                    stream,
+                    "HTTP/1.1 {}\r\ncontent-type: {}\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                    status,
+                    content_type,
+                    body.len(),
+                    body
+                );
+            }
+        });
+        (base, rx)
+    }
+
+    const CHAT_REPLY: &str = r#"{"choices":[{"message":{"content":"hi"}}]}"#;
+
+    fn opts() -> ChatOpts {
+        ChatOpts {
+            model: "gpt-test".into(),
+            temperature: None,
+            top_p: None,
+            max_tokens: None,
+            reasoning_summary: false,
+        }
+    }
+
+    fn probed(base: &str) -> Option<ChatWire> {
+        probed_wires().lock().unwrap().get(base).copied()
+    }
+
+    #[test]
+    fn only_missing_endpoints_count_as_unsupported() {
+        let other = |s: &str| ChatError::Other(s.into());
+        assert!(responses_unsupported(&ChatError::Protocol(
+            "404 Not Found".into()
+        )));
+        assert!(responses_unsupported(&other("405 Method Not Allowed")));
+        assert!(responses_unsupported(&other(
+            "400 Unsupported endpoint: /v1/responses"
+        )));
+        assert!(!responses_unsupported(&other(
+            "400 Unsupported parameter: temperature"
+        )));
+        assert!(!responses_unsupported(&other(
+            "400 invalid model [request id: req_404]"
+        )));
+        assert!(!responses_unsupported(&ChatError::Auth(
+            "401 bad key".into()
+        )));
+    }
+
+    #[tokio::test]
+    async fn auto_remembers_chat_only_when_the_fallback_starts() {
+        let (base, heads) = serve(vec![
+            ("404 Not Found", "application/json", "{}"),
+            (
+                "400 Bad Request",
+                "application/json",
+                r#"{"error":{"message":"bad"}}"#,
+            ),
+        ]);
+        let client = OpenAiClient::new(OpenAiConfig::for_tests(base.clone())).unwrap();
+        let res = client
+            .stream_auto(Vec::new(), opts(), CancellationToken::new())
+            .await;
+        assert!(res.is_err());
+        assert!(heads.recv().unwrap().starts_with("post /v1/responses"));
+        assert!(heads
+            .recv()
+            .unwrap()
+            .starts_with("post /v1/chat/completions"));
+        assert_eq!(probed(&base), None, "a failed fallback is not cached");
+
+        let (base, _heads) = serve(vec![
+            ("404 Not Found", "application/json", "{}"),
+            ("200 OK", "text/event-stream", "data: [DONE]\n\n"),
+        ]);
+        let client = OpenAiClient::new(OpenAiConfig::for_tests(base.clone())).unwrap();
+        let res = client
+            .stream_auto(Vec::new(), opts(), CancellationToken::new())
+            .await;
+        assert!(res.is_ok());
+        assert_eq!(probed(&base), Some(ChatWire::Chat));
+    }
+
+    #[tokio::test]
+    async fn a_missing_responses_endpoint_is_asked_once() {
+        let (base, heads) = serve(vec![
+            ("404 Not Found", "application/json", "{}"),
+            ("200 OK", "text/event-stream", "data: [DONE]\n\n"),
+        ]);
+        let mut cfg = OpenAiConfig::for_tests(base);
+        cfg.stream_max_retries = 5;
+        let client = OpenAiClient::new(cfg).unwrap();
+        let res = client
+            .stream_auto(Vec::new(), opts(), CancellationToken::new())
+            .await;
+        assert!(res.is_ok());
+        assert!(heads.recv().unwrap().starts_with("post /v1/responses"));
+        assert!(heads
+            .recv()
+            .unwrap()
+            .starts_with("post /v1/chat/completions"));
+        assert!(heads.try_recv().is_err());
+
+        // The explicit Responses wire does not retry a 404 either
+        let (base, heads) = serve(vec![
+            ("404 Not Found", "application/json", "{}"),
+            ("200 OK", "text/event-stream", "data: [DONE]\n\n"),
+        ]);
+        let mut cfg = OpenAiConfig::for_tests(base);
+        cfg.stream_max_retries = 5;
+        let client = OpenAiClient::new(cfg).unwrap();
+        let res = client
+            .stream_chat(
+                Vec::new(),
+                opts(),
+                ChatWire::Responses,
+                CancellationToken::new(),
+            )
+            .await;
+        let mut stream = first_output(res).await.unwrap();
+        while stream.next().await.is_some() {}
+        assert!(heads.recv().unwrap().starts_with("post /v1/responses"));
+        assert!(heads
+            .recv()
+            .unwrap()
+            .starts_with("post /v1/chat/completions"));
+    }
+
+    #[test]
+    fn error_bodies_read_as_one_line() {
+        let envelope = r#"{"error":{"message":"Rate limit reached","type":"requests","code":"rate_limit_exceeded"}}"#;
+        assert_eq!(
+            describe_error_body(envelope),
+            "Rate limit reached (type=requests, code=rate_limit_exceeded)"
+        );
+        assert_eq!(
+            describe_error_body(r#"{"error":{"message":"bad","code":400}}"#),
+            "bad (code=400)"
+        );
+        // Some gateways send the message as the whole error
+        assert_eq!(
+            describe_error_body(r#"{"error":"no such model"}"#),
+            "no such model"
+        );
+        // Plain text, and JSON that is not an error envelope, stay as they are
+        assert_eq!(
+            describe_error_body("  upstream connect error\n"),
+            "upstream connect error"
+        );
+        assert_eq!(
+            describe_error_body(r#"{"detail":"x"}"#),
+            r#"{"detail":"x"}"#
+        );
+        // Bodies are decoded lossily before they get here
+        let lossy = String::from_utf8_lossy(&[b'o', b'k', 0xff, 0xfe]).into_owned();
+        assert_eq!(describe_error_body(&lossy), "ok\u{fffd}\u{fffd}");
+    }
+
+    #[test]
+    fn status_errors_keep_kind_body_and_request_id() {
+        let e = map_status_err(
+            StatusCode::TOO_MANY_REQUESTS,
+            Some("req_1".into()),
+            Some(r#"{"error":{"message":"slow down","type":"tokens"}}"#.into()),
+        );
+        assert!(
+            matches!(&e, ChatError::RateLimit(s) if s == "429 slow down (type=tokens) [request id: req_1]"),
+            "{:?}",
+            e
+        );
+        let e = map_status_err(
+            StatusCode::BAD_GATEWAY,
+            None,
+            Some("<html>bad gateway</html>".into()),
+        );
+        assert!(
+            matches!(&e, ChatError::Network(s) if s == "502 <html>bad gateway</html>"),
+            "{:?}",
+            e
+        );
+        // No body, or an unreadable one: the status reason stands in
+        let e = map_status_err(StatusCode::SERVICE_UNAVAILABLE, None, Some("  ".into()));
+        assert!(
+            matches!(&e, ChatError::Network(s) if s == "503 Service Unavailable"),
+            "{:?}",
+            e
+        );
+        let e = map_status_err(StatusCode::UNAUTHORIZED, None, None);
+        assert!(
+            matches!(&e, ChatError::Auth(s) if s == "401 Unauthorized"),
+            "{:?}",
+            e
+        );
+    }
+
+    #[tokio::test]
+    async fn embeddings_keep_input_order_across_batches() {
+        let (base, heads) = serve(vec![
+            (
+                "200 OK",
+                "application/json",
+                r#"{"data":[{"index":1,"embedding":[1.0]},{"index":0,"embedding":[0.0]}]}"#,
+            ),
+            (
+                "200 OK",
+                "application/json",
+                r#"{"data":[{"index":0,"embedding":[2.0]}],"usage":{"prompt_tokens":1}}"#,
+            ),
+        ]);
+        let client = OpenAiClient::new(OpenAiConfig::for_tests(base)).unwrap();
+        let inputs: Vec<String> = ["a", "b", "c"].iter().map(|s| s.to_string()).collect();
+        let out = client.embed_batched(&inputs, "emb", 2).await.unwrap();
+        assert_eq!(out, vec![vec![0.0], vec![1.0], vec![2.0]]);
+        for _ in 0..2 {
+            assert!(heads.recv().unwrap().starts_with("post /v1/embeddings"));
+        }
+        assert!(heads.try_recv().is_err());
+    }
+
+    #[test]
+    fn embeddings_with_a_missing_or_stray_index_fail() {
+        let v = serde_json::json!({"data":[{"index":0,"embedding":[0.5]}]});
+        assert!(
+            matches!(parse_embeddings(&v, 2), Err(ChatError::Decode(e)) if e.contains("input 1"))
+        );
+        let v = serde_json::json!({"data":[{"index":3,"embedding":[0.5]}]});
+        assert!(
+            matches!(parse_embeddings(&v, 1), Err(ChatError::Decode(e)) if e.contains("index 3"))
+        );
+    }
+
+    // A DeepSeek-style stream: reasoning and answer deltas interleaved,
+    // keep-alive comments between events, CRLF framing on some of them.
+    const INTERLEAVED_SSE: &str = ": keep-alive\n\n\
+        data: {\"choices\":[{\"delta\":{\"role\":\"assistant\"}}]}\n\n\
+        data: {\"choices\":[{\"delta\":{\"reasoning_content\":\"Think\",\"content\":null}}]}\n\n\
+        : ping\r\n\r\n\
+        data: {\"choices\":[{\"delta\":{\"reasoning_content\":\"\",\"content\":\"Hel\"}}]}\r\n\r\n\
+        data: {\"choices\":[{\"delta\":{\"reasoning_content\":\" more\"}}]}\n\n\
+        : comment before data\n\
+        data: {\"choices\":[{\"delta\":{\"content\":\"lo\"}}]}\n\n\
+        data: {\"choices\":[{\"delta\":{},\"finish_reason\":\"stop\"}]}\n\n\
+        data: [DONE]\n\n";
+
+    // Errors reported inside a 200 stream, after some output
+    const ERROR_OBJECT_SSE: &str = "data: {\"choices\":[{\"delta\":{\"content\":\"par\"}}]}\n\n\
+        data: {\"error\":{\"message\":\"overloaded\",\"type\":\"server_error\"}}\n\n";
+    const ERROR_EVENT_SSE: &str = "event: error\ndata: upstream reset\n\n";
+
+    // Each event of `stream` run through the parser, as "kind:payload"
+    fn parse_fixture(stream: &str) -> Vec<String> {
+        let mut buf = bytes::BytesMut::from(stream.as_bytes());
+        let mut out = Vec::new();
+        while let Some(pos) = find_event_boundary(&buf) {
+            let ev = buf.split_to(pos).freeze();
+            let _ = if buf.starts_with(b"\r\n\r\n") {
+                buf.split_to(4)
+            } else {
+                buf.split_to(2)
+            };
+            out.push(match parse_chat_sse_event(&ev) {
+                Ok(None) => continue,
+                Ok(Some(ChatDelta::Text(t))) => format!("text:{}", t),
+                Ok(Some(ChatDelta::Reasoning(r))) => format!("reasoning:{}", r),
+                Ok(Some(ChatDelta::Finish(f))) => format!("finish:{}", f.unwrap_or_default()),
+                Ok(Some(d)) => delta_kind(&d).to_string(),
+                Err(e) => format!("error:{:?}", e),
+            });
+        }
+        out
+    }
+
+    #[test]
+    fn chat_sse_fixture_with_reasoning_and_comments() {
+        assert_eq!(
+            parse_fixture(INTERLEAVED_SSE),
+            [
+                "role",
+                "reasoning:Think",
+                "text:Hel",
+                "reasoning: more",
+                "text:lo",
+                "finish:stop",
+                "finish:",
+            ]
+        );
+    }
+
+    #[test]
+    fn chat_sse_fixture_with_errors_in_the_stream() {
+        assert_eq!(
+            parse_fixture(ERROR_OBJECT_SSE),
+            [
+                "text:par",
+                r#"error:Protocol("overloaded (type=server_error)")"#,
+            ]
+        );
+        assert_eq!(
+            parse_fixture(ERROR_EVENT_SSE),
+            [r#"error:Protocol("upstream reset")"#]
+        );
+        // Noise that is neither JSON nor an error event is a decode error,
+        // which the stream skips unless it repeats
+        let bad = parse_fixture("data: {not json\n\n");
+        assert!(bad[0].starts_with("error:Decode("), "{:?}", bad);
+    }
+
+    #[tokio::test]
+    async fn sends_the_configured_user_agent() {
+        let (base, heads) = serve(vec![("200 OK", "application/json", CHAT_REPLY)]);
+        let mut cfg = OpenAiConfig::for_tests(base);
+        cfg.user_agent = "gateway-test/1.0".into();
+        let client = OpenAiClient::new(cfg).unwrap();
+        let res = client.send_chat(&[], &opts()).await.unwrap();
+        assert_eq!(res.text, "hi");
+        let head = heads.recv().unwrap();
+        assert!(
+            head.contains("user-agent: gateway-test/1.0\r\n"),
+            "{}",
+            head
+        );
+        assert!(head.contains("authorization: bearer sk-test\r\n"));
+        assert!(!head.contains(CLIENT_SESSION_HEADER));
+    }
+
+    #[tokio::test]
+    async fn session_header_is_opt_in_and_stable() {
+        let (base, heads) = serve(vec![
+            ("200 OK", "application/json", CHAT_REPLY),
+            ("200 OK", "application/json", CHAT_REPLY),
+        ]);
+        let mut cfg = OpenAiConfig::for_tests(base);
+        cfg.client_session_header = true;
+        let client = OpenAiClient::new(cfg).unwrap();
+        let expected = format!("{}: {}\r\n", CLIENT_SESSION_HEADER, client_session_id());
+        for _ in 0..2 {
+            client.send_chat(&[], &opts()).await.unwrap();
+            let head = heads.recv().unwrap();
+            assert!(head.contains(&expected), "{}", head);
+            assert!(head.contains(&format!("user-agent: {}\r\n", DEFAULT_USER_AGENT)));
+        }
+    }
+}
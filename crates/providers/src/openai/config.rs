@@ -1,6 +1,12 @@
 use directories::BaseDirs;
 use serde::Deserialize;
-use std::{env, fs, path::PathBuf, time::Duration};
+use std::{
+    collections::BTreeMap,
+    env, fs, io,
+    path::{Path, PathBuf},
+    sync::RwLock,
+    time::Duration,
+};
 
 #[derive(Clone, Debug, Deserialize)]
 pub struct OpenAiFileConfig {
@@ -12,9 +18,220 @@ pub struct OpenAiFileConfig {
     pub timeout_ms: Option<u64>,
     pub model_providers: Option<serde_json::Value>,
     pub model_suggestions: Option<Vec<String>>, // optional list of model names for pickers
+    pub log_content: Option<String>,            // "off" | "truncated" | "full"
+    pub update_check: Option<bool>,             // daily release check (default off)
+    pub update_url: Option<String>,             // releases API returning tag_name/body
+    pub transcript: Option<TranscriptTemplates>, // [transcript] export/print formatting
+    pub log_requests: Option<bool>,             // wire-level logging to fast-http.log
+    pub log_body_max_bytes: Option<usize>,      // truncate logged bodies/events past this size
+    pub cache: Option<bool>,                    // reuse replies to identical requests
+    pub cache_ttl_secs: Option<u64>,
+    pub cache_max_bytes: Option<u64>, // total size of the cache dir
+    pub user_agent: Option<String>,   // default fast-cli/<version>
+    pub client_session_header: Option<bool>, // send X-Client-Session (default off)
+    pub collapse_preview_lines: Option<usize>, // lines shown of a collapsed message
+    pub collapse_threshold_lines: Option<usize>, // longer messages start collapsed
+    pub render_markdown: Option<bool>, // style assistant Markdown (default off)
+    pub code_line_numbers: Option<bool>, // number lines in fenced code (Markdown on)
+    pub compact_width: Option<u16>,   // compact layout below this many columns (0: never)
+    pub clipboard: Option<String>,    // "auto" (system, OSC 52 fallback) | "osc52"
+    pub timestamp_style: Option<String>, // "absolute" (default) | "relative"
+    pub confirm_quit: Option<bool>,   // Esc twice to quit with unsent input
+    pub max_context_messages: Option<usize>, // drop the oldest turns past this many messages
+    pub max_context_tokens: Option<usize>, // ...or past this many estimated tokens
+    pub paste_attach_lines: Option<usize>, // longer pastes wait under the input (0: never)
+    pub paste_attach_bytes: Option<usize>, // ...as do pastes past this size
+    // [env] for processes the TUI spawns; never applied to requests
+    pub env: Option<BTreeMap<String, String>>,
+    // [profile.<name>] endpoints switched to with /profile
+    pub profile: Option<BTreeMap<String, ProfileConfig>>,
 }
 
-#[derive(Clone, Debug)]
+// `[profile.<name>]`: an alternate endpoint. Unset keys keep the top-level value.
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct ProfileConfig {
+    pub base_url: Option<String>, // replaces model_provider's endpoint
+    pub model: Option<String>,
+    pub wire_api: Option<String>,
+    pub api_key_env: Option<String>, // checked before OPENAI_API_KEY
+    pub user_agent: Option<String>,
+    pub client_session_header: Option<bool>,
+}
+
+impl OpenAiFileConfig {
+    // Parsed config.toml, if present and valid.
+    pub fn load() -> Option<Self> {
+        Self::load_checked().ok().flatten()
+    }
+
+    // Profile names in config.toml, sorted.
+    pub fn profile_names() -> Vec<String> {
+        Self::load()
+            .and_then(|c| c.profile)
+            .map(|p| p.into_keys().collect())
+            .unwrap_or_default()
+    }
+
+    // Parsed config.toml, None when there is none; a file that does not
+    // parse is an error naming the file, line and key.
+    pub fn load_checked() -> anyhow::Result<Option<Self>> {
+        match OpenAiConfig::config_path() {
+            Some(path) => Self::load_from(&path),
+            None => Ok(None),
+        }
+    }
+
+    // Like `load_checked` for a given file. The default location may be
+    // missing; a file named by --config or FAST_CONFIG must exist, and any
+    // other read error (permissions, a directory) is reported.
+    pub fn load_from(path: &Path) -> anyhow::Result<Option<Self>> {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound && !path_overridden() => return Ok(None),
+            Err(e) => anyhow::bail!("{}: {}", path.display(), e),
+        };
+        toml::from_str(&text)
+            .map(Some)
+            .map_err(|e| anyhow::anyhow!("{}: {}", path.display(), describe_toml_error(&text, &e)))
+    }
+}
+
+// "line 4 (timeout_ms): invalid type: string \"30s\", expected u64"
+fn describe_toml_error(text: &str, e: &toml::de::Error) -> String {
+    let Some(span) = e.span() else {
+        return e.message().to_string();
+    };
+    let start = span.start.min(text.len());
+    let line_no = text[..start].matches('\n').count() + 1;
+    let line = text.lines().nth(line_no - 1).unwrap_or("");
+    match line.split_once('=') {
+        Some((key, _)) if !key.trim().is_empty() && !key.contains('[') => {
+            format!("line {} ({}): {}", line_no, key.trim(), e.message())
+        }
+        _ => format!("line {}: {}", line_no, e.message()),
+    }
+}
+
+// `[transcript]` table: per-role overrides for exported and printed transcripts.
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct TranscriptTemplates {
+    pub user_label: Option<String>,
+    pub assistant_label: Option<String>,
+    pub user_prefix: Option<String>, // plain/terminal line prefix
+    pub assistant_prefix: Option<String>, // plain/terminal line prefix
+    pub heading_level: Option<u8>,   // markdown heading level per turn (1-6)
+    pub rules: Option<bool>,         // horizontal rule between turns
+}
+
+// `[profile.<name>]` applied on top of the file; None is the top level.
+static ACTIVE_PROFILE: RwLock<Option<String>> = RwLock::new(None);
+
+pub fn set_active_profile(name: Option<String>) {
+    if let Ok(mut p) = ACTIVE_PROFILE.write() {
+        *p = name;
+    }
+}
+
+pub fn active_profile() -> Option<String> {
+    ACTIVE_PROFILE.read().ok().and_then(|p| p.clone())
+}
+
+// FAST_WIRE, if set; anything but a known wire is an error.
+pub fn env_wire() -> anyhow::Result<Option<String>> {
+    let Some(w) = env::var(WIRE_ENV).ok().filter(|w| !w.trim().is_empty()) else {
+        return Ok(None);
+    };
+    let w = w.trim().to_lowercase();
+    if !matches!(w.as_str(), "responses" | "chat" | "auto") {
+        anyhow::bail!("{} '{}' must be one of responses, chat, auto", WIRE_ENV, w);
+    }
+    Ok(Some(w))
+}
+
+fn path_overridden() -> bool {
+    env_config_path().is_some()
+}
+
+fn env_config_path() -> Option<PathBuf> {
+    env::var_os(CONFIG_ENV)
+        .filter(|p| !p.is_empty())
+        .map(PathBuf::from)
+}
+
+// Alternate config.toml; `--config <path>` sets it for the whole process
+pub const CONFIG_ENV: &str = "FAST_CONFIG";
+
+// Override `model` and `wire_api` of config.toml, e.g. in CI
+pub const MODEL_ENV: &str = "FAST_MODEL";
+pub const WIRE_ENV: &str = "FAST_WIRE";
+
+pub const DEFAULT_PROVIDER_ID: &str = "openai";
+
+pub const DEFAULT_CACHE_TTL_SECS: u64 = 24 * 60 * 60;
+pub const DEFAULT_CACHE_MAX_BYTES: u64 = 8 * 1024 * 1024;
+
+pub const DEFAULT_LOG_BODY_MAX_BYTES: usize = 16 * 1024;
+
+pub const DEFAULT_USER_AGENT: &str = concat!("fast-cli/", env!("CARGO_PKG_VERSION"));
+pub const DEFAULT_UPDATE_URL: &str = "https://api.github.com/repos/z23cc/fast-cli/releases/latest";
+
+// OpenAI-compatible endpoint selected by `model_provider` in config.toml.
+#[derive(Clone, Copy, Debug)]
+pub struct ProviderPreset {
+    pub id: &'static str,
+    // Shown in the status bar
+    pub label: &'static str,
+    pub base_url: &'static str,
+    // Checked before OPENAI_API_KEY
+    pub api_key_env: &'static str,
+    // No Responses endpoint: wire_api is forced to "chat"
+    pub chat_only: bool,
+    // Picker entries; the first is the default model
+    pub models: &'static [&'static str],
+}
+
+pub const PRESETS: &[ProviderPreset] = &[
+    ProviderPreset {
+        id: "deepseek",
+        label: "DeepSeek",
+        base_url: "https://api.deepseek.com/v1",
+        api_key_env: "DEEPSEEK_API_KEY",
+        chat_only: true,
+        models: &["deepseek-chat", "deepseek-reasoner"],
+    },
+    ProviderPreset {
+        id: "groq",
+        label: "Groq",
+        base_url: "https://api.groq.com/openai/v1",
+        api_key_env: "GROQ_API_KEY",
+        chat_only: true,
+        models: &[
+            "llama-3.3-70b-versatile",
+            "llama-3.1-8b-instant",
+            "gemma2-9b-it",
+        ],
+    },
+    ProviderPreset {
+        id: "mistral",
+        label: "Mistral",
+        base_url: "https://api.mistral.ai/v1",
+        api_key_env: "MISTRAL_API_KEY",
+        chat_only: true,
+        models: &[
+            "mistral-large-latest",
+            "mistral-small-latest",
+            "codestral-latest",
+        ],
+    },
+];
+
+pub fn preset(id: &str) -> Option<&'static ProviderPreset> {
+    let id = id.trim().to_lowercase();
+    PRESETS.iter().find(|p| p.id == id)
+}
+
+// Debug is written out below so the key never reaches a log line.
+#[derive(Clone)]
 pub struct OpenAiConfig {
     pub api_key: String,
     pub base_url: String,
@@ -25,76 +242,224 @@ pub struct OpenAiConfig {
     pub stream_idle_timeout: Duration,
     pub proxy: Option<String>,
     pub model_suggestions: Vec<String>,
+    pub log_content: String, // "off" | "truncated" | "full"
+    pub update_check: bool,
+    pub update_url: String,
+    pub transcript: TranscriptTemplates,
+    pub log_requests: bool,
+    pub log_body_max_bytes: usize,
+    pub cache: bool,
+    pub cache_ttl: Duration,
+    pub cache_max_bytes: u64,
+    pub user_agent: String,
+    pub client_session_header: bool,
+    // Active `model_provider` preset, if any
+    pub preset: Option<&'static ProviderPreset>,
+    // Active `[profile.<name>]`, if any
+    pub profile: Option<String>,
+}
+
+impl std::fmt::Debug for OpenAiConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OpenAiConfig")
+            .field("api_key", &"<redacted>")
+            .field("base_url", &self.base_url)
+            .field("model", &self.model)
+            .field("wire_api", &self.wire_api)
+            .field("timeout", &self.timeout)
+            .field("stream_max_retries", &self.stream_max_retries)
+            .field("stream_idle_timeout", &self.stream_idle_timeout)
+            .field("proxy", &self.proxy)
+            .field("model_suggestions", &self.model_suggestions)
+            .field("log_content", &self.log_content)
+            .field("update_check", &self.update_check)
+            .field("update_url", &self.update_url)
+            .field("transcript", &self.transcript)
+            .field("log_requests", &self.log_requests)
+            .field("log_body_max_bytes", &self.log_body_max_bytes)
+            .field("cache", &self.cache)
+            .field("cache_ttl", &self.cache_ttl)
+            .field("cache_max_bytes", &self.cache_max_bytes)
+            .field("user_agent", &self.user_agent)
+            .field("client_session_header", &self.client_session_header)
+            .field("preset", &self.preset.map(|p| p.id))
+            .field("profile", &self.profile)
+            .finish()
+    }
 }
 
 impl OpenAiConfig {
-    pub fn from_env_and_file() -> anyhow::Result<Self> {
-        let api_key =
-            env::var("OPENAI_API_KEY").map_err(|_| anyhow::anyhow!("OPENAI_API_KEY not set"))?;
-        let base_url =
-            env::var("OPENAI_BASE_URL").unwrap_or_else(|_| "https://api.openai.com/v1".to_string());
+    // `path` reads that file, which must exist, instead of `config_path()`.
+    // Model and wire come from FAST_MODEL / FAST_WIRE, else the active
+    // profile, else the file; the endpoint from the profile, else
+    // OPENAI_BASE_URL, else the preset.
+    pub fn from_env_and_file(path: Option<&Path>) -> anyhow::Result<Self> {
+        let file = match path {
+            Some(p) => Some(
+                OpenAiFileConfig::load_from(p)?
+                    .ok_or_else(|| anyhow::anyhow!("{}: not found", p.display()))?,
+            ),
+            None => OpenAiFileConfig::load_checked()?,
+        };
+        let profile_name = active_profile();
+        let profile = match &profile_name {
+            Some(name) => Some(
+                file.as_ref()
+                    .and_then(|c| c.profile.as_ref())
+                    .and_then(|p| p.get(name))
+                    .cloned()
+                    .ok_or_else(|| anyhow::anyhow!("no [profile.{}] in config.toml", name))?,
+            ),
+            None => None,
+        };
+        // A profile with its own endpoint is not the model_provider preset
+        let own_endpoint = profile.as_ref().and_then(|p| p.base_url.as_ref()).is_some();
+        let preset = file
+            .as_ref()
+            .and_then(|c| c.model_provider.as_deref())
+            .and_then(preset)
+            .filter(|_| !own_endpoint);
+        let key_env = profile
+            .as_ref()
+            .and_then(|p| p.api_key_env.as_deref())
+            .or(preset.map(|p| p.api_key_env));
+        let api_key = key_env
+            .and_then(|k| env::var(k).ok())
+            .or_else(|| env::var("OPENAI_API_KEY").ok())
+            .ok_or_else(|| match key_env {
+                Some(k) => anyhow::anyhow!("{} (or OPENAI_API_KEY) not set", k),
+                None => anyhow::anyhow!("OPENAI_API_KEY not set"),
+            })?;
+        let base_url = match profile.as_ref().and_then(|p| p.base_url.clone()) {
+            Some(url) => url,
+            None => env::var("OPENAI_BASE_URL").unwrap_or_else(|_| {
+                preset
+                    .map(|p| p.base_url)
+                    .unwrap_or("https://api.openai.com/v1")
+                    .to_string()
+            }),
+        };
 
-        let mut model = "gpt-5".to_string();
+        let mut model = preset
+            .and_then(|p| p.models.first().copied())
+            .unwrap_or("gpt-5")
+            .to_string();
         let mut wire_api = "responses".to_string();
         let mut timeout_ms = 30_000u64;
         let mut stream_max_retries = 5u32;
         let mut stream_idle_timeout_ms = 300_000u64;
+        let mut log_content = "off".to_string();
+        let mut update_check = false;
+        let mut update_url = DEFAULT_UPDATE_URL.to_string();
+        let mut transcript = TranscriptTemplates::default();
+        let mut log_requests = false;
+        let mut log_body_max_bytes = DEFAULT_LOG_BODY_MAX_BYTES;
+        let mut cache = false;
+        let mut cache_ttl_secs = DEFAULT_CACHE_TTL_SECS;
+        let mut cache_max_bytes = DEFAULT_CACHE_MAX_BYTES;
+        let mut user_agent = DEFAULT_USER_AGENT.to_string();
+        let mut client_session_header = false;
 
-        if let Some(path) = Self::config_path() {
-            if path.exists() {
-                if let Ok(toml) = fs::read_to_string(&path) {
-                    if let Ok(file_cfg) = toml::from_str::<OpenAiFileConfig>(&toml) {
-                        if let Some(m) = file_cfg.model {
-                            model = m;
-                        }
-                        if let Some(w) = file_cfg.wire_api {
-                            wire_api = w;
-                        }
-                        if let Some(t) = file_cfg.timeout_ms {
-                            timeout_ms = t;
-                        }
-                        if let Some(r) = file_cfg.stream_max_retries {
-                            stream_max_retries = r;
-                        }
-                        if let Some(idle) = file_cfg.stream_idle_timeout_ms {
-                            stream_idle_timeout_ms = idle;
-                        }
-                        // Suggestions (top-level list) if present
-                        let suggestions = file_cfg.model_suggestions.unwrap_or_default();
-                        if !suggestions.is_empty() {
-                            // We'll set them later in return struct
-                        }
-                    }
-                }
+        if let Some(file_cfg) = file.clone() {
+            if let Some(m) = file_cfg.model {
+                model = m;
+            }
+            if let Some(w) = file_cfg.wire_api {
+                wire_api = w;
+            }
+            if let Some(t) = file_cfg.timeout_ms {
+                timeout_ms = t;
+            }
+            if let Some(r) = file_cfg.stream_max_retries {
+                stream_max_retries = r;
+            }
+            if let Some(idle) = file_cfg.stream_idle_timeout_ms {
+                stream_idle_timeout_ms = idle;
+            }
+            if let Some(lc) = file_cfg.log_content {
+                log_content = lc;
+            }
+            if let Some(u) = file_cfg.update_check {
+                update_check = u;
+            }
+            if let Some(u) = file_cfg.update_url {
+                update_url = u;
+            }
+            if let Some(t) = file_cfg.transcript {
+                transcript = t;
+            }
+            if let Some(l) = file_cfg.log_requests {
+                log_requests = l;
+            }
+            if let Some(n) = file_cfg.log_body_max_bytes {
+                log_body_max_bytes = n;
+            }
+            if let Some(c) = file_cfg.cache {
+                cache = c;
+            }
+            if let Some(t) = file_cfg.cache_ttl_secs {
+                cache_ttl_secs = t;
+            }
+            if let Some(n) = file_cfg.cache_max_bytes {
+                cache_max_bytes = n;
+            }
+            if let Some(ua) = file_cfg.user_agent.filter(|u| !u.trim().is_empty()) {
+                user_agent = ua;
+            }
+            if let Some(h) = file_cfg.client_session_header {
+                client_session_header = h;
+            }
+            // Suggestions (top-level list) if present
+            let suggestions = file_cfg.model_suggestions.unwrap_or_default();
+            if !suggestions.is_empty() {
+                // We'll set them later in return struct
             }
         }
 
         // Optionally read suggestions from model_providers map if not provided directly
         let mut model_suggestions: Vec<String> = Vec::new();
-        if let Some(path) = Self::config_path() {
-            if path.exists() {
-                if let Ok(toml) = fs::read_to_string(&path) {
-                    if let Ok(file_cfg) = toml::from_str::<OpenAiFileConfig>(&toml) {
-                        if let Some(list) = file_cfg.model_suggestions {
-                            model_suggestions = list;
-                        } else if let Some(mp) = file_cfg.model_providers {
-                            // Try common shapes: { openai: { suggestions: [..] } }
-                            if let Some(openai) = mp.get("openai") {
-                                if let Some(arr) =
-                                    openai.get("suggestions").and_then(|v| v.as_array())
-                                {
-                                    model_suggestions = arr
-                                        .iter()
-                                        .filter_map(|v| v.as_str().map(|s| s.to_string()))
-                                        .collect();
-                                }
-                            }
-                        }
+        if let Some(file_cfg) = file {
+            if let Some(list) = file_cfg.model_suggestions {
+                model_suggestions = list;
+            } else if let Some(mp) = file_cfg.model_providers {
+                // Try common shapes: { openai: { suggestions: [..] } }
+                if let Some(openai) = mp.get("openai") {
+                    if let Some(arr) = openai.get("suggestions").and_then(|v| v.as_array()) {
+                        model_suggestions = arr
+                            .iter()
+                            .filter_map(|v| v.as_str().map(|s| s.to_string()))
+                            .collect();
                     }
                 }
             }
         }
 
+        if let Some(p) = profile {
+            if let Some(m) = p.model {
+                model = m;
+            }
+            if let Some(w) = p.wire_api {
+                wire_api = w;
+            }
+            if let Some(ua) = p.user_agent.filter(|u| !u.trim().is_empty()) {
+                user_agent = ua;
+            }
+            if let Some(h) = p.client_session_header {
+                client_session_header = h;
+            }
+        }
+
+        if let Some(m) = env::var(MODEL_ENV).ok().filter(|m| !m.trim().is_empty()) {
+            model = m;
+        }
+        if let Some(w) = env_wire()? {
+            wire_api = w;
+        }
+
+        if preset.is_some_and(|p| p.chat_only) {
+            wire_api = "chat".to_string();
+        }
+
         let proxy = env::var("HTTPS_PROXY")
             .ok()
             .or_else(|| env::var("HTTP_PROXY").ok());
@@ -109,10 +474,63 @@ impl OpenAiConfig {
             stream_idle_timeout: Duration::from_millis(stream_idle_timeout_ms),
             proxy,
             model_suggestions,
+            log_content,
+            update_check,
+            update_url,
+            transcript,
+            log_requests,
+            log_body_max_bytes,
+            cache,
+            cache_ttl: Duration::from_secs(cache_ttl_secs),
+            cache_max_bytes,
+            user_agent,
+            client_session_header,
+            preset,
+            profile: profile_name,
         })
     }
 
-    fn config_path() -> Option<PathBuf> {
+    // Settings that would otherwise only fail at request time.
+    pub fn validate(&self) -> anyhow::Result<()> {
+        let url = url::Url::parse(&self.base_url).map_err(|e| {
+            anyhow::anyhow!("base_url '{}' is not a valid URL: {}", self.base_url, e)
+        })?;
+        if !matches!(url.scheme(), "http" | "https") {
+            anyhow::bail!("base_url '{}' must use http or https", self.base_url);
+        }
+        if !matches!(self.wire_api.as_str(), "responses" | "chat" | "auto") {
+            anyhow::bail!(
+                "wire_api '{}' must be one of responses, chat, auto",
+                self.wire_api
+            );
+        }
+        Ok(())
+    }
+
+    // Registry id of the active provider: the preset id, or "openai".
+    pub fn provider_id(&self) -> &'static str {
+        self.preset.map(|p| p.id).unwrap_or(DEFAULT_PROVIDER_ID)
+    }
+
+    pub fn provider_label(&self) -> &'static str {
+        self.preset.map(|p| p.label).unwrap_or("OpenAI")
+    }
+
+    pub fn preset_models(&self) -> Vec<String> {
+        self.preset
+            .map(|p| p.models.iter().map(|m| m.to_string()).collect())
+            .unwrap_or_default()
+    }
+
+    pub fn chat_only(&self) -> bool {
+        self.preset.is_some_and(|p| p.chat_only)
+    }
+
+    // FAST_CONFIG (which --config sets), then the per-user default.
+    pub fn config_path() -> Option<PathBuf> {
+        if let Some(p) = env_config_path() {
+            return Some(p);
+        }
         let base = BaseDirs::new()?;
         let p = if cfg!(target_os = "windows") {
             base.home_dir().join(".fast").join("config.toml")
@@ -122,3 +540,168 @@ impl OpenAiConfig {
         Some(p)
     }
 }
+
+#[cfg(test)]
+impl OpenAiConfig {
+    // Fixed settings for tests; no file or environment involved.
+    pub(crate) fn for_tests(base_url: String) -> Self {
+        OpenAiConfig {
+            api_key: "sk-test".into(),
+            base_url,
+            model: "gpt-test".into(),
+            wire_api: "chat".into(),
+            timeout: Duration::from_secs(5),
+            stream_max_retries: 1,
+            stream_idle_timeout: Duration::from_secs(5),
+            proxy: None,
+            model_suggestions: Vec::new(),
+            log_content: "off".into(),
+            update_check: false,
+            update_url: String::new(),
+            transcript: TranscriptTemplates::default(),
+            log_requests: false,
+            log_body_max_bytes: 1024,
+            cache: false,
+            cache_ttl: Duration::ZERO,
+            cache_max_bytes: 0,
+            user_agent: DEFAULT_USER_AGENT.into(),
+            client_session_header: false,
+            preset: None,
+            profile: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn toml_error(text: &str) -> String {
+        let e = toml::from_str::<OpenAiFileConfig>(text).unwrap_err();
+        describe_toml_error(text, &e)
+    }
+
+    // Scratch directory unique to this test process and name
+    fn scratch(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("fast-config-{}-{}", std::process::id(), name));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    // Key variable named by the test files' profiles, so no real key is needed
+    const TEST_KEY_ENV: &str = "FAST_CONFIG_TEST_KEY";
+
+    // `from_env_and_file(Some(path))` with `profile` active. The active
+    // profile is process-wide, so these calls take turns.
+    fn load_with_profile(path: &Path, profile: &str) -> anyhow::Result<OpenAiConfig> {
+        static LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
+        let _turn = LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        env::set_var(TEST_KEY_ENV, "sk-from-test");
+        set_active_profile(Some(profile.to_string()));
+        let res = OpenAiConfig::from_env_and_file(Some(path));
+        set_active_profile(None);
+        res
+    }
+
+    #[test]
+    fn an_explicit_path_is_read_and_must_exist() {
+        let dir = scratch("explicit");
+        let path = dir.join("alt.toml");
+        fs::write(
+            &path,
+            format!(
+                "timeout_ms = 1234\n[profile.p]\napi_key_env = \"{}\"\n",
+                TEST_KEY_ENV
+            ),
+        )
+        .unwrap();
+        let cfg = load_with_profile(&path, "p").unwrap();
+        assert_eq!(cfg.timeout, Duration::from_millis(1234));
+        assert_eq!(cfg.api_key, "sk-from-test");
+        let missing = dir.join("missing.toml");
+        let err = load_with_profile(&missing, "p").unwrap_err().to_string();
+        assert!(err.contains("missing.toml"), "{}", err);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn profile_headers_override_the_top_level() {
+        let dir = scratch("profile-headers");
+        let path = dir.join("config.toml");
+        fs::write(
+            &path,
+            format!(
+                "user_agent = \"global/1.0\"\nclient_session_header = true\n\
+                 [profile.gw]\napi_key_env = \"{0}\"\nuser_agent = \"gateway/2.0\"\n\
+                 client_session_header = false\n\
+                 [profile.plain]\napi_key_env = \"{0}\"\n",
+                TEST_KEY_ENV
+            ),
+        )
+        .unwrap();
+        let gw = load_with_profile(&path, "gw").unwrap();
+        assert_eq!(gw.user_agent, "gateway/2.0");
+        assert!(!gw.client_session_header);
+        // Unset in the profile: the top-level values stay
+        let plain = load_with_profile(&path, "plain").unwrap();
+        assert_eq!(plain.user_agent, "global/1.0");
+        assert!(plain.client_session_header);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn toml_errors_name_the_line_and_key() {
+        let msg = toml_error("model = \"gpt-5\"\n\ntimeout_ms = \"30s\"\n");
+        assert!(msg.starts_with("line 3 (timeout_ms): "), "{}", msg);
+        // Keys inside a table keep their own name
+        let msg = toml_error("[transcript]\nheading_level = \"big\"\n");
+        assert!(msg.starts_with("line 2 (heading_level): "), "{}", msg);
+        // A broken table header has no key to name
+        let msg = toml_error("model = \"gpt-5\"\n[transcript\n");
+        assert!(msg.starts_with("line 2: "), "{}", msg);
+    }
+
+    #[test]
+    fn validate_checks_url_and_wire() {
+        let ok = OpenAiConfig::for_tests("https://api.example.com/v1".into());
+        assert!(ok.validate().is_ok());
+        for wire in ["responses", "chat", "auto"] {
+            let mut c = ok.clone();
+            c.wire_api = wire.into();
+            assert!(c.validate().is_ok(), "{}", wire);
+        }
+        let mut c = ok.clone();
+        c.wire_api = "grpc".into();
+        assert!(c
+            .validate()
+            .unwrap_err()
+            .to_string()
+            .contains("wire_api 'grpc'"));
+        for url in ["api.example.com/v1", "ftp://example.com", ""] {
+            let c = OpenAiConfig::for_tests(url.into());
+            let err = c.validate().unwrap_err().to_string();
+            assert!(err.contains("base_url"), "{}: {}", url, err);
+        }
+    }
+
+    #[test]
+    fn load_from_reports_errors_other_than_a_missing_file() {
+        let dir = scratch("load");
+        assert!(OpenAiFileConfig::load_from(&dir.join("absent.toml"))
+            .unwrap()
+            .is_none());
+        // Unreadable (here: a directory) is an error, not "no config"
+        let err = OpenAiFileConfig::load_from(&dir).unwrap_err().to_string();
+        assert!(err.starts_with(&dir.display().to_string()), "{}", err);
+        let bad = dir.join("bad.toml");
+        fs::write(&bad, "cache = yes\n").unwrap();
+        let err = OpenAiFileConfig::load_from(&bad).unwrap_err().to_string();
+        assert!(err.contains("line 1 (cache)"), "{}", err);
+        let good = dir.join("good.toml");
+        fs::write(&good, "model = \"gpt-x\"\n").unwrap();
+        let file = OpenAiFileConfig::load_from(&good).unwrap().unwrap();
+        assert_eq!(file.model.as_deref(), Some("gpt-x"));
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
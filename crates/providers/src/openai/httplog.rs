@@ -0,0 +1,199 @@
+// Wire-level request/response logging (`log_requests = true`). Events go to
+// the `providers::http` target, which the TUI routes to fast-http.log; every
+// line passes through `redact` first.
+
+use crate::openai::config::OpenAiConfig;
+use tracing::info;
+
+pub const TARGET: &str = "providers::http";
+pub const LOG_FILE: &str = "fast-http.log";
+
+const REDACTED: &str = "<redacted>";
+// JSON keys whose string values are always replaced
+const SECRET_KEYS: [&str; 5] = ["api_key", "apikey", "authorization", "x-api-key", "token"];
+// Key prefixes used by OpenAI-compatible providers (OpenAI, Groq, ...)
+const KEY_PREFIXES: [&str; 2] = ["sk-", "gsk_"];
+// Shorter runs after a prefix are ordinary words (e.g. "sk-learn")
+const MIN_KEY_TAIL: usize = 16;
+
+#[derive(Clone)]
+pub struct HttpLog {
+    api_key: String,
+    max_bytes: usize,
+}
+
+impl std::fmt::Debug for HttpLog {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HttpLog")
+            .field("api_key", &REDACTED)
+            .field("max_bytes", &self.max_bytes)
+            .finish()
+    }
+}
+
+impl HttpLog {
+    pub fn from_config(cfg: &OpenAiConfig) -> Option<Self> {
+        cfg.log_requests.then(|| Self {
+            api_key: cfg.api_key.clone(),
+            max_bytes: cfg.log_body_max_bytes,
+        })
+    }
+
+    pub fn request(&self, url: &str, body: &serde_json::Value) {
+        let mut body = body.clone();
+        redact_json(&mut body);
+        let text = serde_json::to_string(&body).unwrap_or_default();
+        info!(target: TARGET, "POST {} authorization=Bearer {} body={}", url, REDACTED, self.clean(&text));
+    }
+
+    pub fn event(&self, raw: &[u8]) {
+        let text = String::from_utf8_lossy(raw);
+        info!(target: TARGET, "event {}", self.clean(text.trim_end()));
+    }
+
+    fn clean(&self, text: &str) -> String {
+        truncate(&redact(text, &self.api_key), self.max_bytes)
+    }
+}
+
+// `redact` where the configured key is not at hand: patterns only.
+pub fn scrub(text: &str) -> String {
+    redact(text, "")
+}
+
+// Replace the configured key, `Bearer` tokens and provider-style keys.
+pub fn redact(text: &str, api_key: &str) -> String {
+    let mut out = if api_key.len() >= 8 {
+        text.replace(api_key, REDACTED)
+    } else {
+        text.to_string()
+    };
+    out = redact_after(&out, "Bearer ", 1, true);
+    for prefix in KEY_PREFIXES {
+        out = redact_after(&out, prefix, MIN_KEY_TAIL, false);
+    }
+    out
+}
+
+// Replace the token following `marker` when it has at least `min_tail` key
+// characters; the marker itself is dropped too unless `keep_marker`.
+fn redact_after(text: &str, marker: &str, min_tail: usize, keep_marker: bool) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(pos) = rest.find(marker) {
+        let after = &rest[pos + marker.len()..];
+        let tail = after
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.'))
+            .unwrap_or(after.len());
+        out.push_str(&rest[..pos]);
+        if tail >= min_tail {
+            if keep_marker {
+                out.push_str(marker);
+            }
+            out.push_str(REDACTED);
+        } else {
+            out.push_str(&rest[pos..pos + marker.len() + tail]);
+        }
+        rest = &after[tail..];
+    }
+    out.push_str(rest);
+    out
+}
+
+pub fn redact_json(value: &mut serde_json::Value) {
+    match value {
+        serde_json::Value::Object(map) => {
+            for (k, v) in map.iter_mut() {
+                let k = k.to_lowercase();
+                if v.is_string() && SECRET_KEYS.iter().any(|s| k == *s) {
+                    *v = serde_json::Value::String(REDACTED.into());
+                } else {
+                    redact_json(v);
+                }
+            }
+        }
+        serde_json::Value::Array(items) => items.iter_mut().for_each(redact_json),
+        _ => {}
+    }
+}
+
+// Keep the first `max` bytes (on a char boundary) and note what was dropped.
+pub fn truncate(text: &str, max: usize) -> String {
+    if text.len() <= max {
+        return text.to_string();
+    }
+    let mut end = max;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    format!(
+        "{}... [truncated {} of {} bytes]",
+        &text[..end],
+        text.len() - end,
+        text.len()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn redacts_provider_keys_by_prefix() {
+        let text = "key=sk-proj-abcdefghijklmnopqrstuv groq=gsk_ABCDEFGHIJKLMNOPQRST end";
+        assert_eq!(redact(text, ""), "key=<redacted> groq=<redacted> end");
+    }
+
+    #[test]
+    fn keeps_short_words_after_a_prefix() {
+        let text = "pip install sk-learn; use gsk_tool";
+        assert_eq!(redact(text, ""), text);
+    }
+
+    #[test]
+    fn redacts_bearer_tokens_but_keeps_the_scheme() {
+        assert_eq!(
+            redact("Authorization: Bearer abc.def-ghi_jk\nnext", ""),
+            "Authorization: Bearer <redacted>\nnext"
+        );
+        // A bare "Bearer " with no token is left alone
+        assert_eq!(redact("Bearer ", ""), "Bearer ");
+    }
+
+    #[test]
+    fn redacts_the_configured_key_anywhere() {
+        let key = "custom-key-12345";
+        assert_eq!(
+            redact(&format!("a{}b {}", key, key), key),
+            "a<redacted>b <redacted>"
+        );
+        // Too short to replace safely
+        assert_eq!(redact("abc abc", "abc"), "abc abc");
+    }
+
+    #[test]
+    fn redacts_nested_json_secrets() {
+        let mut v = serde_json::json!({
+            "model": "gpt-5",
+            "API_KEY": "plain",
+            "headers": [{"Authorization": "Bearer x"}, {"token": 42}],
+            "nested": {"x-api-key": "k", "note": "keep"}
+        });
+        redact_json(&mut v);
+        assert_eq!(
+            v,
+            serde_json::json!({
+                "model": "gpt-5",
+                "API_KEY": "<redacted>",
+                "headers": [{"Authorization": "<redacted>"}, {"token": 42}],
+                "nested": {"x-api-key": "<redacted>", "note": "keep"}
+            })
+        );
+    }
+
+    #[test]
+    fn truncate_stops_on_a_char_boundary() {
+        assert_eq!(truncate("short", 10), "short");
+        assert_eq!(truncate("日本語", 4), "日... [truncated 6 of 9 bytes]");
+    }
+}
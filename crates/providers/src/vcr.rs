@@ -0,0 +1,262 @@
+// Record-and-replay wrapper around a `ModelClient` (`FAST_VCR=record|replay`).
+// Record mode proxies to the real client and stores each delta sequence in a
+// cassette file; replay mode serves streams from that file without network.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use anyhow::Context;
+use directories::BaseDirs;
+use fast_core::llm::{
+    CancellationToken, ChatDelta, ChatError, ChatOpts, ChatResult, ChatStream, ChatWire, Message,
+    ModelClient,
+};
+use futures::{future::BoxFuture, StreamExt};
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+pub const VCR_ENV: &str = "FAST_VCR";
+// Overrides the cassette location
+pub const VCR_FILE_ENV: &str = "FAST_VCR_FILE";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VcrMode {
+    Record,
+    Replay,
+}
+
+impl VcrMode {
+    pub fn from_env() -> Option<Self> {
+        match std::env::var(VCR_ENV).ok()?.trim().to_lowercase().as_str() {
+            "record" => Some(VcrMode::Record),
+            "replay" => Some(VcrMode::Replay),
+            _ => None,
+        }
+    }
+}
+
+// `$FAST_VCR_FILE`, else `<data dir>/fast/vcr/cassette.json`.
+pub fn cassette_path() -> Option<PathBuf> {
+    if let Some(p) = std::env::var_os(VCR_FILE_ENV) {
+        return Some(PathBuf::from(p));
+    }
+    let base = BaseDirs::new()?;
+    Some(
+        base.data_dir()
+            .join("fast")
+            .join("vcr")
+            .join("cassette.json"),
+    )
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct Cassette {
+    entries: Vec<Entry>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct Entry {
+    // Fingerprint of messages + opts
+    key: String,
+    model: String,
+    messages: usize,
+    deltas: Vec<Result<ChatDelta, ChatError>>,
+}
+
+pub struct RecordingClient<C> {
+    // None in replay mode: nothing is sent
+    inner: Option<C>,
+    path: PathBuf,
+    cassette: Mutex<Cassette>,
+}
+
+impl<C: ModelClient> RecordingClient<C> {
+    pub fn record(inner: C, path: PathBuf) -> anyhow::Result<Self> {
+        let cassette = load_cassette(&path)?;
+        Ok(Self {
+            inner: Some(inner),
+            path,
+            cassette: Mutex::new(cassette),
+        })
+    }
+
+    pub fn replay(path: PathBuf) -> anyhow::Result<Self> {
+        let cassette = load_cassette(&path)?;
+        Ok(Self {
+            inner: None,
+            path,
+            cassette: Mutex::new(cassette),
+        })
+    }
+
+    fn lookup(&self, key: &str) -> Option<Entry> {
+        let cassette = self.cassette.lock().unwrap_or_else(|e| e.into_inner());
+        cassette.entries.iter().find(|e| e.key == key).cloned()
+    }
+
+    fn unmatched(&self, key: &str, msgs: &[Message], opts: &ChatOpts) -> ChatError {
+        ChatError::Other(format!(
+            "vcr: no recording for request {} (model {}, {} messages) in {}; record it with {}=record",
+            key,
+            opts.model,
+            msgs.len(),
+            self.path.display(),
+            VCR_ENV
+        ))
+    }
+
+    // Add or replace the entry for `key` and rewrite the cassette.
+    fn store(&self, entry: Entry) {
+        let mut cassette = self.cassette.lock().unwrap_or_else(|e| e.into_inner());
+        cassette.entries.retain(|e| e.key != entry.key);
+        info!(target:"providers::vcr","recorded {} deltas for {}", entry.deltas.len(), entry.key);
+        cassette.entries.push(entry);
+        if let Err(e) = save_cassette(&self.path, &cassette) {
+            warn!(target:"providers::vcr","cassette write failed: {:#}", e);
+        }
+    }
+}
+
+impl<C: ModelClient> ModelClient for RecordingClient<C> {
+    fn send_chat<'a>(
+        &'a self,
+        msgs: &'a [Message],
+        opts: &'a ChatOpts,
+    ) -> BoxFuture<'a, Result<ChatResult, ChatError>> {
+        Box::pin(async move {
+            let key = fingerprint(msgs, opts);
+            let Some(inner) = &self.inner else {
+                let entry = self
+                    .lookup(&key)
+                    .ok_or_else(|| self.unmatched(&key, msgs, opts))?;
+                return result_of(entry.deltas);
+            };
+            let res = inner.send_chat(msgs, opts).await?;
+            let mut deltas = vec![Ok(ChatDelta::Text(res.text.clone()))];
+            if res.prompt_tokens.is_some() || res.completion_tokens.is_some() {
+                deltas.push(Ok(ChatDelta::Usage {
+                    prompt_tokens: res.prompt_tokens,
+                    completion_tokens: res.completion_tokens,
+                }));
+            }
+            deltas.push(Ok(ChatDelta::Finish(res.finish_reason.clone())));
+            self.store(Entry {
+                key,
+                model: opts.model.clone(),
+                messages: msgs.len(),
+                deltas,
+            });
+            Ok(res)
+        })
+    }
+
+    fn stream_chat<'a>(
+        &'a self,
+        msgs: Vec<Message>,
+        opts: ChatOpts,
+        wire: ChatWire,
+        cancel: CancellationToken,
+    ) -> BoxFuture<'a, Result<ChatStream<'a>, ChatError>> {
+        Box::pin(async move {
+            let key = fingerprint(&msgs, &opts);
+            let Some(inner) = &self.inner else {
+                let entry = self
+                    .lookup(&key)
+                    .ok_or_else(|| self.unmatched(&key, &msgs, &opts))?;
+                info!(target:"providers::vcr","replaying {} deltas for {}", entry.deltas.len(), key);
+                return Ok(Box::pin(futures::stream::iter(entry.deltas)) as ChatStream<'a>);
+            };
+            let model = opts.model.clone();
+            let count = msgs.len();
+            let mut upstream = inner.stream_chat(msgs, opts, wire, cancel).await?;
+            let s = async_stream::stream! {
+                let mut deltas = Vec::new();
+                let mut stored = false;
+                while let Some(d) = upstream.next().await {
+                    let canceled = matches!(d, Err(ChatError::Canceled));
+                    let last = matches!(d, Ok(ChatDelta::Finish(_)) | Err(_));
+                    if !canceled {
+                        deltas.push(d.clone());
+                    }
+                    // Store before yielding the last item: consumers stop polling after it
+                    if last && !canceled {
+                        self.store(Entry { key: key.clone(), model: model.clone(), messages: count, deltas: deltas.clone() });
+                        stored = true;
+                    }
+                    yield d;
+                    if last {
+                        break;
+                    }
+                }
+                if !stored && !deltas.is_empty() {
+                    self.store(Entry { key, model, messages: count, deltas });
+                }
+            };
+            Ok(Box::pin(s) as ChatStream<'a>)
+        })
+    }
+}
+
+fn result_of(deltas: Vec<Result<ChatDelta, ChatError>>) -> Result<ChatResult, ChatError> {
+    let mut res = ChatResult {
+        text: String::new(),
+        finish_reason: None,
+        prompt_tokens: None,
+        completion_tokens: None,
+        rate_info: None,
+    };
+    for d in deltas {
+        match d? {
+            ChatDelta::Text(t) => res.text.push_str(&t),
+            ChatDelta::Finish(r) => res.finish_reason = r,
+            ChatDelta::Usage {
+                prompt_tokens,
+                completion_tokens,
+            } => {
+                res.prompt_tokens = prompt_tokens;
+                res.completion_tokens = completion_tokens;
+            }
+            ChatDelta::RateInfo(r) => res.rate_info = Some(r),
+            ChatDelta::RoleStart(_) | ChatDelta::Reasoning(_) | ChatDelta::Retrying { .. } => {}
+        }
+    }
+    Ok(res)
+}
+
+// FNV-1a over the JSON of messages + opts; stable across builds, unlike
+// `DefaultHasher`, so cassettes stay valid.
+fn fingerprint(msgs: &[Message], opts: &ChatOpts) -> String {
+    let json = serde_json::to_string(&(msgs, opts)).unwrap_or_default();
+    fnv1a(json.as_bytes())
+}
+
+pub(crate) fn fnv1a(data: &[u8]) -> String {
+    let mut h: u64 = 0xcbf2_9ce4_8422_2325;
+    for &b in data {
+        h ^= b as u64;
+        h = h.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+    format!("{:016x}", h)
+}
+
+fn load_cassette(path: &Path) -> anyhow::Result<Cassette> {
+    if !path.exists() {
+        return Ok(Cassette::default());
+    }
+    let data = fs::read(path).with_context(|| format!("read cassette {}", path.display()))?;
+    serde_json::from_slice(&data).with_context(|| format!("parse cassette {}", path.display()))
+}
+
+fn save_cassette(path: &Path, cassette: &Cassette) -> anyhow::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).ok();
+    }
+    let data = serde_json::to_vec_pretty(cassette)?;
+    let tmp = path.with_extension("json.tmp");
+    fs::write(&tmp, data).with_context(|| format!("write {}", tmp.display()))?;
+    fs::rename(&tmp, path).with_context(|| format!("persist cassette {}", path.display()))?;
+    Ok(())
+}
@@ -0,0 +1,343 @@
+use std::{
+    fs,
+    io::Cursor,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{Context, Result};
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use super::{App, NoticeLevel};
+
+// Encoded PNG size cap for a single pasted image
+pub const MAX_ATTACHMENT_BYTES: usize = 8 * 1024 * 1024;
+// Pending (unsent) attachments allowed at once
+pub const MAX_PENDING_ATTACHMENTS: usize = 4;
+
+#[derive(Clone, Debug)]
+pub struct Attachment {
+    pub path: PathBuf,
+    // Display name (original file name for /attach)
+    pub name: String,
+    pub bytes: u64,
+    pub width: u32,
+    pub height: u32,
+}
+
+// Paste over the `paste_attach_*` limits, held under the input and
+// appended to the next message.
+#[derive(Clone, Debug)]
+pub struct PastedText {
+    pub text: String,
+    pub lines: usize,
+    pub tokens: usize,
+}
+
+impl PastedText {
+    fn new(text: String) -> Self {
+        let tokens = fast_core::llm::Message::text(fast_core::llm::Role::User, text.as_str())
+            .estimated_tokens();
+        Self {
+            lines: text.lines().count(),
+            tokens,
+            text,
+        }
+    }
+}
+
+// Image stored with a sent message; re-encoded from `path` on every request.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MessageImage {
+    pub path: PathBuf,
+    pub name: String,
+    pub bytes: u64,
+}
+
+impl App {
+    // Ctrl+V: attach an image from the clipboard, or insert clipboard text.
+    pub fn paste_from_clipboard(&mut self) {
+        let mut clip = match arboard::Clipboard::new() {
+            Ok(c) => c,
+            Err(e) => {
+                warn!(target: "tui", "clipboard unavailable: {}", e);
+                self.notify(
+                    NoticeLevel::Warning,
+                    "clipboard not accessible in this terminal; use /attach <path> for images",
+                );
+                return;
+            }
+        };
+        match clip.get_image() {
+            Ok(img) => {
+                if let Err(e) = self.attach_clipboard_image(img.width, img.height, &img.bytes) {
+                    warn!(target: "tui", "clipboard image attach failed: {}", e);
+                    self.notify(NoticeLevel::Error, format!("image paste failed: {}", e));
+                }
+            }
+            Err(arboard::Error::ContentNotAvailable) => {
+                if let Ok(text) = clip.get_text() {
+                    self.insert_text(&text);
+                }
+            }
+            Err(e) => {
+                warn!(target: "tui", "clipboard image read failed: {}", e);
+                self.notify(
+                    NoticeLevel::Warning,
+                    "could not read an image from the clipboard; use /attach <path> instead",
+                );
+            }
+        }
+    }
+
+    fn attach_clipboard_image(&mut self, width: usize, height: usize, rgba: &[u8]) -> Result<()> {
+        if self.pending_attachments.len() >= MAX_PENDING_ATTACHMENTS {
+            anyhow::bail!("at most {} pending attachments", MAX_PENDING_ATTACHMENTS);
+        }
+        let png = encode_png(width as u32, height as u32, rgba)?;
+        if png.len() > MAX_ATTACHMENT_BYTES {
+            anyhow::bail!(
+                "image is {} KB, limit is {} KB",
+                png.len() / 1024,
+                MAX_ATTACHMENT_BYTES / 1024
+            );
+        }
+        let path = save_png(self.current_session_name(), &png)?;
+        info!(target: "tui", "attached clipboard image {}x{} ({} bytes) at {}", width, height, png.len(), path.display());
+        let name = file_name(&path);
+        self.pending_attachments.push(Attachment {
+            path,
+            name,
+            bytes: png.len() as u64,
+            width: width as u32,
+            height: height as u32,
+        });
+        Ok(())
+    }
+
+    // `/attach <path>`: copy a local PNG/JPEG into the attachments dir and queue it.
+    pub fn attach_file(&mut self, path: &str) {
+        match self.attach_local_image(Path::new(path)) {
+            Ok(()) => {
+                let chips = self.attachment_chips();
+                self.notify(NoticeLevel::Info, format!("attached: {}", chips));
+            }
+            Err(e) => {
+                warn!(target: "tui", "attach {} failed: {:#}", path, e);
+                self.notify(NoticeLevel::Error, format!("attach failed: {:#}", e));
+            }
+        }
+    }
+
+    fn attach_local_image(&mut self, src: &Path) -> Result<()> {
+        if self.pending_attachments.len() >= MAX_PENDING_ATTACHMENTS {
+            anyhow::bail!("at most {} pending attachments", MAX_PENDING_ATTACHMENTS);
+        }
+        if image_mime(src).is_none() {
+            anyhow::bail!("only PNG and JPEG images are supported");
+        }
+        let bytes = fs::metadata(src)
+            .with_context(|| format!("read {}", src.display()))?
+            .len();
+        if bytes as usize > MAX_ATTACHMENT_BYTES {
+            anyhow::bail!(
+                "image is {} KB, limit is {} KB",
+                bytes / 1024,
+                MAX_ATTACHMENT_BYTES / 1024
+            );
+        }
+        let (width, height) =
+            image::image_dimensions(src).with_context(|| format!("decode {}", src.display()))?;
+        let name = file_name(src);
+        let dir = crate::persist::attachment_dir(self.current_session_name())
+            .context("no data dir for attachments")?;
+        fs::create_dir_all(&dir).with_context(|| format!("create {}", dir.display()))?;
+        let path = dir.join(format!("attach-{}-{}", now_millis(), name));
+        fs::copy(src, &path).with_context(|| format!("copy to {}", path.display()))?;
+        info!(target: "tui", "attached {} {}x{} ({} bytes) at {}", src.display(), width, height, bytes, path.display());
+        self.pending_attachments.push(Attachment {
+            path,
+            name,
+            bytes,
+            width,
+            height,
+        });
+        Ok(())
+    }
+
+    // Move pending attachments onto the message being sent.
+    pub fn take_pending_images(&mut self) -> Vec<MessageImage> {
+        self.pending_attachments
+            .drain(..)
+            .map(|a| MessageImage {
+                path: a.path,
+                name: a.name,
+                bytes: a.bytes,
+            })
+            .collect()
+    }
+
+    // Whether a bracketed paste is big enough to be held as an attachment
+    // rather than inserted (a limit of 0 turns that check off).
+    pub(super) fn paste_is_attachment(&self, text: &str) -> bool {
+        let over = |n: usize, limit: usize| limit > 0 && n > limit;
+        over(text.lines().count(), self.paste_attach_lines)
+            || over(text.len(), self.paste_attach_bytes)
+    }
+
+    // Hold `text` for the next message; a second large paste joins the first.
+    pub(super) fn attach_pasted_text(&mut self, text: String) {
+        let text = match self.pending_paste.take() {
+            Some(p) => format!("{}\n\n{}", p.text, text),
+            None => text,
+        };
+        let pasted = PastedText::new(text);
+        info!(target: "tui", "paste held as attachment: {} lines, {} bytes", pasted.lines, pasted.text.len());
+        self.pending_paste = Some(pasted);
+    }
+
+    // Ctrl+D in the input
+    pub fn drop_pasted_text(&mut self) {
+        if self.pending_paste.take().is_some() {
+            self.notify(NoticeLevel::Info, "pasted text dropped");
+        }
+    }
+
+    // Move the held paste onto the message being sent.
+    pub fn take_pasted_text(&mut self) -> Option<String> {
+        self.pending_paste.take().map(|p| p.text)
+    }
+
+    // Chips shown in the input title, e.g. "[image 1: 412×280]".
+    pub fn attachment_chips(&self) -> String {
+        self.pending_attachments
+            .iter()
+            .enumerate()
+            .map(|(i, a)| format!("[image {}: {}×{}]", i + 1, a.width, a.height))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    // Remove files of attachments that were never sent (called on quit).
+    pub fn discard_pending_attachments(&mut self) {
+        for a in self.pending_attachments.drain(..) {
+            let _ = fs::remove_file(&a.path);
+        }
+    }
+}
+
+// Transcript line for a sent image, e.g. "[image: shot.png, 234KB]".
+pub fn image_label(img: &MessageImage) -> String {
+    format!("[image: {}, {}KB]", img.name, img.bytes.div_ceil(1024))
+}
+
+// Data-URL image part for the provider; None (logged) when the file is gone.
+pub fn image_part(img: &MessageImage) -> Option<fast_core::llm::ContentPart> {
+    let mime = image_mime(&img.path)?;
+    match fs::read(&img.path) {
+        Ok(data) => {
+            let b64 = base64::engine::general_purpose::STANDARD.encode(data);
+            Some(fast_core::llm::ContentPart::ImageUrl {
+                url: format!("data:{};base64,{}", mime, b64),
+                detail: None,
+            })
+        }
+        Err(e) => {
+            warn!(target: "tui", "image {} not sent: {}", img.path.display(), e);
+            None
+        }
+    }
+}
+
+fn image_mime(path: &Path) -> Option<&'static str> {
+    let ext = path.extension()?.to_str()?.to_lowercase();
+    match ext.as_str() {
+        "png" => Some("image/png"),
+        "jpg" | "jpeg" => Some("image/jpeg"),
+        _ => None,
+    }
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "image".to_string())
+}
+
+fn now_millis() -> u128 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or(0)
+}
+
+pub fn encode_png(width: u32, height: u32, rgba: &[u8]) -> Result<Vec<u8>> {
+    let img = image::RgbaImage::from_raw(width, height, rgba.to_vec())
+        .context("clipboard image size does not match its pixel data")?;
+    let mut out = Cursor::new(Vec::new());
+    img.write_to(&mut out, image::ImageFormat::Png)
+        .context("encode png")?;
+    Ok(out.into_inner())
+}
+
+fn save_png(session: &str, png: &[u8]) -> Result<PathBuf> {
+    let dir = crate::persist::attachment_dir(session).context("no data dir for attachments")?;
+    fs::create_dir_all(&dir).with_context(|| format!("create {}", dir.display()))?;
+    let path = dir.join(format!("paste-{}.png", now_millis()));
+    fs::write(&path, png).with_context(|| format!("write {}", path.display()))?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{app_with, TempRoot};
+
+    // 2×2 RGBA: red, green, blue, half-transparent white
+    const PIXELS: [u8; 16] = [
+        255, 0, 0, 255, 0, 255, 0, 255, 0, 0, 255, 255, 255, 255, 255, 128,
+    ];
+
+    #[test]
+    fn clipboard_image_is_saved_as_png_and_sent_as_data_url() {
+        let root = TempRoot::new();
+        let mut app = app_with(Vec::new());
+        app.attach_clipboard_image(2, 2, &PIXELS).unwrap();
+
+        let a = &app.pending_attachments[0];
+        assert!(a.path.starts_with(root.path()), "{}", a.path.display());
+        assert!(a.name.starts_with("paste-") && a.name.ends_with(".png"));
+        assert_eq!((a.width, a.height), (2, 2));
+        let saved = fs::read(&a.path).unwrap();
+        assert_eq!(a.bytes, saved.len() as u64);
+        let decoded = image::load_from_memory(&saved).unwrap().to_rgba8();
+        assert_eq!(decoded.dimensions(), (2, 2));
+        assert_eq!(decoded.into_raw(), PIXELS);
+        assert_eq!(app.attachment_chips(), "[image 1: 2×2]");
+
+        let images = app.take_pending_images();
+        assert!(app.pending_attachments.is_empty());
+        let Some(fast_core::llm::ContentPart::ImageUrl { url, .. }) = image_part(&images[0]) else {
+            panic!("no image part");
+        };
+        let b64 = url.strip_prefix("data:image/png;base64,").unwrap();
+        let bytes = base64::engine::general_purpose::STANDARD
+            .decode(b64)
+            .unwrap();
+        assert_eq!(bytes, saved);
+    }
+
+    #[test]
+    fn clipboard_image_with_short_pixel_data_is_refused() {
+        let _root = TempRoot::new();
+        let mut app = app_with(Vec::new());
+        let err = app
+            .attach_clipboard_image(3, 3, &PIXELS)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("does not match"), "{}", err);
+        assert!(app.pending_attachments.is_empty());
+    }
+}
@@ -0,0 +1,190 @@
+use super::{App, BookmarkPickerState, NoticeLevel, Role};
+
+// Max snippet width (chars) shown in the bookmark list and /bookmarks output
+const SNIPPET_CHARS: usize = 60;
+
+impl App {
+    // Toggle the bookmark flag on a message and persist the session.
+    pub fn toggle_bookmark(&mut self, idx: usize) {
+        let Some(m) = self.messages.get_mut(idx) else {
+            return;
+        };
+        m.bookmarked = !m.bookmarked;
+        // Header marker changes the wrapped text; force a rewrap.
+        self.chat_wrap_width = 0;
+        self.save_current_session();
+    }
+
+    // `m` in the chat: toggle the bookmark on the selected message.
+    pub fn toggle_bookmark_selected(&mut self) {
+        if let Some(idx) = self.selected_message {
+            self.toggle_bookmark(idx);
+        }
+    }
+
+    pub fn bookmarked_indices(&self) -> Vec<usize> {
+        self.messages
+            .iter()
+            .enumerate()
+            .filter(|(_, m)| m.bookmarked)
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    pub fn bookmark_label(&self, idx: usize) -> String {
+        let Some(m) = self.messages.get(idx) else {
+            return String::new();
+        };
+        let role = match m.role {
+            Role::User => "user",
+            Role::Assistant => "assistant",
+            Role::System => "system",
+        };
+        format!("#{} {}: {}", idx + 1, role, snippet(&m.content))
+    }
+
+    pub fn open_bookmark_picker(&mut self) {
+        self.bookmark_picker = Some(BookmarkPickerState {
+            items: self.bookmarked_indices(),
+            selected: 0,
+        });
+    }
+
+    // Scroll so the first line of message `idx` sits at the top of the viewport.
+    pub fn jump_to_message(&mut self, idx: usize) {
+        if idx >= self.messages.len() {
+            return;
+        }
+        let Some(area) = self.chat_area else {
+            return;
+        };
+        let inner = self.panel_inner(area);
+        let (inner_w, inner_h) = (inner.width, inner.height);
+        self.ensure_chat_wrapped(inner_w);
+        let global = self.message_global_start(idx);
+        self.set_scroll_to_show_global(inner_h, global);
+    }
+
+    // `/bookmarks`: list bookmarks of the current session as an info block.
+    pub fn push_bookmarks_info(&mut self) {
+        let items = self.bookmarked_indices();
+        if items.is_empty() {
+            self.notify(NoticeLevel::Info, "no bookmarks in this session");
+            return;
+        }
+        let mut text = format!("[info] bookmarks ({}):", items.len());
+        for i in items {
+            text.push('\n');
+            text.push_str(&self.bookmark_label(i));
+        }
+        self.push_info(&text);
+    }
+}
+
+fn snippet(content: &str) -> String {
+    let flat = content.split_whitespace().collect::<Vec<_>>().join(" ");
+    if flat.chars().count() > SNIPPET_CHARS {
+        let cut: String = flat.chars().take(SNIPPET_CHARS).collect();
+        format!("{}...", cut)
+    } else {
+        flat
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+    use ratatui::layout::Rect;
+
+    use crate::app::{Focus, Message};
+    use crate::test_support::{app_with, TempRoot};
+
+    fn press(app: &mut crate::app::App, ch: char) {
+        app.on_key(KeyEvent::new(KeyCode::Char(ch), KeyModifiers::NONE));
+    }
+
+    #[test]
+    fn m_toggles_the_selected_message_and_persists_it() {
+        let _root = TempRoot::new();
+        let mut app = app_with(vec![
+            Message::user("question"),
+            Message::assistant("key answer"),
+        ]);
+        app.focus = Focus::Chat;
+        app.selected_message = Some(1);
+        press(&mut app, 'm');
+        assert_eq!(app.bookmarked_indices(), vec![1]);
+
+        let saved = crate::persist::load_session("test").unwrap();
+        assert!(!saved[0].bookmarked);
+        assert!(saved[1].bookmarked);
+
+        press(&mut app, 'm');
+        assert!(app.bookmarked_indices().is_empty());
+        assert!(!crate::persist::load_session("test").unwrap()[1].bookmarked);
+    }
+
+    #[test]
+    fn bookmark_flag_is_optional_in_session_lines() {
+        let old: Message = serde_json::from_str(r#"{"role":"User","content":"hi"}"#).unwrap();
+        assert!(!old.bookmarked);
+        let mut m = Message::user("hi");
+        assert!(!serde_json::to_string(&m).unwrap().contains("bookmarked"));
+        m.bookmarked = true;
+        let back: Message = serde_json::from_str(&serde_json::to_string(&m).unwrap()).unwrap();
+        assert!(back.bookmarked);
+    }
+
+    #[test]
+    fn quote_opens_the_list_of_bookmarks() {
+        let mut app = app_with(vec![
+            Message::user("a"),
+            Message::assistant("b"),
+            Message::user("c"),
+        ]);
+        app.messages[0].bookmarked = true;
+        app.messages[2].bookmarked = true;
+        app.focus = Focus::Chat;
+        press(&mut app, '\'');
+        assert_eq!(app.bookmark_picker.as_ref().unwrap().items, vec![0, 2]);
+        assert_eq!(app.bookmark_label(2), "#3 user: c");
+    }
+
+    #[test]
+    fn bookmarks_listing_is_not_sent_to_the_model() {
+        let _root = TempRoot::new();
+        let mut app = app_with(vec![
+            Message::user("question"),
+            Message::assistant("answer-in-bookmark"),
+        ]);
+        app.messages[1].bookmarked = true;
+        app.dry_run = true;
+        app.push_bookmarks_info();
+        assert!(app.messages[2].is_info());
+        app.send_prompt("next", vec!["m".into()]);
+        let body = &app.messages.last().unwrap().content;
+        assert!(!body.contains("[info]"), "{}", body);
+        // The reply itself goes once, not again inside the listing
+        assert_eq!(body.matches("answer-in-bookmark").count(), 1, "{}", body);
+    }
+
+    #[test]
+    fn jump_puts_the_target_on_top_past_collapsed_messages() {
+        let long = vec!["row"; 60].join("\n");
+        let mut msgs = vec![Message::user("first"), Message::assistant(long)];
+        msgs.push(Message::user("target"));
+        msgs.extend((0..30).map(|i| Message::assistant(format!("after {}", i))));
+        let mut app = app_with(msgs);
+        app.chat_area = Some(Rect::new(0, 0, 40, 12));
+        app.jump_to_message(2);
+
+        // The 60-row reply folds to its preview plus the indicator row
+        assert!(app.collapsed[1]);
+        let preview = app.collapse_preview_lines;
+        assert_eq!(app.message_global_start(2), 1 + preview + 1);
+        let inner_h = app.panel_inner(Rect::new(0, 0, 40, 12)).height;
+        let top = app.compute_chat_layout(inner_h).start;
+        assert_eq!(top, app.message_global_start(2));
+        assert_eq!(app.message_at_global(top), Some(2));
+    }
+}
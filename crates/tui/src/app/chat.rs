@@ -1,121 +1,788 @@
 use textwrap::{wrap, Options};
 use unicode_width::UnicodeWidthStr;
 
-use crate::strings::{PREFIX_ASSISTANT, PREFIX_USER};
+use crate::strings::{
+    model_label, reasoning_summary, BOOKMARK_MARKER, PREFIX_ASSISTANT, PREFIX_SYSTEM, PREFIX_USER,
+    REASONING_HEADER,
+};
 
-use super::{App, Message, Role, WrappedMsg};
+use super::{App, Focus, Message, NoticeLevel, Role, WrappedMsg};
+use crate::chat_layout::{self, Fold, MsgLines, Window};
+
+// Columns moved per Left/Right while lines are not wrapped
+const HSCROLL_STEP: u16 = 4;
+// Wrap width standing in for "no wrapping": only newlines break lines
+const NO_WRAP_WIDTH: u16 = u16::MAX;
+
+#[cfg(test)]
+thread_local! {
+    // `wrap_message` calls on this thread, for the rewrap tests
+    static WRAP_CALLS: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+}
 
 impl App {
-    // Compute displayed lines for a message considering collapse/threshold rules.
-    pub fn message_display_info(&self, idx: usize) -> (usize, bool) {
-        let base = self.chat_cache.get(idx).map(|w| w.lines.len()).unwrap_or(0);
-        let collapsed = self.collapsed.get(idx).copied().unwrap_or(false);
-        let preview = self.collapse_preview_lines;
-        let threshold = self.collapse_threshold_lines;
-        let display = if collapsed && base > preview {
-            preview
-        } else {
-            base
-        };
-        let has_indicator = if collapsed && base > preview {
-            true
-        } else {
-            !collapsed && base > threshold
-        };
-        (display, has_indicator)
+    pub fn chat_fold(&self) -> Fold {
+        Fold {
+            preview: self.collapse_preview_lines,
+            threshold: self.collapse_threshold_lines,
+        }
+    }
+
+    // Folded row counts of every wrapped message, for `chat_layout`.
+    pub fn chat_lines(&self) -> Vec<MsgLines> {
+        let fold = self.chat_fold();
+        self.chat_cache
+            .iter()
+            .enumerate()
+            .map(|(i, w)| {
+                let collapsed = self.collapsed.get(i).copied().unwrap_or(false);
+                MsgLines::new(w.lines.len(), collapsed, fold)
+            })
+            .collect()
     }
 
     // Total effective lines including indicators.
     pub fn effective_total_lines(&self) -> usize {
-        let mut total = 0usize;
-        for i in 0..self.chat_cache.len() {
-            let (d, ind) = self.message_display_info(i);
-            total += d + if ind { 1 } else { 0 };
-        }
-        total
+        chat_layout::total(&self.chat_lines())
     }
 
-    // Compute viewport, max_scroll, and start_offset from current scroll state.
-    pub fn compute_chat_layout(&self, inner_height: u16) -> (usize, u16, usize, usize) {
-        let viewport = inner_height.saturating_sub(0).max(1) as usize;
-        let effective_total = self.effective_total_lines();
-        let max_scroll = effective_total.saturating_sub(viewport) as u16;
-        let distance_from_bottom = if self.stick_to_bottom {
-            0
-        } else {
-            self.chat_scroll.min(max_scroll)
-        };
-        let start_offset = max_scroll.saturating_sub(distance_from_bottom) as usize;
-        (viewport, max_scroll, start_offset, effective_total)
+    // Global (effective) line index where message `idx` starts.
+    pub fn message_global_start(&self, idx: usize) -> usize {
+        chat_layout::start_of(&self.chat_lines(), idx)
+    }
+
+    // Message index owning a global (effective) line, indicators included.
+    pub fn message_at_global(&self, global: usize) -> Option<usize> {
+        chat_layout::resolve(&self.chat_lines(), global).map(|p| p.msg)
+    }
+
+    // Viewport and top line for the current scroll state.
+    pub fn compute_chat_layout(&self, inner_height: u16) -> Window {
+        chat_layout::window(
+            self.effective_total_lines(),
+            inner_height,
+            self.chat_scroll,
+            self.stick_to_bottom,
+        )
     }
 
     // Adjust chat_scroll to bring a global line index into view.
     pub fn set_scroll_to_show_global(&mut self, inner_height: u16, global_line: usize) {
-        let (viewport, max_scroll, _start, effective_total) =
-            self.compute_chat_layout(inner_height);
-        let _ = viewport; // not used further, but kept for clarity
-        if effective_total == 0 {
-            self.chat_scroll = 0;
-            self.stick_to_bottom = true;
+        let total = self.effective_total_lines();
+        self.chat_scroll = chat_layout::scroll_to_show(total, inner_height, global_line);
+        self.stick_to_bottom = self.chat_scroll == 0;
+    }
+
+    // `/wrap` and the palette, for the current session. Unwrapped, messages
+    // are "wrapped" only at their own newlines and the view scrolls
+    // sideways instead.
+    pub fn set_wrap_lines(&mut self, on: bool) {
+        self.wrap_lines = on;
+        self.chat_hscroll = 0;
+        self.chat_wrap_width = 0;
+        self.save_session_view(|v| v.wrap = Some(on));
+    }
+
+    // Left/Right in the chat while unwrapped, up to the widest line.
+    pub fn scroll_chat_sideways(&mut self, dir: i32) {
+        if dir < 0 {
+            self.chat_hscroll = self.chat_hscroll.saturating_sub(HSCROLL_STEP);
             return;
         }
-        let y_offset = global_line.min(effective_total.saturating_sub(1));
-        self.chat_scroll = max_scroll.saturating_sub(y_offset as u16).min(max_scroll);
-        self.stick_to_bottom = self.chat_scroll == 0;
+        let widest = self
+            .chat_cache
+            .iter()
+            .flat_map(|w| &w.lines)
+            .map(|l| UnicodeWidthStr::width(l.as_str()))
+            .max()
+            .unwrap_or(0);
+        let max = widest.saturating_sub(1).min(u16::MAX as usize) as u16;
+        self.chat_hscroll = self.chat_hscroll.saturating_add(HSCROLL_STEP).min(max);
     }
+
+    // Keep `chat_cache` in step with `messages`. Only a width change (or
+    // `chat_wrap_width = 0`, set wherever messages are removed or replaced)
+    // wraps everything again; new messages are appended and a streaming
+    // reply is re-wrapped on its own.
     pub fn ensure_chat_wrapped(&mut self, width: u16) {
-        let width = width.max(1);
-        if self.chat_wrap_width != width || self.chat_cache.len() != self.messages.len() {
+        let mut width = if self.wrap_lines {
+            width.max(1)
+        } else {
+            NO_WRAP_WIDTH
+        };
+        // Resizing: keep the rows of the old width until the new one holds
+        // for a tick, rather than rewrapping everything on every step
+        let settled = self.wrap_pending.take() == Some(width);
+        if self.chat_wrap_width != 0 && self.chat_wrap_width != width && !settled {
+            self.wrap_pending = Some(width);
+            width = self.chat_wrap_width;
+        }
+        let mut rewrapped = false;
+        if self.chat_wrap_width != width || self.chat_cache.len() > self.messages.len() {
+            rewrapped = true;
             self.chat_cache.clear();
-            for m in &self.messages {
-                self.chat_cache.push(Self::wrap_message(m, width));
-            }
-            self.chat_total_lines = self.chat_cache.iter().map(|w| w.lines.len()).sum();
+            self.chat_total_lines = 0;
             self.chat_wrap_width = width;
-            if self.collapsed.len() != self.messages.len() {
-                let old_len = self.collapsed.len();
-                self.collapsed.resize(self.messages.len(), false);
-                for i in old_len..self.messages.len() {
-                    let lines = self.chat_cache.get(i).map(|w| w.lines.len()).unwrap_or(0);
-                    self.collapsed[i] = lines > self.collapse_threshold_lines;
-                }
+            // Its rows no longer mean the same lines
+            self.visual = None;
+        }
+        let appended = self.chat_cache.len();
+        rewrapped |= appended < self.messages.len();
+        for m in &self.messages[appended..] {
+            let w = self.wrap_message(m, width);
+            self.chat_total_lines += w.lines.len();
+            self.chat_cache.push(w);
+        }
+        if self.collapsed.len() != self.messages.len() {
+            let old_len = self.collapsed.len();
+            self.collapsed.resize(self.messages.len(), false);
+            for i in old_len..self.messages.len() {
+                let lines = self.chat_cache.get(i).map(|w| w.lines.len()).unwrap_or(0);
+                self.collapsed[i] = lines > self.collapse_threshold_lines;
             }
+        }
+        // Re-wrap messages that grew since the last frame; several can
+        // stream at once (`/compare`), so only the tail of the list is scanned
+        let streaming_from = self
+            .messages
+            .iter()
+            .rposition(|m| m.role == Role::User)
+            .unwrap_or(0)
+            .min(appended);
+        let fold = self.chat_fold();
+        let mut grown = 0usize;
+        for idx in streaming_from..appended {
+            let (m, w) = (&self.messages[idx], &self.chat_cache[idx]);
+            if m.content.len() == w.content_len && m.reasoning.len() == w.reasoning_len {
+                continue;
+            }
+            rewrapped = true;
+            let collapsed = self.collapsed.get(idx).copied().unwrap_or(false);
+            let old = w.lines.len();
+            let old_rows = MsgLines::new(old, collapsed, fold).effective();
+            let markdown = self.render_markdown && m.role == Role::Assistant;
+            if markdown || !extend_raw(m, &mut self.chat_cache[idx], width) {
+                self.chat_cache[idx] = self.wrap_message(m, width);
+            }
+            let new = self.chat_cache[idx].lines.len();
+            let rows = MsgLines::new(new, collapsed, fold).effective();
+            self.chat_total_lines = self.chat_total_lines - old + new;
+            grown += rows.saturating_sub(old_rows);
+        }
+        // Scrolled up: keep the view where it is and flag the new lines
+        if !self.stick_to_bottom && grown > 0 {
+            self.chat_scroll = self.chat_scroll.saturating_add(grown);
+            self.new_below = true;
+        }
+        // Hits are byte offsets into wrapped rows; rows that changed need
+        // fresh ones
+        if rewrapped && self.search_query.is_some() {
+            self.recompute_search_hits();
+            self.search_current = self
+                .search_current
+                .min(self.search_hits.len().saturating_sub(1));
+        }
+    }
+
+    // Lines the chat can scroll up from the bottom at the current viewport.
+    fn max_chat_scroll(&self) -> usize {
+        self.effective_total_lines()
+            .saturating_sub(self.chat_viewport.max(1))
+    }
+
+    // Scroll up `n` lines, stopping at the first line.
+    pub fn scroll_chat_up(&mut self, n: usize) {
+        let max = self.max_chat_scroll();
+        self.chat_scroll = self.chat_scroll.min(max).saturating_add(n).min(max);
+        self.stick_to_bottom = self.chat_scroll == 0;
+    }
+
+    // Scroll down `n` lines; reaching the bottom follows the stream again.
+    pub fn scroll_chat_down(&mut self, n: usize) {
+        self.chat_scroll = self
+            .chat_scroll
+            .min(self.max_chat_scroll())
+            .saturating_sub(n);
+        self.stick_to_bottom = self.chat_scroll == 0;
+    }
+
+    // Ctrl+Home: the first line of the conversation at the top.
+    pub fn scroll_to_top(&mut self) {
+        self.chat_scroll = self.max_chat_scroll();
+        self.stick_to_bottom = self.chat_scroll == 0;
+    }
+
+    // End / Ctrl+End: back to the bottom, following the stream again.
+    pub fn jump_to_latest(&mut self) {
+        self.chat_scroll = 0;
+        self.stick_to_bottom = true;
+        self.new_below = false;
+    }
+
+    // Apply whichever of the two is given, keeping preview < threshold;
+    // returns false (and changes nothing) otherwise.
+    pub fn set_collapse_lines(&mut self, preview: Option<usize>, threshold: Option<usize>) -> bool {
+        let p = preview.unwrap_or(self.collapse_preview_lines);
+        let t = threshold.unwrap_or(self.collapse_threshold_lines);
+        if p == 0 || p >= t {
+            return false;
+        }
+        self.collapse_preview_lines = p;
+        self.collapse_threshold_lines = t;
+        true
+    }
+
+    // `/collapse [preview|threshold <n>]`
+    pub fn collapse_command(&mut self, arg: &str) {
+        let mut words = arg.split_whitespace();
+        let (key, value) = (
+            words.next(),
+            words.next().and_then(|v| v.parse::<usize>().ok()),
+        );
+        let applied = match (key, value) {
+            (None, _) => {
+                self.notify(
+                    NoticeLevel::Info,
+                    format!(
+                        "collapse: preview {} lines, threshold {} lines",
+                        self.collapse_preview_lines, self.collapse_threshold_lines
+                    ),
+                );
+                return;
+            }
+            (Some("preview"), Some(n)) => self.set_collapse_lines(Some(n), None),
+            (Some("threshold"), Some(n)) => self.set_collapse_lines(None, Some(n)),
+            _ => {
+                self.notify(
+                    NoticeLevel::Info,
+                    "usage: /collapse [preview|threshold <n>]",
+                );
+                return;
+            }
+        };
+        if !applied {
+            self.notify(
+                NoticeLevel::Info,
+                format!(
+                    "preview must be at least 1 and below the threshold (now {} / {})",
+                    self.collapse_preview_lines, self.collapse_threshold_lines
+                ),
+            );
+            return;
+        }
+        self.collapse_overridden = true;
+        self.mark_state_dirty();
+        self.notify(
+            NoticeLevel::Info,
+            format!(
+                "collapse: preview {} lines, threshold {} lines",
+                self.collapse_preview_lines, self.collapse_threshold_lines
+            ),
+        );
+        // Re-evaluate every message against the new threshold on the next wrap
+        self.collapsed.clear();
+        self.chat_wrap_width = 0;
+    }
+
+    pub(super) fn chat_inner_height(&self) -> Option<u16> {
+        self.chat_area.map(|a| self.panel_inner(a).height)
+    }
+
+    // Chat footer for the selected message: (chars, estimated tokens). Kept
+    // until the selection or the message's length changes, so frames do not
+    // recount it.
+    pub fn selected_message_stats(&mut self) -> Option<(usize, usize)> {
+        let idx = self.selected_message?;
+        let m = self.messages.get(idx)?;
+        let key = (idx, m.content.len());
+        if let Some((k, stats)) = self.selected_stats {
+            if k == key {
+                return Some(stats);
+            }
+        }
+        let chars = m.content.chars().count();
+        let tokens = fast_core::llm::Message::text(fast_core::llm::Role::User, m.content.as_str())
+            .estimated_tokens();
+        self.selected_stats = Some((key, (chars, tokens)));
+        Some((chars, tokens))
+    }
+
+    // Start selection on the first message visible in the chat pane.
+    pub fn select_message_at_view_top(&mut self) {
+        let top = self
+            .chat_inner_height()
+            .and_then(|h| self.message_at_global(self.compute_chat_layout(h).start));
+        self.selected_message = top.or(self.messages.len().checked_sub(1));
+    }
+
+    // Move the selection by `delta` messages and scroll it into view.
+    pub fn select_message_step(&mut self, delta: isize) {
+        let Some(last) = self.messages.len().checked_sub(1) else {
+            self.selected_message = None;
+            return;
+        };
+        let idx = match self.selected_message {
+            Some(i) => i.min(last).saturating_add_signed(delta).min(last),
+            None => last,
+        };
+        self.selected_message = Some(idx);
+        self.reveal_message(idx);
+    }
+
+    // Alt+Up / Alt+Down, and Alt+U for user turns only: put the first line
+    // of the previous / next message at the top of the view. A message
+    // scrolled partly past the top counts as the previous one.
+    pub fn jump_message(&mut self, forward: bool, user_only: bool) {
+        let Some(h) = self.chat_inner_height() else {
+            return;
+        };
+        let lines = self.chat_lines();
+        let top = self.compute_chat_layout(h).start;
+        let Some(cur) = chat_layout::resolve(&lines, top).map(|p| p.msg) else {
             return;
+        };
+        let wanted =
+            |i: &usize| !user_only || self.messages.get(*i).is_some_and(|m| m.role == Role::User);
+        let target = if forward {
+            (cur + 1..lines.len()).find(wanted)
+        } else if chat_layout::start_of(&lines, cur) < top {
+            (0..=cur).rev().find(wanted)
+        } else {
+            (0..cur).rev().find(wanted)
+        };
+        let Some(idx) = target else {
+            let edge = if forward { "last" } else { "first" };
+            self.notify(
+                NoticeLevel::Info,
+                format!("already at the {} message", edge),
+            );
+            return;
+        };
+        self.set_scroll_to_show_global(h, chat_layout::start_of(&lines, idx));
+        if self.focus == Focus::Chat {
+            self.selected_message = Some(idx);
         }
-        if let (Some(last_msg), Some(last_wrap)) = (self.messages.last(), self.chat_cache.last()) {
-            if last_msg.content.len() != last_wrap.content_len {
-                let idx = self.messages.len() - 1;
-                self.chat_cache[idx] = Self::wrap_message(last_msg, width);
-                self.chat_total_lines = self.chat_cache.iter().map(|w| w.lines.len()).sum();
+        self.notify(
+            NoticeLevel::Info,
+            format!("msg {}/{}", idx + 1, self.messages.len()),
+        );
+    }
+
+    // Scroll so message `idx` is in view: its first line at the top when it
+    // starts above the viewport, its last line at the bottom when below.
+    fn reveal_message(&mut self, idx: usize) {
+        let Some(h) = self.chat_inner_height() else {
+            return;
+        };
+        let lines = self.chat_lines();
+        let win = self.compute_chat_layout(h);
+        let start = chat_layout::start_of(&lines, idx);
+        let end = chat_layout::start_of(&lines, idx + 1);
+        if start < win.start || end - start > win.viewport {
+            self.set_scroll_to_show_global(h, start);
+        } else if end > win.start + win.viewport {
+            self.set_scroll_to_show_global(h, end - win.viewport);
+        }
+    }
+
+    // Expand or collapse every message, keeping the message at the top of
+    // the view there (or the bottom pinned when following the stream).
+    pub fn set_all_collapsed(&mut self, collapsed: bool) {
+        let inner_h = self.chat_area.map(|a| self.panel_inner(a).height);
+        let top = match inner_h {
+            Some(h) if !self.stick_to_bottom => {
+                self.message_at_global(self.compute_chat_layout(h).start)
+            }
+            _ => None,
+        };
+        self.collapsed.iter_mut().for_each(|c| *c = collapsed);
+        match (top, inner_h) {
+            (Some(idx), Some(h)) => {
+                let global = self.message_global_start(idx);
+                self.set_scroll_to_show_global(h, global);
+            }
+            _ => {
+                self.chat_scroll = 0;
+                self.stick_to_bottom = true;
             }
         }
     }
 
+    // Fold or unfold message `idx` without moving it on screen: its first
+    // line stays on the same row, or goes to the top when it started above.
     pub fn toggle_collapse_at(&mut self, idx: usize) {
-        if idx < self.collapsed.len() {
+        if idx >= self.collapsed.len() {
+            return;
+        }
+        let Some(h) = self.chat_inner_height() else {
             self.collapsed[idx] = !self.collapsed[idx];
+            return;
+        };
+        let top = self.compute_chat_layout(h).start;
+        let row = self.message_global_start(idx).saturating_sub(top);
+        self.collapsed[idx] = !self.collapsed[idx];
+        let start = self.message_global_start(idx);
+        self.set_scroll_to_show_global(h, start.saturating_sub(row));
+    }
+
+    // Space / z in the chat: the selected message, else the one at the top
+    // of the view.
+    pub fn toggle_collapse_current(&mut self) {
+        let idx = self.selected_message.or_else(|| {
+            self.chat_inner_height()
+                .and_then(|h| self.message_at_global(self.compute_chat_layout(h).start))
+        });
+        if let Some(idx) = idx {
+            self.toggle_collapse_at(idx);
         }
     }
 
-    fn wrap_message(m: &Message, width: u16) -> WrappedMsg {
+    // Z in the chat: collapse everything while any long message is open,
+    // otherwise expand everything.
+    pub fn toggle_all_collapsed(&mut self) {
+        let preview = self.collapse_preview_lines;
+        let any_open = self
+            .collapsed
+            .iter()
+            .zip(&self.chat_cache)
+            .any(|(c, w)| !c && w.lines.len() > preview);
+        self.set_all_collapsed(any_open);
+    }
+
+    fn wrap_message(&self, m: &Message, width: u16) -> WrappedMsg {
+        #[cfg(test)]
+        WRAP_CALLS.with(|n| n.set(n.get() + 1));
+        let (show_reasoning, markdown) = (self.show_reasoning, self.render_markdown);
         let prefix = match m.role {
             Role::User => PREFIX_USER,
             Role::Assistant => PREFIX_ASSISTANT,
+            Role::System => PREFIX_SYSTEM,
         };
-        let full = format!("{}{}", prefix, m.content);
+        let marker = if m.bookmarked { BOOKMARK_MARKER } else { "" };
         let indent_width = UnicodeWidthStr::width(prefix);
         let indent = " ".repeat(indent_width);
         let opts = Options::new(width as usize).subsequent_indent(&indent);
-        let lines = wrap(&full, opts)
-            .into_iter()
-            .map(|c| c.into_owned())
-            .collect::<Vec<_>>();
+        let meta = match &m.model {
+            Some(model) if m.role == Role::Assistant => {
+                model_label(model, m.wire.as_deref(), m.context_dropped, m.cached)
+            }
+            _ => String::new(),
+        };
+        let meta_len = meta.len();
+        let head = format!("{}{}{}", prefix, marker, meta);
+        // Reasoning block goes first, wrapped on its own so its line count is known
+        let mut lines: Vec<String> = Vec::new();
+        let reasoning = m.reasoning.trim();
+        if !reasoning.is_empty() {
+            let block = if show_reasoning {
+                format!("{}{}\n{}", head, REASONING_HEADER, reasoning)
+            } else {
+                format!("{}{}", head, reasoning_summary(reasoning.chars().count()))
+            };
+            lines.extend(wrap(&block, &opts).into_iter().map(|c| c.into_owned()));
+        }
+        let reasoning_lines = lines.len();
+        let mut styles = vec![Vec::new(); reasoning_lines];
+        if markdown && m.role == Role::Assistant && !m.content.is_empty() {
+            let lead = if reasoning_lines == 0 {
+                head
+            } else {
+                indent.clone()
+            };
+            let mut sources = vec![None; reasoning_lines];
+            for (line, ranges, source) in crate::markdown::render(
+                &m.content,
+                &lead,
+                indent_width,
+                width as usize,
+                self.code_line_numbers,
+            ) {
+                lines.push(line);
+                styles.push(ranges);
+                sources.push(Some(source));
+            }
+            for img in &m.images {
+                let label = format!("{}{}", indent, super::attachments::image_label(img));
+                lines.extend(wrap(&label, &opts).into_iter().map(|c| c.into_owned()));
+            }
+            styles.resize(lines.len(), Vec::new());
+            sources.resize(lines.len(), None);
+            return WrappedMsg {
+                role: m.role.clone(),
+                content_len: m.content.len(),
+                bookmarked: m.bookmarked,
+                timestamp: m.timestamp,
+                reasoning_len: m.reasoning.len(),
+                reasoning_lines,
+                meta_len,
+                lines,
+                styles,
+                sources,
+            };
+        }
+        let mut body = if reasoning_lines == 0 {
+            format!("{}{}", head, m.content)
+        } else {
+            format!("{}{}", indent, m.content)
+        };
+        for img in &m.images {
+            body.push('\n');
+            body.push_str(&super::attachments::image_label(img));
+        }
+        let mut sources = vec![None; reasoning_lines];
+        // Nothing answered yet: keep only the reasoning block
+        if reasoning_lines == 0 || !m.content.is_empty() || !m.images.is_empty() {
+            let content_lines = m.content.split('\n').count();
+            push_raw_rows(&body, 0, content_lines, &opts, &mut lines, &mut sources);
+        }
         WrappedMsg {
             role: m.role.clone(),
             content_len: m.content.len(),
+            bookmarked: m.bookmarked,
+            timestamp: m.timestamp,
+            reasoning_len: m.reasoning.len(),
+            reasoning_lines,
+            meta_len,
+            styles: vec![Vec::new(); lines.len()],
             lines,
+            sources,
         }
     }
 }
 
-// tests removed as requested
+// Wrap `body` one source line at a time, so each row knows which line of
+// the content it shows; `first` is the index of body's first line, and
+// lines from `content_lines` on are image labels. A leading "\n" keeps
+// textwrap's indent for the lines after the very first; the empty row it
+// adds is dropped.
+fn push_raw_rows(
+    body: &str,
+    first: usize,
+    content_lines: usize,
+    opts: &Options,
+    lines: &mut Vec<String>,
+    sources: &mut Vec<Option<usize>>,
+) {
+    for (n, piece) in body.split('\n').enumerate() {
+        let i = first + n;
+        let rows: Vec<String> = if i == 0 {
+            wrap(piece, opts)
+                .into_iter()
+                .map(|c| c.into_owned())
+                .collect()
+        } else {
+            let text = format!("\n{}", piece);
+            let rows = wrap(&text, opts);
+            rows.into_iter().skip(1).map(|c| c.into_owned()).collect()
+        };
+        let source = (i < content_lines).then_some(i);
+        sources.resize(sources.len() + rows.len(), source);
+        lines.extend(rows);
+    }
+}
+
+// A raw message whose content only grew (streaming): rewrap from its last
+// source line on instead of the whole message. False when that does not
+// apply and the message needs a full wrap.
+fn extend_raw(m: &Message, w: &mut WrappedMsg, width: u16) -> bool {
+    let old_len = w.content_len;
+    if m.content.len() <= old_len
+        || m.reasoning.len() != w.reasoning_len
+        || !m.images.is_empty()
+        || !m.content.is_char_boundary(old_len)
+    {
+        return false;
+    }
+    // The first line carries the header; rewrapping it is a full wrap anyway
+    let Some(Some(last)) = w.sources.last().copied().filter(|s| *s != Some(0)) else {
+        return false;
+    };
+    let Some(cut) = w.sources.iter().position(|s| *s == Some(last)) else {
+        return false;
+    };
+    let start = m.content[..old_len].rfind('\n').map_or(0, |p| p + 1);
+    let prefix = match m.role {
+        Role::User => PREFIX_USER,
+        Role::Assistant => PREFIX_ASSISTANT,
+        Role::System => PREFIX_SYSTEM,
+    };
+    let indent = " ".repeat(UnicodeWidthStr::width(prefix));
+    let opts = Options::new(width as usize).subsequent_indent(&indent);
+    w.lines.truncate(cut);
+    w.sources.truncate(cut);
+    let content_lines = last + m.content[start..].split('\n').count();
+    push_raw_rows(
+        &m.content[start..],
+        last,
+        content_lines,
+        &opts,
+        &mut w.lines,
+        &mut w.sources,
+    );
+    w.styles.resize(w.lines.len(), Vec::new());
+    w.content_len = m.content.len();
+    w.timestamp = m.timestamp;
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::app_with;
+
+    fn wrap_calls() -> usize {
+        WRAP_CALLS.with(|n| n.get())
+    }
+
+    fn long_conversation() -> Vec<Message> {
+        (0..1_000)
+            .map(|i| {
+                if i % 2 == 0 {
+                    Message::user(format!("question {}", i))
+                } else {
+                    Message::assistant(format!("answer {}\nwith a second line", i))
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn a_streaming_delta_rewraps_only_its_message() {
+        let mut app = app_with(long_conversation());
+        app.render_markdown = true;
+        app.ensure_chat_wrapped(80);
+        let before = wrap_calls();
+        app.messages
+            .last_mut()
+            .unwrap()
+            .content
+            .push_str(" and more");
+        app.ensure_chat_wrapped(80);
+        assert_eq!(wrap_calls() - before, 1);
+        // Nothing changed: nothing is wrapped
+        app.ensure_chat_wrapped(80);
+        assert_eq!(wrap_calls() - before, 1);
+    }
+
+    #[test]
+    fn raw_streaming_extends_without_a_full_wrap() {
+        let mut app = app_with(long_conversation());
+        app.ensure_chat_wrapped(40);
+        let before = wrap_calls();
+        let last = app.messages.last_mut().unwrap();
+        last.content
+            .push_str(" that keeps going well past one row\nand a third");
+        app.ensure_chat_wrapped(40);
+        assert_eq!(wrap_calls(), before);
+        let full = app.wrap_message(app.messages.last().unwrap(), 40);
+        assert_eq!(app.chat_cache.last().unwrap().lines, full.lines);
+        let total: usize = app.chat_cache.iter().map(|w| w.lines.len()).sum();
+        assert_eq!(app.chat_total_lines, total);
+    }
+
+    // One message far taller than u16::MAX rows, never folded
+    fn tall_app(lines: usize) -> App {
+        let text = vec!["line"; lines].join("\n");
+        let mut app = app_with(vec![Message::assistant(text)]);
+        app.collapse_threshold_lines = usize::MAX;
+        app.chat_viewport = 20;
+        app.ensure_chat_wrapped(80);
+        app
+    }
+
+    #[test]
+    fn layout_and_scrolling_past_u16_max() {
+        let mut app = tall_app(100_000);
+        let total = app.effective_total_lines();
+        assert!(total > u16::MAX as usize);
+        let w = app.compute_chat_layout(20);
+        assert_eq!(
+            (w.viewport, w.max_scroll, w.start),
+            (20, total - 20, total - 20)
+        );
+        app.stick_to_bottom = false;
+        app.chat_scroll = 70_000;
+        assert_eq!(app.compute_chat_layout(20).start, total - 20 - 70_000);
+        app.scroll_to_top();
+        assert_eq!(app.compute_chat_layout(20).start, 0);
+        // Scrolling past the top stays there
+        app.chat_scroll = usize::MAX;
+        assert_eq!(app.compute_chat_layout(20).start, 0);
+    }
+
+    #[test]
+    fn scroll_to_show_global_past_u16_max() {
+        let mut app = tall_app(100_000);
+        let total = app.effective_total_lines();
+        app.set_scroll_to_show_global(20, 80_000);
+        assert!(!app.stick_to_bottom);
+        assert_eq!(app.compute_chat_layout(20).start, 80_000);
+        // The last lines cannot reach the top: the view ends at the bottom
+        app.set_scroll_to_show_global(20, total - 5);
+        assert!(app.stick_to_bottom);
+        assert_eq!(app.compute_chat_layout(20).start, total - 20);
+        app.set_scroll_to_show_global(20, 0);
+        assert_eq!(app.compute_chat_layout(20).start, 0);
+    }
+
+    // Thirty 12-line replies, all expanded, drawn on a 60x20 terminal
+    fn foldable_app() -> App {
+        let reply = (1..=12).map(|n| format!("row {}", n)).collect::<Vec<_>>();
+        let mut app = app_with(vec![Message::assistant(reply.join("\n")); 30]);
+        app.collapse_preview_lines = 3;
+        app.collapse_threshold_lines = 6;
+        crate::test_support::render(&mut app, 60, 20);
+        app.set_all_collapsed(false);
+        app
+    }
+
+    fn top_message(app: &App) -> (usize, usize) {
+        let h = app.chat_inner_height().unwrap();
+        let start = app.compute_chat_layout(h).start;
+        let idx = app.message_at_global(start).unwrap();
+        (idx, start - app.message_global_start(idx))
+    }
+
+    #[test]
+    fn folding_everything_keeps_the_top_message() {
+        let mut app = foldable_app();
+        let h = app.chat_inner_height().unwrap();
+        app.set_scroll_to_show_global(h, app.message_global_start(6));
+        assert_eq!(top_message(&app), (6, 0));
+        // Six messages above the view shrink, then grow back
+        app.set_all_collapsed(true);
+        assert_eq!(top_message(&app), (6, 0));
+        app.set_all_collapsed(false);
+        assert_eq!(top_message(&app), (6, 0));
+        assert!(!app.stick_to_bottom);
+    }
+
+    #[test]
+    fn folding_a_message_cut_at_the_top_keeps_it_there() {
+        let mut app = foldable_app();
+        let h = app.chat_inner_height().unwrap();
+        app.set_scroll_to_show_global(h, app.message_global_start(4) + 5);
+        assert_eq!(top_message(&app), (4, 5));
+        app.toggle_collapse_at(4);
+        assert!(app.collapsed[4]);
+        assert_eq!(top_message(&app), (4, 0));
+        app.toggle_collapse_at(4);
+        assert_eq!(top_message(&app), (4, 0));
+        // A message inside the view stays on its row
+        let row = app.message_global_start(5) - app.compute_chat_layout(h).start;
+        app.toggle_collapse_at(5);
+        assert_eq!(top_message(&app), (4, 0));
+        let start = app.compute_chat_layout(h).start;
+        assert_eq!(app.message_global_start(5) - start, row);
+    }
+
+    #[test]
+    fn a_new_message_wraps_only_itself() {
+        let mut app = app_with(long_conversation());
+        app.ensure_chat_wrapped(80);
+        let before = wrap_calls();
+        app.messages.push(Message::user("next"));
+        app.ensure_chat_wrapped(80);
+        assert_eq!(wrap_calls() - before, 1);
+    }
+}
@@ -0,0 +1,108 @@
+use tracing::warn;
+
+use super::{App, Message, NoticeLevel, Role};
+
+impl App {
+    // F9: fenced code of the newest reply that has one.
+    pub fn copy_last_code_block(&mut self) {
+        let block = self
+            .messages
+            .iter()
+            .rev()
+            .filter(|m| is_reply(m))
+            .find_map(|m| last_code_block(&m.content));
+        match block {
+            Some(code) => self.copy_text(&code),
+            None => self.notify(NoticeLevel::Info, "no code block to copy"),
+        }
+    }
+
+    // Shift+F9: raw content of the newest reply.
+    pub fn copy_last_reply(&mut self) {
+        match self.messages.iter().rev().find(|m| is_reply(m)) {
+            Some(m) => {
+                let text = m.content.clone();
+                self.copy_text(&text);
+            }
+            None => self.notify(NoticeLevel::Info, "no reply to copy"),
+        }
+    }
+
+    // `y` with the chat pane focused.
+    pub fn copy_selected_message(&mut self) {
+        if let Some(m) = self.selected_message.and_then(|i| self.messages.get(i)) {
+            let text = m.content.clone();
+            self.copy_text(&text);
+        }
+    }
+
+    // System clipboard first; OSC 52 when there is none (SSH, no display)
+    // or when config `clipboard = "osc52"` asks for it.
+    pub(super) fn copy_text(&mut self, text: &str) {
+        let native = if self.clipboard_osc52 {
+            None
+        } else {
+            match arboard::Clipboard::new().and_then(|mut c| c.set_text(text)) {
+                Ok(()) => Some(text.len()),
+                Err(e) => {
+                    warn!(target: "tui", "clipboard unavailable, using OSC 52: {}", e);
+                    None
+                }
+            }
+        };
+        let sent = match native {
+            Some(n) => n,
+            None => match crate::terminal::osc52_copy(text) {
+                Ok(n) => n,
+                Err(e) => {
+                    warn!(target: "tui", "OSC 52 write failed: {}", e);
+                    self.notify(NoticeLevel::Error, "copy failed: no clipboard available");
+                    return;
+                }
+            },
+        };
+        let lines = text[..sent].lines().count().max(1);
+        let unit = if lines == 1 { "line" } else { "lines" };
+        if sent < text.len() {
+            warn!(target: "tui", "OSC 52 copy truncated to {} of {} bytes", sent, text.len());
+            self.notify(
+                NoticeLevel::Info,
+                format!(
+                    "Copied {} {} (truncated to {} KB)",
+                    lines,
+                    unit,
+                    sent / 1024
+                ),
+            );
+        } else {
+            self.notify(NoticeLevel::Info, format!("Copied {} {}", lines, unit));
+        }
+    }
+}
+
+fn is_reply(m: &Message) -> bool {
+    m.role == Role::Assistant && !m.is_info()
+}
+
+// Body of the last ``` / ~~~ fenced block; an unclosed fence runs to the end.
+fn last_code_block(content: &str) -> Option<String> {
+    let mut last = None;
+    let mut current: Option<Vec<&str>> = None;
+    for line in content.lines() {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            match current.take() {
+                Some(body) => last = Some(body.join("\n")),
+                None => current = Some(Vec::new()),
+            }
+            continue;
+        }
+        if let Some(body) = current.as_mut() {
+            body.push(line);
+        }
+    }
+    current
+        .filter(|body| !body.is_empty())
+        .map(|body| body.join("\n"))
+        .or(last)
+}
@@ -0,0 +1,228 @@
+use tracing::{info, warn};
+
+use super::{App, ConflictState, Message, NoticeLevel};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConflictChoice {
+    KeepMine,
+    TakeTheirs,
+    Merge,
+}
+
+impl ConflictChoice {
+    pub const ALL: [ConflictChoice; 3] = [
+        ConflictChoice::KeepMine,
+        ConflictChoice::TakeTheirs,
+        ConflictChoice::Merge,
+    ];
+}
+
+impl App {
+    // Save the current session unless its file changed on disk since we last
+    // loaded or wrote it; in that case open the resolution popup instead.
+    pub fn save_current_session(&mut self) {
+        let name = self.current_session_name().to_string();
+        if self.conflict.as_ref().is_some_and(|c| c.session == name) {
+            return;
+        }
+        if let Some(expected) = self.session_fingerprints.get(&name) {
+            let on_disk = crate::persist::session_fingerprint(&name);
+            if on_disk != *expected {
+                warn!(target: "tui", "session '{}' changed on disk; save held back", name);
+                self.open_conflict(name);
+                return;
+            }
+        }
+        self.write_current_session();
+    }
+
+    // Remember the on-disk state the in-memory messages were loaded from.
+    pub fn record_session_fingerprint(&mut self) {
+        let name = self.current_session_name().to_string();
+        let fp = crate::persist::session_fingerprint(&name);
+        self.session_fingerprints.insert(name, fp);
+    }
+
+    fn write_current_session(&mut self) {
+        let _ = crate::persist::save_session(self.current_session_name(), &self.messages);
+        if let Some(ts) = self.messages.iter().filter_map(|m| m.timestamp).max() {
+            let name = self.current_session_name().to_string();
+            self.session_activity.insert(name, ts);
+        }
+        self.record_session_fingerprint();
+    }
+
+    fn open_conflict(&mut self, session: String) {
+        let disk = crate::persist::load_session(&session).unwrap_or_default();
+        let mine_only = self
+            .messages
+            .iter()
+            .filter(|m| !disk.iter().any(|d| same_message(m, d)))
+            .cloned()
+            .collect();
+        let theirs_only = disk
+            .iter()
+            .filter(|d| !self.messages.iter().any(|m| same_message(m, d)))
+            .count();
+        self.conflict = Some(ConflictState {
+            session,
+            disk,
+            mine_only,
+            theirs_only,
+            selected: 0,
+        });
+        self.dirty = true;
+    }
+
+    pub fn resolve_conflict(&mut self, choice: ConflictChoice) {
+        let Some(c) = self.conflict.take() else {
+            return;
+        };
+        if choice != ConflictChoice::KeepMine && self.is_busy() {
+            self.conflict = Some(c);
+            self.notify(
+                NoticeLevel::Info,
+                "wait for the reply to finish, or keep this window's messages",
+            );
+            return;
+        }
+        info!(target: "tui", "session '{}' conflict resolved: {:?}", c.session, choice);
+        match choice {
+            ConflictChoice::KeepMine => {}
+            ConflictChoice::TakeTheirs => self.replace_messages(c.disk),
+            ConflictChoice::Merge => {
+                let merged = merge_messages(&self.messages, &c.disk);
+                self.replace_messages(merged);
+            }
+        }
+        self.write_current_session();
+        self.dirty = true;
+    }
+
+    fn replace_messages(&mut self, msgs: Vec<Message>) {
+        self.messages = msgs;
+        self.chat_wrap_width = 0;
+        self.chat_cache.clear();
+        self.chat_total_lines = 0;
+        self.collapsed.clear();
+        self.stick_to_bottom = true;
+    }
+}
+
+// Identity across copies of a session: role, text and send time.
+fn same_message(a: &Message, b: &Message) -> bool {
+    a.role == b.role && a.content == b.content && a.timestamp == b.timestamp
+}
+
+// Shared prefix, then the messages unique to each side interleaved by
+// timestamp (disk first on ties or when a timestamp is missing).
+pub fn merge_messages(mine: &[Message], theirs: &[Message]) -> Vec<Message> {
+    let prefix = mine
+        .iter()
+        .zip(theirs)
+        .take_while(|(a, b)| same_message(a, b))
+        .count();
+    let theirs_tail = &theirs[prefix..];
+    let mine_tail: Vec<&Message> = mine[prefix..]
+        .iter()
+        .filter(|m| !theirs_tail.iter().any(|t| same_message(m, t)))
+        .collect();
+    let mut out = theirs[..prefix].to_vec();
+    let (mut i, mut j) = (0, 0);
+    while i < theirs_tail.len() || j < mine_tail.len() {
+        let take_theirs = match (theirs_tail.get(i), mine_tail.get(j)) {
+            (Some(t), Some(m)) => match (t.timestamp, m.timestamp) {
+                (Some(a), Some(b)) => a <= b,
+                _ => true,
+            },
+            (Some(_), None) => true,
+            _ => false,
+        };
+        if take_theirs {
+            out.push(theirs_tail[i].clone());
+            i += 1;
+        } else {
+            out.push(mine_tail[j].clone());
+            j += 1;
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(m: Message, ts: i64) -> Message {
+        m.with_timestamp(ts)
+    }
+
+    fn texts(msgs: &[Message]) -> Vec<&str> {
+        msgs.iter().map(|m| m.content.as_str()).collect()
+    }
+
+    #[test]
+    fn identical_sides_merge_to_themselves() {
+        let msgs = vec![at(Message::user("a"), 1), at(Message::assistant("b"), 2)];
+        assert_eq!(texts(&merge_messages(&msgs, &msgs)), ["a", "b"]);
+    }
+
+    #[test]
+    fn unique_tails_interleave_by_timestamp() {
+        let shared = at(Message::user("start"), 1);
+        let mine = vec![
+            shared.clone(),
+            at(Message::user("mine 3"), 3),
+            at(Message::user("mine 5"), 5),
+        ];
+        let theirs = vec![
+            shared,
+            at(Message::user("theirs 2"), 2),
+            at(Message::user("theirs 4"), 4),
+        ];
+        assert_eq!(
+            texts(&merge_messages(&mine, &theirs)),
+            ["start", "theirs 2", "mine 3", "theirs 4", "mine 5"]
+        );
+    }
+
+    #[test]
+    fn ties_and_missing_timestamps_put_disk_first() {
+        let mine = vec![at(Message::user("mine"), 7), Message::user("mine, no time")];
+        let theirs = vec![at(Message::user("theirs"), 7)];
+        assert_eq!(
+            texts(&merge_messages(&mine, &theirs)),
+            ["theirs", "mine", "mine, no time"]
+        );
+        let theirs = vec![Message::user("theirs, no time")];
+        assert_eq!(
+            texts(&merge_messages(&mine, &theirs)),
+            ["theirs, no time", "mine", "mine, no time"]
+        );
+    }
+
+    #[test]
+    fn messages_on_both_sides_after_divergence_appear_once() {
+        let mine = vec![
+            at(Message::user("a"), 1),
+            at(Message::user("mine"), 2),
+            at(Message::user("both"), 3),
+        ];
+        let theirs = vec![
+            at(Message::user("a"), 1),
+            at(Message::user("both"), 3),
+            at(Message::user("theirs"), 4),
+        ];
+        assert_eq!(
+            texts(&merge_messages(&mine, &theirs)),
+            ["a", "mine", "both", "theirs"]
+        );
+    }
+
+    #[test]
+    fn same_text_with_another_role_is_kept() {
+        let mine = vec![at(Message::assistant("ok"), 1)];
+        let theirs = vec![at(Message::user("ok"), 1)];
+        assert_eq!(merge_messages(&mine, &theirs).len(), 2);
+    }
+}
@@ -0,0 +1,73 @@
+use providers::openai::{client::request_preview, config::OpenAiConfig};
+use tracing::info;
+
+use super::{now_unix, App, Message};
+
+// Data URLs longer than this are shortened in the preview
+const DATA_URL_PREVIEW_CHARS: usize = 64;
+
+impl App {
+    // /dryrun: answer the turn with the request each model would get, as a
+    // fenced JSON block, instead of sending it. Both messages are kept out of
+    // later requests.
+    pub(super) fn show_dry_run(
+        &mut self,
+        models: Vec<String>,
+        msgs: &[fast_core::llm::Message],
+        wire: fast_core::llm::ChatWire,
+        dropped: usize,
+    ) {
+        // No key is needed to look; a config problem is noted with the body
+        let (base_url, problem) = match OpenAiConfig::from_env_and_file(None) {
+            Ok(c) => (c.base_url, String::new()),
+            Err(e) => (String::new(), format!(" (config: {:#})", e)),
+        };
+        for model in models {
+            let opts = fast_core::llm::ChatOpts {
+                model: model.clone(),
+                temperature: self.temperature,
+                top_p: self.top_p,
+                max_tokens: self.max_tokens,
+                reasoning_summary: self.show_reasoning,
+            };
+            let (url, mut body) = request_preview(&base_url, msgs, &opts, wire);
+            shorten_data_urls(&mut body);
+            let json = serde_json::to_string_pretty(&body).unwrap_or_default();
+            info!(target: "tui", "dry run: model={} url={} body={} bytes", model, url, json.len());
+            let mut reply = Message::assistant(format!(
+                "dry run: POST {}{}\n\n```json\n{}\n```",
+                url, problem, json
+            ))
+            .with_timestamp(now_unix());
+            reply.model = Some(model);
+            reply.wire = Some(self.wire_label.clone());
+            reply.context_dropped = (dropped > 0).then_some(dropped);
+            reply.dry_run = true;
+            self.messages.push(reply);
+            // Folded like a long reply
+            self.collapsed
+                .push(json.lines().count() > self.collapse_threshold_lines);
+        }
+        self.chat_wrap_width = 0;
+        self.save_current_session();
+        self.dirty = true;
+    }
+}
+
+// Image data URLs run to megabytes of base64; keep their head and size.
+fn shorten_data_urls(v: &mut serde_json::Value) {
+    match v {
+        serde_json::Value::String(s)
+            if s.starts_with("data:") && s.len() > DATA_URL_PREVIEW_CHARS =>
+        {
+            let mut end = DATA_URL_PREVIEW_CHARS;
+            while !s.is_char_boundary(end) {
+                end -= 1;
+            }
+            *s = format!("{}… ({} bytes)", &s[..end], s.len());
+        }
+        serde_json::Value::Array(items) => items.iter_mut().for_each(shorten_data_urls),
+        serde_json::Value::Object(map) => map.values_mut().for_each(shorten_data_urls),
+        _ => {}
+    }
+}
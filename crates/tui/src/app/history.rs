@@ -1,5 +1,26 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use tracing::warn;
+use unicode_segmentation::UnicodeSegmentation;
+
+use super::input::{delete_left, delete_right, insert_graphemes};
 use super::App;
 
+// Ctrl+R reverse-incremental search over input history
+pub struct HistorySearch {
+    pub buffer: String,
+    pub cursor: usize,
+    // Indices into `history` containing `buffer`, newest first
+    pub matches: Vec<usize>,
+    pub selected: usize,
+}
+
+impl HistorySearch {
+    pub fn current<'a>(&self, history: &'a [String]) -> Option<&'a str> {
+        let idx = *self.matches.get(self.selected)?;
+        history.get(idx).map(String::as_str)
+    }
+}
+
 impl App {
     // Record input text to history if it's new, and reset history navigation state.
     pub fn record_history_entry(&mut self, text: &str) {
@@ -11,7 +32,100 @@ impl App {
         }
         self.history.push(text.to_string());
         self.history_index = None;
+        if self.history.len() > crate::persist::HISTORY_MAX {
+            self.history.remove(0);
+        }
+        if let Err(e) = crate::persist::append_history(text) {
+            warn!(target: "tui", "history append failed: {:#}", e);
+        }
+    }
+
+    pub fn open_history_search(&mut self) {
+        let mut state = HistorySearch {
+            buffer: String::new(),
+            cursor: 0,
+            matches: Vec::new(),
+            selected: 0,
+        };
+        filter_history(&self.history, &mut state);
+        self.history_search = Some(state);
+    }
+
+    // Keys while the history search prompt is open. Typing narrows the
+    // matches; Ctrl+R / Up go to older ones, Ctrl+S / Down to newer.
+    pub fn on_history_search_key(&mut self, key: KeyEvent) {
+        let Some(state) = self.history_search.as_mut() else {
+            return;
+        };
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Esc => {
+                self.history_search = None;
+            }
+            KeyCode::Char('c' | 'g') if ctrl => {
+                self.history_search = None;
+            }
+            KeyCode::Enter => {
+                if let Some(entry) = state.current(&self.history).map(str::to_string) {
+                    self.replace_input(entry);
+                }
+                self.history_search = None;
+                self.history_index = None;
+            }
+            // Older match
+            KeyCode::Char('r') | KeyCode::Up
+                if (ctrl || key.code == KeyCode::Up)
+                    && state.selected + 1 < state.matches.len() =>
+            {
+                state.selected += 1;
+            }
+            // Newer match
+            KeyCode::Char('s') | KeyCode::Down if ctrl || key.code == KeyCode::Down => {
+                state.selected = state.selected.saturating_sub(1);
+            }
+            KeyCode::Backspace => {
+                delete_left(&mut state.buffer, &mut state.cursor);
+                filter_history(&self.history, state);
+            }
+            KeyCode::Delete => {
+                delete_right(&mut state.buffer, state.cursor);
+                filter_history(&self.history, state);
+            }
+            KeyCode::Left if state.cursor > 0 => {
+                state.cursor -= 1;
+            }
+            KeyCode::Right => {
+                state.cursor = (state.cursor + 1).min(state.buffer.graphemes(true).count());
+            }
+            KeyCode::Home => {
+                state.cursor = 0;
+            }
+            KeyCode::End => {
+                state.cursor = state.buffer.graphemes(true).count();
+            }
+            KeyCode::Char(ch) if !ctrl => {
+                insert_graphemes(&mut state.buffer, &mut state.cursor, &ch.to_string());
+                filter_history(&self.history, state);
+            }
+            _ => {}
+        }
     }
 }
 
+// Case-insensitive substring matches, newest first; repeats of an entry
+// show once.
+fn filter_history(history: &[String], state: &mut HistorySearch) {
+    let needle = state.buffer.to_lowercase();
+    let mut seen = std::collections::HashSet::new();
+    state.matches = history
+        .iter()
+        .enumerate()
+        .rev()
+        .filter(|(_, h)| h.to_lowercase().contains(&needle))
+        .filter(|(_, h)| seen.insert(h.as_str()))
+        .map(|(i, _)| i)
+        .collect();
+    state.selected = 0;
+}
+
 // tests removed as requested
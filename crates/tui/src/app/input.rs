@@ -1,42 +1,248 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
 use unicode_segmentation::UnicodeSegmentation;
 
-use super::App;
+use super::{App, Focus, NoticeLevel};
+
+// Pastes larger than this ask before landing in the input
+const PASTE_CONFIRM_BYTES: usize = 32 * 1024;
+// Anything past this is cut off a paste
+const PASTE_MAX_BYTES: usize = 4 * 1024 * 1024;
+// Input snapshots kept for Ctrl+Z
+const UNDO_DEPTH: usize = 200;
+
+// Undo/redo of the input: (text, cursor) before each edit. A run of typed
+// characters is one step; submitting starts over.
+#[derive(Default)]
+pub struct InputUndo {
+    undo: Vec<(String, usize)>,
+    redo: Vec<(String, usize)>,
+    // Cursor after the last typed character, while a run can continue
+    typing_at: Option<usize>,
+}
+
+impl InputUndo {
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+}
 
 impl App {
-    pub fn insert_text(&mut self, s: &str) {
-        let parts: Vec<&str> = self.input.graphemes(true).collect();
-        let idx = self.input_cursor.min(parts.len());
-        let mut new_input = String::new();
-        for g in &parts[..idx] {
-            new_input.push_str(g);
+    // Bracketed paste: inserted literally (newlines included), never submitted.
+    // Pastes over the `paste_attach_*` limits are held under the input as an
+    // attachment; others over PASTE_CONFIRM_BYTES wait for a Y/N confirmation.
+    pub fn on_paste(&mut self, s: &str) {
+        if self.read_only {
+            return;
+        }
+        self.dirty = true;
+        let mut text = clean_paste(s);
+        if text.len() > PASTE_MAX_BYTES {
+            let mut end = PASTE_MAX_BYTES;
+            while !text.is_char_boundary(end) {
+                end -= 1;
+            }
+            text.truncate(end);
+            self.notify(
+                NoticeLevel::Warning,
+                format!(
+                    "paste cut to its first {} MB",
+                    PASTE_MAX_BYTES / (1024 * 1024)
+                ),
+            );
         }
-        new_input.push_str(s);
-        for g in &parts[idx..] {
-            new_input.push_str(g);
+        if self.paste_is_attachment(&text) {
+            self.attach_pasted_text(text);
+            return;
+        }
+        if text.len() > PASTE_CONFIRM_BYTES {
+            self.confirm = Some(super::ConfirmState {
+                action: super::ConfirmAction::LargePaste(text),
+            });
+            return;
         }
-        self.input = new_input;
-        let added = s.graphemes(true).count();
-        self.input_cursor = (idx + added).min(self.input.graphemes(true).count());
+        self.insert_text(&text);
+    }
+
+    // Replaces the selection, if any.
+    pub fn insert_text(&mut self, s: &str) {
+        let typing =
+            s != "\n" && s.graphemes(true).count() == 1 && self.input_selection().is_none();
+        self.edit_input(typing, |app| {
+            app.delete_input_selection_now();
+            insert_graphemes(&mut app.input, &mut app.input_cursor, s)
+        });
     }
 
     pub fn delete_left_grapheme(&mut self) {
-        if self.input_cursor == 0 {
-            return;
+        self.edit_input(false, |app| {
+            if !app.delete_input_selection_now() {
+                delete_left(&mut app.input, &mut app.input_cursor)
+            }
+        });
+    }
+
+    pub fn delete_right_grapheme(&mut self) {
+        self.edit_input(false, |app| {
+            if !app.delete_input_selection_now() {
+                delete_right(&mut app.input, app.input_cursor)
+            }
+        });
+    }
+
+    // Selected graphemes as a start..end range, when any are selected.
+    pub fn input_selection(&self) -> Option<(usize, usize)> {
+        let len = self.input.graphemes(true).count();
+        let anchor = self.input_anchor?.min(len);
+        let cursor = self.input_cursor.min(len);
+        (anchor != cursor).then(|| (anchor.min(cursor), anchor.max(cursor)))
+    }
+
+    // Shift+arrows: move the cursor with `step`, anchoring a selection where
+    // it started unless one is already open.
+    pub fn extend_input_selection(&mut self, step: impl FnOnce(&mut Self)) {
+        self.input_anchor.get_or_insert(self.input_cursor);
+        step(self);
+    }
+
+    // Keys that leave the selection open: Shift+arrows and Ctrl+C on it.
+    // Anything else handled by `on_key` drops it afterwards.
+    pub(super) fn keeps_input_selection(&self, key: &KeyEvent) -> bool {
+        self.focus == Focus::Input
+            && match key.code {
+                KeyCode::Left | KeyCode::Right | KeyCode::Home | KeyCode::End => {
+                    key.modifiers.contains(KeyModifiers::SHIFT)
+                }
+                KeyCode::Char('c') => key.modifiers.contains(KeyModifiers::CONTROL),
+                _ => false,
+            }
+    }
+
+    fn selected_input_text(&self) -> Option<String> {
+        let (start, end) = self.input_selection()?;
+        Some(
+            self.input
+                .graphemes(true)
+                .skip(start)
+                .take(end - start)
+                .collect(),
+        )
+    }
+
+    // Ctrl+C with a selection
+    pub fn copy_input_selection(&mut self) {
+        if let Some(text) = self.selected_input_text() {
+            self.copy_text(&text);
         }
+    }
+
+    // Ctrl+X with a selection
+    pub fn cut_input_selection(&mut self) {
+        let Some(text) = self.selected_input_text() else {
+            return;
+        };
+        self.copy_text(&text);
+        self.edit_input(false, |app| {
+            app.delete_input_selection_now();
+        });
+    }
+
+    // Remove the selected graphemes, leaving the cursor where they began.
+    // False when nothing was selected.
+    fn delete_input_selection_now(&mut self) -> bool {
+        let Some((start, end)) = self.input_selection() else {
+            return false;
+        };
         let mut parts: Vec<&str> = self.input.graphemes(true).collect();
-        let idx = self.input_cursor;
-        parts.remove(idx - 1);
+        parts.drain(start..end);
         self.input = parts.concat();
-        self.input_cursor = idx - 1;
+        self.input_cursor = start;
+        self.input_anchor = None;
+        true
     }
 
-    pub fn delete_right_grapheme(&mut self) {
-        let mut parts: Vec<&str> = self.input.graphemes(true).collect();
-        let idx = self.input_cursor.min(parts.len());
-        if idx < parts.len() {
-            parts.remove(idx);
-            self.input = parts.concat();
+    // Swap in `text` (history recall and the like) with the cursor at its end.
+    pub fn replace_input(&mut self, text: String) {
+        self.edit_input(false, |app| {
+            app.input_cursor = text.graphemes(true).count();
+            app.input = text;
+        });
+    }
+
+    // Run an edit of the input and record the text before it for undo,
+    // unless nothing changed or it continues a run of typing.
+    fn edit_input(&mut self, typing: bool, edit: impl FnOnce(&mut Self)) {
+        let before = (self.input.clone(), self.input_cursor);
+        edit(self);
+        self.input_anchor = None;
+        if self.input == before.0 {
+            return;
+        }
+        let u = &mut self.input_undo;
+        if !(typing && u.typing_at == Some(before.1)) {
+            u.undo.push(before);
+            if u.undo.len() > UNDO_DEPTH {
+                u.undo.remove(0);
+            }
+        }
+        u.typing_at = typing.then_some(self.input_cursor);
+        u.redo.clear();
+    }
+
+    // Ctrl+X without a selection and the palette. Refused mid-stream: the
+    // editor holds the terminal, and replies could not be drawn meanwhile.
+    pub fn request_external_edit(&mut self) {
+        if self.read_only {
+            self.notify(NoticeLevel::Info, super::READ_ONLY_NOTE);
+        } else if self.llm_rx.is_some() {
+            self.notify(
+                NoticeLevel::Info,
+                "wait for the reply to finish before editing externally",
+            );
+        } else {
+            self.external_edit = true;
+        }
+    }
+
+    // The editor's result, back from the event loop. The edit is one undo
+    // step; a failed editor leaves the input alone.
+    pub fn finish_external_edit(&mut self, res: anyhow::Result<Option<String>>) {
+        match res {
+            Ok(Some(text)) => {
+                self.replace_input(text);
+                self.update_slash_picker_on_input_change();
+                self.focus = Focus::Input;
+            }
+            Ok(None) => self.notify(
+                NoticeLevel::Warning,
+                "editor exited with an error; input kept",
+            ),
+            Err(e) => self.notify(NoticeLevel::Error, format!("external editor: {:#}", e)),
         }
+        self.dirty = true;
+    }
+
+    // Ctrl+Z / Ctrl+_
+    pub fn undo_input(&mut self) {
+        let Some(prev) = self.input_undo.undo.pop() else {
+            return;
+        };
+        let current = std::mem::replace(&mut self.input, prev.0);
+        self.input_undo.redo.push((current, self.input_cursor));
+        self.input_cursor = prev.1;
+        self.input_anchor = None;
+        self.input_undo.typing_at = None;
+    }
+
+    // Ctrl+Y / Ctrl+Shift+Z
+    pub fn redo_input(&mut self) {
+        let Some(next) = self.input_undo.redo.pop() else {
+            return;
+        };
+        let current = std::mem::replace(&mut self.input, next.0);
+        self.input_undo.undo.push((current, self.input_cursor));
+        self.input_cursor = next.1;
+        self.input_anchor = None;
+        self.input_undo.typing_at = None;
     }
 
     pub fn move_cursor_line_start(&mut self) {
@@ -64,6 +270,10 @@ impl App {
     }
 
     pub fn delete_prev_word(&mut self) {
+        self.edit_input(false, Self::delete_prev_word_now);
+    }
+
+    fn delete_prev_word_now(&mut self) {
         let parts: Vec<&str> = self.input.graphemes(true).collect();
         if self.input_cursor == 0 {
             return;
@@ -81,7 +291,32 @@ impl App {
         self.input_cursor = i;
     }
 
+    // Alt+D: through the end of the next word, like Alt+F moves.
+    pub fn delete_next_word(&mut self) {
+        self.edit_input(false, Self::delete_next_word_now);
+    }
+
+    fn delete_next_word_now(&mut self) {
+        let parts: Vec<&str> = self.input.graphemes(true).collect();
+        let start = self.input_cursor.min(parts.len());
+        let mut end = start;
+        while end < parts.len() && parts[end].trim().is_empty() {
+            end += 1;
+        }
+        while end < parts.len() && !parts[end].trim().is_empty() {
+            end += 1;
+        }
+        let mut newp = parts.clone();
+        newp.drain(start..end);
+        self.input = newp.concat();
+        self.input_cursor = start;
+    }
+
     pub fn kill_to_line_start(&mut self) {
+        self.edit_input(false, Self::kill_to_line_start_now);
+    }
+
+    fn kill_to_line_start_now(&mut self) {
         let parts: Vec<&str> = self.input.graphemes(true).collect();
         let mut start = self.input_cursor.min(parts.len());
         while start > 0 {
@@ -97,6 +332,10 @@ impl App {
     }
 
     pub fn kill_to_line_end(&mut self) {
+        self.edit_input(false, Self::kill_to_line_end_now);
+    }
+
+    fn kill_to_line_end_now(&mut self) {
         let parts: Vec<&str> = self.input.graphemes(true).collect();
         let mut end = self.input_cursor.min(parts.len());
         while end < parts.len() {
@@ -133,4 +372,136 @@ impl App {
         }
         self.input_cursor = i;
     }
+
+    // Keys ignored in read-only mode: typing, editing and submitting in the
+    // input, bookmarking, and session/context changes from their panes.
+    // Scrolling, search, selection and session switching still work.
+    pub fn blocked_by_read_only(&self, key: &KeyEvent) -> bool {
+        if !self.read_only {
+            return false;
+        }
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        let alt = key.modifiers.contains(KeyModifiers::ALT);
+        match (self.focus, key.code) {
+            (_, KeyCode::Char('b' | 'r')) if ctrl => true,
+            (_, KeyCode::F(5)) => true,
+            // Alt+B/F only move the cursor
+            (Focus::Input, KeyCode::Char('b' | 'f')) if alt => false,
+            (Focus::Input, KeyCode::Char(c)) => {
+                !ctrl
+                    || matches!(
+                        c,
+                        'w' | 'u' | 'k' | 'h' | 'v' | 'x' | 'z' | 'Z' | 'y' | '_' | '7'
+                    )
+            }
+            (Focus::Input, KeyCode::Backspace | KeyCode::Delete | KeyCode::Enter) => true,
+            (Focus::Input, KeyCode::Up | KeyCode::Down) => key.modifiers.is_empty(),
+            (Focus::Sidebar, KeyCode::Char(c)) => "nNrRdD".contains(c),
+            (Focus::Context, KeyCode::Char(c)) => "aA".contains(c),
+            (Focus::Sidebar | Focus::Context, KeyCode::Delete) => true,
+            _ => false,
+        }
+    }
+}
+
+// Grapheme editing on a buffer and its cursor (in graphemes); shared by the
+// input and the history search prompt.
+pub(super) fn insert_graphemes(buf: &mut String, cursor: &mut usize, s: &str) {
+    let parts: Vec<&str> = buf.graphemes(true).collect();
+    let idx = (*cursor).min(parts.len());
+    let mut out = parts[..idx].concat();
+    out.push_str(s);
+    out.push_str(&parts[idx..].concat());
+    *buf = out;
+    let added = s.graphemes(true).count();
+    *cursor = (idx + added).min(buf.graphemes(true).count());
+}
+
+pub(super) fn delete_left(buf: &mut String, cursor: &mut usize) {
+    let mut parts: Vec<&str> = buf.graphemes(true).collect();
+    let idx = (*cursor).min(parts.len());
+    if idx == 0 {
+        return;
+    }
+    parts.remove(idx - 1);
+    *buf = parts.concat();
+    *cursor = idx - 1;
+}
+
+pub(super) fn delete_right(buf: &mut String, cursor: usize) {
+    let mut parts: Vec<&str> = buf.graphemes(true).collect();
+    if cursor < parts.len() {
+        parts.remove(cursor);
+        *buf = parts.concat();
+    }
+}
+
+// CRLF and lone CR become "\n"; other control characters but tab are
+// dropped, since they would render as stray glyphs and skew widths.
+fn clean_paste(s: &str) -> String {
+    s.replace("\r\n", "\n")
+        .replace('\r', "\n")
+        .chars()
+        .filter(|c| !c.is_control() || matches!(c, '\n' | '\t'))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::clean_paste;
+    use crate::test_support::app_with;
+
+    // `input` after Alt+D at grapheme `cursor`, and the cursor after it
+    fn delete_next_word(input: &str, cursor: usize) -> (String, usize) {
+        let mut app = app_with(Vec::new());
+        app.input = input.into();
+        app.input_cursor = cursor;
+        app.delete_next_word();
+        (app.input, app.input_cursor)
+    }
+
+    #[test]
+    fn delete_next_word_eats_the_whitespace_run_before_it() {
+        assert_eq!(delete_next_word("foo   bar baz", 3), ("foo baz".into(), 3));
+        assert_eq!(delete_next_word("foo bar", 0), (" bar".into(), 0));
+        // Tabs and newlines count as whitespace too
+        assert_eq!(delete_next_word("a \t\n b c", 1), ("a c".into(), 1));
+        // Trailing whitespace only: all of it goes
+        assert_eq!(delete_next_word("a   ", 1), ("a".into(), 1));
+        assert_eq!(delete_next_word("abc", 3), ("abc".into(), 3));
+        assert_eq!(delete_next_word("", 0), (String::new(), 0));
+    }
+
+    #[test]
+    fn delete_next_word_counts_graphemes() {
+        assert_eq!(delete_next_word("日本語 テスト", 0), (" テスト".into(), 0));
+        assert_eq!(delete_next_word("日本語 テスト", 3), ("日本語".into(), 3));
+        assert_eq!(delete_next_word("e\u{301}tude x", 0), (" x".into(), 0));
+        // Mid-word: only the rest of the word
+        assert_eq!(delete_next_word("cafe\u{301}s ok", 3), ("caf ok".into(), 3));
+        let family = "👨\u{200d}👩\u{200d}👧 ok";
+        assert_eq!(delete_next_word(family, 0), (" ok".into(), 0));
+        assert_eq!(
+            delete_next_word(family, 1),
+            ("👨\u{200d}👩\u{200d}👧".into(), 1)
+        );
+        // A full-width space separates words as well
+        assert_eq!(delete_next_word("前\u{3000}後", 1), ("前".into(), 1));
+    }
+
+    #[test]
+    fn clean_paste_normalizes_line_endings() {
+        assert_eq!(clean_paste("a\r\nb\r\n"), "a\nb\n");
+        assert_eq!(clean_paste("a\rb\r"), "a\nb\n");
+        // Mixed, and CR CR LF is one lone CR plus one CRLF
+        assert_eq!(clean_paste("a\r\nb\rc\nd"), "a\nb\nc\nd");
+        assert_eq!(clean_paste("a\r\r\nb"), "a\n\nb");
+        assert_eq!(clean_paste("\r\n"), "\n");
+    }
+
+    #[test]
+    fn clean_paste_drops_other_control_chars() {
+        assert_eq!(clean_paste("a\tb\x1b[31mc\x07\x00"), "a\tb[31mc");
+        assert_eq!(clean_paste("日本\u{7f}語 😀"), "日本語 😀");
+    }
 }
@@ -0,0 +1,132 @@
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use crossterm::event::{KeyCode, KeyEvent};
+
+use super::App;
+
+// Lines kept from the end of fast-tui.log...
+const LOG_VIEW_LINES: usize = 500;
+// ...read from at most this many trailing bytes
+const LOG_VIEW_BYTES: u64 = 256 * 1024;
+// How often an open viewer rereads the file
+const LOG_VIEW_REFRESH: Duration = Duration::from_secs(1);
+
+// F12 and the palette: the tail of the log file over the chat
+pub struct LogViewState {
+    pub lines: Vec<String>,
+    // First line shown; None follows the end as new lines arrive
+    pub scroll: Option<usize>,
+    // Set by the draw from the popup's inner height
+    pub viewport: usize,
+    // Why there are no lines, e.g. the file is missing
+    pub error: Option<String>,
+    // File length at the last read, to skip rereads when nothing changed
+    len: u64,
+    read_at: Instant,
+}
+
+impl LogViewState {
+    // First line shown, resolving "follow" against the current viewport.
+    pub fn top(&self) -> usize {
+        let last_page = self.lines.len().saturating_sub(self.viewport.max(1));
+        self.scroll.map_or(last_page, |s| s.min(last_page))
+    }
+
+    fn scroll_by(&mut self, delta: isize) {
+        let last_page = self.lines.len().saturating_sub(self.viewport.max(1));
+        let top = self.top().saturating_add_signed(delta).min(last_page);
+        // Back at the end: follow again
+        self.scroll = (top < last_page).then_some(top);
+    }
+}
+
+impl App {
+    pub fn open_log_view(&mut self) {
+        let mut state = LogViewState {
+            lines: Vec::new(),
+            scroll: None,
+            viewport: 0,
+            error: None,
+            len: 0,
+            read_at: Instant::now(),
+        };
+        reload(&mut state);
+        self.log_view = Some(state);
+    }
+
+    // From `on_tick`: pick up lines written since the last read.
+    pub(super) fn refresh_log_view(&mut self) {
+        let Some(state) = self.log_view.as_mut() else {
+            return;
+        };
+        if state.read_at.elapsed() < LOG_VIEW_REFRESH {
+            return;
+        }
+        if reload(state) {
+            self.dirty = true;
+        }
+    }
+
+    pub fn on_log_view_key(&mut self, key: KeyEvent) {
+        let Some(state) = self.log_view.as_mut() else {
+            return;
+        };
+        let page = state.viewport.saturating_sub(1).max(1) as isize;
+        match key.code {
+            KeyCode::Esc | KeyCode::F(12) => {
+                self.log_view = None;
+            }
+            KeyCode::Up | KeyCode::Char('k') => state.scroll_by(-1),
+            KeyCode::Down | KeyCode::Char('j') => state.scroll_by(1),
+            KeyCode::PageUp => state.scroll_by(-page),
+            KeyCode::PageDown => state.scroll_by(page),
+            KeyCode::Home | KeyCode::Char('g') => state.scroll = Some(0),
+            KeyCode::End | KeyCode::Char('G') => state.scroll = None,
+            _ => {}
+        }
+        self.dirty = true;
+    }
+}
+
+// Reread the tail when the file changed size; true when the lines changed.
+fn reload(state: &mut LogViewState) -> bool {
+    state.read_at = Instant::now();
+    let path = crate::persist::log_dir().join(crate::persist::LOG_FILE);
+    let len = std::fs::metadata(&path).map_or(0, |m| m.len());
+    if len == state.len && (state.error.is_some() || !state.lines.is_empty()) {
+        return false;
+    }
+    state.len = len;
+    match read_tail(&path, LOG_VIEW_BYTES) {
+        Ok(text) => {
+            let lines: Vec<&str> = text.lines().collect();
+            let start = lines.len().saturating_sub(LOG_VIEW_LINES);
+            state.lines = lines[start..].iter().map(|l| l.to_string()).collect();
+            state.error = None;
+        }
+        Err(e) => {
+            state.lines.clear();
+            state.error = Some(format!("{}: {}", path.display(), e));
+        }
+    }
+    true
+}
+
+// Up to `max` bytes from the end of `path`, starting at a line boundary.
+fn read_tail(path: &Path, max: u64) -> std::io::Result<String> {
+    let mut file = File::open(path)?;
+    let len = file.metadata()?.len();
+    let start = len.saturating_sub(max);
+    file.seek(SeekFrom::Start(start))?;
+    let mut bytes = Vec::new();
+    file.read_to_end(&mut bytes)?;
+    let text = String::from_utf8_lossy(&bytes);
+    // Drop the partial first line of a cut-off read
+    Ok(match text.find('\n').filter(|_| start > 0) {
+        Some(i) => text[i + 1..].to_string(),
+        None => text.into_owned(),
+    })
+}
@@ -1,31 +1,90 @@
 use crossterm::event::{KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
-use fast_core::llm::ModelClient as _;
 use ratatui::layout::Rect;
 use serde::{Deserialize, Serialize};
-use std::sync::{
-    atomic::{AtomicBool, Ordering},
-    Arc,
-};
-use std::time::Duration;
-use tracing::{error, info};
+use tracing::{debug, error, info, warn};
 use unicode_segmentation::UnicodeSegmentation;
 
+pub use notice::{Notice, NoticeLevel};
+
+pub mod attachments;
+pub mod bookmarks;
 pub mod chat;
+pub mod clipboard;
+pub mod conflict;
+pub mod dryrun;
 pub mod history;
 pub mod input;
+pub mod logview;
+pub mod notice;
+pub mod profiles;
+pub mod retry;
 pub mod search;
+pub mod selection;
 pub mod sessions;
+pub mod templates;
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+// Default config `compact_width`: below this the sidebar alone takes a third
+const DEFAULT_COMPACT_WIDTH: u16 = 70;
+// Extra columns needed before leaving compact mode
+const COMPACT_HYSTERESIS: u16 = 4;
+// Collapsed messages show this many lines...
+const DEFAULT_COLLAPSE_PREVIEW_LINES: usize = 8;
+// ...and messages longer than this start collapsed
+const DEFAULT_COLLAPSE_THRESHOLD_LINES: usize = 40;
+// Pastes with more lines than this, or more bytes than the next, become
+// an attachment under the input instead of input text
+const DEFAULT_PASTE_ATTACH_LINES: usize = 200;
+const DEFAULT_PASTE_ATTACH_BYTES: usize = 16 * 1024;
+// Esc with `confirm_quit` and unsent input: a second Esc within this quits
+const QUIT_CONFIRM_WINDOW: std::time::Duration = std::time::Duration::from_secs(1);
+// Shortest gap between two writes of `ui_state.json`
+const STATE_SAVE_INTERVAL: std::time::Duration = std::time::Duration::from_millis(300);
+// Shown when a key or action is ignored in read-only mode
+const READ_ONLY_NOTE: &str = "read-only: Ctrl+P > Toggle read-only mode to edit";
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Role {
     User,
     Assistant,
+    System,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Message {
     pub role: Role,
     pub content: String,
+    // Absent in older session files
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub bookmarked: bool,
+    // Unix seconds at send (user) or first received chunk (assistant)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<i64>,
+    // Thinking text streamed before the answer; shown dimmed, never sent back
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub reasoning: String,
+    // Images sent with this message (files under the attachments dir)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub images: Vec<attachments::MessageImage>,
+    // Model that produced an assistant reply
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    // Wire API selected when the reply was requested (responses/chat/auto)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wire: Option<String>,
+    // Token usage reported for this reply
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prompt_tokens: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub completion_tokens: Option<u32>,
+    // Earlier messages left out of the request for this reply
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub context_dropped: Option<usize>,
+    // A /dryrun turn: the prompt and the request body shown for it; never sent
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub dry_run: bool,
+    // Reply served from the response cache; tagged in the header
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub cached: bool,
 }
 
 impl Message {
@@ -33,19 +92,58 @@ impl Message {
         Self {
             role: Role::User,
             content: s.into(),
+            bookmarked: false,
+            timestamp: None,
+            reasoning: String::new(),
+            images: Vec::new(),
+            model: None,
+            wire: None,
+            prompt_tokens: None,
+            completion_tokens: None,
+            context_dropped: None,
+            dry_run: false,
+            cached: false,
         }
     }
     pub fn assistant<S: Into<String>>(s: S) -> Self {
         Self {
             role: Role::Assistant,
             content: s.into(),
+            bookmarked: false,
+            timestamp: None,
+            reasoning: String::new(),
+            images: Vec::new(),
+            model: None,
+            wire: None,
+            prompt_tokens: None,
+            completion_tokens: None,
+            context_dropped: None,
+            dry_run: false,
+            cached: false,
         }
     }
+    pub fn with_timestamp(mut self, ts: i64) -> Self {
+        self.timestamp = Some(ts);
+        self
+    }
+    // Inline "[info] ..." feedback, stored as an assistant message
+    pub fn is_info(&self) -> bool {
+        self.role == Role::Assistant && self.content.starts_with("[info]")
+    }
+}
+
+pub fn now_unix() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Focus {
     Input,
+    // Message selection in the chat pane
+    Chat,
     Sidebar,
     Context,
 }
@@ -64,20 +162,37 @@ pub struct ConfirmState {
 #[derive(Clone)]
 pub enum ConfirmAction {
     DeleteSession(usize),
+    // Paste text held back until the user accepts its size
+    LargePaste(String),
 }
 
 pub struct App {
     pub messages: Vec<Message>,
     pub input: String,
     pub input_cursor: usize,
+    // Other end of the Shift+arrow selection; the cursor is the free end
+    pub input_anchor: Option<usize>,
+    pub input_undo: input::InputUndo,
+    // Ctrl+X asked for $EDITOR; the event loop runs it between frames
+    pub external_edit: bool,
     pub history: Vec<String>,
     pub history_index: Option<usize>,
+    pub history_search: Option<history::HistorySearch>,
+    // Input submitted while a reply streams; sent when the reply ends
+    pub queued_input: Option<String>,
     pub sessions: Vec<String>,
+    // Parallel to `sessions`: name was generated (not set by the user)
+    pub session_auto_named: Vec<bool>,
     pub current_session: usize,
     pub should_quit: bool,
-    pub chat_scroll: u16,
+    pub chat_scroll: usize,
     tick: u64,
     stream: Option<StreamState>,
+    // Output rate of the replies streaming now, for the input border
+    stream_meter: Option<StreamMeter>,
+    // Reply whose request is being sent again: (target, attempt, max), for
+    // the input border until its first output
+    pub stream_retry: Option<(usize, u32, u32)>,
     pub show_sidebar: bool,
     pub show_help: bool,
     pub chat_area: Option<Rect>,
@@ -87,20 +202,50 @@ pub struct App {
     pub rename: Option<RenameState>,
     pub confirm: Option<ConfirmState>,
     pub chat_wrap_width: u16,
+    // Chat width seen on the last draw while resizing, not yet wrapped to
+    pub wrap_pending: Option<u16>,
     pub chat_cache: Vec<WrappedMsg>,
     pub chat_total_lines: usize,
     pub collapsed: Vec<bool>,
     pub collapse_preview_lines: usize,
     pub collapse_threshold_lines: usize,
+    // Set by /collapse; only then are the two saved in ui_state.json, so
+    // config.toml keeps control otherwise
+    pub collapse_overridden: bool,
     pub search_input: Option<SearchInput>,
     pub search_query: Option<String>,
     pub search_hits: Vec<SearchHit>,
     pub search_current: usize,
+    pub global_search: Option<GlobalSearchState>,
+    pub global_search_rx:
+        Option<std::sync::mpsc::Receiver<anyhow::Result<Vec<crate::persist::GlobalHit>>>>,
+    // Session file changed on disk since load/save; save waits for a choice
+    pub conflict: Option<ConflictState>,
+    // Session name -> hash of its file as last loaded or written
+    pub session_fingerprints: std::collections::HashMap<String, Option<u64>>,
+    // Newest message time per session, shown in the sidebar
+    pub session_activity: std::collections::HashMap<String, i64>,
+    // Search ignores case unless the query has an uppercase letter; off
+    // (Alt+C) matches case exactly
+    pub search_smart_case: bool,
+    pub search_whole_word: bool,
+    pub show_timestamps: bool,
+    // Config `timestamp_style = "relative"`: "2h ago" instead of the time
+    pub relative_timestamps: bool,
+    // Expand reasoning blocks (collapsed to one summary line otherwise)
+    pub show_reasoning: bool,
     pub stick_to_bottom: bool,
-    pub chat_viewport: u16,
+    // Text arrived below the view while scrolled up ("↓ new" in the chat border)
+    pub new_below: bool,
+    pub chat_viewport: usize,
     pub input_visible_lines: u16,
     pub input_max_lines: u16,
+    // Needs a redraw
     pub dirty: bool,
+    // UI state changed since `ui_state.json` was last written; flushed from
+    // `on_tick` at most every STATE_SAVE_INTERVAL
+    pub state_dirty: bool,
+    state_saved_at: std::time::Instant,
     // Context pane
     pub show_context: bool,
     pub context_items: Vec<String>,
@@ -111,10 +256,26 @@ pub struct App {
     pub model_picker: Option<ModelPickerState>,
     pub wire_picker: Option<WirePickerState>,
     pub slash_picker: Option<SlashPickerState>,
-    pub llm_rx: Option<std::sync::mpsc::Receiver<StreamEvent>>,
-    pub llm_cancel: Option<Arc<AtomicBool>>,
+    pub bookmark_picker: Option<BookmarkPickerState>,
+    pub template_picker: Option<templates::TemplatePickerState>,
+    pub profile_picker: Option<profiles::ProfilePickerState>,
+    // `v` in the chat: rows picked for copying
+    pub visual: Option<selection::VisualSelection>,
+    // Images waiting to be sent with the next message
+    pub pending_attachments: Vec<attachments::Attachment>,
+    // Large paste waiting to be appended to the next message (Ctrl+D drops)
+    pub pending_paste: Option<attachments::PastedText>,
+    pub paste_attach_lines: usize,
+    pub paste_attach_bytes: usize,
+    // (target message index, event); one sender per in-flight reply
+    pub llm_rx: Option<std::sync::mpsc::Receiver<(usize, StreamEvent)>>,
+    pub llm_cancel: Option<fast_core::llm::CancellationToken>,
     // Provider/model info for status bar
     pub provider_label: String,
+    // Registry id used to build the client for submit
+    pub provider_id: String,
+    // Preset provider without a Responses endpoint: wire stays "chat"
+    pub chat_only: bool,
     pub model_label: String,
     pub wire_label: String,
     // Sampling overrides
@@ -123,18 +284,144 @@ pub struct App {
     pub max_tokens: Option<u32>,
     // Model suggestions from config
     pub model_suggestions: Vec<String>,
+    // Models of the active provider preset (used when no suggestions are configured)
+    pub preset_models: Vec<String>,
     // Last-turn usage tokens (if provided by provider)
     pub usage_prompt_tokens: Option<u32>,
     pub usage_completion_tokens: Option<u32>,
+    // Rate-limit headroom from the last response; cleared when the model changes
+    pub rate_info: Option<fast_core::llm::RateInfo>,
+    // Release check (config `update_check`)
+    // `None` from the check means it succeeded with nothing newer
+    pub update_rx: Option<std::sync::mpsc::Receiver<Option<crate::update::ReleaseInfo>>>,
+    pub update_notice: Option<crate::update::ReleaseInfo>,
+    pub update_last_checked: Option<i64>,
+    pub changelog: Option<ChangelogState>,
+    pub log_view: Option<logview::LogViewState>,
+    // Role templates for Markdown export and print-on-exit (config `[transcript]`)
+    pub transcript: crate::format::Templates,
+    // Preset colors with theme.toml overrides
+    pub theme: crate::theme::Theme,
+    // Active preset, switched with /theme
+    pub theme_name: String,
+    // Message highlighted while the chat pane has focus
+    pub selected_message: Option<usize>,
+    // ((index, content bytes), (chars, tokens)) behind the chat footer
+    selected_stats: Option<((usize, usize), (usize, usize))>,
+    // Render assistant Markdown in the current session (/markdown), else
+    // config `render_markdown`
+    pub render_markdown: bool,
+    pub markdown_default: bool,
+    // Line-number gutter in fenced code (config `code_line_numbers`, /lineno)
+    pub code_line_numbers: bool,
+    // Wrap chat lines to the pane in the current session (/wrap), else
+    // `wrap_default`; off clips them instead, and Left/Right in the chat
+    // scroll sideways by `chat_hscroll` columns
+    pub wrap_lines: bool,
+    pub wrap_default: bool,
+    pub chat_hscroll: u16,
+    // Narrow-terminal layout, toggled by ui::draw from the frame width
+    pub compact: bool,
+    // Config `compact_width`; 0 disables compact mode
+    pub compact_width: u16,
+    // Replies replaced by the last /retry, restored by /undo
+    pub retry_undo: Option<retry::RetryUndo>,
+    // Last session deleted from the sidebar, for "Undo delete session"
+    pub deleted_session: Option<sessions::DeletedSession>,
+    // Requests of the latest turn, kept so a failed reply can be re-sent as is
+    sent_replies: Vec<ReplyRequest>,
+    // Targets of those requests that ended in an error
+    pub failed_replies: Vec<usize>,
+    // Failed requests whose empty placeholder was removed, with that placeholder
+    dropped_replies: Vec<(ReplyRequest, Message)>,
+    // Transcript view: input and session edits are ignored (palette toggle)
+    pub read_only: bool,
+    // /dryrun: a prompt shows the request body instead of being sent
+    pub dry_run: bool,
+    // One-line banner above the input ("Copied 12 lines", failed replies)
+    pub notice: Option<Notice>,
+    // Config `clipboard = "osc52"`: skip the system clipboard
+    pub clipboard_osc52: bool,
+    // Config `confirm_quit`: Esc with unsent input must be pressed twice
+    pub confirm_quit: bool,
+    // When Esc last asked for that confirmation
+    quit_armed_at: Option<std::time::Instant>,
+    // Config `max_context_messages` / `max_context_tokens`
+    pub max_context_messages: Option<usize>,
+    pub max_context_tokens: Option<usize>,
+}
+
+pub struct ChangelogState {
+    pub scroll: u16,
 }
 
 impl App {
+    // Columns and rows taken by a panel's borders: compact mode keeps only
+    // the top rule.
+    pub fn panel_chrome(&self) -> (u16, u16) {
+        if self.compact {
+            (0, 1)
+        } else {
+            (2, 2)
+        }
+    }
+
+    pub fn panel_inner(&self, area: Rect) -> Rect {
+        let (w, h) = self.panel_chrome();
+        Rect {
+            x: area.x.saturating_add(w / 2),
+            y: area.y.saturating_add(1),
+            width: area.width.saturating_sub(w),
+            height: area.height.saturating_sub(h),
+        }
+    }
+
+    // Enter compact mode below `compact_width`, leave it only once the
+    // terminal is COMPACT_HYSTERESIS columns wider so resizes near the
+    // threshold don't flap. Returns whether the mode changed.
+    pub fn update_compact(&mut self, width: u16) -> bool {
+        let compact = if self.compact_width == 0 {
+            false
+        } else if self.compact {
+            width < self.compact_width.saturating_add(COMPACT_HYSTERESIS)
+        } else {
+            width < self.compact_width
+        };
+        if compact == self.compact {
+            return false;
+        }
+        self.compact = compact;
+        self.chat_wrap_width = 0;
+        self.dirty = true;
+        true
+    }
+
+    // Append an inline "[info] ..." block to the transcript, for output
+    // longer than the one-line notice. It is never sent to the model.
+    fn push_info(&mut self, text: &str) {
+        self.messages.push(Message::assistant(text));
+        self.collapsed.push(false);
+    }
     fn set_sampling_temp(&mut self, t: Option<f32>) {
         self.temperature = t;
     }
     fn set_sampling_top_p(&mut self, p: Option<f32>) {
         self.top_p = p;
     }
+    fn set_theme(&mut self, name: &str) {
+        let name = name.trim().to_lowercase();
+        if crate::theme::preset(&name).is_none() {
+            self.notify(
+                NoticeLevel::Info,
+                format!("usage: /theme <{}>", crate::theme::PRESETS.join("|")),
+            );
+            return;
+        }
+        self.theme = crate::theme::Theme::load(&name);
+        self.theme_name = name;
+        self.dirty = true;
+        self.mark_state_dirty();
+    }
     fn set_sampling_max_tokens(&mut self, m: Option<u32>) {
         self.max_tokens = m;
     }
@@ -157,13 +444,12 @@ impl App {
                     return true;
                 }
                 self.model_label = arg.to_string();
-                let _ = crate::persist::save_state(self);
-                // Show an inline info line to the user
-                self.messages.push(Message::assistant(format!(
-                    "[info] model set to '{}'",
-                    self.model_label
-                )));
-                self.collapsed.push(false);
+                self.rate_info = None;
+                self.mark_state_dirty();
+                self.notify(
+                    NoticeLevel::Info,
+                    format!("model set to '{}'", self.model_label),
+                );
                 true
             }
             "wire" => {
@@ -173,14 +459,28 @@ impl App {
                     return true;
                 }
                 let v = arg.to_lowercase();
+                if self.chat_only && v != "chat" {
+                    self.notify(
+                        NoticeLevel::Info,
+                        format!("{} only supports the chat wire", self.provider_label),
+                    );
+                    return true;
+                }
                 if matches!(v.as_str(), "responses" | "chat" | "auto") {
                     self.wire_label = v;
-                    let _ = crate::persist::save_state(self);
-                    self.messages.push(Message::assistant(format!(
-                        "[info] wire set to '{}'",
-                        self.wire_label
-                    )));
-                    self.collapsed.push(false);
+                    self.mark_state_dirty();
+                    self.notify(
+                        NoticeLevel::Info,
+                        format!("wire set to '{}'", self.wire_label),
+                    );
+                }
+                true
+            }
+            "profile" => {
+                if arg.is_empty() {
+                    self.open_profile_picker();
+                } else {
+                    self.switch_profile(arg);
                 }
                 true
             }
@@ -188,16 +488,190 @@ impl App {
                 self.show_help = true;
                 true
             }
+            "bookmarks" => {
+                self.push_bookmarks_info();
+                true
+            }
+            "attach" => {
+                if arg.is_empty() {
+                    self.notify(NoticeLevel::Info, "usage: /attach <path.png|.jpg>");
+                } else {
+                    self.attach_file(arg);
+                }
+                true
+            }
+            "bugreport" => {
+                self.bug_report(arg);
+                true
+            }
+            "template" => {
+                if arg.is_empty() {
+                    self.open_template_picker();
+                } else {
+                    self.input.clear();
+                    self.input_cursor = 0;
+                    self.insert_template_named(arg);
+                }
+                true
+            }
+            "cache" => {
+                if arg == "clear" {
+                    match providers::cache::clear() {
+                        Ok(n) => {
+                            self.notify(NoticeLevel::Info, format!("cleared {} cached replies", n))
+                        }
+                        Err(e) => {
+                            self.notify(NoticeLevel::Error, format!("cache clear failed: {:#}", e))
+                        }
+                    }
+                } else {
+                    self.notify(NoticeLevel::Info, "usage: /cache clear");
+                }
+                true
+            }
+            "compare" => {
+                self.compare(arg);
+                true
+            }
+            "collapse" => {
+                self.collapse_command(arg);
+                true
+            }
+            "dryrun" => {
+                self.dry_run = match arg {
+                    "on" => true,
+                    "off" => false,
+                    "" => !self.dry_run,
+                    _ => {
+                        self.notify(NoticeLevel::Info, "usage: /dryrun [on|off]");
+                        return true;
+                    }
+                };
+                let note = if self.dry_run {
+                    "dry run on: prompts show the request body instead of being sent"
+                } else {
+                    "dry run off"
+                };
+                self.notify(NoticeLevel::Info, note);
+                true
+            }
+            "markdown" => {
+                match arg {
+                    "on" | "off" => {
+                        self.render_markdown = arg == "on";
+                        self.chat_wrap_width = 0;
+                        self.save_session_view(|v| v.markdown = Some(arg == "on"));
+                        self.notify(
+                            NoticeLevel::Info,
+                            format!("markdown rendering {} for this session", arg),
+                        );
+                    }
+                    _ => self.notify(NoticeLevel::Info, "usage: /markdown on|off"),
+                }
+                true
+            }
+            "lineno" => {
+                match arg {
+                    "on" | "off" => {
+                        self.code_line_numbers = arg == "on";
+                        self.chat_wrap_width = 0;
+                        let note = if self.render_markdown {
+                            format!("code line numbers {}", arg)
+                        } else {
+                            format!("code line numbers {} (shown with /markdown on)", arg)
+                        };
+                        self.notify(NoticeLevel::Info, note);
+                    }
+                    _ => self.notify(NoticeLevel::Info, "usage: /lineno on|off"),
+                }
+                true
+            }
+            "wrap" => {
+                match arg {
+                    "on" | "off" => {
+                        self.set_wrap_lines(arg == "on");
+                        self.notify(
+                            NoticeLevel::Info,
+                            format!("line wrap {} for this session", arg),
+                        );
+                    }
+                    _ => self.notify(NoticeLevel::Info, "usage: /wrap on|off"),
+                }
+                true
+            }
+            "theme" => {
+                if arg.is_empty() {
+                    self.notify(
+                        NoticeLevel::Info,
+                        format!(
+                            "theme: {} (available: {})",
+                            self.theme_name,
+                            crate::theme::PRESETS.join(", ")
+                        ),
+                    );
+                } else {
+                    self.set_theme(arg);
+                }
+                true
+            }
+            "env" => {
+                self.env_command(arg);
+                true
+            }
+            "retry" => {
+                self.retry();
+                true
+            }
+            "undo" => {
+                self.undo_retry();
+                true
+            }
+            "branch" => {
+                self.branch_from_selected();
+                true
+            }
+            "changelog" => {
+                match &self.update_notice {
+                    Some(_) => self.changelog = Some(ChangelogState { scroll: 0 }),
+                    None => self.notify(
+                        NoticeLevel::Info,
+                        format!(
+                            "no newer release known (running v{})",
+                            crate::update::CURRENT_VERSION
+                        ),
+                    ),
+                }
+                true
+            }
+            "export" => {
+                self.export_current_session(arg);
+                true
+            }
+            "import" => {
+                let mut words = arg.splitn(2, char::is_whitespace);
+                let first = words.next().unwrap_or("");
+                match crate::import::ArchiveKind::parse(first) {
+                    Some(kind) => {
+                        let path = words.next().unwrap_or("").trim();
+                        if path.is_empty() {
+                            self.notify(NoticeLevel::Info, "usage: /import chatgpt <conversations.json> | /import claude <export dir>");
+                        } else {
+                            self.import_archive_inline(kind, path);
+                        }
+                    }
+                    None if arg.is_empty() => {
+                        self.notify(NoticeLevel::Info, "usage: /import <path.json> | /import chatgpt <file> | /import claude <dir>");
+                    }
+                    None => self.import_session(arg),
+                }
+                true
+            }
             "temp" => {
                 if !arg.is_empty() {
                     if let Ok(v) = arg.parse::<f32>() {
                         self.set_sampling_temp(Some(v));
-                        self.messages.push(Message::assistant(format!(
-                            "[info] temperature set to {}",
-                            v
-                        )));
-                        self.collapsed.push(false);
-                        let _ = crate::persist::save_state(self);
+                        self.notify(NoticeLevel::Info, format!("temperature set to {}", v));
+                        self.mark_state_dirty();
                     }
                 }
                 true
@@ -206,10 +680,8 @@ impl App {
                 if !arg.is_empty() {
                     if let Ok(v) = arg.parse::<f32>() {
                         self.set_sampling_top_p(Some(v));
-                        self.messages
-                            .push(Message::assistant(format!("[info] top_p set to {}", v)));
-                        self.collapsed.push(false);
-                        let _ = crate::persist::save_state(self);
+                        self.notify(NoticeLevel::Info, format!("top_p set to {}", v));
+                        self.mark_state_dirty();
                     }
                 }
                 true
@@ -218,12 +690,8 @@ impl App {
                 if !arg.is_empty() {
                     if let Ok(v) = arg.parse::<u32>() {
                         self.set_sampling_max_tokens(Some(v));
-                        self.messages.push(Message::assistant(format!(
-                            "[info] max_tokens set to {}",
-                            v
-                        )));
-                        self.collapsed.push(false);
-                        let _ = crate::persist::save_state(self);
+                        self.notify(NoticeLevel::Info, format!("max_tokens set to {}", v));
+                        self.mark_state_dirty();
                     }
                 }
                 true
@@ -231,19 +699,29 @@ impl App {
             _ => true, // Unknown slash cmd: consume it quietly
         }
     }
-    pub fn new() -> Self {
-        let mut s = Self {
+    // App with built-in defaults only: reads no config, state or session files.
+    // Rendering harnesses build fixtures from this instead of `new`.
+    pub fn blank() -> Self {
+        Self {
             messages: vec![Message::assistant("Welcome to fast TUI (preview). Enter: send; Shift+Enter: newline; Esc/Ctrl-C: quit.")],
             input: String::new(),
             input_cursor: 0,
+            input_anchor: None,
+            input_undo: input::InputUndo::default(),
+            external_edit: false,
             history: Vec::new(),
             history_index: None,
+            history_search: None,
+            queued_input: None,
             sessions: vec!["default".to_string()],
+            session_auto_named: vec![false],
             current_session: 0,
             should_quit: false,
             chat_scroll: 0,
             tick: 0,
             stream: None,
+            stream_meter: None,
+            stream_retry: None,
             show_sidebar: false,
             show_help: false,
             chat_area: None,
@@ -253,20 +731,35 @@ impl App {
             rename: None,
             confirm: None,
             chat_wrap_width: 0,
+            wrap_pending: None,
             chat_cache: Vec::new(),
             chat_total_lines: 0,
             collapsed: Vec::new(),
-            collapse_preview_lines: 8,
-            collapse_threshold_lines: 40,
+            collapse_preview_lines: DEFAULT_COLLAPSE_PREVIEW_LINES,
+            collapse_threshold_lines: DEFAULT_COLLAPSE_THRESHOLD_LINES,
+            collapse_overridden: false,
             search_input: None,
             search_query: None,
             search_hits: Vec::new(),
             search_current: 0,
+            global_search: None,
+            global_search_rx: None,
+            conflict: None,
+            session_fingerprints: std::collections::HashMap::new(),
+            session_activity: std::collections::HashMap::new(),
+            search_smart_case: true,
+            search_whole_word: false,
+            show_timestamps: false,
+            relative_timestamps: false,
+            show_reasoning: false,
             stick_to_bottom: true,
+            new_below: false,
             chat_viewport: 0,
             input_visible_lines: 1,
             input_max_lines: 6,
             dirty: true,
+            state_dirty: false,
+            state_saved_at: std::time::Instant::now(),
             show_context: false,
             context_items: Vec::new(),
             context_area: None,
@@ -276,37 +769,172 @@ impl App {
             model_picker: None,
             wire_picker: None,
             slash_picker: None,
+            bookmark_picker: None,
+            template_picker: None,
+            profile_picker: None,
+            visual: None,
+            pending_attachments: Vec::new(),
+            pending_paste: None,
+            paste_attach_lines: DEFAULT_PASTE_ATTACH_LINES,
+            paste_attach_bytes: DEFAULT_PASTE_ATTACH_BYTES,
             llm_rx: None,
             llm_cancel: None,
             provider_label: String::from("OpenAI"),
+            provider_id: providers::openai::config::DEFAULT_PROVIDER_ID.to_string(),
+            chat_only: false,
             model_label: String::from("gpt-5"),
             wire_label: String::from("responses"),
             temperature: None,
             top_p: None,
             max_tokens: None,
             model_suggestions: Vec::new(),
+            preset_models: Vec::new(),
             usage_prompt_tokens: None,
             usage_completion_tokens: None,
+            rate_info: None,
+            update_rx: None,
+            update_notice: None,
+            update_last_checked: None,
+            changelog: None,
+            log_view: None,
+            transcript: crate::format::Templates::default(),
+            theme: crate::theme::Theme::default(),
+            theme_name: "dark".into(),
+            selected_message: None,
+            selected_stats: None,
+            render_markdown: false,
+            markdown_default: false,
+            code_line_numbers: false,
+            wrap_lines: true,
+            wrap_default: true,
+            chat_hscroll: 0,
+            compact: false,
+            compact_width: DEFAULT_COMPACT_WIDTH,
+            read_only: false,
+            dry_run: false,
+            retry_undo: None,
+            deleted_session: None,
+            sent_replies: Vec::new(),
+            failed_replies: Vec::new(),
+            dropped_replies: Vec::new(),
+            notice: None,
+            clipboard_osc52: false,
+            confirm_quit: false,
+            quit_armed_at: None,
+            max_context_messages: None,
+            max_context_tokens: None,
+        }
+    }
+
+    // Endpoint labels, model and wire of a freshly read config. Under
+    // FAST_MOCK the mock provider stays in place.
+    fn apply_provider_config(&mut self, cfg: &providers::openai::config::OpenAiConfig) {
+        crate::redact::add_api_key(&cfg.api_key);
+        self.model_label = cfg.model.clone();
+        self.wire_label = cfg.wire_api.clone();
+        self.model_suggestions = cfg.model_suggestions.clone();
+        self.preset_models = cfg.preset_models();
+        if providers::mock_enabled() {
+            return;
+        }
+        self.provider_label = match &cfg.profile {
+            Some(p) => format!("{} [{}]", cfg.provider_label(), p),
+            None => cfg.provider_label().to_string(),
         };
+        self.provider_id = cfg.provider_id().to_string();
+        self.chat_only = cfg.chat_only();
+    }
+
+    pub fn new() -> Self {
+        let mut s = Self::blank();
+        let mut update_url = None;
+        // Config problems that would otherwise only show as failed requests
+        let mut config_problems = Vec::new();
+        if let Err(e) = providers::openai::config::OpenAiFileConfig::load_checked() {
+            config_problems.push(format!("{:#}", e));
+        }
+        if let Err(e) = providers::openai::config::env_wire() {
+            config_problems.push(format!("{:#}", e));
+        }
+        let saved = crate::persist::load_state().ok().flatten();
+        // The profile from last run, while config.toml still has it
+        if let Some(name) = saved.as_ref().and_then(|p| p.profile.clone()) {
+            if providers::openai::config::OpenAiFileConfig::profile_names().contains(&name) {
+                providers::openai::config::set_active_profile(Some(name));
+            }
+        }
         // Try to read provider config for status
-        if let Ok(cfg) = providers::openai::config::OpenAiConfig::from_env_and_file() {
-            s.model_label = cfg.model.clone();
-            s.wire_label = cfg.wire_api.clone();
-            s.model_suggestions = cfg.model_suggestions.clone();
+        if let Ok(cfg) = providers::openai::config::OpenAiConfig::from_env_and_file(None) {
+            if let Err(e) = cfg.validate() {
+                config_problems.push(format!("{:#}", e));
+            }
+            s.transcript = crate::format::Templates::from_config(&cfg.transcript);
+            if cfg.update_check {
+                update_url = Some(cfg.update_url.clone());
+            }
+            s.apply_provider_config(&cfg);
+            match crate::redact::LogContent::parse(&cfg.log_content) {
+                Some(p) => crate::redact::set_policy(p),
+                None => {
+                    warn!(target: "tui", "unknown log_content '{}', using off", cfg.log_content)
+                }
+            }
+        }
+        // UI settings; values saved by /collapse override the file
+        if let Some(file) = providers::openai::config::OpenAiFileConfig::load() {
+            if let Some(w) = file.compact_width {
+                s.compact_width = w;
+            }
+            s.markdown_default = file.render_markdown.unwrap_or(false);
+            s.render_markdown = s.markdown_default;
+            s.code_line_numbers = file.code_line_numbers.unwrap_or(false);
+            s.relative_timestamps = file
+                .timestamp_style
+                .is_some_and(|t| t.trim().eq_ignore_ascii_case("relative"));
+            s.clipboard_osc52 = file
+                .clipboard
+                .is_some_and(|c| c.trim().eq_ignore_ascii_case("osc52"));
+            s.set_collapse_lines(file.collapse_preview_lines, file.collapse_threshold_lines);
+            s.confirm_quit = file.confirm_quit.unwrap_or(false);
+            s.max_context_messages = file.max_context_messages.filter(|n| *n > 0);
+            s.max_context_tokens = file.max_context_tokens.filter(|n| *n > 0);
+            s.paste_attach_lines = file
+                .paste_attach_lines
+                .unwrap_or(DEFAULT_PASTE_ATTACH_LINES);
+            s.paste_attach_bytes = file
+                .paste_attach_bytes
+                .unwrap_or(DEFAULT_PASTE_ATTACH_BYTES);
         }
-        if let Ok(Some(p)) = crate::persist::load_state() {
+        s.history = crate::persist::load_history();
+        if let Some(p) = saved {
             if !p.sessions.is_empty() {
                 s.sessions = p.sessions;
             }
+            s.session_auto_named = match p.session_auto_named {
+                Some(v) if v.len() == s.sessions.len() => v,
+                _ => s
+                    .sessions
+                    .iter()
+                    .map(|n| sessions::is_default_session_name(n))
+                    .collect(),
+            };
             if !s.sessions.is_empty() {
                 s.current_session = p.current_session.min(s.sessions.len() - 1);
             }
             s.show_sidebar = p.show_sidebar;
             s.sidebar_scroll = p.sidebar_scroll;
-            if let Some(m) = p.model {
+            // FAST_MODEL / FAST_WIRE win over the last run's picks
+            let env_set = |k| std::env::var(k).is_ok_and(|v| !v.trim().is_empty());
+            if let Some(m) = p
+                .model
+                .filter(|_| !env_set(providers::openai::config::MODEL_ENV))
+            {
                 s.model_label = m;
             }
-            if let Some(w) = p.wire_api {
+            if let Some(w) = p
+                .wire_api
+                .filter(|_| !s.chat_only && !env_set(providers::openai::config::WIRE_ENV))
+            {
                 s.wire_label = w;
             }
             if let Some(t) = p.temperature {
@@ -318,20 +946,80 @@ impl App {
             if let Some(m) = p.max_tokens {
                 s.max_tokens = Some(m);
             }
+            if let Some(smart) = p.search_smart_case {
+                s.search_smart_case = smart;
+            }
+            if let Some(ww) = p.search_whole_word {
+                s.search_whole_word = ww;
+            }
+            if let Some(ts) = p.show_timestamps {
+                s.show_timestamps = ts;
+            }
+            if let Some(r) = p.show_reasoning {
+                s.show_reasoning = r;
+            }
+            if let Some(t) = p.theme.filter(|t| crate::theme::preset(t).is_some()) {
+                s.theme_name = t;
+            }
+            // Older state files saved the lines even when unchanged; those
+            // matching config.toml leave it in charge
+            let from_config = (s.collapse_preview_lines, s.collapse_threshold_lines);
+            s.set_collapse_lines(p.collapse_preview_lines, p.collapse_threshold_lines);
+            s.collapse_overridden =
+                (s.collapse_preview_lines, s.collapse_threshold_lines) != from_config;
+            s.update_last_checked = p.update_last_checked;
         }
+        s.theme = crate::theme::Theme::load(&s.theme_name);
         if !s.sessions.is_empty() {
             if let Ok(msgs) = crate::persist::load_session(&s.sessions[s.current_session]) {
                 if !msgs.is_empty() {
                     s.messages = msgs;
                 }
             }
+            s.apply_session_view();
+            s.record_session_fingerprint();
+        }
+        for name in &s.sessions {
+            if let Some(ts) = crate::persist::session_last_activity(name) {
+                s.session_activity.insert(name.clone(), ts);
+            }
+        }
+        // FAST_MOCK: offline scripted replies, no API key needed
+        if providers::mock_enabled() {
+            s.provider_id = providers::MOCK_PROVIDER_ID.to_string();
+            s.provider_label = "Mock".to_string();
+            s.chat_only = false;
+        }
+        if let Some(url) = update_url {
+            let now = now_unix();
+            // The check time is recorded when the fetch succeeds (on_tick)
+            if crate::update::check_due(s.update_last_checked, now) {
+                s.update_rx = Some(crate::update::spawn_check(url));
+            }
+        }
+        if crate::redact::policy() == crate::redact::LogContent::Full {
+            warn!(target: "tui", "log_content=full: conversation text is written to the log");
+            s.notify(
+                NoticeLevel::Warning,
+                "log_content = \"full\": conversation text is being written to the log file",
+            );
+        }
+        // Config problems matter more than the log warning, so they take the notice
+        if !config_problems.is_empty() {
+            for problem in &config_problems {
+                warn!(target: "tui", "config: {}", problem);
+            }
+            s.notify(
+                NoticeLevel::Error,
+                format!("config problem: {}", config_problems.join("; ")),
+            );
         }
         s
     }
 
     pub fn submit(&mut self) {
         let text = self.input.trim().to_string();
-        if text.is_empty() {
+        if text.is_empty() && self.pending_attachments.is_empty() && self.pending_paste.is_none() {
             return;
         }
         // Reset last-turn usage at the start of a new request
@@ -340,128 +1028,236 @@ impl App {
 
         // Slash commands (e.g., /model <name>, /wire <responses|chat|auto>)
         if self.try_handle_slash_command(&text) {
-            self.input.clear();
-            self.input_cursor = 0;
+            // A command that refilled the input (/template) keeps its text
+            if self.input.trim() == text {
+                self.input.clear();
+                self.input_cursor = 0;
+                self.input_undo.clear();
+            }
             self.dirty = true;
             return;
         }
 
+        // Only a held paste or images: nothing to queue, they go with the
+        // next message sent
+        if text.is_empty() && self.llm_rx.is_some() {
+            self.notify(
+                NoticeLevel::Info,
+                "reply streaming; the attachment waits for the next message",
+            );
+            return;
+        }
         self.record_history_entry(&text);
-        self.messages.push(Message::user(text.clone()));
-        self.collapsed.push(false);
+        if self.llm_rx.is_some() {
+            // One slot: a second follow-up joins the first
+            self.queued_input = Some(match self.queued_input.take() {
+                Some(q) => format!("{}\n\n{}", q, text),
+                None => text,
+            });
+        } else {
+            let model = self.model_label.clone();
+            self.send_prompt(&text, vec![model]);
+        }
+        self.input.clear();
+        self.input_cursor = 0;
+        self.input_undo.clear();
+        self.stick_to_bottom = true;
+        self.chat_scroll = 0;
+        self.dirty = true;
+    }
 
-        let _assistant_index = self.messages.len();
-        self.messages.push(Message::assistant(String::new()));
-        self.collapsed.push(false);
-        // Start real LLM streaming in a background thread
-        let (tx, rx) = std::sync::mpsc::channel::<StreamEvent>();
-        self.llm_rx = Some(rx);
-        let cancel_flag = Arc::new(AtomicBool::new(false));
-        self.llm_cancel = Some(cancel_flag.clone());
+    // Push the user turn and one assistant placeholder per model, then stream
+    // each reply on its own thread. Replies share one channel and cancel token.
+    pub fn send_prompt(&mut self, text: &str, models: Vec<String>) {
+        // A held-back paste goes after the typed text, folded in the chat
+        let pasted = self.take_pasted_text();
+        let content = match &pasted {
+            Some(p) if text.is_empty() => p.clone(),
+            Some(p) => format!("{}\n\n{}", text, p),
+            None => text.to_string(),
+        };
+        let mut user_msg = Message::user(content.as_str()).with_timestamp(now_unix());
+        user_msg.images = self.take_pending_images();
+        self.messages.push(user_msg);
+        self.collapsed.push(pasted.is_some());
+        self.maybe_auto_title(if text.is_empty() { &content } else { text });
+        self.retry_undo = None;
+        // Log submit intent (model/wire)
+        info!(target: "tui", "submit: models={} wire={} input_len={} chars content={}", models.join(","), self.wire_label, content.len(), crate::redact::preview(&content));
+        self.start_replies(models);
+    }
+
+    // Stream one reply per model to the conversation so far, which ends with
+    // the user turn being answered.
+    fn start_replies(&mut self, models: Vec<String>) {
         // Build snapshot for provider: drop any assistant messages before the
         // first user message (e.g., the initial welcome banner), and skip
-        // empty assistant placeholders we append for streaming.
+        // empty assistant placeholders we append for streaming and "[info]"
+        // blocks, which are UI output (/env, /bookmarks). System
+        // messages go first wherever they are, and are never trimmed.
         let first_user_idx = self
             .messages
             .iter()
             .position(|m| matches!(m.role, Role::User))
             .unwrap_or(0);
-        let msgs_snapshot = self.messages[first_user_idx..]
-            .iter()
-            .filter(|m| !(matches!(m.role, Role::Assistant) && m.content.trim().is_empty()))
-            .map(|m| fast_core::llm::Message {
+        let to_llm = |m: &Message| {
+            let mut content = vec![fast_core::llm::ContentPart::Text(m.content.clone())];
+            content.extend(m.images.iter().filter_map(attachments::image_part));
+            fast_core::llm::Message {
                 role: match m.role {
                     Role::User => fast_core::llm::Role::User,
                     Role::Assistant => fast_core::llm::Role::Assistant,
+                    Role::System => fast_core::llm::Role::System,
                 },
-                content: m.content.clone(),
-            })
+                content,
+            }
+        };
+        // Earlier /dryrun turns stay out; the turn being answered is last
+        let last = self.messages.len().saturating_sub(1);
+        let mut msgs_snapshot = self.messages[first_user_idx..]
+            .iter()
+            .enumerate()
+            .filter(|(i, m)| first_user_idx + i == last || !m.dry_run)
+            .map(|(_, m)| m)
+            .filter(|m| !(matches!(m.role, Role::Assistant) && m.content.trim().is_empty()))
+            .filter(|m| m.role != Role::System && !m.is_info())
+            .map(to_llm)
             .collect::<Vec<_>>();
-        // Log submit intent (model/wire)
-        info!(target: "tui", "submit: model={} wire={} input_len={} chars", self.model_label, self.wire_label, text.len());
-        // Capture runtime selections for this request
-        let selected_model = self.model_label.clone();
-        let selected_wire = self.wire_label.clone();
-        let sel_temp = self.temperature;
-        let sel_top_p = self.top_p;
-        let sel_max_tokens = self.max_tokens;
-        std::thread::spawn(move || {
-            let rt = tokio::runtime::Runtime::new().expect("rt");
-            let _ = rt.block_on(async move {
-                let cfg = match providers::openai::config::OpenAiConfig::from_env_and_file() {
-                    Ok(c) => c,
-                    Err(e) => {
-                        let _ = tx.send(StreamEvent::Error(format!("config: {}", e)));
-                        error!(target: "tui", "submit config error: {}", e);
-                        return;
-                    }
-                };
-                let client = match providers::openai::OpenAiClient::new(cfg.clone()) {
-                    Ok(c) => c,
-                    Err(e) => {
-                        let _ = tx.send(StreamEvent::Error(format!("client: {}", e)));
-                        error!(target: "tui", "submit client build error: {}", e);
-                        return;
-                    }
-                };
-                let opts = fast_core::llm::ChatOpts {
-                    model: selected_model.clone(),
-                    temperature: sel_temp,
-                    top_p: sel_top_p,
-                    max_tokens: sel_max_tokens,
-                };
-                let wire = match selected_wire.as_str() {
-                    "chat" => fast_core::llm::ChatWire::Chat,
-                    "responses" => fast_core::llm::ChatWire::Responses,
-                    "auto" => fast_core::llm::ChatWire::Auto,
-                    _ => fast_core::llm::ChatWire::Responses,
-                };
-                let res = client.stream_chat(msgs_snapshot, opts, wire).await;
-                match res {
-                    Ok(mut s) => {
-                        use futures::StreamExt;
-                        let mut tick = tokio::time::interval(std::time::Duration::from_millis(100));
-                        loop {
-                            tokio::select! {
-                                _ = tick.tick() => {
-                                    if cancel_flag.load(Ordering::Relaxed) {
-                                        let _ = tx.send(StreamEvent::Error("canceled".into()));
-                                        break;
-                                    }
-                                }
-                                it = s.next() => {
-                                    match it {
-                                        Some(Ok(fast_core::llm::ChatDelta::Text(t))) => { let _ = tx.send(StreamEvent::Text(t)); }
-                                        Some(Ok(fast_core::llm::ChatDelta::Usage{prompt_tokens, completion_tokens})) => { let _ = tx.send(StreamEvent::Usage{prompt_tokens, completion_tokens}); }
-                                        Some(Ok(fast_core::llm::ChatDelta::Finish(_))) => { break; }
-                                        Some(Ok(_)) => { /* ignore other events for now */ }
-                                        Some(Err(e)) => {
-                                            let _ = tx.send(StreamEvent::Error(format!("{}", e)));
-                                            error!(target: "tui", "stream delta error: {}", e);
-                                            break;
-                                        }
-                                        None => { break; }
-                                    }
-                                }
-                            }
-                        }
-                    }
-                    Err(e) => {
-                        let _ = tx.send(StreamEvent::Error(format!("{}", e)));
-                        error!(target: "tui", "stream start error: {}", e);
-                    }
-                }
-            });
-        });
-        self.input.clear();
-        self.input_cursor = 0;
-        self.stick_to_bottom = true;
-        self.chat_scroll = 0;
+        let dropped = trim_context(
+            &mut msgs_snapshot,
+            self.max_context_messages,
+            self.max_context_tokens,
+        );
+        if dropped > 0 {
+            info!(target: "tui", "context: dropped {} oldest messages", dropped);
+        }
+        let system = self
+            .messages
+            .iter()
+            .filter(|m| m.role == Role::System && !m.content.trim().is_empty())
+            .map(to_llm);
+        msgs_snapshot.splice(0..0, system);
+        let wire = chat_wire(&self.wire_label);
+        // A retried dry run is sent for real once /dryrun is off
+        if let Some(turn) = self.messages.last_mut() {
+            turn.dry_run = self.dry_run;
+        }
+        if self.dry_run {
+            self.show_dry_run(models, &msgs_snapshot, wire, dropped);
+            return;
+        }
+        let (tx, rx) = std::sync::mpsc::channel::<(usize, StreamEvent)>();
+        self.llm_rx = Some(rx);
+        let cancel = fast_core::llm::CancellationToken::new();
+        self.llm_cancel = Some(cancel.clone());
+        self.sent_replies.clear();
+        self.failed_replies.clear();
+        self.dropped_replies.clear();
+        for model in models {
+            let mut placeholder = Message::assistant(String::new());
+            placeholder.model = Some(model.clone());
+            placeholder.wire = Some(self.wire_label.clone());
+            placeholder.context_dropped = (dropped > 0).then_some(dropped);
+            self.messages.push(placeholder);
+            self.collapsed.push(false);
+            // Capture runtime selections for this request
+            let req = ReplyRequest {
+                target: self.messages.len() - 1,
+                provider: self.provider_id.clone(),
+                msgs: msgs_snapshot.clone(),
+                opts: fast_core::llm::ChatOpts {
+                    model,
+                    temperature: self.temperature,
+                    top_p: self.top_p,
+                    max_tokens: self.max_tokens,
+                    reasoning_summary: self.show_reasoning,
+                },
+                wire,
+                cancel: cancel.clone(),
+            };
+            self.sent_replies.push(req.clone());
+            spawn_reply(req, tx.clone());
+        }
+    }
+
+    // Once the stream ends: send the queued follow-up, or hand it back to
+    // the input when a reply failed so it is not sent after a broken turn.
+    fn flush_queued_input(&mut self) {
+        let Some(text) = self.queued_input.take() else {
+            return;
+        };
+        if self.failed_replies.is_empty() && self.dropped_replies.is_empty() {
+            let model = self.model_label.clone();
+            self.send_prompt(&text, vec![model]);
+            self.stick_to_bottom = true;
+            self.chat_scroll = 0;
+        } else {
+            self.unqueue_input();
+        }
         self.dirty = true;
     }
 
+    // Move the queued follow-up back into the input for editing, after
+    // whatever is typed there already.
+    fn unqueue_input(&mut self) {
+        let Some(text) = self.queued_input.take() else {
+            return;
+        };
+        let text = if self.input.trim().is_empty() {
+            text
+        } else {
+            format!("{}\n{}", text, self.input)
+        };
+        self.replace_input(text);
+        self.dirty = true;
+    }
+
+    // Esc outside any popup: take back a queued follow-up, else stop the
+    // stream if one is running, otherwise quit (twice within a second with
+    // `confirm_quit` and unsent input).
+    fn on_esc(&mut self) {
+        if self.queued_input.is_some() {
+            self.unqueue_input();
+            return;
+        }
+        if self.llm_rx.is_some() {
+            if let Some(cancel) = &self.llm_cancel {
+                cancel.cancel();
+            }
+            self.notify(NoticeLevel::Info, crate::strings::STREAM_CANCELED);
+            return;
+        }
+        let armed = self
+            .quit_armed_at
+            .take()
+            .is_some_and(|at| at.elapsed() < QUIT_CONFIRM_WINDOW);
+        if self.confirm_quit && !self.input.trim().is_empty() && !armed {
+            self.quit_armed_at = Some(std::time::Instant::now());
+            self.notify(NoticeLevel::Warning, crate::strings::CONFIRM_QUIT);
+            return;
+        }
+        self.should_quit = true;
+    }
+
+    // SIGINT/SIGTERM/SIGHUP: stop a reply, keep what arrived, and quit
+    // without the confirmations a key press would get.
+    pub fn on_terminate_signal(&mut self) {
+        info!(target: "tui", "quit on signal");
+        if self.llm_rx.is_some() || self.stream.is_some() {
+            if let Some(cancel) = &self.llm_cancel {
+                cancel.cancel();
+            }
+            self.save_current_session();
+        }
+        self.should_quit = true;
+    }
+
     pub fn on_key(&mut self, key: KeyEvent) {
         if let KeyEventKind::Press = key.kind {
+            // Any key dismisses the notice banner
+            if self.notice.take().is_some() {
+                self.dirty = true;
+            }
             if let Some(p) = &mut self.palette {
                 match key.code {
                     KeyCode::Esc => {
@@ -473,25 +1269,19 @@ impl App {
                             self.palette = None;
                         }
                     }
-                    KeyCode::Up => {
-                        if p.selected > 0 {
-                            p.selected -= 1;
-                        }
+                    KeyCode::Up if p.selected > 0 => {
+                        p.selected -= 1;
                     }
-                    KeyCode::Down => {
-                        if p.selected + 1 < p.filtered.len() {
-                            p.selected += 1;
-                        }
+                    KeyCode::Down if p.selected + 1 < p.filtered.len() => {
+                        p.selected += 1;
                     }
-                    KeyCode::Backspace => {
-                        if p.cursor > 0 {
-                            let mut parts: Vec<&str> = p.buffer.graphemes(true).collect();
-                            let c = p.cursor.min(parts.len());
-                            parts.remove(c - 1);
-                            p.buffer = parts.concat();
-                            p.cursor -= 1;
-                            App::palette_filter(p);
-                        }
+                    KeyCode::Backspace if p.cursor > 0 => {
+                        let mut parts: Vec<&str> = p.buffer.graphemes(true).collect();
+                        let c = p.cursor.min(parts.len());
+                        parts.remove(c - 1);
+                        p.buffer = parts.concat();
+                        p.cursor -= 1;
+                        App::palette_filter(p);
                     }
                     KeyCode::Delete => {
                         let mut parts: Vec<&str> = p.buffer.graphemes(true).collect();
@@ -502,10 +1292,8 @@ impl App {
                             App::palette_filter(p);
                         }
                     }
-                    KeyCode::Left => {
-                        if p.cursor > 0 {
-                            p.cursor -= 1;
-                        }
+                    KeyCode::Left if p.cursor > 0 => {
+                        p.cursor -= 1;
                     }
                     KeyCode::Right => {
                         let l = p.buffer.graphemes(true).count();
@@ -519,16 +1307,14 @@ impl App {
                     KeyCode::End => {
                         p.cursor = p.buffer.graphemes(true).count();
                     }
-                    KeyCode::Char(ch) => {
-                        if !key.modifiers.contains(KeyModifiers::CONTROL) {
-                            let mut parts: Vec<&str> = p.buffer.graphemes(true).collect();
-                            let c = p.cursor.min(parts.len());
-                            let mut buf = [0u8; 4];
-                            parts.insert(c, ch.encode_utf8(&mut buf));
-                            p.buffer = parts.concat();
-                            p.cursor += 1;
-                            App::palette_filter(p);
-                        }
+                    KeyCode::Char(ch) if !key.modifiers.contains(KeyModifiers::CONTROL) => {
+                        let mut parts: Vec<&str> = p.buffer.graphemes(true).collect();
+                        let c = p.cursor.min(parts.len());
+                        let mut buf = [0u8; 4];
+                        parts.insert(c, ch.encode_utf8(&mut buf));
+                        p.buffer = parts.concat();
+                        p.cursor += 1;
+                        App::palette_filter(p);
                     }
                     _ => {}
                 }
@@ -548,34 +1334,28 @@ impl App {
                     KeyCode::Enter => {
                         if let Some(sel) = st.filtered.get(st.selected).cloned() {
                             self.model_label = sel;
+                            self.rate_info = None;
                             self.model_picker = None;
-                            let _ = crate::persist::save_state(self);
-                            self.messages.push(Message::assistant(format!(
-                                "[info] model set to '{}'",
-                                self.model_label
-                            )));
-                            self.collapsed.push(false);
+                            self.mark_state_dirty();
+                            self.notify(
+                                NoticeLevel::Info,
+                                format!("model set to '{}'", self.model_label),
+                            );
                         }
                     }
-                    KeyCode::Up => {
-                        if st.selected > 0 {
-                            st.selected -= 1;
-                        }
+                    KeyCode::Up if st.selected > 0 => {
+                        st.selected -= 1;
                     }
-                    KeyCode::Down => {
-                        if st.selected + 1 < st.filtered.len() {
-                            st.selected += 1;
-                        }
+                    KeyCode::Down if st.selected + 1 < st.filtered.len() => {
+                        st.selected += 1;
                     }
-                    KeyCode::Backspace => {
-                        if st.cursor > 0 {
-                            let mut parts: Vec<&str> = st.buffer.graphemes(true).collect();
-                            let c = st.cursor.min(parts.len());
-                            parts.remove(c - 1);
-                            st.buffer = parts.concat();
-                            st.cursor -= 1;
-                            App::model_filter(&model_all, st);
-                        }
+                    KeyCode::Backspace if st.cursor > 0 => {
+                        let mut parts: Vec<&str> = st.buffer.graphemes(true).collect();
+                        let c = st.cursor.min(parts.len());
+                        parts.remove(c - 1);
+                        st.buffer = parts.concat();
+                        st.cursor -= 1;
+                        App::model_filter(&model_all, st);
                     }
                     KeyCode::Delete => {
                         let mut parts: Vec<&str> = st.buffer.graphemes(true).collect();
@@ -586,10 +1366,8 @@ impl App {
                             App::model_filter(&model_all, st);
                         }
                     }
-                    KeyCode::Left => {
-                        if st.cursor > 0 {
-                            st.cursor -= 1;
-                        }
+                    KeyCode::Left if st.cursor > 0 => {
+                        st.cursor -= 1;
                     }
                     KeyCode::Right => {
                         let l = st.buffer.graphemes(true).count();
@@ -603,16 +1381,14 @@ impl App {
                     KeyCode::End => {
                         st.cursor = st.buffer.graphemes(true).count();
                     }
-                    KeyCode::Char(ch) => {
-                        if !key.modifiers.contains(KeyModifiers::CONTROL) {
-                            let mut parts: Vec<&str> = st.buffer.graphemes(true).collect();
-                            let c = st.cursor.min(parts.len());
-                            let mut buf = [0u8; 4];
-                            parts.insert(c, ch.encode_utf8(&mut buf));
-                            st.buffer = parts.concat();
-                            st.cursor += 1;
-                            App::model_filter(&model_all, st);
-                        }
+                    KeyCode::Char(ch) if !key.modifiers.contains(KeyModifiers::CONTROL) => {
+                        let mut parts: Vec<&str> = st.buffer.graphemes(true).collect();
+                        let c = st.cursor.min(parts.len());
+                        let mut buf = [0u8; 4];
+                        parts.insert(c, ch.encode_utf8(&mut buf));
+                        st.buffer = parts.concat();
+                        st.cursor += 1;
+                        App::model_filter(&model_all, st);
                     }
                     _ => {}
                 }
@@ -632,33 +1408,26 @@ impl App {
                         if let Some(sel) = st.filtered.get(st.selected).cloned() {
                             self.wire_label = sel;
                             self.wire_picker = None;
-                            let _ = crate::persist::save_state(self);
-                            self.messages.push(Message::assistant(format!(
-                                "[info] wire set to '{}'",
-                                self.wire_label
-                            )));
-                            self.collapsed.push(false);
+                            self.mark_state_dirty();
+                            self.notify(
+                                NoticeLevel::Info,
+                                format!("wire set to '{}'", self.wire_label),
+                            );
                         }
                     }
-                    KeyCode::Up => {
-                        if st.selected > 0 {
-                            st.selected -= 1;
-                        }
+                    KeyCode::Up if st.selected > 0 => {
+                        st.selected -= 1;
                     }
-                    KeyCode::Down => {
-                        if st.selected + 1 < st.filtered.len() {
-                            st.selected += 1;
-                        }
+                    KeyCode::Down if st.selected + 1 < st.filtered.len() => {
+                        st.selected += 1;
                     }
-                    KeyCode::Backspace => {
-                        if st.cursor > 0 {
-                            let mut parts: Vec<&str> = st.buffer.graphemes(true).collect();
-                            let c = st.cursor.min(parts.len());
-                            parts.remove(c - 1);
-                            st.buffer = parts.concat();
-                            st.cursor -= 1;
-                            App::wire_filter(st);
-                        }
+                    KeyCode::Backspace if st.cursor > 0 => {
+                        let mut parts: Vec<&str> = st.buffer.graphemes(true).collect();
+                        let c = st.cursor.min(parts.len());
+                        parts.remove(c - 1);
+                        st.buffer = parts.concat();
+                        st.cursor -= 1;
+                        App::wire_filter(st);
                     }
                     KeyCode::Delete => {
                         let mut parts: Vec<&str> = st.buffer.graphemes(true).collect();
@@ -669,10 +1438,8 @@ impl App {
                             App::wire_filter(st);
                         }
                     }
-                    KeyCode::Left => {
-                        if st.cursor > 0 {
-                            st.cursor -= 1;
-                        }
+                    KeyCode::Left if st.cursor > 0 => {
+                        st.cursor -= 1;
                     }
                     KeyCode::Right => {
                         let l = st.buffer.graphemes(true).count();
@@ -686,16 +1453,14 @@ impl App {
                     KeyCode::End => {
                         st.cursor = st.buffer.graphemes(true).count();
                     }
-                    KeyCode::Char(ch) => {
-                        if !key.modifiers.contains(KeyModifiers::CONTROL) {
-                            let mut parts: Vec<&str> = st.buffer.graphemes(true).collect();
-                            let c = st.cursor.min(parts.len());
-                            let mut buf = [0u8; 4];
-                            parts.insert(c, ch.encode_utf8(&mut buf));
-                            st.buffer = parts.concat();
-                            st.cursor += 1;
-                            App::wire_filter(st);
-                        }
+                    KeyCode::Char(ch) if !key.modifiers.contains(KeyModifiers::CONTROL) => {
+                        let mut parts: Vec<&str> = st.buffer.graphemes(true).collect();
+                        let c = st.cursor.min(parts.len());
+                        let mut buf = [0u8; 4];
+                        parts.insert(c, ch.encode_utf8(&mut buf));
+                        st.buffer = parts.concat();
+                        st.cursor += 1;
+                        App::wire_filter(st);
                     }
                     _ => {}
                 }
@@ -716,25 +1481,19 @@ impl App {
                             self.slash_execute(&cmd);
                         }
                     }
-                    KeyCode::Up => {
-                        if st.selected > 0 {
-                            st.selected -= 1;
-                        }
+                    KeyCode::Up if st.selected > 0 => {
+                        st.selected -= 1;
                     }
-                    KeyCode::Down => {
-                        if st.selected + 1 < st.filtered.len() {
-                            st.selected += 1;
-                        }
+                    KeyCode::Down if st.selected + 1 < st.filtered.len() => {
+                        st.selected += 1;
                     }
-                    KeyCode::Backspace => {
-                        if st.cursor > 0 {
-                            let mut parts: Vec<&str> = st.buffer.graphemes(true).collect();
-                            let c = st.cursor.min(parts.len());
-                            parts.remove(c - 1);
-                            st.buffer = parts.concat();
-                            st.cursor -= 1;
-                            App::slash_filter(st);
-                        }
+                    KeyCode::Backspace if st.cursor > 0 => {
+                        let mut parts: Vec<&str> = st.buffer.graphemes(true).collect();
+                        let c = st.cursor.min(parts.len());
+                        parts.remove(c - 1);
+                        st.buffer = parts.concat();
+                        st.cursor -= 1;
+                        App::slash_filter(st);
                     }
                     KeyCode::Delete => {
                         let mut parts: Vec<&str> = st.buffer.graphemes(true).collect();
@@ -745,10 +1504,8 @@ impl App {
                             App::slash_filter(st);
                         }
                     }
-                    KeyCode::Left => {
-                        if st.cursor > 0 {
-                            st.cursor -= 1;
-                        }
+                    KeyCode::Left if st.cursor > 0 => {
+                        st.cursor -= 1;
                     }
                     KeyCode::Right => {
                         let l = st.buffer.graphemes(true).count();
@@ -762,19 +1519,115 @@ impl App {
                     KeyCode::End => {
                         st.cursor = st.buffer.graphemes(true).count();
                     }
-                    KeyCode::Char(ch) => {
-                        if !key.modifiers.contains(KeyModifiers::CONTROL) {
-                            let mut parts: Vec<&str> = st.buffer.graphemes(true).collect();
-                            let c = st.cursor.min(parts.len());
-                            let mut buf = [0u8; 4];
-                            parts.insert(c, ch.encode_utf8(&mut buf));
-                            st.buffer = parts.concat();
-                            st.cursor += 1;
-                            App::slash_filter(st);
+                    KeyCode::Char(ch) if !key.modifiers.contains(KeyModifiers::CONTROL) => {
+                        let mut parts: Vec<&str> = st.buffer.graphemes(true).collect();
+                        let c = st.cursor.min(parts.len());
+                        let mut buf = [0u8; 4];
+                        parts.insert(c, ch.encode_utf8(&mut buf));
+                        st.buffer = parts.concat();
+                        st.cursor += 1;
+                        App::slash_filter(st);
+                    }
+                    _ => {}
+                }
+                return;
+            }
+
+            if let Some(st) = &mut self.bookmark_picker {
+                match key.code {
+                    KeyCode::Esc | KeyCode::F(4) => {
+                        self.bookmark_picker = None;
+                    }
+                    KeyCode::Enter => {
+                        if let Some(idx) = st.items.get(st.selected).copied() {
+                            self.bookmark_picker = None;
+                            self.jump_to_message(idx);
                         }
                     }
+                    KeyCode::Up if st.selected > 0 => {
+                        st.selected -= 1;
+                    }
+                    KeyCode::Down if st.selected + 1 < st.items.len() => {
+                        st.selected += 1;
+                    }
                     _ => {}
                 }
+                self.dirty = true;
+                return;
+            }
+
+            if let Some(c) = &mut self.conflict {
+                use conflict::ConflictChoice;
+                let n = ConflictChoice::ALL.len();
+                match key.code {
+                    // Postpone; the next save asks again
+                    KeyCode::Esc => {
+                        self.conflict = None;
+                    }
+                    KeyCode::Up => {
+                        c.selected = (c.selected + n - 1) % n;
+                    }
+                    KeyCode::Down | KeyCode::Tab => {
+                        c.selected = (c.selected + 1) % n;
+                    }
+                    KeyCode::Enter => {
+                        let choice = ConflictChoice::ALL[c.selected];
+                        self.resolve_conflict(choice);
+                    }
+                    KeyCode::Char('k') => self.resolve_conflict(ConflictChoice::KeepMine),
+                    KeyCode::Char('t') => self.resolve_conflict(ConflictChoice::TakeTheirs),
+                    KeyCode::Char('m') => self.resolve_conflict(ConflictChoice::Merge),
+                    _ => {}
+                }
+                self.dirty = true;
+                return;
+            }
+
+            if let Some(gs) = &mut self.global_search {
+                match key.code {
+                    KeyCode::Esc => {
+                        self.global_search = None;
+                        self.global_search_rx = None;
+                    }
+                    KeyCode::Enter => {
+                        self.open_global_hit();
+                    }
+                    KeyCode::Up if gs.selected > 0 => {
+                        gs.selected -= 1;
+                    }
+                    KeyCode::Down if gs.selected + 1 < gs.hits.len() => {
+                        gs.selected += 1;
+                    }
+                    _ => {}
+                }
+                self.dirty = true;
+                return;
+            }
+
+            if let Some(cl) = &mut self.changelog {
+                match key.code {
+                    KeyCode::Esc | KeyCode::Enter => {
+                        self.changelog = None;
+                    }
+                    // Dismiss the update notice along with the popup
+                    KeyCode::Char('d') => {
+                        self.changelog = None;
+                        self.update_notice = None;
+                    }
+                    KeyCode::Up => {
+                        cl.scroll = cl.scroll.saturating_sub(1);
+                    }
+                    KeyCode::Down => {
+                        cl.scroll = cl.scroll.saturating_add(1);
+                    }
+                    _ => {}
+                }
+                self.dirty = true;
+                return;
+            }
+
+            if self.log_view.is_some() {
+                self.on_log_view_key(key);
                 return;
             }
 
@@ -791,6 +1644,26 @@ impl App {
                 return;
             }
 
+            if self.history_search.is_some() {
+                self.on_history_search_key(key);
+                return;
+            }
+
+            if self.template_picker.is_some() {
+                self.on_template_picker_key(key);
+                return;
+            }
+
+            if self.profile_picker.is_some() {
+                self.on_profile_picker_key(key);
+                return;
+            }
+
+            if self.visual.is_some() {
+                self.on_visual_key(key);
+                return;
+            }
+
             if let Some(state) = &mut self.search_input {
                 match key.code {
                     KeyCode::Esc => {
@@ -799,14 +1672,24 @@ impl App {
                     KeyCode::Enter => {
                         self.commit_search();
                     }
-                    KeyCode::Backspace => {
-                        if state.cursor > 0 {
-                            let mut parts: Vec<&str> = state.buffer.graphemes(true).collect();
-                            let c = state.cursor.min(parts.len());
-                            parts.remove(c - 1);
-                            state.buffer = parts.concat();
-                            state.cursor -= 1;
-                        }
+                    KeyCode::Char('c') | KeyCode::Char('C')
+                        if key.modifiers.contains(KeyModifiers::ALT) =>
+                    {
+                        self.search_smart_case = !self.search_smart_case;
+                        self.mark_state_dirty();
+                    }
+                    KeyCode::Char('w') | KeyCode::Char('W')
+                        if key.modifiers.contains(KeyModifiers::ALT) =>
+                    {
+                        self.search_whole_word = !self.search_whole_word;
+                        self.mark_state_dirty();
+                    }
+                    KeyCode::Backspace if state.cursor > 0 => {
+                        let mut parts: Vec<&str> = state.buffer.graphemes(true).collect();
+                        let c = state.cursor.min(parts.len());
+                        parts.remove(c - 1);
+                        state.buffer = parts.concat();
+                        state.cursor -= 1;
                     }
                     KeyCode::Delete => {
                         let mut parts: Vec<&str> = state.buffer.graphemes(true).collect();
@@ -816,10 +1699,8 @@ impl App {
                             state.buffer = parts.concat();
                         }
                     }
-                    KeyCode::Left => {
-                        if state.cursor > 0 {
-                            state.cursor -= 1;
-                        }
+                    KeyCode::Left if state.cursor > 0 => {
+                        state.cursor -= 1;
                     }
                     KeyCode::Right => {
                         let l = state.buffer.graphemes(true).count();
@@ -833,15 +1714,13 @@ impl App {
                     KeyCode::End => {
                         state.cursor = state.buffer.graphemes(true).count();
                     }
-                    KeyCode::Char(ch) => {
-                        if !key.modifiers.contains(KeyModifiers::CONTROL) {
-                            let mut parts: Vec<&str> = state.buffer.graphemes(true).collect();
-                            let c = state.cursor.min(parts.len());
-                            let mut buf = [0u8; 4];
-                            parts.insert(c, ch.encode_utf8(&mut buf));
-                            state.buffer = parts.concat();
-                            state.cursor += 1;
-                        }
+                    KeyCode::Char(ch) if !key.modifiers.contains(KeyModifiers::CONTROL) => {
+                        let mut parts: Vec<&str> = state.buffer.graphemes(true).collect();
+                        let c = state.cursor.min(parts.len());
+                        let mut buf = [0u8; 4];
+                        parts.insert(c, ch.encode_utf8(&mut buf));
+                        state.buffer = parts.concat();
+                        state.cursor += 1;
                     }
                     _ => {}
                 }
@@ -860,21 +1739,21 @@ impl App {
                             let new_name = state.buffer.trim().to_string();
                             if new_name != old {
                                 let _ = crate::persist::rename_session(&old, &new_name);
+                                self.rename_session_activity(&old, &new_name);
                                 self.sessions[idx] = new_name;
+                                self.session_auto_named[idx] = false;
                             }
                             self.current_session = idx;
                         }
                         self.rename = None;
-                        let _ = crate::persist::save_state(self);
+                        self.mark_state_dirty();
                     }
-                    KeyCode::Backspace => {
-                        if state.cursor > 0 {
-                            let mut parts: Vec<&str> = state.buffer.graphemes(true).collect();
-                            let c = state.cursor.min(parts.len());
-                            parts.remove(c - 1);
-                            state.buffer = parts.concat();
-                            state.cursor -= 1;
-                        }
+                    KeyCode::Backspace if state.cursor > 0 => {
+                        let mut parts: Vec<&str> = state.buffer.graphemes(true).collect();
+                        let c = state.cursor.min(parts.len());
+                        parts.remove(c - 1);
+                        state.buffer = parts.concat();
+                        state.cursor -= 1;
                     }
                     KeyCode::Delete => {
                         let mut parts: Vec<&str> = state.buffer.graphemes(true).collect();
@@ -884,10 +1763,8 @@ impl App {
                             state.buffer = parts.concat();
                         }
                     }
-                    KeyCode::Left => {
-                        if state.cursor > 0 {
-                            state.cursor -= 1;
-                        }
+                    KeyCode::Left if state.cursor > 0 => {
+                        state.cursor -= 1;
                     }
                     KeyCode::Right => {
                         let l = state.buffer.graphemes(true).count();
@@ -899,17 +1776,15 @@ impl App {
                         state.cursor = 0;
                     }
                     KeyCode::End => {
-                        state.cursor = state.buffer.graphemes(true).count();
-                    }
-                    KeyCode::Char(ch) => {
-                        if !key.modifiers.contains(KeyModifiers::CONTROL) {
-                            let mut parts: Vec<&str> = state.buffer.graphemes(true).collect();
-                            let c = state.cursor.min(parts.len());
-                            let mut buf = [0u8; 4];
-                            parts.insert(c, ch.encode_utf8(&mut buf));
-                            state.buffer = parts.concat();
-                            state.cursor += 1;
-                        }
+                        state.cursor = state.buffer.graphemes(true).count();
+                    }
+                    KeyCode::Char(ch) if !key.modifiers.contains(KeyModifiers::CONTROL) => {
+                        let mut parts: Vec<&str> = state.buffer.graphemes(true).collect();
+                        let c = state.cursor.min(parts.len());
+                        let mut buf = [0u8; 4];
+                        parts.insert(c, ch.encode_utf8(&mut buf));
+                        state.buffer = parts.concat();
+                        state.cursor += 1;
                     }
                     _ => {}
                 }
@@ -920,20 +1795,11 @@ impl App {
                 match key.code {
                     KeyCode::Char('y') | KeyCode::Char('Y') => {
                         match confirm.action {
-                            ConfirmAction::DeleteSession(idx) => {
-                                if idx < self.sessions.len() {
-                                    let name = self.sessions.remove(idx);
-                                    let _ = crate::persist::delete_session(&name);
-                                    if self.sessions.is_empty() {
-                                        self.sessions.push("default".to_string());
-                                    }
-                                    let new_idx = idx.min(self.sessions.len() - 1);
-                                    self.current_session = new_idx;
-                                }
-                            }
+                            ConfirmAction::DeleteSession(idx) => self.delete_session_at(idx),
+                            ConfirmAction::LargePaste(text) => self.insert_text(&text),
                         }
                         self.confirm = None;
-                        let _ = crate::persist::save_state(self);
+                        self.mark_state_dirty();
                     }
                     KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Esc => {
                         self.confirm = None;
@@ -943,25 +1809,72 @@ impl App {
                 return;
             }
 
+            if self.blocked_by_read_only(&key) {
+                self.notify(NoticeLevel::Info, READ_ONLY_NOTE);
+                return;
+            }
+            let keeps_selection = self.keeps_input_selection(&key);
             match key.code {
+                KeyCode::Char('c')
+                    if key.modifiers.contains(KeyModifiers::CONTROL)
+                        && self.input_selection().is_some() =>
+                {
+                    self.copy_input_selection();
+                }
+                KeyCode::Char('x')
+                    if key.modifiers.contains(KeyModifiers::CONTROL)
+                        && self.input_selection().is_some() =>
+                {
+                    self.cut_input_selection();
+                    self.update_slash_picker_on_input_change();
+                }
+                KeyCode::Char('x')
+                    if key.modifiers.contains(KeyModifiers::CONTROL)
+                        && matches!(self.focus, Focus::Input) =>
+                {
+                    self.request_external_edit();
+                }
+                KeyCode::Char('d')
+                    if key.modifiers.contains(KeyModifiers::CONTROL)
+                        && matches!(self.focus, Focus::Input)
+                        && self.pending_paste.is_some() =>
+                {
+                    self.drop_pasted_text();
+                }
                 KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
                     // Ctrl+C: cancel active stream if any; otherwise quit
                     if self.llm_rx.is_some() {
                         if let Some(cancel) = &self.llm_cancel {
-                            cancel.store(true, Ordering::Relaxed);
+                            cancel.cancel();
                         }
                     } else {
                         self.should_quit = true;
                     }
                 }
-                KeyCode::Esc => self.should_quit = true,
+                KeyCode::Esc => self.on_esc(),
                 KeyCode::Char('p') if key.modifiers.contains(KeyModifiers::CONTROL) => {
                     self.open_palette();
                 }
                 KeyCode::F(1) => {
                     self.show_help = true;
                 }
+                KeyCode::F(12) => {
+                    self.open_log_view();
+                }
+                KeyCode::Char('r') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    self.open_history_search();
+                }
+                KeyCode::F(5) => {
+                    self.retry();
+                }
 
+                KeyCode::Char('f' | 'F')
+                    if key
+                        .modifiers
+                        .contains(KeyModifiers::CONTROL | KeyModifiers::SHIFT) =>
+                {
+                    self.open_global_search();
+                }
                 KeyCode::Char('f') if key.modifiers.contains(KeyModifiers::CONTROL) => {
                     self.open_search();
                 }
@@ -975,6 +1888,7 @@ impl App {
                     // Cycle focus across visible panes: Input -> Sidebar? -> Context? -> Input
                     let mut order = Vec::new();
                     order.push(Focus::Input);
+                    order.push(Focus::Chat);
                     if self.show_sidebar {
                         order.push(Focus::Sidebar);
                     }
@@ -988,6 +1902,9 @@ impl App {
                     } else {
                         self.focus = Focus::Input;
                     }
+                    if self.focus == Focus::Chat && self.selected_message.is_none() {
+                        self.select_message_at_view_top();
+                    }
                 }
 
                 KeyCode::Enter if key.modifiers.contains(KeyModifiers::SHIFT) => {
@@ -998,8 +1915,49 @@ impl App {
                     if matches!(self.focus, Focus::Input) {
                         info!(target: "tui", "on_key: Enter => submit");
                         self.submit();
+                    } else if matches!(self.focus, Focus::Chat) {
+                        if let Some(idx) = self.selected_message {
+                            self.toggle_collapse_at(idx);
+                        }
                     }
                 }
+                // Readline word keys. Ctrl+Backspace arrives as Ctrl+H from
+                // most terminals.
+                KeyCode::Backspace
+                    if key
+                        .modifiers
+                        .intersects(KeyModifiers::ALT | KeyModifiers::CONTROL)
+                        && matches!(self.focus, Focus::Input) =>
+                {
+                    self.delete_prev_word();
+                    self.update_slash_picker_on_input_change();
+                }
+                KeyCode::Char('h')
+                    if key.modifiers.contains(KeyModifiers::CONTROL)
+                        && matches!(self.focus, Focus::Input) =>
+                {
+                    self.delete_prev_word();
+                    self.update_slash_picker_on_input_change();
+                }
+                KeyCode::Char('d')
+                    if key.modifiers.contains(KeyModifiers::ALT)
+                        && matches!(self.focus, Focus::Input) =>
+                {
+                    self.delete_next_word();
+                    self.update_slash_picker_on_input_change();
+                }
+                KeyCode::Char('b')
+                    if key.modifiers.contains(KeyModifiers::ALT)
+                        && matches!(self.focus, Focus::Input) =>
+                {
+                    self.move_cursor_word_left();
+                }
+                KeyCode::Char('f')
+                    if key.modifiers.contains(KeyModifiers::ALT)
+                        && matches!(self.focus, Focus::Input) =>
+                {
+                    self.move_cursor_word_right();
+                }
                 KeyCode::Backspace if matches!(self.focus, Focus::Input) => {
                     self.delete_left_grapheme();
                     self.update_slash_picker_on_input_change();
@@ -1008,6 +1966,9 @@ impl App {
                     self.delete_right_grapheme();
                     self.update_slash_picker_on_input_change();
                 }
+                KeyCode::Char('u') if key.modifiers.contains(KeyModifiers::ALT) => {
+                    self.jump_message(false, true);
+                }
                 KeyCode::Char('w') if key.modifiers.contains(KeyModifiers::CONTROL) => {
                     self.delete_prev_word();
                 }
@@ -1017,12 +1978,36 @@ impl App {
                 KeyCode::Char('k') if key.modifiers.contains(KeyModifiers::CONTROL) => {
                     self.kill_to_line_end();
                 }
+                KeyCode::Char('z' | 'Z')
+                    if key
+                        .modifiers
+                        .contains(KeyModifiers::CONTROL | KeyModifiers::SHIFT) =>
+                {
+                    self.redo_input();
+                    self.update_slash_picker_on_input_change();
+                }
+                // Ctrl+_ arrives as Ctrl+7 from most terminals
+                KeyCode::Char('z' | '_' | '7') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    self.undo_input();
+                    self.update_slash_picker_on_input_change();
+                }
+                KeyCode::Char('y') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    self.redo_input();
+                    self.update_slash_picker_on_input_change();
+                }
                 KeyCode::Char('a') if key.modifiers.contains(KeyModifiers::CONTROL) => {
                     self.move_cursor_line_start();
                 }
                 KeyCode::Char('e') if key.modifiers.contains(KeyModifiers::CONTROL) => {
                     self.move_cursor_line_end();
                 }
+                KeyCode::Char('v')
+                    if key.modifiers.contains(KeyModifiers::CONTROL)
+                        && matches!(self.focus, Focus::Input) =>
+                {
+                    self.paste_from_clipboard();
+                    self.update_slash_picker_on_input_change();
+                }
                 KeyCode::Char(ch) => {
                     if matches!(self.focus, Focus::Context) {
                         match ch {
@@ -1031,6 +2016,19 @@ impl App {
                             }
                             _ => {}
                         }
+                    } else if matches!(self.focus, Focus::Chat) {
+                        match ch {
+                            'j' => self.select_message_step(1),
+                            'k' => self.select_message_step(-1),
+                            'y' => self.copy_selected_message(),
+                            'b' => self.branch_from_selected(),
+                            'm' => self.toggle_bookmark_selected(),
+                            '\'' => self.open_bookmark_picker(),
+                            'v' => self.start_visual_selection(),
+                            ' ' | 'z' => self.toggle_collapse_current(),
+                            'Z' => self.toggle_all_collapsed(),
+                            _ => {}
+                        }
                     } else if matches!(self.focus, Focus::Sidebar) {
                         match ch {
                             'n' | 'N' => {
@@ -1051,10 +2049,29 @@ impl App {
                         self.update_slash_picker_on_input_change();
                     }
                 }
-                KeyCode::Left if key.modifiers.is_empty() && matches!(self.focus, Focus::Input) => {
-                    if self.input_cursor > 0 {
-                        self.input_cursor -= 1;
-                    }
+                KeyCode::Left | KeyCode::Right | KeyCode::Home | KeyCode::End
+                    if key.modifiers.contains(KeyModifiers::SHIFT)
+                        && matches!(self.focus, Focus::Input) =>
+                {
+                    let word = key.modifiers.contains(KeyModifiers::CONTROL);
+                    self.extend_input_selection(|app| match key.code {
+                        KeyCode::Left if word => app.move_cursor_word_left(),
+                        KeyCode::Right if word => app.move_cursor_word_right(),
+                        KeyCode::Left => app.input_cursor = app.input_cursor.saturating_sub(1),
+                        KeyCode::Right => {
+                            let len = app.input.graphemes(true).count();
+                            app.input_cursor = (app.input_cursor + 1).min(len);
+                        }
+                        KeyCode::Home => app.move_cursor_line_start(),
+                        _ => app.move_cursor_line_end(),
+                    });
+                }
+                KeyCode::Left
+                    if key.modifiers.is_empty()
+                        && matches!(self.focus, Focus::Input)
+                        && self.input_cursor > 0 =>
+                {
+                    self.input_cursor -= 1;
                 }
                 KeyCode::Right
                     if key.modifiers.is_empty() && matches!(self.focus, Focus::Input) =>
@@ -1064,6 +2081,12 @@ impl App {
                         self.input_cursor += 1;
                     }
                 }
+                KeyCode::Left if matches!(self.focus, Focus::Chat) && !self.wrap_lines => {
+                    self.scroll_chat_sideways(-1);
+                }
+                KeyCode::Right if matches!(self.focus, Focus::Chat) && !self.wrap_lines => {
+                    self.scroll_chat_sideways(1);
+                }
                 KeyCode::Left
                     if key.modifiers.contains(KeyModifiers::CONTROL)
                         && matches!(self.focus, Focus::Input) =>
@@ -1077,10 +2100,10 @@ impl App {
                     self.move_cursor_word_right();
                 }
                 KeyCode::Home if key.modifiers.contains(KeyModifiers::CONTROL) => {
-                    self.chat_scroll = u16::MAX;
+                    self.scroll_to_top();
                 }
                 KeyCode::End if key.modifiers.contains(KeyModifiers::CONTROL) => {
-                    self.chat_scroll = 0;
+                    self.jump_to_latest();
                 }
                 KeyCode::Up if key.modifiers.is_empty() && matches!(self.focus, Focus::Input) => {
                     if self.history.is_empty() {
@@ -1092,22 +2115,43 @@ impl App {
                         Some(i) => i.saturating_sub(1),
                     };
                     self.history_index = Some(idx);
-                    self.input = self.history[idx].clone();
-                    self.input_cursor = self.input.graphemes(true).count();
+                    self.replace_input(self.history[idx].clone());
                 }
                 KeyCode::Down if key.modifiers.is_empty() && matches!(self.focus, Focus::Input) => {
                     if let Some(i) = self.history_index {
                         if i + 1 < self.history.len() {
                             self.history_index = Some(i + 1);
-                            self.input = self.history[i + 1].clone();
-                            self.input_cursor = self.input.graphemes(true).count();
+                            self.replace_input(self.history[i + 1].clone());
                         } else {
                             self.history_index = None;
-                            self.input.clear();
-                            self.input_cursor = 0;
+                            self.replace_input(String::new());
                         }
                     }
                 }
+                KeyCode::Up
+                    if key.modifiers.contains(KeyModifiers::ALT)
+                        && matches!(self.focus, Focus::Sidebar) =>
+                {
+                    self.sidebar_move_current(false);
+                }
+                KeyCode::Down
+                    if key.modifiers.contains(KeyModifiers::ALT)
+                        && matches!(self.focus, Focus::Sidebar) =>
+                {
+                    self.sidebar_move_current(true);
+                }
+                KeyCode::Up if key.modifiers.contains(KeyModifiers::ALT) => {
+                    self.jump_message(false, false);
+                }
+                KeyCode::Down if key.modifiers.contains(KeyModifiers::ALT) => {
+                    self.jump_message(true, false);
+                }
+                KeyCode::Up if matches!(self.focus, Focus::Chat) => {
+                    self.select_message_step(-1);
+                }
+                KeyCode::Down if matches!(self.focus, Focus::Chat) => {
+                    self.select_message_step(1);
+                }
                 KeyCode::Up if matches!(self.focus, Focus::Sidebar) => {
                     self.sidebar_select_up();
                 }
@@ -1129,90 +2173,149 @@ impl App {
                 KeyCode::Home if matches!(self.focus, Focus::Sidebar) => {
                     self.current_session = 0;
                     self.ensure_sidebar_visible();
-                    let _ = crate::persist::save_state(self);
+                    self.mark_state_dirty();
                 }
                 KeyCode::End if matches!(self.focus, Focus::Sidebar) => {
                     if !self.sessions.is_empty() {
                         self.current_session = self.sessions.len() - 1;
                     }
                     self.ensure_sidebar_visible();
-                    let _ = crate::persist::save_state(self);
+                    self.mark_state_dirty();
+                }
+                KeyCode::Home if matches!(self.focus, Focus::Input) => {
+                    self.move_cursor_line_start();
+                }
+                KeyCode::End if matches!(self.focus, Focus::Input) => {
+                    self.move_cursor_line_end();
+                }
+                KeyCode::End => {
+                    self.jump_to_latest();
                 }
                 KeyCode::PageUp if key.modifiers.contains(KeyModifiers::SHIFT) => {
-                    let step = self.chat_viewport.saturating_mul(2).max(1);
-                    self.chat_scroll = self.chat_scroll.saturating_add(step);
-                    self.stick_to_bottom = false;
+                    self.scroll_chat_up(self.chat_viewport.saturating_mul(2).max(1));
                 }
                 KeyCode::PageDown if key.modifiers.contains(KeyModifiers::SHIFT) => {
-                    let step = self.chat_viewport.saturating_mul(2).max(1);
-                    self.chat_scroll = self.chat_scroll.saturating_sub(step);
-                    if self.chat_scroll == 0 {
-                        self.stick_to_bottom = true;
-                    }
+                    self.scroll_chat_down(self.chat_viewport.saturating_mul(2).max(1));
                 }
                 KeyCode::PageUp => {
-                    let step = self.chat_viewport.max(1);
-                    self.chat_scroll = self.chat_scroll.saturating_add(step);
-                    self.stick_to_bottom = false;
+                    self.scroll_chat_up(self.chat_viewport.max(1));
                 }
                 KeyCode::PageDown => {
-                    let step = self.chat_viewport.max(1);
-                    self.chat_scroll = self.chat_scroll.saturating_sub(step);
-                    if self.chat_scroll == 0 {
-                        self.stick_to_bottom = true;
-                    }
+                    self.scroll_chat_down(self.chat_viewport.max(1));
                 }
                 KeyCode::Up if key.modifiers.contains(KeyModifiers::CONTROL) => {
-                    self.chat_scroll = self.chat_scroll.saturating_add(1);
-                    self.stick_to_bottom = false;
+                    self.scroll_chat_up(1);
                 }
                 KeyCode::Down if key.modifiers.contains(KeyModifiers::CONTROL) => {
-                    self.chat_scroll = self.chat_scroll.saturating_sub(1);
-                    if self.chat_scroll == 0 {
-                        self.stick_to_bottom = true;
-                    }
+                    self.scroll_chat_down(1);
                 }
                 KeyCode::F(2) => {
                     self.show_sidebar = !self.show_sidebar;
-                    let _ = crate::persist::save_state(self);
+                    self.mark_state_dirty();
                 }
                 KeyCode::F(6) => {
                     self.show_context = !self.show_context;
                     self.dirty = true;
                 }
+                KeyCode::F(7) => {
+                    self.set_all_collapsed(false);
+                }
+                KeyCode::F(8) => {
+                    self.set_all_collapsed(true);
+                }
+                KeyCode::F(9) if key.modifiers.contains(KeyModifiers::SHIFT) => {
+                    self.copy_last_reply();
+                }
+                KeyCode::F(9) => {
+                    self.copy_last_code_block();
+                }
                 KeyCode::Delete if matches!(self.focus, Focus::Sidebar) => {
                     self.sidebar_delete_current();
                 }
                 // Context pane shortcuts
-                KeyCode::Up if matches!(self.focus, Focus::Context) => {
-                    if self.context_current > 0 {
-                        self.context_current -= 1;
-                    }
+                KeyCode::Up if matches!(self.focus, Focus::Context) && self.context_current > 0 => {
+                    self.context_current -= 1;
                 }
-                KeyCode::Down if matches!(self.focus, Focus::Context) => {
-                    if self.context_current + 1 < self.context_items.len() {
-                        self.context_current += 1;
-                    }
+                KeyCode::Down
+                    if matches!(self.focus, Focus::Context)
+                        && self.context_current + 1 < self.context_items.len() =>
+                {
+                    self.context_current += 1;
                 }
-                KeyCode::Delete if matches!(self.focus, Focus::Context) => {
-                    if self.context_current < self.context_items.len() {
-                        self.context_items.remove(self.context_current);
-                        if self.context_current >= self.context_items.len()
-                            && !self.context_items.is_empty()
-                        {
-                            self.context_current = self.context_items.len() - 1;
-                        }
+                KeyCode::Delete
+                    if matches!(self.focus, Focus::Context)
+                        && self.context_current < self.context_items.len() =>
+                {
+                    self.context_items.remove(self.context_current);
+                    if self.context_current >= self.context_items.len()
+                        && !self.context_items.is_empty()
+                    {
+                        self.context_current = self.context_items.len() - 1;
                     }
                 }
                 _ => {}
             }
+            if !keeps_selection {
+                self.input_anchor = None;
+            }
             // Mark dirty on any handled key press path.
             self.dirty = true;
         }
     }
 
+    // Work that needs frequent ticks: an active LLM stream, the typing
+    // animation, or a pending redraw.
+    pub fn is_busy(&self) -> bool {
+        self.llm_rx.is_some()
+            || self.stream.is_some()
+            || self.dirty
+            || self.state_dirty
+            || self.wrap_pending.is_some()
+            || self.global_search_rx.is_some()
+    }
+
+    // Estimated output tokens per second of the replies streaming now.
+    pub fn stream_rate(&self) -> Option<f64> {
+        self.stream_meter.as_ref()?.tokens_per_sec()
+    }
+
+    // Persist UI state (sessions, selection, toggles) on a later tick
+    // instead of writing it on every key.
+    pub fn mark_state_dirty(&mut self) {
+        self.state_dirty = true;
+    }
+
+    // Write `ui_state.json` now: on quit, and from `on_tick` once due.
+    pub fn save_state_now(&mut self) {
+        if let Err(e) = crate::persist::save_state(self) {
+            warn!(target: "tui", "save state: {:#}", e);
+        }
+        self.state_dirty = false;
+        self.state_saved_at = std::time::Instant::now();
+    }
+
     pub fn on_tick(&mut self) {
         self.tick = self.tick.wrapping_add(1);
+        // Draw again to see whether the new chat width held
+        self.dirty |= self.wrap_pending.is_some();
+        if self.state_dirty && self.state_saved_at.elapsed() >= STATE_SAVE_INTERVAL {
+            self.save_state_now();
+        }
+        if let Some(rx) = &self.update_rx {
+            match rx.try_recv() {
+                Ok(rel) => {
+                    self.update_notice = rel;
+                    self.update_rx = None;
+                    self.update_last_checked = Some(now_unix());
+                    self.save_state_now();
+                    self.dirty = true;
+                }
+                Err(std::sync::mpsc::TryRecvError::Disconnected) => self.update_rx = None,
+                Err(std::sync::mpsc::TryRecvError::Empty) => {}
+            }
+        }
+        self.drain_global_search();
+        self.refresh_log_view();
         if let Some(stream) = &mut self.stream {
             let graphemes: Vec<&str> =
                 UnicodeSegmentation::graphemes(stream.content.as_str(), true).collect();
@@ -1227,68 +2330,283 @@ impl App {
             if stream.pos >= graphemes.len() {
                 self.stream = None;
                 self.stick_to_bottom = true;
-                let _ = crate::persist::save_session(self.current_session_name(), &self.messages);
+                self.save_current_session();
             }
             self.dirty = true;
         }
         // Drain LLM streaming receiver
         if let Some(rx) = &self.llm_rx {
+            // Text received this tick; logged once per batch, not per delta
+            let mut batch = String::new();
+            // ...and appended once per reply, so each message is rewrapped
+            // once per frame rather than per fragment
+            let mut pending: Vec<(usize, String)> = Vec::new();
+            let mut failure = None;
+            let mut finished = false;
             for _ in 0..64 {
-                match rx.try_recv() {
-                    Ok(StreamEvent::Text(s)) => {
-                        if let Some(msg) = self.messages.last_mut() {
-                            msg.content.push_str(&s);
+                let (target, ev) = match rx.try_recv() {
+                    Ok(item) => item,
+                    Err(std::sync::mpsc::TryRecvError::Empty) => break,
+                    // Every reply finished (or failed)
+                    Err(std::sync::mpsc::TryRecvError::Disconnected) => {
+                        finished = true;
+                        break;
+                    }
+                };
+                let Some(msg) = self.messages.get_mut(target) else {
+                    continue;
+                };
+                match ev {
+                    StreamEvent::Text(s) => {
+                        StreamMeter::record(&mut self.stream_meter, &s);
+                        clear_retry(&mut self.stream_retry, target);
+                        batch.push_str(&s);
+                        msg.timestamp.get_or_insert_with(now_unix);
+                        match pending.iter_mut().find(|(t, _)| *t == target) {
+                            Some((_, text)) => text.push_str(&s),
+                            None => pending.push((target, s)),
                         }
-                        self.dirty = true;
-                        self.stick_to_bottom = true;
                     }
-                    Ok(StreamEvent::Usage {
+                    StreamEvent::Usage {
                         prompt_tokens,
                         completion_tokens,
-                    }) => {
+                    } => {
+                        msg.prompt_tokens = prompt_tokens;
+                        msg.completion_tokens = completion_tokens;
+                        // usage info will be rendered persistently in the status line
                         self.usage_prompt_tokens = prompt_tokens;
                         self.usage_completion_tokens = completion_tokens;
-                        // usage info will be rendered persistently in the status line
-                        self.dirty = true;
                     }
-                    Ok(StreamEvent::Error(e)) => {
-                        if let Some(msg) = self.messages.last_mut() {
-                            msg.content.push_str(&format!("\n[error] {}", e));
+                    StreamEvent::Reasoning(s) => {
+                        StreamMeter::record(&mut self.stream_meter, &s);
+                        clear_retry(&mut self.stream_retry, target);
+                        msg.timestamp.get_or_insert_with(now_unix);
+                        msg.reasoning.push_str(&s);
+                    }
+                    StreamEvent::RateInfo(r) => {
+                        self.rate_info = Some(r);
+                    }
+                    StreamEvent::Retrying {
+                        attempt,
+                        max_attempts,
+                    } => {
+                        self.stream_retry = Some((target, attempt, max_attempts));
+                    }
+                    StreamEvent::Cached => {
+                        if let Some(pos) = pending.iter().position(|(t, _)| *t == target) {
+                            msg.content.push_str(&pending.remove(pos).1);
                         }
-                        self.llm_rx = None;
-                        self.llm_cancel = None;
-                        let _ = crate::persist::save_session(
-                            self.current_session_name(),
-                            &self.messages,
-                        );
-                        break;
+                        msg.cached = true;
+                        // The header tag changes; force a rewrap
+                        self.chat_wrap_width = 0;
+                    }
+                    // Only this reply stops; others keep streaming. Partial
+                    // output stays, the error goes to the notice.
+                    StreamEvent::Error(e) => {
+                        clear_retry(&mut self.stream_retry, target);
+                        if !self.failed_replies.contains(&target) {
+                            self.failed_replies.push(target);
+                        }
+                        failure = Some(e);
                     }
-                    Err(std::sync::mpsc::TryRecvError::Empty) => {
+                }
+                self.dirty = true;
+            }
+            for (target, text) in pending {
+                if let Some(msg) = self.messages.get_mut(target) {
+                    msg.content.push_str(&text);
+                }
+            }
+            if finished {
+                if let Some(rate) = self.stream_meter.take().and_then(|m| m.tokens_per_sec()) {
+                    info!(target: "tui", "stream: ~{:.0} tok/s ({} wire)", rate, self.wire_label);
+                }
+                self.llm_rx = None;
+                self.llm_cancel = None;
+                self.stream_retry = None;
+                self.drop_failed_placeholders();
+                self.save_current_session();
+            }
+            if !batch.is_empty() {
+                debug!(target: "tui", "stream delta batch: {} chars content={}", batch.len(), crate::redact::preview(&batch));
+            }
+            let restored = finished
+                && self.queued_input.is_some()
+                && !(self.failed_replies.is_empty() && self.dropped_replies.is_empty());
+            if finished {
+                self.flush_queued_input();
+            }
+            match failure {
+                Some(fast_core::llm::ChatError::Canceled) => {
+                    self.notify(NoticeLevel::Info, crate::strings::STREAM_CANCELED);
+                }
+                Some(e) => {
+                    let level = if crate::strings::error_is_transient(&e) {
+                        NoticeLevel::Warning
+                    } else {
+                        NoticeLevel::Error
+                    };
+                    self.notify(level, crate::strings::reply_error_notice(&e));
+                }
+                // The error was already shown; say why the input came back
+                None if restored => {
+                    self.notify(NoticeLevel::Warning, crate::strings::QUEUED_RESTORED);
+                }
+                None => {}
+            }
+        }
+    }
+}
+
+// The reply at `target` produced output or failed: no longer retrying.
+fn clear_retry(retry: &mut Option<(usize, u32, u32)>, target: usize) {
+    if retry.is_some_and(|(t, _, _)| t == target) {
+        *retry = None;
+    }
+}
+
+// Drop the oldest turns until `msgs` fits both limits, keeping at least the
+// turn being answered. Returns how many messages were dropped.
+fn trim_context(
+    msgs: &mut Vec<fast_core::llm::Message>,
+    max_messages: Option<usize>,
+    max_tokens: Option<usize>,
+) -> usize {
+    let mut tokens: usize = msgs.iter().map(|m| m.estimated_tokens()).sum();
+    let mut start = 0;
+    loop {
+        let over_count = max_messages.is_some_and(|n| msgs.len() - start > n);
+        let over_tokens = max_tokens.is_some_and(|n| tokens > n);
+        if !over_count && !over_tokens {
+            break;
+        }
+        // Whole turns only: a user message and the replies after it
+        let Some(next) = msgs[start + 1..]
+            .iter()
+            .position(|m| m.role == fast_core::llm::Role::User)
+            .map(|i| start + 1 + i)
+        else {
+            break;
+        };
+        tokens -= msgs[start..next]
+            .iter()
+            .map(|m| m.estimated_tokens())
+            .sum::<usize>();
+        start = next;
+    }
+    msgs.drain(..start);
+    start
+}
+
+// Wire for a `wire_api` label; unknown labels use the Responses API.
+pub fn chat_wire(label: &str) -> fast_core::llm::ChatWire {
+    match label {
+        "chat" => fast_core::llm::ChatWire::Chat,
+        "auto" => fast_core::llm::ChatWire::Auto,
+        _ => fast_core::llm::ChatWire::Responses,
+    }
+}
+
+// One model's reply to stream into `messages[target]`.
+#[derive(Clone)]
+struct ReplyRequest {
+    target: usize,
+    provider: String,
+    msgs: Vec<fast_core::llm::Message>,
+    opts: fast_core::llm::ChatOpts,
+    wire: fast_core::llm::ChatWire,
+    cancel: fast_core::llm::CancellationToken,
+}
+
+fn spawn_reply(req: ReplyRequest, tx: std::sync::mpsc::Sender<(usize, StreamEvent)>) {
+    std::thread::spawn(move || {
+        let rt = tokio::runtime::Runtime::new().expect("rt");
+        rt.block_on(stream_reply(req, tx));
+    });
+}
+
+async fn stream_reply(req: ReplyRequest, tx: std::sync::mpsc::Sender<(usize, StreamEvent)>) {
+    let target = req.target;
+    let send = |ev: StreamEvent| {
+        let _ = tx.send((target, ev));
+    };
+    let client = match providers::registry().create(&req.provider) {
+        Ok(c) => c,
+        Err(e) => {
+            send(StreamEvent::Error(fast_core::llm::ChatError::Other(
+                e.to_string(),
+            )));
+            error!(target: "tui", "submit client build error: provider={} {}", req.provider, e);
+            return;
+        }
+    };
+    let model = req.opts.model.clone();
+    let res = client
+        .stream_chat(req.msgs, req.opts, req.wire, req.cancel)
+        .await;
+    match res {
+        Ok(mut s) => {
+            use futures::StreamExt;
+            // Cancellation surfaces as ChatError::Canceled from the provider
+            while let Some(it) = s.next().await {
+                match it {
+                    Ok(fast_core::llm::ChatDelta::Text(t)) => send(StreamEvent::Text(t)),
+                    Ok(fast_core::llm::ChatDelta::Reasoning(t)) => send(StreamEvent::Reasoning(t)),
+                    Ok(fast_core::llm::ChatDelta::Usage {
+                        prompt_tokens,
+                        completion_tokens,
+                    }) => send(StreamEvent::Usage {
+                        prompt_tokens,
+                        completion_tokens,
+                    }),
+                    Ok(fast_core::llm::ChatDelta::RateInfo(r)) => send(StreamEvent::RateInfo(r)),
+                    Ok(fast_core::llm::ChatDelta::Retrying {
+                        attempt,
+                        max_attempts,
+                    }) => send(StreamEvent::Retrying {
+                        attempt,
+                        max_attempts,
+                    }),
+                    Ok(fast_core::llm::ChatDelta::Finish(reason)) => {
+                        if reason.as_deref() == Some(providers::cache::CACHED_FINISH_REASON) {
+                            send(StreamEvent::Cached);
+                        }
                         break;
                     }
-                    Err(std::sync::mpsc::TryRecvError::Disconnected) => {
-                        self.llm_rx = None;
-                        self.llm_cancel = None;
-                        let _ = crate::persist::save_session(
-                            self.current_session_name(),
-                            &self.messages,
-                        );
+                    Ok(_) => { /* ignore other events for now */ }
+                    Err(e) => {
+                        error!(target: "tui", "stream delta error: model={} {}", model, e);
+                        send(StreamEvent::Error(e));
                         break;
                     }
                 }
             }
         }
+        Err(e) => {
+            error!(target: "tui", "stream start error: model={} {}", model, e);
+            send(StreamEvent::Error(e));
+        }
     }
 }
 
 #[derive(Clone, Debug)]
 pub enum StreamEvent {
     Text(String),
+    Reasoning(String),
     Usage {
         prompt_tokens: Option<u32>,
         completion_tokens: Option<u32>,
     },
-    Error(String),
+    RateInfo(fast_core::llm::RateInfo),
+    // The request failed before any output and is being sent again
+    Retrying {
+        attempt: u32,
+        max_attempts: u32,
+    },
+    // Reply was served from the response cache
+    Cached,
+    // Kept typed so the reply can show a hint for its kind
+    Error(fast_core::llm::ChatError),
 }
 
 // no toast: usage info is shown persistently in the status line above input
@@ -1297,6 +2615,31 @@ pub enum StreamEvent {
 pub struct SearchInput {
     pub buffer: String,
     pub cursor: usize,
+    // Search saved transcripts of every session instead of the current one
+    pub all_sessions: bool,
+}
+
+pub struct ConflictState {
+    pub session: String,
+    pub disk: Vec<Message>,
+    // In-memory messages that "take theirs" would drop
+    pub mine_only: Vec<Message>,
+    // Disk messages not in memory
+    pub theirs_only: usize,
+    pub selected: usize,
+}
+
+#[derive(Clone)]
+pub struct GlobalSearchState {
+    pub query: String,
+    pub hits: Vec<crate::persist::GlobalHit>,
+    pub selected: usize,
+    // Sessions scanned so far of `total`; results stream in from
+    // `global_search_rx`
+    pub searched: usize,
+    pub total: usize,
+    // Sessions whose file could not be read
+    pub failed: usize,
 }
 
 #[derive(Clone)]
@@ -1313,6 +2656,35 @@ struct StreamState {
     pos: usize,
 }
 
+// Text and reasoning received since the first delta of a turn, all replies
+// together. Tokens are estimated like context trimming does: 4 chars each.
+struct StreamMeter {
+    first: std::time::Instant,
+    last: std::time::Instant,
+    chars: usize,
+}
+
+impl StreamMeter {
+    // Shortest span worth a rate; the first deltas often arrive in a burst
+    const MIN_SPAN: std::time::Duration = std::time::Duration::from_millis(500);
+
+    fn record(meter: &mut Option<StreamMeter>, text: &str) {
+        let now = std::time::Instant::now();
+        let m = meter.get_or_insert(StreamMeter {
+            first: now,
+            last: now,
+            chars: 0,
+        });
+        m.last = now;
+        m.chars += text.chars().count();
+    }
+
+    fn tokens_per_sec(&self) -> Option<f64> {
+        let span = self.last.duration_since(self.first);
+        (span >= Self::MIN_SPAN).then(|| self.chars.div_ceil(4) as f64 / span.as_secs_f64())
+    }
+}
+
 #[derive(Clone)]
 pub struct PaletteState {
     pub buffer: String,
@@ -1325,12 +2697,36 @@ pub struct PaletteState {
 pub enum PaletteAction {
     ToggleSidebar,
     ToggleContext,
+    ToggleTimestamps,
+    ToggleReasoning,
+    ToggleWrap,
+    ToggleLineNumbers,
+    ToggleTheme,
+    ExpandAll,
+    CollapseAll,
+    CopyLastCodeBlock,
+    CopyLastReply,
+    ToggleReadOnly,
+    RetryLast,
     NewSession,
+    ImportChatExport,
     RenameSession,
     DeleteSession,
+    UndoDeleteSession,
     OpenSearch,
+    SearchAllSessions,
     SwitchModel,
     SwitchWire,
+    SwitchProfile,
+    Bookmarks,
+    InsertTemplate,
+    BranchFromMessage,
+    ExportSession,
+    EditInEditor,
+    ViewLog,
+    PreviousMessage,
+    NextMessage,
+    PreviousUserMessage,
     Quit,
 }
 
@@ -1339,12 +2735,36 @@ impl PaletteAction {
         match self {
             PaletteAction::ToggleSidebar => "Toggle sidebar",
             PaletteAction::ToggleContext => "Toggle context",
+            PaletteAction::ToggleTimestamps => "Toggle timestamps",
+            PaletteAction::ToggleReasoning => "Toggle reasoning",
+            PaletteAction::ToggleWrap => "Toggle line wrap",
+            PaletteAction::ToggleLineNumbers => "Toggle code line numbers",
+            PaletteAction::ToggleTheme => "Toggle light/dark theme",
+            PaletteAction::ExpandAll => "Expand all messages",
+            PaletteAction::CollapseAll => "Collapse all messages",
+            PaletteAction::CopyLastCodeBlock => "Copy last code block",
+            PaletteAction::CopyLastReply => "Copy last assistant message",
+            PaletteAction::ToggleReadOnly => "Toggle read-only mode",
+            PaletteAction::RetryLast => "Retry last response",
             PaletteAction::NewSession => "New session",
+            PaletteAction::ImportChatExport => "Import ChatGPT/Claude export",
             PaletteAction::RenameSession => "Rename session",
             PaletteAction::DeleteSession => "Delete session",
+            PaletteAction::UndoDeleteSession => "Undo delete session",
             PaletteAction::OpenSearch => "Open search",
+            PaletteAction::SearchAllSessions => "Search all sessions",
             PaletteAction::SwitchModel => "Switch model",
             PaletteAction::SwitchWire => "Switch wire",
+            PaletteAction::SwitchProfile => "Switch profile",
+            PaletteAction::Bookmarks => "Bookmarks",
+            PaletteAction::InsertTemplate => "Insert prompt template",
+            PaletteAction::BranchFromMessage => "Branch from selected message",
+            PaletteAction::ExportSession => "Export session",
+            PaletteAction::EditInEditor => "Edit in external editor",
+            PaletteAction::ViewLog => "View log",
+            PaletteAction::PreviousMessage => "Jump to previous message",
+            PaletteAction::NextMessage => "Jump to next message",
+            PaletteAction::PreviousUserMessage => "Jump to previous user message",
             PaletteAction::Quit => "Quit",
         }
     }
@@ -1366,12 +2786,36 @@ impl App {
         let all = vec![
             PaletteAction::ToggleSidebar,
             PaletteAction::ToggleContext,
+            PaletteAction::ToggleTimestamps,
+            PaletteAction::ToggleReasoning,
+            PaletteAction::ToggleWrap,
+            PaletteAction::ToggleLineNumbers,
+            PaletteAction::ToggleTheme,
+            PaletteAction::ExpandAll,
+            PaletteAction::CollapseAll,
+            PaletteAction::CopyLastCodeBlock,
+            PaletteAction::CopyLastReply,
+            PaletteAction::ToggleReadOnly,
+            PaletteAction::RetryLast,
             PaletteAction::NewSession,
+            PaletteAction::ImportChatExport,
             PaletteAction::RenameSession,
             PaletteAction::DeleteSession,
+            PaletteAction::UndoDeleteSession,
             PaletteAction::OpenSearch,
+            PaletteAction::SearchAllSessions,
             PaletteAction::SwitchModel,
             PaletteAction::SwitchWire,
+            PaletteAction::SwitchProfile,
+            PaletteAction::Bookmarks,
+            PaletteAction::InsertTemplate,
+            PaletteAction::BranchFromMessage,
+            PaletteAction::ExportSession,
+            PaletteAction::EditInEditor,
+            PaletteAction::ViewLog,
+            PaletteAction::PreviousMessage,
+            PaletteAction::NextMessage,
+            PaletteAction::PreviousUserMessage,
             PaletteAction::Quit,
         ];
         let q = st.buffer.to_lowercase();
@@ -1389,14 +2833,63 @@ impl App {
         match act {
             PaletteAction::ToggleSidebar => {
                 self.show_sidebar = !self.show_sidebar;
-                let _ = crate::persist::save_state(self);
+                self.mark_state_dirty();
             }
             PaletteAction::ToggleContext => {
                 self.show_context = !self.show_context;
             }
+            PaletteAction::ToggleTimestamps => {
+                self.show_timestamps = !self.show_timestamps;
+                self.mark_state_dirty();
+            }
+            PaletteAction::ToggleReasoning => {
+                self.show_reasoning = !self.show_reasoning;
+                self.chat_wrap_width = 0;
+                self.mark_state_dirty();
+            }
+            PaletteAction::ToggleWrap => self.set_wrap_lines(!self.wrap_lines),
+            PaletteAction::InsertTemplate => self.open_template_picker(),
+            PaletteAction::UndoDeleteSession => self.undo_delete_session(),
+            PaletteAction::PreviousMessage => self.jump_message(false, false),
+            PaletteAction::NextMessage => self.jump_message(true, false),
+            PaletteAction::PreviousUserMessage => self.jump_message(false, true),
+            PaletteAction::ToggleLineNumbers => {
+                self.code_line_numbers = !self.code_line_numbers;
+                self.chat_wrap_width = 0;
+            }
+            PaletteAction::ExpandAll => self.set_all_collapsed(false),
+            PaletteAction::CollapseAll => self.set_all_collapsed(true),
+            PaletteAction::CopyLastCodeBlock => self.copy_last_code_block(),
+            PaletteAction::CopyLastReply => self.copy_last_reply(),
+            PaletteAction::ToggleReadOnly => {
+                self.read_only = !self.read_only;
+            }
+            PaletteAction::RetryLast
+            | PaletteAction::NewSession
+            | PaletteAction::ImportChatExport
+            | PaletteAction::RenameSession
+            | PaletteAction::DeleteSession
+                if self.read_only =>
+            {
+                self.notify(NoticeLevel::Info, READ_ONLY_NOTE);
+            }
+            PaletteAction::ToggleTheme => {
+                let next = if self.theme_name == "light" {
+                    "dark"
+                } else {
+                    "light"
+                };
+                self.set_theme(next);
+            }
+            PaletteAction::RetryLast => self.retry(),
             PaletteAction::NewSession => {
                 self.sidebar_new_session();
             }
+            PaletteAction::ImportChatExport => {
+                self.focus = Focus::Input;
+                self.input = "/import chatgpt ".to_string();
+                self.input_cursor = self.input.chars().count();
+            }
             PaletteAction::RenameSession => {
                 self.sidebar_rename_current();
             }
@@ -1406,12 +2899,33 @@ impl App {
             PaletteAction::OpenSearch => {
                 self.open_search();
             }
+            PaletteAction::SearchAllSessions => {
+                self.open_global_search();
+            }
             PaletteAction::SwitchModel => {
                 self.open_model_picker();
             }
             PaletteAction::SwitchWire => {
                 self.open_wire_picker();
             }
+            PaletteAction::SwitchProfile => {
+                self.open_profile_picker();
+            }
+            PaletteAction::Bookmarks => {
+                self.open_bookmark_picker();
+            }
+            PaletteAction::BranchFromMessage => {
+                self.branch_from_selected();
+            }
+            PaletteAction::ExportSession => {
+                self.export_current_session("");
+            }
+            PaletteAction::EditInEditor => {
+                self.request_external_edit();
+            }
+            PaletteAction::ViewLog => {
+                self.open_log_view();
+            }
             PaletteAction::Quit => {
                 self.should_quit = true;
             }
@@ -1424,6 +2938,7 @@ impl App {
         self.search_input = Some(SearchInput {
             buffer: String::new(),
             cursor: 0,
+            all_sessions: false,
         });
     }
 }
@@ -1433,12 +2948,36 @@ impl App {
         let all = vec![
             PaletteAction::ToggleSidebar,
             PaletteAction::ToggleContext,
+            PaletteAction::ToggleTimestamps,
+            PaletteAction::ToggleReasoning,
+            PaletteAction::ToggleWrap,
+            PaletteAction::ToggleLineNumbers,
+            PaletteAction::ToggleTheme,
+            PaletteAction::ExpandAll,
+            PaletteAction::CollapseAll,
+            PaletteAction::CopyLastCodeBlock,
+            PaletteAction::CopyLastReply,
+            PaletteAction::ToggleReadOnly,
+            PaletteAction::RetryLast,
             PaletteAction::NewSession,
+            PaletteAction::ImportChatExport,
             PaletteAction::RenameSession,
             PaletteAction::DeleteSession,
+            PaletteAction::UndoDeleteSession,
             PaletteAction::OpenSearch,
+            PaletteAction::SearchAllSessions,
             PaletteAction::SwitchModel,
             PaletteAction::SwitchWire,
+            PaletteAction::SwitchProfile,
+            PaletteAction::Bookmarks,
+            PaletteAction::InsertTemplate,
+            PaletteAction::BranchFromMessage,
+            PaletteAction::ExportSession,
+            PaletteAction::EditInEditor,
+            PaletteAction::ViewLog,
+            PaletteAction::PreviousMessage,
+            PaletteAction::NextMessage,
+            PaletteAction::PreviousUserMessage,
             PaletteAction::Quit,
         ];
         let q = st.buffer.to_lowercase();
@@ -1457,7 +2996,26 @@ impl App {
 pub struct WrappedMsg {
     pub role: Role,
     pub content_len: usize,
+    pub bookmarked: bool,
+    pub timestamp: Option<i64>,
+    pub reasoning_len: usize,
+    // Leading lines (header included) that belong to the reasoning block
+    pub reasoning_lines: usize,
+    // Bytes of the model/wire tag after the role prefix on the first line;
+    // dimmed, and skipped by search
+    pub meta_len: usize,
     pub lines: Vec<String>,
+    // Markdown emphasis per line (parallel to `lines`; empty when raw)
+    pub styles: Vec<crate::markdown::Ranges>,
+    // Line of `content` each row shows (parallel to `lines`); None for
+    // reasoning and image rows
+    pub sources: Vec<Option<usize>>,
+}
+
+#[derive(Clone)]
+pub struct BookmarkPickerState {
+    pub items: Vec<usize>, // message indices
+    pub selected: usize,
 }
 
 #[derive(Clone)]
@@ -1486,6 +3044,8 @@ impl App {
         }
         let source: Vec<String> = if !self.model_suggestions.is_empty() {
             self.model_suggestions.clone()
+        } else if !self.preset_models.is_empty() {
+            self.preset_models.clone()
         } else {
             vec![
                 "gpt-5".to_string(),
@@ -1532,6 +3092,13 @@ pub struct WirePickerState {
 
 impl App {
     fn open_wire_picker(&mut self) {
+        if self.chat_only {
+            self.notify(
+                NoticeLevel::Info,
+                format!("{} only supports the chat wire", self.provider_label),
+            );
+            return;
+        }
         let filtered = vec![
             "responses".to_string(),
             "chat".to_string(),
@@ -1584,6 +3151,71 @@ impl App {
             ("model".into(), "pick a model".into()),
             ("wire".into(), "select protocol: responses/chat/auto".into()),
             ("help".into(), "open help".into()),
+            ("bookmarks".into(), "list bookmarked messages".into()),
+            (
+                "attach".into(),
+                "attach a PNG/JPEG image to the next message".into(),
+            ),
+            (
+                "template".into(),
+                "[name]: insert a saved prompt template".into(),
+            ),
+            (
+                "profile".into(),
+                "[name]: switch to a [profile.<name>] from config.toml".into(),
+            ),
+            (
+                "bugreport".into(),
+                "zip config/state/log for a bug report".into(),
+            ),
+            ("cache".into(), "clear: wipe the response cache".into()),
+            (
+                "branch".into(),
+                "new session up to the selected message".into(),
+            ),
+            (
+                "changelog".into(),
+                "release notes of an available update".into(),
+            ),
+            (
+                "compare".into(),
+                "m1,m2 <prompt>: ask several models at once".into(),
+            ),
+            ("theme".into(), "dark|light: switch color preset".into()),
+            (
+                "dryrun".into(),
+                "[on|off]: show the request body instead of sending".into(),
+            ),
+            (
+                "markdown".into(),
+                "on|off: render assistant Markdown in this session".into(),
+            ),
+            (
+                "lineno".into(),
+                "on|off: line numbers in fenced code blocks".into(),
+            ),
+            (
+                "wrap".into(),
+                "on|off: wrap chat lines in this session, or clip and scroll".into(),
+            ),
+            (
+                "collapse".into(),
+                "preview|threshold <n>: long message folding".into(),
+            ),
+            (
+                "env".into(),
+                "env for spawned processes; set KEY=VALUE / unset KEY".into(),
+            ),
+            ("retry".into(), "regenerate the last reply (F5)".into()),
+            ("undo".into(), "restore the reply replaced by /retry".into()),
+            (
+                "export".into(),
+                "[md|json] [path][!]: Markdown, or JSON that /import reads".into(),
+            ),
+            (
+                "import".into(),
+                "load a session JSON, or chatgpt/claude exports".into(),
+            ),
             ("temp".into(), "set temperature (0-2)".into()),
             ("top_p".into(), "set nucleus sampling (0-1)".into()),
             ("max_tokens".into(), "set completion cap".into()),
@@ -1614,10 +3246,37 @@ impl App {
                 self.input_cursor = 0;
                 self.open_wire_picker();
             }
+            "template" => {
+                self.input.clear();
+                self.input_cursor = 0;
+                self.open_template_picker();
+            }
+            "profile" => {
+                self.input.clear();
+                self.input_cursor = 0;
+                self.open_profile_picker();
+            }
             "help" => {
                 self.show_help = true;
             }
-            "temp" | "top_p" | "max_tokens" => {
+            "bookmarks" => {
+                self.input.clear();
+                self.input_cursor = 0;
+                self.push_bookmarks_info();
+            }
+            "changelog" => {
+                self.input.clear();
+                self.input_cursor = 0;
+                self.try_handle_slash_command("/changelog");
+            }
+            "retry" | "undo" | "branch" | "dryrun" => {
+                self.input.clear();
+                self.input_cursor = 0;
+                self.try_handle_slash_command(&format!("/{}", cmd));
+            }
+            "temp" | "top_p" | "max_tokens" | "attach" | "export" | "import" | "bugreport"
+            | "cache" | "compare" | "env" | "theme" | "collapse" | "markdown" | "wrap"
+            | "lineno" => {
                 self.input = format!("/{} ", cmd);
                 self.input_cursor = self.input.chars().count();
             }
@@ -1650,3 +3309,208 @@ impl App {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        Focus, KeyCode, KeyEvent, KeyModifiers, COMPACT_HYSTERESIS, DEFAULT_COMPACT_WIDTH,
+    };
+    use crate::test_support::{app_with, TempRoot};
+    use crate::update::ReleaseInfo;
+
+    #[test]
+    fn failed_update_check_leaves_last_checked_unset() {
+        let _root = TempRoot::new();
+        let mut app = app_with(Vec::new());
+        let (tx, rx) = std::sync::mpsc::channel::<Option<ReleaseInfo>>();
+        app.update_rx = Some(rx);
+        drop(tx);
+        app.on_tick();
+        assert!(app.update_rx.is_none());
+        assert_eq!(app.update_last_checked, None);
+        assert!(crate::persist::load_state()
+            .ok()
+            .flatten()
+            .and_then(|s| s.update_last_checked)
+            .is_none());
+    }
+
+    #[test]
+    fn successful_update_check_records_time() {
+        let _root = TempRoot::new();
+        let mut app = app_with(Vec::new());
+        let (tx, rx) = std::sync::mpsc::channel();
+        app.update_rx = Some(rx);
+        app.on_tick();
+        assert_eq!(app.update_last_checked, None, "still waiting");
+        tx.send(None).unwrap();
+        app.on_tick();
+        assert!(app.update_last_checked.is_some());
+        assert!(app.update_notice.is_none());
+        assert_eq!(
+            crate::persist::load_state()
+                .ok()
+                .flatten()
+                .and_then(|s| s.update_last_checked),
+            app.update_last_checked
+        );
+    }
+
+    #[test]
+    fn newer_release_sets_notice() {
+        let _root = TempRoot::new();
+        let mut app = app_with(Vec::new());
+        let (tx, rx) = std::sync::mpsc::channel();
+        app.update_rx = Some(rx);
+        tx.send(Some(ReleaseInfo {
+            version: "9.9.9".into(),
+            notes: String::new(),
+        }))
+        .unwrap();
+        app.on_tick();
+        assert_eq!(
+            app.update_notice.map(|r| r.version).as_deref(),
+            Some("9.9.9")
+        );
+        assert!(app.update_last_checked.is_some());
+    }
+
+    #[test]
+    fn compact_mode_switches_with_hysteresis() {
+        let mut app = app_with(Vec::new());
+        assert_eq!(app.compact_width, DEFAULT_COMPACT_WIDTH);
+        assert!(!app.update_compact(DEFAULT_COMPACT_WIDTH));
+        app.chat_wrap_width = 80;
+        assert!(app.update_compact(DEFAULT_COMPACT_WIDTH - 1));
+        assert!(app.compact);
+        assert_eq!(app.chat_wrap_width, 0, "mode change rewraps the chat");
+        // Near the threshold the mode holds
+        assert!(!app.update_compact(DEFAULT_COMPACT_WIDTH + COMPACT_HYSTERESIS - 1));
+        assert!(app.compact);
+        assert!(app.update_compact(DEFAULT_COMPACT_WIDTH + COMPACT_HYSTERESIS));
+        assert!(!app.compact);
+        assert_eq!(app.panel_chrome(), (2, 2));
+    }
+
+    #[test]
+    fn compact_width_zero_disables_compact_mode() {
+        let mut app = app_with(Vec::new());
+        app.compact_width = 0;
+        assert!(!app.update_compact(20));
+        assert!(!app.compact);
+    }
+
+    #[test]
+    fn markdown_toggle_is_per_session() {
+        let _root = TempRoot::new();
+        let mut app = app_with(Vec::new());
+        app.sessions = vec!["a".into(), "b".into()];
+        app.session_auto_named = vec![false, false];
+        assert!(app.try_handle_slash_command("/markdown on"));
+        assert!(app.render_markdown);
+        app.current_session = 1;
+        app.load_current_session_messages();
+        assert!(!app.render_markdown, "other sessions keep the default");
+        app.current_session = 0;
+        app.load_current_session_messages();
+        assert!(app.render_markdown);
+        // The setting follows a rename
+        crate::persist::rename_session("a", "c").unwrap();
+        assert_eq!(crate::persist::load_session_view("c").markdown, Some(true));
+        assert_eq!(crate::persist::load_session_view("a").markdown, None);
+    }
+
+    #[test]
+    fn wrap_toggle_is_per_session() {
+        let _root = TempRoot::new();
+        let mut app = app_with(Vec::new());
+        app.sessions = vec!["a".into(), "b".into()];
+        app.session_auto_named = vec![false, false];
+        assert!(app.try_handle_slash_command("/wrap off"));
+        assert!(!app.wrap_lines);
+        app.current_session = 1;
+        app.load_current_session_messages();
+        assert!(app.wrap_lines, "other sessions keep the default");
+        app.current_session = 0;
+        app.load_current_session_messages();
+        assert!(!app.wrap_lines);
+        assert_eq!(crate::persist::load_session_view("a").wrap, Some(false));
+    }
+
+    #[test]
+    fn enter_with_only_a_held_paste_queues_nothing_while_streaming() {
+        let _root = TempRoot::new();
+        let mut app = app_with(Vec::new());
+        let (_tx, rx) = std::sync::mpsc::channel();
+        app.llm_rx = Some(rx);
+        app.attach_pasted_text("long\npasted\ntext".into());
+        app.submit();
+        assert_eq!(app.queued_input, None);
+        assert!(app.pending_paste.is_some(), "the paste stays held");
+        app.input = "see above".into();
+        app.submit();
+        assert_eq!(app.queued_input.as_deref(), Some("see above"));
+    }
+
+    #[test]
+    fn export_picks_markdown_only_for_md_paths() {
+        let root = TempRoot::new();
+        let mut app = app_with(crate::test_support::sample_messages());
+        app.save_current_session();
+        let read = |name: &str| std::fs::read_to_string(root.path().join(name)).unwrap();
+        for (arg, file, json) in [
+            ("notes.md", "notes.md", false),
+            ("notes.txt", "notes.txt", true),
+            ("notes", "notes", true),
+            ("md notes.json", "notes.json", false),
+            ("json notes2.md", "notes2.md", true),
+        ] {
+            let path = root.path().join(arg.split_whitespace().last().unwrap());
+            let arg = match arg.split_once(' ') {
+                Some((word, _)) => format!("{} {}", word, path.display()),
+                None => path.display().to_string(),
+            };
+            app.export_current_session(&format!("{}!", arg));
+            let text = read(file);
+            assert_eq!(text.starts_with('{'), json, "{}: {}", arg, text);
+        }
+    }
+
+    #[test]
+    fn env_listing_is_not_sent_to_the_model() {
+        let _root = TempRoot::new();
+        let mut app = app_with(crate::test_support::sample_messages());
+        app.dry_run = true;
+        app.env_command("set GREETING=env-value-in-listing");
+        app.env_command("");
+        assert!(app.messages.last().unwrap().is_info());
+        app.send_prompt("next", vec!["m".into()]);
+        let body = &app.messages.last().unwrap().content;
+        assert!(body.contains("next"), "{}", body);
+        assert!(!body.contains("[info]"), "{}", body);
+        assert!(!body.contains("env-value-in-listing"), "{}", body);
+    }
+
+    #[test]
+    fn home_end_follow_the_focused_pane() {
+        let _root = TempRoot::new();
+        let mut app = app_with(crate::test_support::sample_messages());
+        let key = |code| KeyEvent::new(code, KeyModifiers::NONE);
+        app.focus = Focus::Input;
+        app.input = "ab\ncd".into();
+        app.input_cursor = 4;
+        app.stick_to_bottom = false;
+        app.chat_scroll = 3;
+        app.on_key(key(KeyCode::Home));
+        assert_eq!(app.input_cursor, 3);
+        app.on_key(key(KeyCode::End));
+        assert_eq!(app.input_cursor, 5);
+        assert_eq!(
+            app.chat_scroll, 3,
+            "End in the input keeps the chat where it is"
+        );
+        app.focus = Focus::Chat;
+        app.on_key(key(KeyCode::End));
+        assert_eq!((app.chat_scroll, app.stick_to_bottom), (0, true));
+    }
+}
@@ -0,0 +1,50 @@
+use std::time::{Duration, Instant};
+
+use super::App;
+
+// How long a notice stays up unless a key dismisses it first
+const INFO_TTL: Duration = Duration::from_secs(4);
+const PROBLEM_TTL: Duration = Duration::from_secs(8);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NoticeLevel {
+    Info,
+    Warning,
+    Error,
+}
+
+// One-line banner above the input: command feedback, config problems and
+// failed replies. Never stored in the session.
+#[derive(Clone, Debug)]
+pub struct Notice {
+    pub level: NoticeLevel,
+    pub text: String,
+    pub created: Instant,
+}
+
+impl Notice {
+    fn ttl(&self) -> Duration {
+        match self.level {
+            NoticeLevel::Info => INFO_TTL,
+            NoticeLevel::Warning | NoticeLevel::Error => PROBLEM_TTL,
+        }
+    }
+}
+
+impl App {
+    pub fn notify(&mut self, level: NoticeLevel, text: impl Into<String>) {
+        self.notice = Some(Notice {
+            level,
+            text: text.into(),
+            created: Instant::now(),
+        });
+        self.dirty = true;
+    }
+
+    // The notice, if still fresh
+    pub fn active_notice(&self) -> Option<&Notice> {
+        self.notice
+            .as_ref()
+            .filter(|n| n.created.elapsed() < n.ttl())
+    }
+}
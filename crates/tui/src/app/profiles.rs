@@ -0,0 +1,151 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use providers::openai::config::{self, OpenAiConfig, OpenAiFileConfig};
+use unicode_segmentation::UnicodeSegmentation;
+
+use super::input::{delete_left, delete_right, insert_graphemes};
+use super::{App, NoticeLevel};
+
+// Picker entry for the top-level settings, i.e. no `[profile.<name>]`
+pub const DEFAULT_PROFILE: &str = "default";
+
+// `/profile` without a name and the palette: pick a `[profile.<name>]`
+pub struct ProfilePickerState {
+    pub buffer: String,
+    pub cursor: usize,
+    // "default" first, then the config's profiles
+    pub all: Vec<String>,
+    // Indices into `all` whose name contains `buffer`
+    pub filtered: Vec<usize>,
+    pub selected: usize,
+}
+
+impl ProfilePickerState {
+    pub fn current(&self) -> Option<&String> {
+        self.all.get(*self.filtered.get(self.selected)?)
+    }
+
+    fn current_position(&self, name: &str) -> Option<usize> {
+        self.filtered.iter().position(|&i| self.all[i] == name)
+    }
+}
+
+impl App {
+    pub fn open_profile_picker(&mut self) {
+        let names = OpenAiFileConfig::profile_names();
+        if names.is_empty() {
+            self.notify(
+                NoticeLevel::Info,
+                "no [profile.<name>] tables in config.toml",
+            );
+            return;
+        }
+        let mut state = ProfilePickerState {
+            buffer: String::new(),
+            cursor: 0,
+            all: std::iter::once(DEFAULT_PROFILE.to_string())
+                .chain(names)
+                .collect(),
+            filtered: Vec::new(),
+            selected: 0,
+        };
+        filter_profiles(&mut state);
+        // Start on the active one
+        let active = config::active_profile().unwrap_or_else(|| DEFAULT_PROFILE.into());
+        if let Some(i) = state.current_position(&active) {
+            state.selected = i;
+        }
+        self.profile_picker = Some(state);
+    }
+
+    // `/profile <name>`: later sends use the profile's endpoint, key, model
+    // and wire; "default" goes back to the top-level settings.
+    pub fn switch_profile(&mut self, name: &str) {
+        let wanted = Some(name.to_string()).filter(|n| n != DEFAULT_PROFILE);
+        let previous = config::active_profile();
+        config::set_active_profile(wanted);
+        let cfg = match OpenAiConfig::from_env_and_file(None) {
+            Ok(cfg) => cfg,
+            Err(e) => {
+                config::set_active_profile(previous);
+                self.notify(NoticeLevel::Error, format!("profile '{}': {:#}", name, e));
+                return;
+            }
+        };
+        if let Err(e) = cfg.validate() {
+            config::set_active_profile(previous);
+            self.notify(NoticeLevel::Error, format!("profile '{}': {:#}", name, e));
+            return;
+        }
+        self.apply_provider_config(&cfg);
+        self.rate_info = None;
+        self.mark_state_dirty();
+        self.notify(
+            NoticeLevel::Info,
+            format!(
+                "profile '{}': {} · {} · {}",
+                name, self.provider_label, self.model_label, self.wire_label
+            ),
+        );
+    }
+
+    pub fn on_profile_picker_key(&mut self, key: KeyEvent) {
+        let Some(state) = self.profile_picker.as_mut() else {
+            return;
+        };
+        match key.code {
+            KeyCode::Esc => {
+                self.profile_picker = None;
+            }
+            KeyCode::Enter => {
+                let picked = state.current().cloned();
+                self.profile_picker = None;
+                if let Some(name) = picked {
+                    self.switch_profile(&name);
+                }
+            }
+            KeyCode::Up => {
+                state.selected = state.selected.saturating_sub(1);
+            }
+            KeyCode::Down if state.selected + 1 < state.filtered.len() => {
+                state.selected += 1;
+            }
+            KeyCode::Backspace => {
+                delete_left(&mut state.buffer, &mut state.cursor);
+                filter_profiles(state);
+            }
+            KeyCode::Delete => {
+                delete_right(&mut state.buffer, state.cursor);
+                filter_profiles(state);
+            }
+            KeyCode::Left if state.cursor > 0 => {
+                state.cursor -= 1;
+            }
+            KeyCode::Right => {
+                state.cursor = (state.cursor + 1).min(state.buffer.graphemes(true).count());
+            }
+            KeyCode::Home => {
+                state.cursor = 0;
+            }
+            KeyCode::End => {
+                state.cursor = state.buffer.graphemes(true).count();
+            }
+            KeyCode::Char(ch) if !key.modifiers.contains(KeyModifiers::CONTROL) => {
+                insert_graphemes(&mut state.buffer, &mut state.cursor, &ch.to_string());
+                filter_profiles(state);
+            }
+            _ => {}
+        }
+    }
+}
+
+fn filter_profiles(state: &mut ProfilePickerState) {
+    let q = state.buffer.to_lowercase();
+    state.filtered = state
+        .all
+        .iter()
+        .enumerate()
+        .filter(|(_, n)| n.to_lowercase().contains(&q))
+        .map(|(i, _)| i)
+        .collect();
+    state.selected = 0;
+}
@@ -0,0 +1,172 @@
+use super::{spawn_reply, App, Message, NoticeLevel, Role, StreamEvent};
+
+// Replies dropped by `/retry`, kept until the next prompt or session switch.
+pub struct RetryUndo {
+    // Index of the user turn they answered
+    pub user: usize,
+    pub replies: Vec<Message>,
+}
+
+impl App {
+    // `/retry`, F5 and the palette: re-send a failed reply if there is one,
+    // otherwise regenerate the last reply.
+    pub fn retry(&mut self) {
+        if !self.retry_failed() {
+            self.retry_last();
+        }
+    }
+
+    // Replace the reply to the last prompt with a fresh one
+    // from the current model and wire.
+    pub fn retry_last(&mut self) {
+        if self.llm_rx.is_some() || self.stream.is_some() {
+            self.notify(NoticeLevel::Info, "wait for the current reply to finish");
+            return;
+        }
+        // Every reply to the last prompt failed and was dropped, and the
+        // requests are gone (session reload): just answer it again.
+        if self.messages.last().is_some_and(|m| m.role == Role::User) {
+            let model = self.model_label.clone();
+            self.start_replies(vec![model]);
+            self.stick_to_bottom = true;
+            self.chat_scroll = 0;
+            return;
+        }
+        let Some(user) = self.last_answered_turn() else {
+            self.notify(
+                NoticeLevel::Info,
+                "nothing to retry: the last message is not a reply",
+            );
+            return;
+        };
+        let replies = self.messages.split_off(user + 1);
+        self.collapsed.truncate(user + 1);
+        self.retry_undo = Some(RetryUndo { user, replies });
+        self.chat_wrap_width = 0;
+        let model = self.model_label.clone();
+        self.start_replies(vec![model]);
+        self.stick_to_bottom = true;
+        self.chat_scroll = 0;
+    }
+
+    // F5: send the newest failed reply's request again, unchanged. A
+    // reply that kept partial output is rewritten in place; one whose empty
+    // placeholder was dropped gets a new one. False when nothing failed.
+    pub fn retry_failed(&mut self) -> bool {
+        if self.failed_replies.is_empty() && self.dropped_replies.is_empty() {
+            return false;
+        }
+        if self.llm_rx.is_some() {
+            self.notify(NoticeLevel::Info, "wait for the current reply to finish");
+            return true;
+        }
+        let mut req = if let Some((mut req, placeholder)) = self.dropped_replies.pop() {
+            self.messages.push(placeholder);
+            self.collapsed.push(false);
+            req.target = self.messages.len() - 1;
+            self.sent_replies.push(req.clone());
+            req
+        } else {
+            let Some(target) = self.failed_replies.pop() else {
+                return false;
+            };
+            let Some(req) = self
+                .sent_replies
+                .iter()
+                .find(|r| r.target == target)
+                .cloned()
+            else {
+                return false;
+            };
+            let Some(msg) = self.messages.get_mut(target) else {
+                return false;
+            };
+            msg.content.clear();
+            msg.reasoning.clear();
+            msg.timestamp = None;
+            msg.prompt_tokens = None;
+            msg.completion_tokens = None;
+            // Cached rows assume the content only grows
+            self.chat_wrap_width = 0;
+            req
+        };
+        let (tx, rx) = std::sync::mpsc::channel::<(usize, StreamEvent)>();
+        self.llm_rx = Some(rx);
+        let cancel = fast_core::llm::CancellationToken::new();
+        self.llm_cancel = Some(cancel.clone());
+        req.cancel = cancel;
+        spawn_reply(req, tx);
+        self.stick_to_bottom = true;
+        self.chat_scroll = 0;
+        true
+    }
+
+    // Once every reply of a turn has ended: remove failed replies that
+    // produced nothing, so no empty assistant turn is saved or sent back.
+    pub(super) fn drop_failed_placeholders(&mut self) {
+        let mut failed = std::mem::take(&mut self.failed_replies);
+        failed.sort_unstable_by(|a, b| b.cmp(a));
+        for target in failed {
+            let empty = self
+                .messages
+                .get(target)
+                .is_some_and(|m| m.content.trim().is_empty() && m.reasoning.trim().is_empty());
+            if !empty {
+                self.failed_replies.push(target);
+                continue;
+            }
+            let placeholder = self.messages.remove(target);
+            self.collapsed.remove(target);
+            if let Some(pos) = self.sent_replies.iter().position(|r| r.target == target) {
+                let req = self.sent_replies.remove(pos);
+                self.dropped_replies.push((req, placeholder));
+            }
+            for r in &mut self.sent_replies {
+                if r.target > target {
+                    r.target -= 1;
+                }
+            }
+            for t in &mut self.failed_replies {
+                if *t > target {
+                    *t -= 1;
+                }
+            }
+            self.chat_wrap_width = 0;
+        }
+        self.failed_replies.reverse();
+    }
+
+    // `/undo`: put back the replies the last /retry replaced.
+    pub fn undo_retry(&mut self) {
+        if self.llm_rx.is_some() || self.stream.is_some() {
+            self.notify(NoticeLevel::Info, "wait for the current reply to finish");
+            return;
+        }
+        let Some(undo) = self
+            .retry_undo
+            .take()
+            .filter(|u| self.last_answered_turn() == Some(u.user))
+        else {
+            self.notify(NoticeLevel::Info, "nothing to undo");
+            return;
+        };
+        self.messages.truncate(undo.user + 1);
+        self.messages.extend(undo.replies);
+        self.sent_replies.clear();
+        self.failed_replies.clear();
+        self.dropped_replies.clear();
+        self.collapsed.truncate(undo.user + 1);
+        self.chat_wrap_width = 0;
+        self.stick_to_bottom = true;
+        self.chat_scroll = 0;
+        self.save_current_session();
+    }
+
+    // The last user turn, when a reply to it follows; "[info]" lines after
+    // it go along with the reply.
+    fn last_answered_turn(&self) -> Option<usize> {
+        let user = self.messages.iter().rposition(|m| m.role == Role::User)?;
+        let answered = self.messages[user + 1..].iter().any(|m| !m.is_info());
+        answered.then_some(user)
+    }
+}
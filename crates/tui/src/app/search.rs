@@ -1,15 +1,36 @@
-use super::{App, SearchHit};
+use std::sync::mpsc::TryRecvError;
+
+use tracing::warn;
+use unicode_segmentation::UnicodeSegmentation;
+
+use super::{App, GlobalSearchState, Role, SearchHit, WrappedMsg};
+use crate::strings::{BOOKMARK_MARKER, PREFIX_ASSISTANT, PREFIX_SYSTEM, PREFIX_USER};
 
 impl App {
     pub fn open_search(&mut self) {
         self.search_input = Some(super::SearchInput {
             buffer: String::new(),
             cursor: 0,
+            all_sessions: false,
+        });
+    }
+
+    pub fn open_global_search(&mut self) {
+        self.search_input = Some(super::SearchInput {
+            buffer: String::new(),
+            cursor: 0,
+            all_sessions: true,
         });
     }
 
     pub fn commit_search(&mut self) {
         if let Some(si) = &self.search_input {
+            if si.all_sessions {
+                let q = si.buffer.clone();
+                self.search_input = None;
+                self.run_global_search(q);
+                return;
+            }
             let q = si.buffer.clone();
             self.search_query = if q.is_empty() { None } else { Some(q) };
         }
@@ -27,24 +48,119 @@ impl App {
         if q.is_empty() {
             return;
         }
+        let ignore_case = ignores_case(self.search_smart_case, q);
         for (mi, w) in self.chat_cache.iter().enumerate() {
             for (li, line) in w.lines.iter().enumerate() {
-                let mut start = 0usize;
-                while let Some(pos) = line[start..].find(q) {
-                    let s = start + pos;
-                    let e = s + q.len();
+                // The header prefix and model/wire tag are not message text
+                let skip = if li == 0 { header_len(line, w) } else { 0 };
+                for (s, e) in find_matches(line, q, ignore_case, self.search_whole_word)
+                    .into_iter()
+                    .filter(|(s, _)| *s >= skip)
+                {
                     self.search_hits.push(SearchHit {
                         msg_idx: mi,
                         line_idx: li,
                         start: s,
                         end: e,
                     });
-                    start = e;
                 }
             }
         }
     }
 
+    fn run_global_search(&mut self, query: String) {
+        if query.is_empty() {
+            return;
+        }
+        let ignore_case = ignores_case(self.search_smart_case, &query);
+        self.global_search_rx = Some(crate::persist::spawn_search_all(
+            self.sessions.clone(),
+            query.clone(),
+            ignore_case,
+            self.search_whole_word,
+        ));
+        self.global_search = Some(GlobalSearchState {
+            query,
+            hits: Vec::new(),
+            selected: 0,
+            searched: 0,
+            total: self.sessions.len(),
+            failed: 0,
+        });
+    }
+
+    // Collect the sessions the background search finished since the last tick.
+    pub(super) fn drain_global_search(&mut self) {
+        let Some(rx) = &self.global_search_rx else {
+            return;
+        };
+        let Some(gs) = self.global_search.as_mut() else {
+            self.global_search_rx = None;
+            return;
+        };
+        loop {
+            match rx.try_recv() {
+                Ok(res) => {
+                    gs.searched += 1;
+                    match res {
+                        Ok(hits) => gs.hits.extend(hits),
+                        Err(e) => {
+                            warn!(target: "tui", "search all sessions: {:#}", e);
+                            gs.failed += 1;
+                        }
+                    }
+                    self.dirty = true;
+                }
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    self.global_search_rx = None;
+                    self.dirty = true;
+                    break;
+                }
+            }
+        }
+    }
+
+    // Switch to the session of the selected global hit and reveal the match.
+    pub fn open_global_hit(&mut self) {
+        let Some(gs) = self.global_search.take() else {
+            return;
+        };
+        self.global_search_rx = None;
+        let Some(hit) = gs.hits.get(gs.selected).cloned() else {
+            return;
+        };
+        let Some(idx) = self.sessions.iter().position(|s| *s == hit.session) else {
+            return;
+        };
+        if idx != self.current_session {
+            self.current_session = idx;
+            self.ensure_sidebar_visible();
+            self.mark_state_dirty();
+            self.load_current_session_messages();
+        }
+        if let Some(area) = self.chat_area {
+            self.ensure_chat_wrapped(self.panel_inner(area).width);
+        }
+        self.search_query = Some(gs.query);
+        self.recompute_search_hits();
+        match self
+            .search_hits
+            .iter()
+            .position(|h| h.msg_idx == hit.msg_idx)
+        {
+            Some(i) => {
+                self.search_current = i;
+                self.reveal_current_search_hit();
+            }
+            // Match spans a wrap boundary: no line hit, jump to the message.
+            None => {
+                self.search_current = 0;
+                self.jump_to_message(hit.msg_idx);
+            }
+        }
+    }
+
     pub fn next_search_hit(&mut self) {
         if self.search_hits.is_empty() {
             return;
@@ -84,51 +200,220 @@ impl App {
                 self.collapsed[hit.msg_idx] = false;
             }
         }
-        let mut acc = 0usize;
-        for (i, w) in self.chat_cache.iter().enumerate() {
-            if i == hit.msg_idx {
-                break;
-            }
-            let base = w.lines.len();
-            let collapsed = self.collapsed.get(i).copied().unwrap_or(false);
-            let preview = self.collapse_preview_lines;
-            let threshold = self.collapse_threshold_lines;
-            let display = if collapsed && base > preview {
-                preview
-            } else {
-                base
-            };
-            let has_indicator = if collapsed && base > preview {
-                true
-            } else {
-                !collapsed && base > threshold
-            };
-            acc += display + if has_indicator { 1 } else { 0 };
-        }
-        let base = self
-            .chat_cache
-            .get(hit.msg_idx)
-            .map(|w| w.lines.len())
-            .unwrap_or(0);
-        let collapsed = self.collapsed.get(hit.msg_idx).copied().unwrap_or(false);
-        let preview = self.collapse_preview_lines;
-        let threshold = self.collapse_threshold_lines;
-        let display = if collapsed && base > preview {
-            preview
-        } else {
-            base
-        };
-        let _has_indicator = if collapsed && base > preview {
-            true
-        } else {
-            !collapsed && base > threshold
-        };
-        let global = acc + hit.line_idx.min(display.saturating_sub(1));
+        let lines = self.chat_lines();
+        let shown = lines.get(hit.msg_idx).map(|m| m.shown).unwrap_or(0);
+        let global = crate::chat_layout::start_of(&lines, hit.msg_idx)
+            + hit.line_idx.min(shown.saturating_sub(1));
         if let Some(area) = self.chat_area {
-            let inner_h = area.height.saturating_sub(2);
+            let inner_h = self.panel_inner(area).height;
             self.set_scroll_to_show_global(inner_h, global);
         }
     }
 }
 
-// tests removed as requested
+// Bytes of the role prefix, bookmark marker and model tag on a message's
+// first wrapped line.
+fn header_len(line: &str, w: &WrappedMsg) -> usize {
+    let prefix = match w.role {
+        Role::User => PREFIX_USER,
+        Role::Assistant => PREFIX_ASSISTANT,
+        Role::System => PREFIX_SYSTEM,
+    };
+    let marker = if w.bookmarked { BOOKMARK_MARKER } else { "" };
+    (prefix.len() + marker.len() + w.meta_len).min(line.len())
+}
+
+// Smart case: an all-lowercase query matches any case, one with a capital
+// only itself.
+fn ignores_case(smart_case: bool, query: &str) -> bool {
+    smart_case && !query.chars().any(char::is_uppercase)
+}
+
+// Widen the byte range `start..end` to whole graphemes of `line`, clamped to
+// its length, so slicing never splits a character or separates a base
+// character from its combining marks.
+pub fn grapheme_span(line: &str, start: usize, end: usize) -> (usize, usize) {
+    let mut s = if start >= line.len() { line.len() } else { 0 };
+    let mut e = line.len();
+    for (i, _) in line.grapheme_indices(true) {
+        if i <= start {
+            s = s.max(i);
+        }
+        if i >= end {
+            e = i;
+            break;
+        }
+    }
+    (s, e.max(s))
+}
+
+// Find non-overlapping matches of `query` in `line`, returned as byte ranges
+// into the original `line` so highlighting can slice it directly. Matches
+// cover whole graphemes: "e" found in "é" (e + combining accent) takes the
+// accent along.
+pub fn find_matches(
+    line: &str,
+    query: &str,
+    case_insensitive: bool,
+    whole_word: bool,
+) -> Vec<(usize, usize)> {
+    let mut out = Vec::new();
+    if query.is_empty() {
+        return out;
+    }
+    let bounds: Vec<usize> = if whole_word {
+        line.split_word_bound_indices()
+            .map(|(i, _)| i)
+            .chain(std::iter::once(line.len()))
+            .collect()
+    } else {
+        Vec::new()
+    };
+    let is_word_match = |s: usize, e: usize| {
+        !whole_word || (bounds.binary_search(&s).is_ok() && bounds.binary_search(&e).is_ok())
+    };
+    let mut push = |s: usize, e: usize| {
+        let (s, e) = grapheme_span(line, s, e);
+        let after_last = out.last().map_or(true, |&(_, last)| s >= last);
+        if s < e && after_last && is_word_match(s, e) {
+            out.push((s, e));
+        }
+    };
+    if case_insensitive {
+        // Lowercasing can change byte lengths, so search a folded copy and map
+        // each folded byte back to the start of its source char.
+        let (folded, map) = fold_with_offsets(line);
+        let q = query.to_lowercase();
+        let mut start = 0usize;
+        while let Some(pos) = folded[start..].find(&q) {
+            let fs = start + pos;
+            let fe = fs + q.len();
+            push(map[fs], map[fe]);
+            start = fe;
+        }
+    } else {
+        let mut start = 0usize;
+        while let Some(pos) = line[start..].find(query) {
+            let s = start + pos;
+            push(s, s + query.len());
+            start = s + query.len();
+        }
+    }
+    out
+}
+
+// Lowercase `s`, returning the folded string plus a table mapping every folded
+// byte offset (and the end offset) to a char boundary in `s`. Bytes in the
+// middle of a char's expansion map to the end of that char.
+fn fold_with_offsets(s: &str) -> (String, Vec<usize>) {
+    let mut folded = String::with_capacity(s.len());
+    let mut map = Vec::with_capacity(s.len() + 1);
+    for (i, ch) in s.char_indices() {
+        let next = i + ch.len_utf8();
+        let before = folded.len();
+        folded.extend(ch.to_lowercase());
+        for b in before..folded.len() {
+            map.push(if b == before { i } else { next });
+        }
+    }
+    map.push(s.len());
+    (folded, map)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Matched text of each hit
+    fn hits<'a>(line: &'a str, query: &str, ignore_case: bool, whole_word: bool) -> Vec<&'a str> {
+        find_matches(line, query, ignore_case, whole_word)
+            .into_iter()
+            .map(|(s, e)| &line[s..e])
+            .collect()
+    }
+
+    #[test]
+    fn smart_case_looks_for_any_capital() {
+        assert!(ignores_case(true, "straße"));
+        assert!(ignores_case(true, "東京 2024"));
+        assert!(!ignores_case(true, "Straße"));
+        assert!(!ignores_case(true, "élan Ω"));
+        assert!(!ignores_case(true, "ÉCOLE"));
+        // Off: the query is always taken literally
+        assert!(!ignores_case(false, "straße"));
+    }
+
+    #[test]
+    fn ignoring_case_folds_unicode_letters() {
+        let line = "Élan, élan, ÉLAN";
+        assert_eq!(hits(line, "élan", true, false), ["Élan", "élan", "ÉLAN"]);
+        assert_eq!(hits(line, "Élan", false, false), ["Élan"]);
+        // Folding changes byte lengths (ẞ is 3 bytes, ß 2); ranges still
+        // point into the original line
+        let line = "GROẞE und große";
+        assert_eq!(hits(line, "große", true, false), ["GROẞE", "große"]);
+        assert_eq!(hits("Σίσυφος", "σίσυφος", true, false), ["Σίσυφος"]);
+    }
+
+    #[test]
+    fn whole_word_uses_unicode_word_bounds() {
+        let line = "naïve naïveté, naïve.";
+        assert_eq!(hits(line, "naïve", false, false).len(), 3);
+        assert_eq!(hits(line, "naïve", false, true), ["naïve", "naïve"]);
+        assert_eq!(hits("Привет, мир! мировой", "мир", false, true), ["мир"]);
+        // An apostrophe between letters does not end a word
+        assert!(hits("the café's menu", "café", false, true).is_empty());
+        assert_eq!(hits("café au lait", "CAFÉ", true, true), ["café"]);
+        // Neither side may sit inside a word
+        assert!(hits("überall", "all", false, true).is_empty());
+        assert!(hits("allüberall", "allü", false, true).is_empty());
+    }
+
+    #[test]
+    fn grapheme_span_widens_to_whole_graphemes() {
+        let line = "a漢😀e\u{301}b";
+        // Bytes: a 0, 漢 1..4, 😀 4..8, e 8, U+0301 9..11, b 11
+        assert_eq!(grapheme_span(line, 2, 3), (1, 4));
+        assert_eq!(grapheme_span(line, 5, 6), (4, 8));
+        assert_eq!(grapheme_span(line, 8, 9), (8, 11));
+        assert_eq!(grapheme_span(line, 10, 11), (8, 11));
+        assert_eq!(grapheme_span(line, 0, 1), (0, 1));
+        // Past the end clamps to an empty span
+        assert_eq!(grapheme_span(line, 40, 50), (line.len(), line.len()));
+        let family = "👨\u{200d}👩\u{200d}👧!";
+        assert_eq!(grapheme_span(family, 4, 5), (0, family.len() - 1));
+    }
+
+    #[test]
+    fn matches_never_split_wide_or_combined_text() {
+        let line = "文字化け と 😀😀 と cafe\u{301} café";
+        for (s, e) in find_matches(line, "字化", false, false)
+            .into_iter()
+            .chain(find_matches(line, "😀", false, false))
+            .chain(find_matches(line, "cafe", true, false))
+        {
+            assert!(line.is_char_boundary(s) && line.is_char_boundary(e));
+        }
+        assert_eq!(hits(line, "字化", false, false), ["字化"]);
+        assert_eq!(hits(line, "😀", false, false), ["😀", "😀"]);
+        // "e" in a decomposed é takes its accent along
+        assert_eq!(hits(line, "cafe", false, false), ["cafe\u{301}"]);
+        // A query with the combining mark finds only the decomposed form
+        assert_eq!(hits(line, "cafe\u{301}", false, false), ["cafe\u{301}"]);
+        assert_eq!(hits(line, "café", false, false), ["café"]);
+        assert_eq!(hits(line, "CAFÉ", true, false), ["café"]);
+        // A bare combining mark widens to its whole grapheme
+        assert_eq!(hits(line, "\u{301}", false, false), ["e\u{301}"]);
+    }
+
+    #[test]
+    fn whole_word_with_wide_and_emoji_text() {
+        // Each ideograph is a word of its own; emoji are their own segments
+        let line = "東京 東京都 😀ok";
+        assert_eq!(hits(line, "東京", false, true).len(), 2);
+        assert_eq!(hits(line, "😀", false, true), ["😀"]);
+        assert_eq!(hits(line, "ok", false, true), ["ok"]);
+        let family = "hi 👨\u{200d}👩\u{200d}👧 there";
+        assert_eq!(hits(family, "👩", false, false), ["👨\u{200d}👩\u{200d}👧"]);
+    }
+}
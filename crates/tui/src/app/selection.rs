@@ -0,0 +1,138 @@
+use crossterm::event::{KeyCode, KeyEvent};
+
+use super::{App, Focus};
+use crate::chat_layout;
+
+// `v` in the chat: a line-wise selection over the rows on screen. Both ends
+// are global lines (see `chat_layout`); `y` copies the source text behind
+// the rows, not the wrapped rows themselves.
+#[derive(Clone, Copy, Debug)]
+pub struct VisualSelection {
+    pub anchor: usize,
+    pub cursor: usize,
+}
+
+impl App {
+    // Start on the selected message's first row, else the top of the view.
+    pub fn start_visual_selection(&mut self) {
+        let Some(h) = self.chat_inner_height() else {
+            return;
+        };
+        let total = self.effective_total_lines();
+        if total == 0 {
+            return;
+        }
+        let win = self.compute_chat_layout(h);
+        let at = self
+            .selected_message
+            .map(|i| self.message_global_start(i))
+            .filter(|g| (win.start..win.start + win.viewport).contains(g))
+            .unwrap_or(win.start)
+            .min(total - 1);
+        self.visual = Some(VisualSelection {
+            anchor: at,
+            cursor: at,
+        });
+    }
+
+    // First and last selected global lines, clamped to the conversation.
+    pub fn visual_range(&self) -> Option<(usize, usize)> {
+        let v = self.visual?;
+        let last = self.effective_total_lines().checked_sub(1)?;
+        let (a, b) = (v.anchor.min(last), v.cursor.min(last));
+        Some((a.min(b), a.max(b)))
+    }
+
+    pub fn on_visual_key(&mut self, key: KeyEvent) {
+        let page = self
+            .chat_inner_height()
+            .map_or(1, |h| (h as usize).saturating_sub(1).max(1));
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('v') => {
+                self.visual = None;
+            }
+            KeyCode::Char('y') => {
+                let text = self.visual_text();
+                self.visual = None;
+                self.copy_text(&text);
+            }
+            KeyCode::Up | KeyCode::Char('k') => self.move_visual_cursor(-1),
+            KeyCode::Down | KeyCode::Char('j') => self.move_visual_cursor(1),
+            KeyCode::PageUp => self.move_visual_cursor(-(page as isize)),
+            KeyCode::PageDown => self.move_visual_cursor(page as isize),
+            KeyCode::Home => self.move_visual_cursor(isize::MIN),
+            KeyCode::End => self.move_visual_cursor(isize::MAX),
+            _ => {}
+        }
+    }
+
+    // Move the free end and scroll just enough to keep it on screen.
+    fn move_visual_cursor(&mut self, delta: isize) {
+        let Some(last) = self.effective_total_lines().checked_sub(1) else {
+            return;
+        };
+        let Some(v) = self.visual.as_mut() else {
+            return;
+        };
+        let cursor = v.cursor.min(last).saturating_add_signed(delta).min(last);
+        v.cursor = cursor;
+        let Some(h) = self.chat_inner_height() else {
+            return;
+        };
+        let win = self.compute_chat_layout(h);
+        if cursor < win.start {
+            self.set_scroll_to_show_global(h, cursor);
+        } else if cursor >= win.start + win.viewport {
+            self.set_scroll_to_show_global(h, cursor + 1 - win.viewport);
+        }
+        if self.focus == Focus::Chat {
+            self.selected_message = self.message_at_global(cursor);
+        }
+    }
+
+    // Unwrapped text of the selected rows: each content line once, however
+    // many rows it wrapped to. Reasoning and image rows go in as shown;
+    // fold indicators are skipped. Messages are separated by a blank line.
+    fn visual_text(&self) -> String {
+        let Some((first, last)) = self.visual_range() else {
+            return String::new();
+        };
+        let lines = self.chat_lines();
+        let mut parts: Vec<(usize, Vec<String>)> = Vec::new();
+        let mut prev_source = None;
+        for pos in chat_layout::visible(&lines, first, last - first + 1) {
+            if pos.indicator {
+                continue;
+            }
+            let (Some(cached), Some(msg)) =
+                (self.chat_cache.get(pos.msg), self.messages.get(pos.msg))
+            else {
+                continue;
+            };
+            if parts.last().map_or(true, |(m, _)| *m != pos.msg) {
+                parts.push((pos.msg, Vec::new()));
+                prev_source = None;
+            }
+            let source = cached.sources.get(pos.line).copied().flatten();
+            let text = match source {
+                Some(s) if prev_source == Some(s) => continue,
+                Some(s) => msg
+                    .content
+                    .split('\n')
+                    .nth(s)
+                    .unwrap_or("")
+                    .trim_end_matches('\r'),
+                None => cached.lines.get(pos.line).map_or("", |l| l.trim()),
+            };
+            prev_source = source;
+            if let Some((_, rows)) = parts.last_mut() {
+                rows.push(text.to_string());
+            }
+        }
+        parts
+            .into_iter()
+            .map(|(_, rows)| rows.join("\n"))
+            .collect::<Vec<_>>()
+            .join("\n\n")
+    }
+}
@@ -1,6 +1,24 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use tracing::warn;
+
 use unicode_segmentation::UnicodeSegmentation;
 
-use super::App;
+use super::{App, NoticeLevel, Role};
+
+// Max length (chars) of a title derived from the first user message
+const AUTO_TITLE_CHARS: usize = 30;
+
+// The last session deleted from the sidebar; its files wait in the trash
+// until "Undo delete session" puts it back where it was.
+pub struct DeletedSession {
+    pub name: String,
+    pub index: usize,
+    pub auto_named: bool,
+    // Stamp its files were trashed under
+    pub trashed: i64,
+}
 
 impl App {
     pub fn sidebar_inner_height(&self) -> u16 {
@@ -23,7 +41,7 @@ impl App {
             self.current_session -= 1;
         }
         self.ensure_sidebar_visible();
-        let _ = crate::persist::save_state(self);
+        self.mark_state_dirty();
         self.load_current_session_messages();
     }
 
@@ -32,10 +50,34 @@ impl App {
             self.current_session += 1;
         }
         self.ensure_sidebar_visible();
-        let _ = crate::persist::save_state(self);
+        self.mark_state_dirty();
         self.load_current_session_messages();
     }
 
+    // Alt+Up / Alt+Down in the sidebar: move the current session one place
+    // up or down; it stays current.
+    pub fn sidebar_move_current(&mut self, down: bool) {
+        let idx = self.current_session;
+        let other = if down {
+            idx + 1
+        } else {
+            let Some(i) = idx.checked_sub(1) else {
+                return;
+            };
+            i
+        };
+        if other >= self.sessions.len() {
+            return;
+        }
+        self.sessions.swap(idx, other);
+        if self.session_auto_named.len() == self.sessions.len() {
+            self.session_auto_named.swap(idx, other);
+        }
+        self.current_session = other;
+        self.ensure_sidebar_visible();
+        self.mark_state_dirty();
+    }
+
     pub fn ensure_sidebar_visible(&mut self) {
         let start = self.sidebar_scroll as usize;
         let h = self.sidebar_inner_height() as usize;
@@ -52,14 +94,78 @@ impl App {
     }
 
     pub fn sidebar_new_session(&mut self) {
+        // Already on an unused session: stay instead of stacking empty ones
+        if !self.messages.iter().any(|m| matches!(m.role, Role::User)) {
+            self.ensure_sidebar_visible();
+            return;
+        }
         let idx = self.sessions.len() + 1;
         let name = format!("session-{}", idx);
         self.sessions.push(name);
+        self.session_auto_named.push(true);
         self.current_session = self.sessions.len() - 1;
         self.ensure_sidebar_visible();
-        let _ = crate::persist::save_state(self);
+        self.mark_state_dirty();
         self.messages.clear();
-        let _ = crate::persist::save_session(self.current_session_name(), &self.messages);
+        self.chat_wrap_width = 0;
+        self.apply_session_view();
+        self.save_current_session();
+    }
+
+    // `b` in the chat, `/branch` and the palette: a new session holding the
+    // conversation up to and including the selected message, switched to so
+    // it can go on differently. The original session is left as it was.
+    pub fn branch_from_selected(&mut self) {
+        if self.read_only {
+            self.notify(NoticeLevel::Info, super::READ_ONLY_NOTE);
+            return;
+        }
+        if self.llm_rx.is_some() {
+            self.notify(
+                NoticeLevel::Info,
+                "wait for the reply to finish before branching",
+            );
+            return;
+        }
+        let Some(idx) = self.selected_message.filter(|&i| i < self.messages.len()) else {
+            self.notify(
+                NoticeLevel::Info,
+                "select a message to branch from (Tab to the chat)",
+            );
+            return;
+        };
+        self.save_current_session();
+        let source = self.current_session_name().to_string();
+        let name =
+            crate::persist::unused_session_name(&format!("{} branch", source), &self.sessions);
+        if let Err(e) = crate::persist::save_session(&name, &self.messages[..=idx]) {
+            self.notify(NoticeLevel::Error, format!("branch failed: {:#}", e));
+            return;
+        }
+        self.sessions.push(name.clone());
+        self.session_auto_named.push(false);
+        self.current_session = self.sessions.len() - 1;
+        self.ensure_sidebar_visible();
+        self.mark_state_dirty();
+        self.load_current_session_messages();
+        self.stick_to_bottom = true;
+        self.focus = super::Focus::Input;
+        self.notify(
+            NoticeLevel::Info,
+            format!(
+                "branched '{}' at message {} into '{}'",
+                source,
+                idx + 1,
+                name
+            ),
+        );
+    }
+
+    // Carry the sidebar's last-activity time over to a renamed session.
+    pub fn rename_session_activity(&mut self, old: &str, new: &str) {
+        if let Some(ts) = self.session_activity.remove(old) {
+            self.session_activity.insert(new.to_string(), ts);
+        }
     }
 
     pub fn sidebar_rename_current(&mut self) {
@@ -86,11 +192,83 @@ impl App {
         });
     }
 
+    // Confirmed delete: trash the files and show the session that took its place.
+    pub(super) fn delete_session_at(&mut self, idx: usize) {
+        if idx >= self.sessions.len() {
+            return;
+        }
+        let name = self.sessions[idx].clone();
+        let trashed = match crate::persist::delete_session(&name) {
+            Ok(stamp) => stamp,
+            Err(e) => {
+                warn!(target: "tui", "delete session {}: {:#}", name, e);
+                self.notify(NoticeLevel::Error, format!("delete failed: {:#}", e));
+                return;
+            }
+        };
+        self.sessions.remove(idx);
+        let auto_named = idx < self.session_auto_named.len() && self.session_auto_named.remove(idx);
+        self.deleted_session = Some(DeletedSession {
+            name: name.clone(),
+            index: idx,
+            auto_named,
+            trashed,
+        });
+        if self.sessions.is_empty() {
+            self.sessions.push("default".to_string());
+            self.session_auto_named.push(false);
+        }
+        self.current_session = idx.min(self.sessions.len() - 1);
+        self.ensure_sidebar_visible();
+        self.mark_state_dirty();
+        self.load_current_session_messages();
+        self.notify(
+            NoticeLevel::Info,
+            format!("deleted '{}' · palette: Undo delete session", name),
+        );
+    }
+
+    // Palette "Undo delete session": restore the last deleted session and
+    // switch to it.
+    pub fn undo_delete_session(&mut self) {
+        let Some(deleted) = self.deleted_session.take() else {
+            self.notify(NoticeLevel::Info, "no deleted session to restore");
+            return;
+        };
+        if self.sessions.contains(&deleted.name) {
+            self.notify(
+                NoticeLevel::Warning,
+                format!("a session named '{}' exists; rename it first", deleted.name),
+            );
+            self.deleted_session = Some(deleted);
+            return;
+        }
+        if let Err(e) = crate::persist::restore_session(&deleted.name, deleted.trashed) {
+            self.notify(NoticeLevel::Error, format!("restore failed: {:#}", e));
+            self.deleted_session = Some(deleted);
+            return;
+        }
+        let idx = deleted.index.min(self.sessions.len());
+        self.sessions.insert(idx, deleted.name.clone());
+        if self.session_auto_named.len() + 1 == self.sessions.len() {
+            self.session_auto_named.insert(idx, deleted.auto_named);
+        }
+        self.current_session = idx;
+        self.ensure_sidebar_visible();
+        self.mark_state_dirty();
+        self.load_current_session_messages();
+        self.notify(
+            NoticeLevel::Info,
+            format!("restored session '{}'", deleted.name),
+        );
+    }
+
     pub fn current_session_name(&self) -> &str {
         &self.sessions[self.current_session]
     }
 
     pub fn load_current_session_messages(&mut self) {
+        self.apply_session_view();
         if let Ok(msgs) = crate::persist::load_session(self.current_session_name()) {
             self.messages = msgs;
             self.chat_wrap_width = 0;
@@ -98,6 +276,360 @@ impl App {
             self.chat_total_lines = 0;
             self.collapsed.clear();
             self.chat_scroll = 0;
+            self.selected_message = None;
+            self.retry_undo = None;
+            self.sent_replies.clear();
+            self.failed_replies.clear();
+            self.dropped_replies.clear();
+            self.record_session_fingerprint();
+        }
+    }
+
+    // The current session's view toggles over the config defaults.
+    pub fn apply_session_view(&mut self) {
+        let view = crate::persist::load_session_view(self.current_session_name());
+        self.render_markdown = view.markdown.unwrap_or(self.markdown_default);
+        self.wrap_lines = view.wrap.unwrap_or(self.wrap_default);
+        self.chat_hscroll = 0;
+        self.chat_wrap_width = 0;
+    }
+
+    pub(super) fn save_session_view(
+        &mut self,
+        change: impl FnOnce(&mut crate::persist::SessionView),
+    ) {
+        let session = self.current_session_name().to_string();
+        let mut view = crate::persist::load_session_view(&session);
+        change(&mut view);
+        if let Err(e) = crate::persist::save_session_view(&session, &view) {
+            self.notify(
+                NoticeLevel::Error,
+                format!("save view settings failed: {:#}", e),
+            );
+        }
+    }
+
+    // Retitle an auto-named session after its first user message.
+    pub fn maybe_auto_title(&mut self, first_text: &str) {
+        let idx = self.current_session;
+        if !self.session_auto_named.get(idx).copied().unwrap_or(false) {
+            return;
+        }
+        let user_msgs = self
+            .messages
+            .iter()
+            .filter(|m| matches!(m.role, Role::User))
+            .count();
+        if user_msgs != 1 {
+            return;
+        }
+        let Some(base) = derive_session_title(first_text) else {
+            return;
+        };
+        let title = self.unique_session_name(&base, idx);
+        let old = self.sessions[idx].clone();
+        if title == old {
+            return;
+        }
+        let _ = crate::persist::rename_session(&old, &title);
+        self.rename_session_activity(&old, &title);
+        self.sessions[idx] = title;
+        self.mark_state_dirty();
+    }
+
+    fn unique_session_name(&self, base: &str, skip: usize) -> String {
+        let taken = |name: &str| {
+            self.sessions
+                .iter()
+                .enumerate()
+                .any(|(i, s)| i != skip && s == name)
+        };
+        let mut name = base.to_string();
+        let mut n = 2;
+        while taken(&name) {
+            name = format!("{} {}", base, n);
+            n += 1;
+        }
+        name
+    }
+
+    // `/export [md|json] [path][!]`: write the current session as Markdown or
+    // as JSON. The format comes from the word, else Markdown without a path
+    // or for a `.md` one, else JSON; the path defaults to the downloads (or
+    // documents) dir. JSON is the object `/import` reads (the messages plus
+    // name, model and dates) rather than a bare array of messages, so an
+    // export can be loaded back. An existing file is kept unless `!` ends
+    // the line.
+    pub fn export_current_session(&mut self, arg: &str) {
+        let name = self.current_session_name().to_string();
+        let (arg, overwrite) = match arg.trim().strip_suffix('!') {
+            Some(rest) => (rest.trim(), true),
+            None => (arg.trim(), false),
+        };
+        let (format, path) = match arg.split_once(char::is_whitespace).unwrap_or((arg, "")) {
+            (f @ ("md" | "json"), rest) => (Some(f), rest.trim()),
+            _ => (None, arg),
+        };
+        let markdown = match format {
+            Some(f) => f == "md",
+            None if path.is_empty() => true,
+            None => Path::new(path).extension().is_some_and(|e| {
+                e.eq_ignore_ascii_case("md") || e.eq_ignore_ascii_case("markdown")
+            }),
+        };
+        let target = if path.is_empty() {
+            crate::persist::default_export_path(&name, if markdown { "md" } else { "json" })
+        } else {
+            PathBuf::from(path)
+        };
+        // Flush so the file matches what is on screen
+        self.save_current_session();
+        let res = if markdown {
+            let text = self.transcript_text(crate::format::Mode::Markdown);
+            crate::persist::write_export(&target, text.as_bytes(), overwrite)
+        } else {
+            crate::persist::export_session_json(
+                &name,
+                &target,
+                &self.model_label,
+                &self.wire_label,
+                overwrite,
+            )
+        };
+        match res {
+            Ok(()) => self.notify(
+                NoticeLevel::Info,
+                format!("exported '{}' to {}", name, target.display()),
+            ),
+            Err(e) => self.notify(NoticeLevel::Error, format!("export failed: {:#}", e)),
+        }
+    }
+
+    // `/compare m1,m2 <prompt>`: one reply per model, streamed concurrently.
+    pub fn compare(&mut self, arg: &str) {
+        let (list, prompt) = arg.split_once(char::is_whitespace).unwrap_or((arg, ""));
+        let models: Vec<String> = list
+            .split(',')
+            .map(str::trim)
+            .filter(|m| !m.is_empty())
+            .map(String::from)
+            .collect();
+        let prompt = prompt.trim();
+        if models.len() < 2 || prompt.is_empty() {
+            self.notify(NoticeLevel::Info, "usage: /compare model1,model2 <prompt>");
+            return;
+        }
+        if self.llm_rx.is_some() {
+            self.notify(NoticeLevel::Info, "wait for the current reply to finish");
+            return;
+        }
+        self.record_history_entry(&format!("/compare {}", arg));
+        self.send_prompt(prompt, models);
+        self.stick_to_bottom = true;
+        self.chat_scroll = 0;
+    }
+
+    // `/env` lists the environment spawned processes get; `/env set KEY=VALUE`
+    // and `/env unset KEY` edit the current session's layer.
+    pub fn env_command(&mut self, arg: &str) {
+        let (cmd, rest) = arg.split_once(char::is_whitespace).unwrap_or((arg, ""));
+        let rest = rest.trim();
+        let session = self.current_session_name().to_string();
+        match cmd {
+            "" => self.push_env_info(&session),
+            "set" => {
+                let Some((key, value)) = rest.split_once('=').filter(|(k, _)| !k.trim().is_empty())
+                else {
+                    self.notify(NoticeLevel::Info, "usage: /env set KEY=VALUE");
+                    return;
+                };
+                let mut env = crate::persist::load_session_env(&session);
+                env.insert(key.trim().to_string(), value.to_string());
+                self.save_session_env(&session, &env, &format!("set {}", key.trim()));
+            }
+            "unset" if !rest.is_empty() => {
+                let mut env = crate::persist::load_session_env(&session);
+                if env.remove(rest).is_none() {
+                    self.notify(
+                        NoticeLevel::Info,
+                        format!("{} is not set for this session", rest),
+                    );
+                    return;
+                }
+                self.save_session_env(&session, &env, &format!("unset {}", rest));
+            }
+            _ => self.notify(NoticeLevel::Info, "usage: /env [set KEY=VALUE | unset KEY]"),
+        }
+    }
+
+    fn save_session_env(
+        &mut self,
+        session: &str,
+        env: &std::collections::BTreeMap<String, String>,
+        what: &str,
+    ) {
+        match crate::persist::save_session_env(session, env) {
+            Ok(()) => self.notify(NoticeLevel::Info, format!("env: {} (session)", what)),
+            Err(e) => self.notify(NoticeLevel::Error, format!("env save failed: {:#}", e)),
         }
     }
+
+    fn push_env_info(&mut self, session: &str) {
+        let entries = crate::spawn_env::effective(session);
+        if entries.is_empty() {
+            self.notify(
+                NoticeLevel::Info,
+                format!(
+                    "no env entries; add [env] to config.toml or {}, or use /env set",
+                    crate::spawn_env::WORKSPACE_CONFIG
+                ),
+            );
+            return;
+        }
+        let mut text =
+            String::from("[info] env for spawned processes (session > workspace > global):");
+        for e in entries {
+            let value = match (&e.value, &e.source) {
+                (Some(v), _) => crate::spawn_env::mask(&e.key, v),
+                (None, Some(src)) => format!("<{} not set>", src),
+                (None, None) => String::new(),
+            };
+            let via = e
+                .source
+                .as_ref()
+                .map(|s| format!(", from ${}", s))
+                .unwrap_or_default();
+            text.push_str(&format!(
+                "\n  {}={}  ({}{})",
+                e.key,
+                value,
+                e.layer.label(),
+                via
+            ));
+        }
+        self.push_info(&text);
+    }
+
+    // `/bugreport [full|structure|none] <path.zip>`; structure-only by default.
+    pub fn bug_report(&mut self, arg: &str) {
+        let mut words = arg.splitn(2, char::is_whitespace);
+        let first = words.next().unwrap_or("");
+        let (level, path) = match crate::bugreport::Redaction::parse(first) {
+            Some(l) => (l, words.next().unwrap_or("").trim()),
+            None => (crate::bugreport::Redaction::Structure, arg),
+        };
+        if path.is_empty() {
+            self.notify(
+                NoticeLevel::Info,
+                "usage: /bugreport [full|structure|none] <path.zip>",
+            );
+            return;
+        }
+        let target = crate::persist::expand_user_path(path);
+        match crate::bugreport::write_bundle(self, level, &target) {
+            Ok(names) => self.notify(
+                NoticeLevel::Info,
+                format!(
+                    "bug report written to {} ({:?} redaction): {}. Review it before sharing.",
+                    target.display(),
+                    level,
+                    names.join(", ")
+                ),
+            ),
+            Err(e) => self.notify(NoticeLevel::Error, format!("bug report failed: {:#}", e)),
+        }
+    }
+
+    // Current session through the shared transcript formatter.
+    pub fn transcript_text(&self, mode: crate::format::Mode) -> String {
+        let meta = crate::format::Meta {
+            session: self.current_session_name(),
+            model: Some(&self.model_label),
+            theme: &self.theme,
+        };
+        crate::format::transcript(&self.messages, &meta, mode, &self.transcript)
+    }
+
+    // `/import <path>`: add an exported session to the sidebar and switch to it.
+    pub fn import_session(&mut self, path: &str) {
+        match crate::persist::import_session_json(Path::new(path), &self.sessions) {
+            Ok(name) => {
+                self.sessions.push(name.clone());
+                self.session_auto_named.push(false);
+                self.current_session = self.sessions.len() - 1;
+                self.ensure_sidebar_visible();
+                self.mark_state_dirty();
+                self.load_current_session_messages();
+                self.notify(NoticeLevel::Info, format!("imported session '{}'", name));
+            }
+            Err(e) => self.notify(NoticeLevel::Error, format!("import failed: {:#}", e)),
+        }
+    }
+
+    // Import a ChatGPT/Claude export as new sessions; `progress(done, total)`
+    // runs after each conversation is written.
+    pub fn import_archive(
+        &mut self,
+        kind: crate::import::ArchiveKind,
+        path: &Path,
+        mut progress: impl FnMut(usize, usize),
+    ) -> Result<crate::import::ImportSummary> {
+        let mut summary = crate::import::ImportSummary::default();
+        let convs = crate::import::read_archive(kind, path, &mut summary)?;
+        let total = convs.len();
+        for (i, conv) in convs.into_iter().enumerate() {
+            let name = crate::persist::unused_session_name(&conv.title, &self.sessions);
+            match crate::persist::save_session(&name, &conv.messages) {
+                Ok(()) => {
+                    self.sessions.push(name);
+                    self.session_auto_named.push(false);
+                    summary.imported += 1;
+                }
+                Err(e) => {
+                    warn!(target: "tui", "import: write {} failed: {:#}", name, e);
+                    summary.skip("write errors");
+                }
+            }
+            progress(i + 1, total);
+        }
+        self.mark_state_dirty();
+        Ok(summary)
+    }
+
+    // `/import chatgpt <file>` and `/import claude <dir>` from inside the TUI.
+    pub fn import_archive_inline(&mut self, kind: crate::import::ArchiveKind, path: &str) {
+        match self.import_archive(kind, Path::new(path), |_, _| {}) {
+            Ok(summary) => self.notify(NoticeLevel::Info, summary.describe()),
+            Err(e) => self.notify(NoticeLevel::Error, format!("import failed: {:#}", e)),
+        }
+    }
+}
+
+// Names given by `sidebar_new_session`; used when older state has no auto-named flags.
+pub fn is_default_session_name(name: &str) -> bool {
+    name.strip_prefix("session-")
+        .is_some_and(|n| !n.is_empty() && n.chars().all(|c| c.is_ascii_digit()))
+}
+
+// Short single-line title: whitespace flattened, path-unsafe characters dropped,
+// cut at a word boundary near AUTO_TITLE_CHARS.
+fn derive_session_title(text: &str) -> Option<String> {
+    let cleaned: String = text
+        .chars()
+        .filter(|c| !matches!(c, '<' | '>' | ':' | '"' | '/' | '\\' | '|' | '?' | '*'))
+        .filter(|c| !c.is_control() || c.is_whitespace())
+        .collect();
+    let flat = cleaned.split_whitespace().collect::<Vec<_>>().join(" ");
+    if flat.is_empty() {
+        return None;
+    }
+    if flat.chars().count() <= AUTO_TITLE_CHARS {
+        return Some(flat);
+    }
+    let cut: String = flat.chars().take(AUTO_TITLE_CHARS).collect();
+    let title = match cut.rfind(' ') {
+        Some(pos) if pos >= AUTO_TITLE_CHARS / 2 => cut[..pos].to_string(),
+        _ => cut,
+    };
+    Some(title.trim_end().to_string())
 }
@@ -0,0 +1,146 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use tracing::warn;
+use unicode_segmentation::UnicodeSegmentation;
+
+use super::input::{delete_left, delete_right, insert_graphemes};
+use super::{App, Focus, NoticeLevel};
+use crate::templates::{self, Template};
+
+// `/template` without a name and the palette: pick one by name
+pub struct TemplatePickerState {
+    pub buffer: String,
+    pub cursor: usize,
+    pub all: Vec<Template>,
+    // Indices into `all` whose name contains `buffer`
+    pub filtered: Vec<usize>,
+    pub selected: usize,
+}
+
+impl TemplatePickerState {
+    pub fn current(&self) -> Option<&Template> {
+        self.all.get(*self.filtered.get(self.selected)?)
+    }
+}
+
+impl App {
+    pub fn open_template_picker(&mut self) {
+        let all = templates::load_all();
+        if all.is_empty() {
+            let dir = templates::templates_dir()
+                .map(|d| d.display().to_string())
+                .unwrap_or_else(|| "the config dir".into());
+            self.notify(NoticeLevel::Info, format!("no templates in {}", dir));
+            return;
+        }
+        let mut state = TemplatePickerState {
+            buffer: String::new(),
+            cursor: 0,
+            all,
+            filtered: Vec::new(),
+            selected: 0,
+        };
+        filter_templates(&mut state);
+        self.template_picker = Some(state);
+    }
+
+    // `/template <name>`
+    pub fn insert_template_named(&mut self, name: &str) {
+        match templates::find(name) {
+            Some(t) => self.insert_template(&t),
+            None => self.notify(NoticeLevel::Info, format!("no template named '{}'", name)),
+        }
+    }
+
+    // Insert at the input cursor. `{{selection}}` is the message selected in
+    // the chat, `{{clipboard}}` the system clipboard; both empty when unset.
+    fn insert_template(&mut self, t: &Template) {
+        if self.read_only {
+            self.notify(NoticeLevel::Info, super::READ_ONLY_NOTE);
+            return;
+        }
+        let body = templates::expand(&t.body, |var| match var {
+            "selection" => Some(
+                self.selected_message
+                    .and_then(|i| self.messages.get(i))
+                    .map(|m| m.content.clone())
+                    .unwrap_or_default(),
+            ),
+            "clipboard" => Some(read_clipboard()),
+            _ => None,
+        });
+        self.insert_text(&body);
+        self.update_slash_picker_on_input_change();
+        self.focus = Focus::Input;
+    }
+
+    pub fn on_template_picker_key(&mut self, key: KeyEvent) {
+        let Some(state) = self.template_picker.as_mut() else {
+            return;
+        };
+        match key.code {
+            KeyCode::Esc => {
+                self.template_picker = None;
+            }
+            KeyCode::Enter => {
+                let picked = state.current().cloned();
+                self.template_picker = None;
+                if let Some(t) = picked {
+                    self.insert_template(&t);
+                }
+            }
+            KeyCode::Up => {
+                state.selected = state.selected.saturating_sub(1);
+            }
+            KeyCode::Down if state.selected + 1 < state.filtered.len() => {
+                state.selected += 1;
+            }
+            KeyCode::Backspace => {
+                delete_left(&mut state.buffer, &mut state.cursor);
+                filter_templates(state);
+            }
+            KeyCode::Delete => {
+                delete_right(&mut state.buffer, state.cursor);
+                filter_templates(state);
+            }
+            KeyCode::Left if state.cursor > 0 => {
+                state.cursor -= 1;
+            }
+            KeyCode::Right => {
+                state.cursor = (state.cursor + 1).min(state.buffer.graphemes(true).count());
+            }
+            KeyCode::Home => {
+                state.cursor = 0;
+            }
+            KeyCode::End => {
+                state.cursor = state.buffer.graphemes(true).count();
+            }
+            KeyCode::Char(ch) if !key.modifiers.contains(KeyModifiers::CONTROL) => {
+                insert_graphemes(&mut state.buffer, &mut state.cursor, &ch.to_string());
+                filter_templates(state);
+            }
+            _ => {}
+        }
+    }
+}
+
+fn filter_templates(state: &mut TemplatePickerState) {
+    let q = state.buffer.to_lowercase();
+    state.filtered = state
+        .all
+        .iter()
+        .enumerate()
+        .filter(|(_, t)| t.name.to_lowercase().contains(&q))
+        .map(|(i, _)| i)
+        .collect();
+    state.selected = 0;
+}
+
+fn read_clipboard() -> String {
+    match arboard::Clipboard::new().and_then(|mut c| c.get_text()) {
+        Ok(text) => text,
+        Err(e) => {
+            warn!(target: "tui", "template: clipboard unreadable: {}", e);
+            String::new()
+        }
+    }
+}
@@ -0,0 +1,134 @@
+// `fast --bench "prompt"`: send one prompt without the TUI, stream the reply
+// to stdout and report timing and token usage on stderr. Uses the provider,
+// model and wire from the config file (or FAST_MOCK), like a fresh session.
+
+use std::io::Write;
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result};
+use fast_core::llm::{CancellationToken, ChatDelta, ChatOpts, Message, Role};
+use futures::StreamExt;
+use providers::openai::config::{self, OpenAiConfig};
+
+pub fn run(prompt: &str) -> Result<()> {
+    let (provider, model, wire_label) = if providers::mock_enabled() {
+        // The config may be incomplete under mock, but not a bad FAST_WIRE
+        config::env_wire().context("config")?;
+        let cfg = OpenAiConfig::from_env_and_file(None).ok();
+        (
+            providers::MOCK_PROVIDER_ID.to_string(),
+            cfg.as_ref().map_or("gpt-5".into(), |c| c.model.clone()),
+            cfg.map_or("responses".into(), |c| c.wire_api),
+        )
+    } else {
+        let cfg = OpenAiConfig::from_env_and_file(None).context("config")?;
+        cfg.validate().context("config")?;
+        (
+            cfg.provider_id().to_string(),
+            cfg.model.clone(),
+            cfg.wire_api,
+        )
+    };
+    let client = providers::registry()
+        .create(&provider)
+        .map_err(|e| anyhow::anyhow!("{}", e))?;
+    let opts = ChatOpts {
+        model: model.clone(),
+        temperature: None,
+        top_p: None,
+        max_tokens: None,
+        reasoning_summary: false,
+    };
+    let msgs = vec![Message::text(Role::User, prompt)];
+    let wire = crate::app::chat_wire(&wire_label);
+
+    let rt = tokio::runtime::Runtime::new().context("tokio runtime")?;
+    let stats = rt.block_on(async {
+        let start = Instant::now();
+        let mut stats = Stats::default();
+        let mut stream = client
+            .stream_chat(msgs, opts, wire, CancellationToken::new())
+            .await?;
+        let mut out = std::io::stdout().lock();
+        while let Some(delta) = stream.next().await {
+            match delta? {
+                ChatDelta::Text(t) => {
+                    stats.first.get_or_insert_with(|| start.elapsed());
+                    stats.chars += t.chars().count();
+                    let _ = out.write_all(t.as_bytes());
+                    let _ = out.flush();
+                }
+                ChatDelta::Reasoning(t) => {
+                    stats.first.get_or_insert_with(|| start.elapsed());
+                    stats.chars += t.chars().count();
+                }
+                ChatDelta::Usage {
+                    prompt_tokens,
+                    completion_tokens,
+                } => {
+                    stats.prompt_tokens = prompt_tokens;
+                    stats.completion_tokens = completion_tokens;
+                }
+                ChatDelta::Finish(_) => break,
+                _ => {}
+            }
+        }
+        let _ = writeln!(out);
+        stats.total = start.elapsed();
+        Ok::<_, fast_core::llm::ChatError>(stats)
+    })?;
+    eprintln!("{}", stats.report(&model, &wire_label));
+    Ok(())
+}
+
+#[derive(Default)]
+struct Stats {
+    // Until the first text or reasoning delta
+    first: Option<Duration>,
+    total: Duration,
+    chars: usize,
+    prompt_tokens: Option<u32>,
+    completion_tokens: Option<u32>,
+}
+
+impl Stats {
+    // e.g. "gpt-5 · responses · first token 0.42s · total 3.10s ·
+    // 12 prompt + 340 completion tokens · 127 tok/s"
+    fn report(&self, model: &str, wire: &str) -> String {
+        let first = self.first.map_or("no output".to_string(), |d| {
+            format!("first token {:.2}s", d.as_secs_f64())
+        });
+        // Estimated like context trimming when the provider sent no usage
+        let (completion, estimated) = match self.completion_tokens {
+            Some(n) => (n as usize, ""),
+            None => (self.chars.div_ceil(4), "~"),
+        };
+        let tokens = match self.prompt_tokens {
+            Some(p) => format!(
+                "{} prompt + {}{} completion tokens",
+                p, estimated, completion
+            ),
+            None => format!("{}{} completion tokens", estimated, completion),
+        };
+        // Output rate after the first token, so latency does not skew it
+        let streaming = self.total.saturating_sub(self.first.unwrap_or_default());
+        let rate = if streaming.is_zero() {
+            String::new()
+        } else {
+            format!(
+                " · {}{:.0} tok/s",
+                estimated,
+                completion as f64 / streaming.as_secs_f64()
+            )
+        };
+        format!(
+            "{} · {} · {} · total {:.2}s · {}{}",
+            model,
+            wire,
+            first,
+            self.total.as_secs_f64(),
+            tokens,
+            rate
+        )
+    }
+}
@@ -0,0 +1,248 @@
+// Line arithmetic of the chat view: how many rows each message takes once
+// folded, where it starts, and which message owns a given row. Rendering,
+// mouse hits, scrolling and search reveal all go through here so they agree.
+//
+// A "global" line counts rows from the top of the conversation: each
+// message's shown lines followed by its indicator row, if any.
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Fold {
+    // Lines shown while a message is collapsed
+    pub preview: usize,
+    // Longer messages get a "Collapse" indicator when expanded
+    pub threshold: usize,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MsgLines {
+    pub shown: usize,
+    // Expand/collapse indicator row after the shown lines
+    pub indicator: bool,
+}
+
+impl MsgLines {
+    pub fn new(base: usize, collapsed: bool, fold: Fold) -> Self {
+        if collapsed && base > fold.preview {
+            MsgLines {
+                shown: fold.preview,
+                indicator: true,
+            }
+        } else {
+            MsgLines {
+                shown: base,
+                indicator: !collapsed && base > fold.threshold,
+            }
+        }
+    }
+
+    pub fn effective(self) -> usize {
+        self.shown + usize::from(self.indicator)
+    }
+}
+
+// Position of a global line inside the conversation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LinePos {
+    pub msg: usize,
+    // Wrapped line within the message; equals `shown` on the indicator row
+    pub line: usize,
+    pub indicator: bool,
+}
+
+pub fn total(msgs: &[MsgLines]) -> usize {
+    msgs.iter().map(|m| m.effective()).sum()
+}
+
+// Global line where message `idx` starts (the total when past the end).
+pub fn start_of(msgs: &[MsgLines], idx: usize) -> usize {
+    total(&msgs[..idx.min(msgs.len())])
+}
+
+pub fn resolve(msgs: &[MsgLines], global: usize) -> Option<LinePos> {
+    visible(msgs, global, 1).into_iter().next()
+}
+
+// The `count` rows starting at global line `start`, in order; shorter only
+// when the conversation ends first.
+pub fn visible(msgs: &[MsgLines], start: usize, count: usize) -> Vec<LinePos> {
+    let mut out = Vec::with_capacity(count);
+    let mut skip = start;
+    for (msg, m) in msgs.iter().enumerate() {
+        if out.len() == count {
+            break;
+        }
+        if skip >= m.effective() {
+            skip -= m.effective();
+            continue;
+        }
+        for line in skip..m.effective() {
+            if out.len() == count {
+                break;
+            }
+            out.push(LinePos {
+                msg,
+                line,
+                indicator: line == m.shown,
+            });
+        }
+        skip = 0;
+    }
+    out
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Window {
+    pub viewport: usize,
+    pub max_scroll: usize,
+    // Global line at the top of the viewport
+    pub start: usize,
+}
+
+// `scroll` counts lines up from the bottom; `stick` pins to the bottom.
+pub fn window(total: usize, viewport: u16, scroll: usize, stick: bool) -> Window {
+    let viewport = viewport.max(1) as usize;
+    let max_scroll = total.saturating_sub(viewport);
+    let from_bottom = if stick { 0 } else { scroll.min(max_scroll) };
+    Window {
+        viewport,
+        max_scroll,
+        start: max_scroll - from_bottom,
+    }
+}
+
+// Scroll value that puts `global` at the top of the viewport, or as close
+// as the end of the conversation allows.
+pub fn scroll_to_show(total: usize, viewport: u16, global: usize) -> usize {
+    let w = window(total, viewport, 0, true);
+    w.max_scroll
+        .saturating_sub(global.min(total.saturating_sub(1)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // xorshift64: deterministic cases without a property-testing crate
+    struct Rng(u64);
+
+    impl Rng {
+        fn below(&mut self, n: usize) -> usize {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            (self.0 % n.max(1) as u64) as usize
+        }
+    }
+
+    const FOLD: Fold = Fold {
+        preview: 3,
+        threshold: 6,
+    };
+
+    // Random conversations, including empty messages and long folded ones
+    fn cases() -> impl Iterator<Item = Vec<MsgLines>> {
+        let mut rng = Rng(0x9e37_79b9_7f4a_7c15);
+        (0..300).map(move |_| {
+            let n = rng.below(12);
+            (0..n)
+                .map(|_| MsgLines::new(rng.below(15), rng.below(2) == 0, FOLD))
+                .collect()
+        })
+    }
+
+    // Every row of the conversation, top to bottom
+    fn all_rows(msgs: &[MsgLines]) -> Vec<LinePos> {
+        let mut rows = Vec::new();
+        for (msg, m) in msgs.iter().enumerate() {
+            for line in 0..m.effective() {
+                rows.push(LinePos {
+                    msg,
+                    line,
+                    indicator: line == m.shown,
+                });
+            }
+        }
+        rows
+    }
+
+    #[test]
+    fn paging_by_viewport_never_skips_or_duplicates_a_line() {
+        for msgs in cases() {
+            let rows = all_rows(&msgs);
+            assert_eq!(total(&msgs), rows.len());
+            for viewport in 1..=8 {
+                let mut seen = Vec::new();
+                let mut start = 0;
+                while start < rows.len() {
+                    let page = visible(&msgs, start, viewport);
+                    assert_eq!(page.len(), viewport.min(rows.len() - start));
+                    seen.extend(page);
+                    start += viewport;
+                }
+                assert_eq!(seen, rows, "viewport {} over {:?}", viewport, msgs);
+            }
+        }
+    }
+
+    #[test]
+    fn every_scroll_position_shows_a_contiguous_slice() {
+        for msgs in cases() {
+            let rows = all_rows(&msgs);
+            for viewport in 1..=8u16 {
+                for scroll in 0..=rows.len() + 2 {
+                    let w = window(rows.len(), viewport, scroll, false);
+                    let shown = visible(&msgs, w.start, w.viewport);
+                    let end = (w.start + w.viewport).min(rows.len());
+                    assert_eq!(shown, rows[w.start..end]);
+                    // A full viewport whenever the conversation is long enough
+                    assert_eq!(shown.len(), w.viewport.min(rows.len()));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn resolve_and_start_of_agree_with_rendering() {
+        for msgs in cases() {
+            let rows = all_rows(&msgs);
+            for (g, row) in rows.iter().enumerate() {
+                assert_eq!(resolve(&msgs, g), Some(*row));
+            }
+            assert_eq!(resolve(&msgs, rows.len()), None);
+            for (i, m) in msgs.iter().enumerate() {
+                let start = start_of(&msgs, i);
+                if m.effective() > 0 {
+                    assert_eq!(resolve(&msgs, start).map(|p| (p.msg, p.line)), Some((i, 0)));
+                }
+            }
+            assert_eq!(start_of(&msgs, msgs.len() + 3), rows.len());
+        }
+    }
+
+    #[test]
+    fn scroll_to_show_puts_the_line_on_screen() {
+        for msgs in cases() {
+            let total = total(&msgs);
+            for viewport in 1..=8u16 {
+                for g in 0..total {
+                    let scroll = scroll_to_show(total, viewport, g);
+                    let w = window(total, viewport, scroll, false);
+                    assert!((w.start..w.start + w.viewport).contains(&g));
+                    // At the top unless the end of the conversation is reached
+                    assert!(w.start == g || w.start == w.max_scroll);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn folding_rules() {
+        let collapsed = MsgLines::new(10, true, FOLD);
+        assert_eq!((collapsed.shown, collapsed.indicator), (3, true));
+        let expanded = MsgLines::new(10, false, FOLD);
+        assert_eq!((expanded.shown, expanded.indicator), (10, true));
+        // Short messages never fold or get an indicator
+        assert_eq!(MsgLines::new(3, true, FOLD).effective(), 3);
+        assert_eq!(MsgLines::new(6, false, FOLD).effective(), 6);
+    }
+}
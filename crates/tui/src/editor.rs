@@ -0,0 +1,107 @@
+// Ctrl+X (no selection) and the palette: edit the input in $VISUAL/$EDITOR.
+// The event loop suspends the terminal around `edit`, since the editor
+// needs the tty to itself.
+
+use std::fs;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{bail, Context, Result};
+
+// When neither $VISUAL nor $EDITOR is set
+const FALLBACK_EDITOR: &str = if cfg!(windows) { "notepad" } else { "vi" };
+
+// Editor command line; the variable may carry arguments ("code --wait").
+fn editor_command() -> Vec<String> {
+    ["VISUAL", "EDITOR"]
+        .iter()
+        .filter_map(|v| std::env::var(v).ok())
+        .find(|v| !v.trim().is_empty())
+        .unwrap_or_else(|| FALLBACK_EDITOR.into())
+        .split_whitespace()
+        .map(str::to_string)
+        .collect()
+}
+
+// Write `text` to a temp file, run the editor on it with the session's
+// spawn env, and return the edited text. None when the editor exits with
+// an error, so the caller keeps the input as it was.
+pub fn edit(text: &str, session: &str) -> Result<Option<String>> {
+    let argv = editor_command();
+    let Some((program, args)) = argv.split_first() else {
+        bail!("no editor configured");
+    };
+    let (path, mut file) = create_temp()?;
+    let written = file.write_all(text.as_bytes());
+    drop(file);
+    if let Err(e) = written {
+        let _ = fs::remove_file(&path);
+        return Err(e).with_context(|| format!("write {}", path.display()));
+    }
+    let mut cmd = Command::new(program);
+    cmd.args(args).arg(&path);
+    for e in crate::spawn_env::effective(session) {
+        if let Some(v) = e.value {
+            cmd.env(&e.key, v);
+        }
+    }
+    let status = cmd.status().with_context(|| format!("run {}", program));
+    let res = match status {
+        Ok(s) if s.success() => fs::read_to_string(&path)
+            .with_context(|| format!("read {}", path.display()))
+            .map(|edited| {
+                // Editors end the file with a newline the input never had
+                let edited = edited.strip_suffix('\n').unwrap_or(&edited);
+                Some(edited.strip_suffix('\r').unwrap_or(edited).to_string())
+            }),
+        Ok(_) => Ok(None),
+        Err(e) => Err(e),
+    };
+    let _ = fs::remove_file(&path);
+    res
+}
+
+// A new file only this user can read (0600 on Unix). `create_new` fails
+// rather than open a file or symlink someone else put at the name.
+fn create_temp() -> Result<(PathBuf, fs::File)> {
+    let dir = std::env::temp_dir();
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.subsec_nanos());
+    for n in 0..100u32 {
+        let name = format!("fast-input-{}-{}-{}.md", std::process::id(), nanos, n);
+        let path = dir.join(name);
+        let mut opts = fs::OpenOptions::new();
+        opts.write(true).create_new(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut opts, 0o600);
+        match opts.open(&path) {
+            Ok(file) => return Ok((path, file)),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e).with_context(|| format!("create {}", path.display())),
+        }
+    }
+    bail!("no free temp file name in {}", dir.display())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn temp_files_are_new_and_private() {
+        let (a, _) = create_temp().unwrap();
+        let (b, _) = create_temp().unwrap();
+        assert_ne!(a, b);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&a).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+        let _ = fs::remove_file(a);
+        let _ = fs::remove_file(b);
+    }
+}
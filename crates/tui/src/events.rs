@@ -3,32 +3,82 @@ use std::time::{Duration, Instant};
 use crossterm::event::{self, Event, MouseButton, MouseEventKind};
 use ratatui::{backend::Backend, Terminal};
 
-use crate::{app::App, ui};
+use crate::{
+    app::{App, NoticeLevel},
+    ui,
+};
+
+// Poll timeout while streaming/animating or shortly after input
+const POLL_FAST: Duration = Duration::from_millis(33);
+// Poll timeout when idle, and after a longer idle stretch
+const POLL_IDLE: Duration = Duration::from_millis(500);
+const POLL_IDLE_LONG: Duration = Duration::from_millis(1000);
+// Stay in fast mode this long after the last input event
+const INPUT_GRACE: Duration = Duration::from_millis(1000);
+// Idle time before stretching to POLL_IDLE_LONG
+const LONG_IDLE_AFTER: Duration = Duration::from_secs(10);
+// Chat lines per mouse wheel notch
+const WHEEL_LINES: usize = 3;
+
+// Picks the event poll timeout from activity: fast while busy or right after
+// input, stretching out the longer the UI stays idle.
+pub struct PollScheduler {
+    last_input: Instant,
+}
+
+impl PollScheduler {
+    pub fn new(now: Instant) -> Self {
+        Self { last_input: now }
+    }
+
+    pub fn on_input(&mut self, now: Instant) {
+        self.last_input = now;
+    }
+
+    pub fn timeout(&self, busy: bool, now: Instant) -> Duration {
+        let since_input = now.saturating_duration_since(self.last_input);
+        if busy || since_input < INPUT_GRACE {
+            POLL_FAST
+        } else if since_input < LONG_IDLE_AFTER {
+            POLL_IDLE
+        } else {
+            POLL_IDLE_LONG
+        }
+    }
+}
 
 pub fn run<B: Backend>(terminal: &mut Terminal<B>, app: &mut App) -> anyhow::Result<()> {
     let mut last_draw = Instant::now();
     let heartbeat = Duration::from_millis(500);
+    let mut sched = PollScheduler::new(Instant::now());
     loop {
+        if crate::signals::take() {
+            app.on_terminate_signal();
+        }
+        // Before drawing: after SIGHUP the terminal may be gone
+        if app.should_quit {
+            app.discard_pending_attachments();
+            app.save_state_now();
+            break;
+        }
         if app.dirty || last_draw.elapsed() >= heartbeat {
             terminal.draw(|f| ui::draw(f, app))?;
             app.dirty = false;
             last_draw = Instant::now();
         }
-        if matches!(app.focus, crate::app::Focus::Input) {
+        if matches!(app.focus, crate::app::Focus::Input) && !app.read_only {
             let _ = terminal.show_cursor();
         } else {
             let _ = terminal.hide_cursor();
         }
 
-        if event::poll(Duration::from_millis(120))? {
+        if event::poll(sched.timeout(app.is_busy(), Instant::now()))? {
+            sched.on_input(Instant::now());
             match event::read()? {
                 Event::Key(key) => {
                     app.on_key(key);
                 }
-                Event::Paste(s) => {
-                    app.insert_text(&s);
-                    app.dirty = true;
-                }
+                Event::Paste(s) => app.on_paste(&s),
                 Event::Resize(_, _) => {}
                 Event::Mouse(me) => {
                     if app.show_help {
@@ -42,55 +92,27 @@ pub fn run<B: Backend>(terminal: &mut Terminal<B>, app: &mut App) -> anyhow::Res
                         if inside {
                             match me.kind {
                                 MouseEventKind::ScrollUp => {
-                                    app.chat_scroll = app.chat_scroll.saturating_add(3);
-                                    app.stick_to_bottom = false;
+                                    app.scroll_chat_up(WHEEL_LINES);
                                     app.dirty = true;
                                 }
                                 MouseEventKind::ScrollDown => {
-                                    app.chat_scroll = app.chat_scroll.saturating_sub(3);
-                                    if app.chat_scroll == 0 {
-                                        app.stick_to_bottom = true;
-                                    }
+                                    app.scroll_chat_down(WHEEL_LINES);
                                     app.dirty = true;
                                 }
-                                MouseEventKind::Down(MouseButton::Left) => {
-                                    let inner_w = area.width.saturating_sub(2);
-                                    let inner_h = area.height.saturating_sub(2);
-                                    app.ensure_chat_wrapped(inner_w);
-                                    let (_viewport, _max_scroll, start_offset, _total) =
-                                        app.compute_chat_layout(inner_h);
-                                    let y_offset = start_offset;
-                                    let rel_y = (y - (area.y + 1)) as usize;
-                                    let global = y_offset.saturating_add(rel_y);
-
-                                    let mut acc = 0usize;
-                                    for (i, w) in app.chat_cache.iter().enumerate() {
-                                        let base = w.lines.len();
-                                        let collapsed =
-                                            app.collapsed.get(i).copied().unwrap_or(false);
-                                        let preview = app.collapse_preview_lines;
-                                        let threshold = app.collapse_threshold_lines;
-                                        let display = if collapsed && base > preview {
-                                            preview
-                                        } else {
-                                            base
-                                        };
-                                        let has_indicator = if collapsed && base > preview {
-                                            true
-                                        } else {
-                                            !collapsed && base > threshold
-                                        };
-                                        let effective = display + if has_indicator { 1 } else { 0 };
-                                        if global >= acc + effective {
-                                            acc += effective;
-                                            continue;
-                                        }
-                                        let offset_in_msg = global - acc;
-                                        if has_indicator && offset_in_msg == display {
-                                            app.toggle_collapse_at(i);
+                                MouseEventKind::Down(MouseButton::Left)
+                                    if app.panel_inner(area).contains((x, y).into()) =>
+                                {
+                                    let inner = app.panel_inner(area);
+                                    app.ensure_chat_wrapped(inner.width);
+                                    let global = app.compute_chat_layout(inner.height).start
+                                        + (y - inner.y) as usize;
+                                    if let Some(pos) =
+                                        crate::chat_layout::resolve(&app.chat_lines(), global)
+                                    {
+                                        if pos.indicator {
+                                            app.toggle_collapse_at(pos.msg);
                                             app.dirty = true;
                                         }
-                                        break;
                                     }
                                 }
                                 _ => {}
@@ -112,26 +134,26 @@ pub fn run<B: Backend>(terminal: &mut Terminal<B>, app: &mut App) -> anyhow::Res
                                     let max = app.sidebar_max_scroll();
                                     app.sidebar_scroll =
                                         app.sidebar_scroll.saturating_sub(1).min(max);
-                                    let _ = crate::persist::save_state(app);
+                                    app.mark_state_dirty();
                                     app.dirty = true;
                                 }
                                 MouseEventKind::ScrollDown => {
                                     let max = app.sidebar_max_scroll();
                                     app.sidebar_scroll = (app.sidebar_scroll + 1).min(max);
-                                    let _ = crate::persist::save_state(app);
+                                    app.mark_state_dirty();
                                     app.dirty = true;
                                 }
-                                MouseEventKind::Down(MouseButton::Left) => {
-                                    if y > area.y && y < area.y + area.height - 1 {
-                                        let start = app.sidebar_scroll as usize;
-                                        let idx = start + (y - (area.y + 1)) as usize;
-                                        if idx < app.sessions.len() {
-                                            app.current_session = idx;
-                                            app.ensure_sidebar_visible();
-                                            let _ = crate::persist::save_state(app);
-                                            app.load_current_session_messages();
-                                            app.dirty = true;
-                                        }
+                                MouseEventKind::Down(MouseButton::Left)
+                                    if y > area.y && y < area.y + area.height - 1 =>
+                                {
+                                    let start = app.sidebar_scroll as usize;
+                                    let idx = start + (y - (area.y + 1)) as usize;
+                                    if idx < app.sessions.len() {
+                                        app.current_session = idx;
+                                        app.ensure_sidebar_visible();
+                                        app.mark_state_dirty();
+                                        app.load_current_session_messages();
+                                        app.dirty = true;
                                     }
                                 }
                                 _ => {}
@@ -177,12 +199,59 @@ pub fn run<B: Backend>(terminal: &mut Terminal<B>, app: &mut App) -> anyhow::Res
             }
         }
 
-        app.on_tick();
-
-        if app.should_quit {
-            let _ = crate::persist::save_state(app);
-            break;
+        if std::mem::take(&mut app.external_edit) {
+            // Errors here end up as notices: leaving the loop would skip the
+            // save on quit
+            let res = crate::terminal::suspend()
+                .and_then(|()| crate::editor::edit(&app.input, app.current_session_name()));
+            // Even after a failed suspend, which may have done half its work
+            let resumed = crate::terminal::resume();
+            // Ctrl+C inside the editor reached this process group too
+            crate::signals::discard_interrupt();
+            terminal.clear()?;
+            app.finish_external_edit(res);
+            if let Err(e) = resumed {
+                app.notify(NoticeLevel::Error, format!("restore terminal: {:#}", e));
+            }
         }
+
+        app.on_tick();
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn poll_timeout_stretches_with_idle_time() {
+        let start = Instant::now();
+        let mut sched = PollScheduler::new(start);
+        let at = |d: Duration| start + d;
+
+        // Busy always polls fast, however long since input
+        assert_eq!(sched.timeout(true, at(Duration::from_secs(60))), POLL_FAST);
+        // Right after input, and until the grace period ends
+        assert_eq!(sched.timeout(false, start), POLL_FAST);
+        assert_eq!(
+            sched.timeout(false, at(INPUT_GRACE - Duration::from_millis(1))),
+            POLL_FAST
+        );
+        // Idle, then idle for long
+        assert_eq!(sched.timeout(false, at(INPUT_GRACE)), POLL_IDLE);
+        assert_eq!(
+            sched.timeout(false, at(LONG_IDLE_AFTER - Duration::from_millis(1))),
+            POLL_IDLE
+        );
+        assert_eq!(sched.timeout(false, at(LONG_IDLE_AFTER)), POLL_IDLE_LONG);
+
+        // New input starts over
+        let later = at(Duration::from_secs(30));
+        sched.on_input(later);
+        assert_eq!(sched.timeout(false, later), POLL_FAST);
+        assert_eq!(sched.timeout(false, later + INPUT_GRACE), POLL_IDLE);
+        // A clock reading before the last input counts as no time passed
+        assert_eq!(sched.timeout(false, start), POLL_FAST);
+    }
+}
@@ -0,0 +1,292 @@
+// Transcript formatting shared by Markdown export and print-on-exit, so the
+// textual renderings of a session cannot drift apart.
+
+use providers::openai::config::TranscriptTemplates;
+use ratatui::style::Color;
+
+use crate::app::{attachments::image_label, Message, Role};
+use crate::strings::{PREFIX_ASSISTANT, PREFIX_SYSTEM, PREFIX_USER};
+use crate::theme::Theme;
+
+const RULE_PLAIN: &str = "----";
+const RULE_MARKDOWN: &str = "---";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mode {
+    Plain,
+    Markdown,
+    // Plain layout with ANSI colors for a terminal
+    Ansi,
+}
+
+// Session-level metadata printed above the transcript when known.
+pub struct Meta<'a> {
+    pub session: &'a str,
+    pub model: Option<&'a str>,
+    // Colors for Ansi mode
+    pub theme: &'a Theme,
+}
+
+#[derive(Clone, Debug)]
+struct RoleTemplate {
+    label: String,
+    prefix: String,
+}
+
+#[derive(Clone, Debug)]
+pub struct Templates {
+    user: RoleTemplate,
+    assistant: RoleTemplate,
+    // Not configurable under `[transcript]` yet
+    system: RoleTemplate,
+    heading_level: usize,
+    rules: bool,
+}
+
+impl Templates {
+    pub fn from_config(t: &TranscriptTemplates) -> Self {
+        Self {
+            user: RoleTemplate {
+                label: t.user_label.clone().unwrap_or_else(|| "User".into()),
+                prefix: t.user_prefix.clone().unwrap_or_else(|| PREFIX_USER.into()),
+            },
+            assistant: RoleTemplate {
+                label: t
+                    .assistant_label
+                    .clone()
+                    .unwrap_or_else(|| "Assistant".into()),
+                prefix: t
+                    .assistant_prefix
+                    .clone()
+                    .unwrap_or_else(|| PREFIX_ASSISTANT.into()),
+            },
+            system: RoleTemplate {
+                label: "System".into(),
+                prefix: PREFIX_SYSTEM.into(),
+            },
+            heading_level: t.heading_level.unwrap_or(2).clamp(1, 6) as usize,
+            rules: t.rules.unwrap_or(true),
+        }
+    }
+
+    fn role(&self, role: &Role) -> &RoleTemplate {
+        match role {
+            Role::User => &self.user,
+            Role::Assistant => &self.assistant,
+            Role::System => &self.system,
+        }
+    }
+}
+
+impl Default for Templates {
+    fn default() -> Self {
+        Self::from_config(&TranscriptTemplates::default())
+    }
+}
+
+pub fn transcript(messages: &[Message], meta: &Meta, mode: Mode, tpl: &Templates) -> String {
+    let mut out = header(meta, mode);
+    for (i, m) in messages.iter().enumerate() {
+        if i > 0 && tpl.rules {
+            out.push_str(&rule(mode, meta.theme));
+        }
+        out.push_str(&turn(m, meta.theme, mode, tpl));
+    }
+    out
+}
+
+fn header(meta: &Meta, mode: Mode) -> String {
+    match mode {
+        Mode::Markdown => {
+            let mut h = format!("# {}\n\n", meta.session);
+            if let Some(model) = meta.model.filter(|m| !m.is_empty()) {
+                h.push_str(&format!("_model: {}_\n\n", model));
+            }
+            h
+        }
+        Mode::Plain | Mode::Ansi => {
+            let mut h = format!("session: {}", meta.session);
+            if let Some(model) = meta.model.filter(|m| !m.is_empty()) {
+                h.push_str(&format!("  model: {}", model));
+            }
+            let h = paint(&h, None, true, mode);
+            format!("{}\n\n", h)
+        }
+    }
+}
+
+fn rule(mode: Mode, theme: &Theme) -> String {
+    match mode {
+        Mode::Markdown => format!("{}\n\n", RULE_MARKDOWN),
+        Mode::Plain => format!("{}\n\n", RULE_PLAIN),
+        Mode::Ansi => format!("{}\n\n", paint(RULE_PLAIN, Some(theme.muted), false, mode)),
+    }
+}
+
+fn turn(m: &Message, theme: &Theme, mode: Mode, tpl: &Templates) -> String {
+    let role = tpl.role(&m.role);
+    let stamp = m.timestamp.map(timestamp).unwrap_or_default();
+    let mut body = m.content.trim_end().to_string();
+    for img in &m.images {
+        if !body.is_empty() {
+            body.push('\n');
+        }
+        body.push_str(&image_label(img));
+    }
+    match mode {
+        Mode::Markdown => {
+            let mut h = format!("{} {}", "#".repeat(tpl.heading_level), role.label);
+            if !stamp.is_empty() {
+                h.push_str(&format!(" · {}", stamp));
+            }
+            format!("{}\n\n{}\n\n", h, body)
+        }
+        Mode::Plain | Mode::Ansi => {
+            let color = role_color(&m.role, theme);
+            let prefix = paint(&role.prefix, color, false, mode);
+            let mut h = role.label.clone();
+            if !stamp.is_empty() {
+                h.push_str(&format!("  {}", stamp));
+            }
+            let mut out = format!("{}{}\n", prefix, paint(&h, color, true, mode));
+            for line in body.lines() {
+                out.push_str(&format!("{}{}\n", prefix, line));
+            }
+            out.push('\n');
+            out
+        }
+    }
+}
+
+// Same role colors as the chat view
+fn role_color(role: &Role, theme: &Theme) -> Option<Color> {
+    match role {
+        Role::User => Some(theme.border_focus),
+        Role::Assistant => None,
+        Role::System => Some(theme.warning),
+    }
+}
+
+fn timestamp(ts: i64) -> String {
+    use chrono::{Local, TimeZone};
+    Local
+        .timestamp_opt(ts, 0)
+        .single()
+        .map(|t| t.format("%Y-%m-%d %H:%M").to_string())
+        .unwrap_or_default()
+}
+
+// Wrap `text` in SGR codes; a no-op outside Ansi mode.
+fn paint(text: &str, fg: Option<Color>, bold: bool, mode: Mode) -> String {
+    if mode != Mode::Ansi {
+        return text.to_string();
+    }
+    let mut codes: Vec<String> = Vec::new();
+    if bold {
+        codes.push("1".into());
+    }
+    if let Some(c) = fg.and_then(ansi_fg) {
+        codes.push(c);
+    }
+    if codes.is_empty() {
+        return text.to_string();
+    }
+    format!("\x1b[{}m{}\x1b[0m", codes.join(";"), text)
+}
+
+fn ansi_fg(c: Color) -> Option<String> {
+    let code = match c {
+        Color::Black => "30",
+        Color::Red => "31",
+        Color::Green => "32",
+        Color::Yellow => "33",
+        Color::Blue => "34",
+        Color::Magenta => "35",
+        Color::Cyan => "36",
+        Color::Gray => "37",
+        Color::DarkGray => "90",
+        Color::LightRed => "91",
+        Color::LightGreen => "92",
+        Color::LightYellow => "93",
+        Color::LightBlue => "94",
+        Color::LightMagenta => "95",
+        Color::LightCyan => "96",
+        Color::White => "97",
+        Color::Indexed(i) => return Some(format!("38;5;{}", i)),
+        Color::Rgb(r, g, b) => return Some(format!("38;2;{};{};{}", r, g, b)),
+        Color::Reset => return None,
+    };
+    Some(code.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::attachments::MessageImage;
+    use crate::test_support::{assert_snapshot, sample_messages};
+    use crate::theme::DEFAULT_THEME;
+
+    // The rendering fixture plus a system prompt, timestamps and an image
+    fn conversation() -> Vec<Message> {
+        let mut system = Message::user("Answer briefly.");
+        system.role = Role::System;
+        let mut msgs = vec![system];
+        msgs.extend(sample_messages());
+        for (i, m) in msgs.iter_mut().enumerate().skip(1) {
+            m.timestamp = Some(1_700_000_000 + 60 * i as i64);
+        }
+        msgs[3].images.push(MessageImage {
+            path: "shot.png".into(),
+            name: "shot.png".into(),
+            bytes: 2048,
+        });
+        msgs
+    }
+
+    fn render(mode: Mode, tpl: &Templates) -> String {
+        let meta = Meta {
+            session: "unicode demo",
+            model: Some("gpt-5"),
+            theme: &DEFAULT_THEME,
+        };
+        transcript(&conversation(), &meta, mode, tpl)
+    }
+
+    #[test]
+    fn transcript_plain() {
+        assert_snapshot(
+            "transcript_plain",
+            &render(Mode::Plain, &Templates::default()),
+        );
+    }
+
+    #[test]
+    fn transcript_markdown() {
+        assert_snapshot(
+            "transcript_markdown",
+            &render(Mode::Markdown, &Templates::default()),
+        );
+    }
+
+    #[test]
+    fn transcript_ansi() {
+        // ESC spelled out so the snapshot stays readable
+        let text = render(Mode::Ansi, &Templates::default()).replace('\x1b', "\\e");
+        assert_snapshot("transcript_ansi", &text);
+    }
+
+    #[test]
+    fn transcript_markdown_with_templates() {
+        let tpl = Templates::from_config(&TranscriptTemplates {
+            user_label: Some("Me".into()),
+            assistant_label: Some("Bot".into()),
+            heading_level: Some(9),
+            rules: Some(false),
+            ..TranscriptTemplates::default()
+        });
+        assert_snapshot(
+            "transcript_markdown_templates",
+            &render(Mode::Markdown, &tpl),
+        );
+    }
+}
@@ -0,0 +1,355 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use serde_json::Value;
+
+use crate::app::Message;
+
+// Max chars of a title taken over as a session name
+const TITLE_CHARS: usize = 60;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ArchiveKind {
+    ChatGpt,
+    Claude,
+}
+
+impl ArchiveKind {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "chatgpt" | "openai" => Some(ArchiveKind::ChatGpt),
+            "claude" | "anthropic" => Some(ArchiveKind::Claude),
+            _ => None,
+        }
+    }
+}
+
+// One conversation mapped onto the session model.
+pub struct ImportedConversation {
+    pub title: String,
+    pub messages: Vec<Message>,
+}
+
+#[derive(Default)]
+pub struct ImportSummary {
+    pub imported: usize,
+    // Reason -> count, e.g. "empty" -> 3
+    pub skipped: HashMap<String, usize>,
+}
+
+impl ImportSummary {
+    pub fn skip(&mut self, reason: &str) {
+        *self.skipped.entry(reason.to_string()).or_insert(0) += 1;
+    }
+
+    pub fn describe(&self) -> String {
+        let skipped: usize = self.skipped.values().sum();
+        if skipped == 0 {
+            return format!("imported {} conversations", self.imported);
+        }
+        let mut reasons: Vec<_> = self.skipped.iter().collect();
+        reasons.sort();
+        let reasons = reasons
+            .iter()
+            .map(|(r, n)| format!("{} {}", n, r))
+            .collect::<Vec<_>>()
+            .join(", ");
+        format!(
+            "imported {} conversations, {} skipped: {}",
+            self.imported, skipped, reasons
+        )
+    }
+}
+
+// Parse an export: ChatGPT `conversations.json`, or a Claude export dir (or its
+// `conversations.json`). Conversations that cannot be mapped are counted in the summary.
+pub fn read_archive(
+    kind: ArchiveKind,
+    path: &Path,
+    summary: &mut ImportSummary,
+) -> Result<Vec<ImportedConversation>> {
+    let file = resolve_conversations_file(path);
+    let data = fs::read(&file).with_context(|| format!("read {}", file.display()))?;
+    let root: Value = serde_json::from_slice(&data).with_context(|| "parse export json")?;
+    let list = root
+        .as_array()
+        .context("expected a JSON array of conversations")?;
+    let mut out = Vec::new();
+    for conv in list {
+        let parsed = match kind {
+            ArchiveKind::ChatGpt => parse_chatgpt(conv),
+            ArchiveKind::Claude => parse_claude(conv),
+        };
+        match parsed {
+            Some(c) if c.messages.is_empty() => summary.skip("empty"),
+            Some(c) => out.push(c),
+            None => summary.skip("unreadable"),
+        }
+    }
+    Ok(out)
+}
+
+fn resolve_conversations_file(path: &Path) -> PathBuf {
+    if path.is_dir() {
+        path.join("conversations.json")
+    } else {
+        path.to_path_buf()
+    }
+}
+
+// ChatGPT: `mapping` is a tree of nodes; follow `current_node` up through
+// `parent` links to get the branch the user last saw.
+fn parse_chatgpt(conv: &Value) -> Option<ImportedConversation> {
+    let mapping = conv.get("mapping")?.as_object()?;
+    let mut chain = Vec::new();
+    let mut node_id = conv.get("current_node").and_then(|v| v.as_str());
+    while let Some(id) = node_id {
+        let node = mapping.get(id)?;
+        chain.push(node);
+        node_id = node.get("parent").and_then(|v| v.as_str());
+    }
+    chain.reverse();
+    let mut messages = Vec::new();
+    for node in chain {
+        let Some(msg) = node.get("message").filter(|m| !m.is_null()) else {
+            continue;
+        };
+        let role = msg["author"]["role"].as_str().unwrap_or("");
+        let content = &msg["content"];
+        let content_type = content["content_type"].as_str().unwrap_or("");
+        let text = if matches!(content_type, "text" | "multimodal_text") {
+            content["parts"]
+                .as_array()
+                .map(|parts| {
+                    parts
+                        .iter()
+                        .map(|p| match p.as_str() {
+                            Some(t) => t.to_string(),
+                            None => placeholder("image"),
+                        })
+                        .collect::<Vec<_>>()
+                        .join("\n")
+                })
+                .unwrap_or_default()
+        } else {
+            placeholder(content_type)
+        };
+        let mut m = match role {
+            "user" => Message::user(text),
+            "assistant" => Message::assistant(text),
+            // Tool calls/results are not modeled; keep a note in their place
+            "tool" => Message::assistant(placeholder("tool output")),
+            _ => continue,
+        };
+        if m.content.trim().is_empty() {
+            continue;
+        }
+        m.timestamp = msg["create_time"].as_f64().map(|t| t as i64);
+        messages.push(m);
+    }
+    Some(ImportedConversation {
+        title: title_of(conv.get("title").and_then(|v| v.as_str())),
+        messages,
+    })
+}
+
+// Claude: flat `chat_messages` with `sender` human/assistant and ISO timestamps.
+fn parse_claude(conv: &Value) -> Option<ImportedConversation> {
+    let list = conv.get("chat_messages")?.as_array()?;
+    let mut messages = Vec::new();
+    for cm in list {
+        let mut text = match cm.get("content").and_then(|c| c.as_array()) {
+            Some(parts) => parts
+                .iter()
+                .map(|p| match p["type"].as_str() {
+                    Some("text") => p["text"].as_str().unwrap_or("").to_string(),
+                    Some(other) => placeholder(other),
+                    None => String::new(),
+                })
+                .filter(|t| !t.is_empty())
+                .collect::<Vec<_>>()
+                .join("\n"),
+            None => cm["text"].as_str().unwrap_or("").to_string(),
+        };
+        for key in ["attachments", "files"] {
+            let n = cm[key].as_array().map(|a| a.len()).unwrap_or(0);
+            if n > 0 {
+                text.push_str(&format!("\n{}", placeholder(&format!("{} {}", n, key))));
+            }
+        }
+        let mut m = match cm["sender"].as_str() {
+            Some("human") => Message::user(text),
+            Some("assistant") => Message::assistant(text),
+            _ => continue,
+        };
+        if m.content.trim().is_empty() {
+            continue;
+        }
+        m.timestamp = cm["created_at"]
+            .as_str()
+            .and_then(|s| chrono::DateTime::parse_from_rfc3339(s).ok())
+            .map(|t| t.timestamp());
+        messages.push(m);
+    }
+    Some(ImportedConversation {
+        title: title_of(conv.get("name").and_then(|v| v.as_str())),
+        messages,
+    })
+}
+
+fn placeholder(what: &str) -> String {
+    format!("[unsupported content: {}]", what)
+}
+
+fn title_of(raw: Option<&str>) -> String {
+    let flat = raw
+        .unwrap_or("")
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ");
+    let t: String = flat.chars().take(TITLE_CHARS).collect();
+    if t.is_empty() {
+        "imported".to_string()
+    } else {
+        t
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::Role;
+    use crate::test_support::TempRoot;
+
+    fn fixture(name: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("src/fixtures")
+            .join(name)
+    }
+
+    fn summary_of(kind: ArchiveKind, path: &Path) -> (Vec<ImportedConversation>, ImportSummary) {
+        let mut summary = ImportSummary::default();
+        let convs = read_archive(kind, path, &mut summary).unwrap();
+        (convs, summary)
+    }
+
+    #[test]
+    fn chatgpt_follows_the_current_branch() {
+        let (convs, summary) =
+            summary_of(ArchiveKind::ChatGpt, &fixture("chatgpt_conversations.json"));
+        assert_eq!(convs.len(), 1);
+        assert_eq!(convs[0].title, "Rust lifetimes question");
+        let got: Vec<_> = convs[0]
+            .messages
+            .iter()
+            .map(|m| (m.role.clone(), m.content.as_str(), m.timestamp))
+            .collect();
+        assert_eq!(
+            got,
+            [
+                (
+                    Role::User,
+                    "[unsupported content: image]\nWhat does 'a mean?",
+                    Some(1700000001)
+                ),
+                (
+                    Role::Assistant,
+                    "[unsupported content: code]",
+                    Some(1700000002)
+                ),
+                (
+                    Role::Assistant,
+                    "[unsupported content: tool output]",
+                    Some(1700000003)
+                ),
+                (Role::Assistant, "It names a lifetime.", Some(1700000004)),
+            ]
+        );
+        // Only the root: empty; no mapping or a missing node: unreadable
+        assert_eq!(summary.skipped.get("empty"), Some(&1));
+        assert_eq!(summary.skipped.get("unreadable"), Some(&2));
+        assert_eq!(
+            summary.describe(),
+            "imported 0 conversations, 3 skipped: 1 empty, 2 unreadable"
+        );
+    }
+
+    #[test]
+    fn claude_reads_an_export_dir() {
+        let root = TempRoot::new();
+        fs::copy(
+            fixture("claude_conversations.json"),
+            root.path().join("conversations.json"),
+        )
+        .unwrap();
+        let (convs, summary) = summary_of(ArchiveKind::Claude, root.path());
+        assert_eq!(convs.len(), 1);
+        assert_eq!(convs[0].title, "Trip planning");
+        let got: Vec<_> = convs[0]
+            .messages
+            .iter()
+            .map(|m| (m.role.clone(), m.content.as_str(), m.timestamp))
+            .collect();
+        assert_eq!(
+            got,
+            [
+                (
+                    Role::User,
+                    "Plan a day in Kyoto\n[unsupported content: 1 attachments]",
+                    Some(1714554001)
+                ),
+                (
+                    Role::Assistant,
+                    "Morning: Fushimi Inari.\n[unsupported content: tool_use]\nEvening: Gion.",
+                    Some(1714554005)
+                ),
+                // A bad date drops only the timestamp
+                (Role::User, "Thanks", None),
+            ]
+        );
+        assert_eq!(summary.skipped.get("empty"), Some(&1));
+        assert_eq!(summary.skipped.get("unreadable"), Some(&1));
+    }
+
+    #[test]
+    fn malformed_archives_are_errors() {
+        let root = TempRoot::new();
+        let path = root.path().join("conversations.json");
+        let mut summary = ImportSummary::default();
+
+        fs::write(&path, "[{\"title\": \"cut off\"").unwrap();
+        let err = read_archive(ArchiveKind::ChatGpt, &path, &mut summary)
+            .err()
+            .unwrap();
+        assert_eq!(err.to_string(), "parse export json");
+
+        fs::write(&path, "{\"conversations\": []}").unwrap();
+        let err = read_archive(ArchiveKind::Claude, &path, &mut summary)
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("JSON array"), "{}", err);
+
+        // A dir without conversations.json names the file it looked for
+        let empty = root.path().join("empty");
+        fs::create_dir_all(&empty).unwrap();
+        let err = read_archive(ArchiveKind::Claude, &empty, &mut summary)
+            .err()
+            .unwrap();
+        assert!(err.to_string().ends_with("conversations.json"), "{}", err);
+        assert_eq!(summary.imported, 0);
+        assert!(summary.skipped.is_empty());
+    }
+
+    #[test]
+    fn archive_kind_accepts_vendor_names() {
+        assert_eq!(ArchiveKind::parse("OpenAI"), Some(ArchiveKind::ChatGpt));
+        assert_eq!(ArchiveKind::parse(" claude "), Some(ArchiveKind::Claude));
+        assert_eq!(ArchiveKind::parse("gemini"), None);
+        assert_eq!(title_of(Some("  ")), "imported");
+        assert_eq!(title_of(Some(&"x".repeat(80))).len(), TITLE_CHARS);
+    }
+}
@@ -1,45 +1,144 @@
 mod app;
+mod bench;
+mod bugreport;
+mod chat_layout;
+mod editor;
 mod events;
+mod format;
+mod import;
+mod markdown;
 mod persist;
+mod redact;
+mod signals;
+mod spawn_env;
 mod strings;
+mod templates;
 mod terminal;
+#[cfg(test)]
+mod test_support;
 mod theme;
 mod ui;
+mod update;
 
 use anyhow::Result;
-use directories::BaseDirs;
+use providers::openai::{
+    config::{self, OpenAiConfig, OpenAiFileConfig},
+    httplog,
+};
 use std::fs;
-use std::path::PathBuf;
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
 use terminal::TerminalGuard;
-use tracing_subscriber::{fmt, EnvFilter};
+use tracing::Level;
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_subscriber::{
+    filter::{self, Targets},
+    fmt,
+    prelude::*,
+    EnvFilter,
+};
 
 fn main() -> Result<()> {
-    init_logging();
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
+    // Before logging, which already reads the config
+    let mut config_path = None;
+    if let Some(i) = args.iter().position(|a| a == "--config") {
+        let Some(path) = args.get(i + 1) else {
+            anyhow::bail!("usage: fast --config <path to config.toml>");
+        };
+        config_path = Some(PathBuf::from(path));
+        args.drain(i..i + 2);
+    }
+    if let Some(path) = &config_path {
+        // Later readers (profiles, theme.toml, bug reports) go through
+        // `config_path()`; no other thread runs yet
+        std::env::set_var(config::CONFIG_ENV, path);
+    }
+    let _log_guards = init_logging(config_path.as_deref());
+    if args.first().map(String::as_str) == Some("import") {
+        return run_import(&args[1..]);
+    }
+    if let Some(i) = args.iter().position(|a| a == "--bench") {
+        let Some(prompt) = args.get(i + 1) else {
+            anyhow::bail!("usage: fast --bench \"<prompt>\"");
+        };
+        return bench::run(prompt);
+    }
+    let print_on_exit = args.iter().any(|a| a == "--print-on-exit");
+    let no_color = args.iter().any(|a| a == "--no-color") || std::env::var_os("NO_COLOR").is_some();
     let mut app = app::App::new();
+    terminal::install_panic_hook();
+    signals::install();
     let mut term = TerminalGuard::new()?;
-    events::run(&mut term.terminal, &mut app)
+    let res = events::run(&mut term.terminal, &mut app);
+    // Restore the terminal before printing
+    drop(term);
+    if print_on_exit {
+        let mode = if !no_color && std::io::stdout().is_terminal() {
+            format::Mode::Ansi
+        } else {
+            format::Mode::Plain
+        };
+        print!("{}", app.transcript_text(mode));
+    }
+    res
 }
 
-fn init_logging() {
-    let log_path: PathBuf = if let Some(base) = BaseDirs::new() {
-        if cfg!(windows) {
-            base.home_dir().join(".fast").join("log")
-        } else {
-            base.config_dir().join("fast").join("log")
-        }
-    } else {
-        PathBuf::from("./log")
-    };
+// The returned guards flush the log writers; keep them alive until exit.
+fn init_logging(config_path: Option<&Path>) -> Vec<WorkerGuard> {
+    let log_path = persist::log_dir();
     let _ = fs::create_dir_all(&log_path);
-    let file_appender = tracing_appender::rolling::never(&log_path, "fast-tui.log");
-    let (nb, _guard) = tracing_appender::non_blocking(file_appender);
+    let file_appender = tracing_appender::rolling::never(&log_path, persist::LOG_FILE);
+    let (nb, guard) = tracing_appender::non_blocking(file_appender);
+    let mut guards = vec![guard];
     let env_filter = EnvFilter::try_from_default_env()
         .unwrap_or_else(|_| EnvFilter::new("info,providers=info,fast_core=info,tui=info"));
-    let subscriber = fmt()
-        .with_env_filter(env_filter)
-        .with_writer(nb)
+    // Scrub the configured key too, not only key-shaped strings; a profile
+    // restored or picked later adds its own
+    if let Ok(cfg) = OpenAiConfig::from_env_and_file(config_path) {
+        redact::add_api_key(&cfg.api_key);
+    }
+    // Wire-level events never go to the main log
+    let main_layer = fmt::layer()
+        .with_writer(redact::Redacting::new(nb))
         .with_ansi(false)
-        .finish();
-    let _ = tracing::subscriber::set_global_default(subscriber);
+        .with_filter(filter::filter_fn(|m| m.target() != httplog::TARGET));
+    // `log_requests = true`: requests and raw SSE events to their own file
+    let http_layer = OpenAiFileConfig::load()
+        .and_then(|c| c.log_requests)
+        .unwrap_or(false)
+        .then(|| {
+            let appender = tracing_appender::rolling::never(&log_path, httplog::LOG_FILE);
+            let (nb, guard) = tracing_appender::non_blocking(appender);
+            guards.push(guard);
+            fmt::layer()
+                .with_writer(nb)
+                .with_ansi(false)
+                .with_filter(Targets::new().with_target(httplog::TARGET, Level::INFO))
+        });
+    let _ = tracing_subscriber::registry()
+        .with(env_filter)
+        .with(main_layer)
+        .with(http_layer)
+        .try_init();
     tracing::info!("fast-tui logging initialized at {:?}", log_path);
+    guards
+}
+
+// `fast import chatgpt <conversations.json>` / `fast import claude <export dir>`
+fn run_import(args: &[String]) -> Result<()> {
+    let usage = "usage: fast import chatgpt <conversations.json> | fast import claude <export dir>";
+    let (Some(kind), Some(path)) = (
+        args.first().and_then(|k| import::ArchiveKind::parse(k)),
+        args.get(1),
+    ) else {
+        anyhow::bail!(usage);
+    };
+    let mut app = app::App::new();
+    let summary = app.import_archive(kind, std::path::Path::new(path), |done, total| {
+        eprint!("\rimporting {}/{}", done, total);
+    })?;
+    eprintln!();
+    println!("{}", summary.describe());
+    Ok(())
 }
@@ -0,0 +1,483 @@
+// Small Markdown renderer for assistant replies (`render_markdown = true`):
+// headings, bold/italic, inline code, bullet and numbered lists, fenced code
+// blocks. Output is plain wrapped text plus styled byte ranges per line, so
+// search offsets and the collapse machinery keep working on what is shown.
+
+use unicode_width::UnicodeWidthChar;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Emphasis {
+    Bold,
+    Italic,
+    Code,
+    Heading,
+    // Frame of a fenced code block
+    Fence,
+}
+
+// Styled byte ranges within one rendered line.
+pub type Ranges = Vec<(usize, usize, Emphasis)>;
+
+const BULLET: &str = "• ";
+const FENCE_TOP: &str = "╭─";
+const FENCE_SIDE: &str = "│ ";
+const FENCE_BOTTOM: &str = "╰─";
+
+// Render `content`: the first line starts with `head`, the rest with
+// `indent` columns; list items hang under their text. `line_numbers` puts
+// a gutter in fenced code, counting from 1 in each block. Each row also
+// carries the index of the `content` line it came from.
+pub fn render(
+    content: &str,
+    head: &str,
+    indent: usize,
+    width: usize,
+    line_numbers: bool,
+) -> Vec<(String, Ranges, usize)> {
+    let width = width.max(indent + 2);
+    let pad = " ".repeat(indent);
+    let mut out = Out {
+        lines: Vec::new(),
+        head: Some(head.to_string()),
+        pad,
+        width,
+        gutter: None,
+        code_line: 0,
+    };
+    let mut in_code = false;
+    let src: Vec<&str> = content.lines().collect();
+    let mut sources: Vec<usize> = Vec::new();
+    for (i, &raw) in src.iter().enumerate() {
+        // Rows pushed so far belong to the previous line
+        sources.resize(out.lines.len(), i.saturating_sub(1));
+        let trimmed = raw.trim_start();
+        if is_fence(trimmed) {
+            if in_code {
+                out.push_plain(FENCE_BOTTOM, Some(Emphasis::Fence));
+            } else {
+                // Wide enough for the block's last number; an unclosed
+                // block runs to the end
+                let len = src[i + 1..]
+                    .iter()
+                    .take_while(|l| !is_fence(l.trim_start()))
+                    .count();
+                out.gutter = line_numbers.then(|| len.max(1).to_string().len());
+                out.code_line = 0;
+                let lang = trimmed.trim_start_matches(['`', '~']).trim();
+                let top = if lang.is_empty() {
+                    FENCE_TOP.to_string()
+                } else {
+                    format!("{} {}", FENCE_TOP, lang)
+                };
+                out.push_plain(&top, Some(Emphasis::Fence));
+            }
+            in_code = !in_code;
+            continue;
+        }
+        if in_code {
+            out.push_code(raw);
+            continue;
+        }
+        if trimmed.is_empty() {
+            out.push_plain("", None);
+            continue;
+        }
+        // Two spaces of source indentation per nesting level
+        let depth = (raw.len() - trimmed.len()) / 2;
+        if let Some(text) = heading(trimmed) {
+            let (text, mut styles) = inline(text);
+            styles.insert(0, (0, text.len(), Emphasis::Heading));
+            out.push_wrapped("", &text, &styles);
+        } else if let Some((marker, text)) = list_item(trimmed) {
+            let lead = format!("{}{}", "  ".repeat(depth), marker);
+            let (text, styles) = inline(text);
+            out.push_wrapped(&lead, &text, &styles);
+        } else {
+            let (text, styles) = inline(trimmed);
+            out.push_wrapped("", &text, &styles);
+        }
+    }
+    if out.lines.is_empty() {
+        out.push_plain("", None);
+    }
+    sources.resize(out.lines.len(), src.len().saturating_sub(1));
+    out.lines
+        .into_iter()
+        .zip(sources)
+        .map(|((line, ranges), source)| (line, ranges, source))
+        .collect()
+}
+
+fn is_fence(trimmed: &str) -> bool {
+    trimmed.starts_with("```") || trimmed.starts_with("~~~")
+}
+
+// Text of an ATX heading ("## Title"); all levels render alike.
+fn heading(line: &str) -> Option<&str> {
+    let level = line.chars().take_while(|c| *c == '#').count();
+    if (1..=6).contains(&level) && line[level..].starts_with(' ') {
+        Some(line[level..].trim())
+    } else {
+        None
+    }
+}
+
+// Bullet ("- ", "* ", "+ ") or number ("1. ", "1) ") marker and the rest.
+fn list_item(line: &str) -> Option<(String, &str)> {
+    for b in ["- ", "* ", "+ "] {
+        if let Some(rest) = line.strip_prefix(b) {
+            return Some((BULLET.to_string(), rest));
+        }
+    }
+    let digits = line.chars().take_while(|c| c.is_ascii_digit()).count();
+    if digits > 0 && digits < 10 {
+        let rest = &line[digits..];
+        if rest.starts_with(". ") || rest.starts_with(") ") {
+            return Some((format!("{}. ", &line[..digits]), &rest[2..]));
+        }
+    }
+    None
+}
+
+// Strip inline markers: `code`, **bold**/__bold__, *italic*/_italic_ and
+// backslash escapes. Unclosed markers stay literal.
+fn inline(src: &str) -> (String, Ranges) {
+    let mut text = String::with_capacity(src.len());
+    let mut styles = Ranges::new();
+    let mut rest = src;
+    let mut prev: Option<char> = None;
+    while let Some(c) = rest.chars().next() {
+        if c == '\\' {
+            if let Some(next) = rest[1..]
+                .chars()
+                .next()
+                .filter(|n| n.is_ascii_punctuation())
+            {
+                text.push(next);
+                prev = Some(next);
+                rest = &rest[1 + next.len_utf8()..];
+                continue;
+            }
+        }
+        let span = if c == '`' {
+            delimited(rest, "`", Emphasis::Code)
+        } else if rest.starts_with("**") || rest.starts_with("__") {
+            delimited(rest, &rest[..2], Emphasis::Bold)
+        } else if c == '*' || (c == '_' && !prev.is_some_and(char::is_alphanumeric)) {
+            delimited(rest, &rest[..1], Emphasis::Italic)
+        } else {
+            None
+        };
+        match span {
+            Some((inner, used, emphasis)) => {
+                let start = text.len();
+                text.push_str(inner);
+                styles.push((start, text.len(), emphasis));
+                prev = inner.chars().last();
+                rest = &rest[used..];
+            }
+            None => {
+                text.push(c);
+                prev = Some(c);
+                rest = &rest[c.len_utf8()..];
+            }
+        }
+    }
+    (text, styles)
+}
+
+// `rest` starts with `marker`; returns the enclosed text and bytes consumed.
+fn delimited<'a>(
+    rest: &'a str,
+    marker: &str,
+    emphasis: Emphasis,
+) -> Option<(&'a str, usize, Emphasis)> {
+    let body = &rest[marker.len()..];
+    // "* item" and "2 * 3" are not emphasis
+    if emphasis != Emphasis::Code && body.starts_with(char::is_whitespace) {
+        return None;
+    }
+    let end = body.find(marker)?;
+    if end == 0 {
+        return None;
+    }
+    Some((&body[..end], marker.len() * 2 + end, emphasis))
+}
+
+struct Out {
+    lines: Vec<(String, Ranges)>,
+    // Message prefix, used by the first line only
+    head: Option<String>,
+    pad: String,
+    width: usize,
+    // Digits of the line-number gutter in the current code block, if shown
+    gutter: Option<usize>,
+    code_line: usize,
+}
+
+impl Out {
+    fn start_line(&mut self) -> (String, Ranges) {
+        let lead = self.head.take().unwrap_or_else(|| self.pad.clone());
+        (lead, Ranges::new())
+    }
+
+    fn push_plain(&mut self, text: &str, emphasis: Option<Emphasis>) {
+        let (mut line, mut ranges) = self.start_line();
+        let start = line.len();
+        line.push_str(text);
+        if let Some(e) = emphasis.filter(|_| !text.is_empty()) {
+            ranges.push((start, line.len(), e));
+        }
+        self.lines.push((line, ranges));
+    }
+
+    // Code keeps its spacing and is cut at the width instead of word-wrapped.
+    // Lines continued after a cut get a blank gutter.
+    fn push_code(&mut self, raw: &str) {
+        let mut chars = raw.chars().peekable();
+        self.code_line += 1;
+        let mut number = Some(self.code_line);
+        loop {
+            let (mut line, mut ranges) = self.start_line();
+            let side = line.len();
+            line.push_str(FENCE_SIDE);
+            if let Some(digits) = self.gutter {
+                match number.take() {
+                    Some(n) => line.push_str(&format!("{:>digits$} ", n)),
+                    None => line.push_str(&" ".repeat(digits + 1)),
+                }
+            }
+            ranges.push((side, line.len(), Emphasis::Fence));
+            let mut used = display_width(&line);
+            let code = line.len();
+            while let Some(&c) = chars.peek() {
+                let w = c.width().unwrap_or(0);
+                if used + w > self.width && line.len() > code {
+                    break;
+                }
+                line.push(c);
+                used += w;
+                chars.next();
+            }
+            if line.len() > code {
+                ranges.push((code, line.len(), Emphasis::Code));
+            }
+            self.lines.push((line, ranges));
+            if chars.peek().is_none() {
+                break;
+            }
+        }
+    }
+
+    // Greedy word wrap of `text`; continuation lines align under the text
+    // after `lead`.
+    fn push_wrapped(&mut self, lead: &str, text: &str, styles: &Ranges) {
+        let style_at = |i: usize| {
+            // Innermost (last pushed) range wins
+            styles
+                .iter()
+                .rev()
+                .find(|(s, e, _)| i >= *s && i < *e)
+                .map(|(_, _, em)| *em)
+        };
+        let (mut line, mut ranges) = self.start_line();
+        line.push_str(lead);
+        let hang = format!("{}{}", self.pad, " ".repeat(display_width(lead)));
+        let mut used = display_width(&line);
+        let mut at_start = true;
+        for (start, word) in words(text) {
+            let ww = display_width(word);
+            if !at_start && used + 1 + ww > self.width {
+                self.lines
+                    .push((std::mem::take(&mut line), std::mem::take(&mut ranges)));
+                line.push_str(&hang);
+                used = display_width(&hang);
+                at_start = true;
+            }
+            if !at_start {
+                // Keep a style running across the gap between two words
+                let gap = style_at(start - 1).filter(|e| Some(*e) == style_at(start));
+                push_styled(&mut line, &mut ranges, " ", gap);
+                used += 1;
+            }
+            for (off, c) in word.char_indices() {
+                let w = c.width().unwrap_or(0);
+                if used + w > self.width && used > display_width(&hang) {
+                    self.lines
+                        .push((std::mem::take(&mut line), std::mem::take(&mut ranges)));
+                    line.push_str(&hang);
+                    used = display_width(&hang);
+                }
+                let mut buf = [0u8; 4];
+                push_styled(
+                    &mut line,
+                    &mut ranges,
+                    c.encode_utf8(&mut buf),
+                    style_at(start + off),
+                );
+                used += w;
+            }
+            at_start = false;
+        }
+        self.lines.push((line, ranges));
+    }
+}
+
+// Append `s`, extending the previous range when the style continues.
+fn push_styled(line: &mut String, ranges: &mut Ranges, s: &str, emphasis: Option<Emphasis>) {
+    let start = line.len();
+    line.push_str(s);
+    let Some(e) = emphasis else {
+        return;
+    };
+    match ranges.last_mut() {
+        Some(last) if last.1 == start && last.2 == e => last.1 = line.len(),
+        _ => ranges.push((start, line.len(), e)),
+    }
+}
+
+// Space-separated words with their byte offsets.
+fn words(text: &str) -> impl Iterator<Item = (usize, &str)> {
+    text.split(' ')
+        .scan(0usize, |pos, w| {
+            let start = *pos;
+            *pos += w.len() + 1;
+            Some((start, w))
+        })
+        .filter(|(_, w)| !w.is_empty())
+}
+
+fn display_width(s: &str) -> usize {
+    unicode_width::UnicodeWidthStr::width(s)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(content: &str, width: usize) -> Vec<String> {
+        render(content, "> ", 2, width, false)
+            .into_iter()
+            .map(|(l, _, _)| l)
+            .collect()
+    }
+
+    // Text covered by each range of `line`
+    fn styled(line: &(String, Ranges, usize)) -> Vec<(&str, Emphasis)> {
+        line.1
+            .iter()
+            .map(|&(s, e, em)| (&line.0[s..e], em))
+            .collect()
+    }
+
+    #[test]
+    fn inline_markers_become_ranges() {
+        let (text, styles) = inline("a **bold** and *it* with `co*de*` 文字");
+        assert_eq!(text, "a bold and it with co*de* 文字");
+        let got: Vec<_> = styles.iter().map(|&(s, e, em)| (&text[s..e], em)).collect();
+        assert_eq!(
+            got,
+            [
+                ("bold", Emphasis::Bold),
+                ("it", Emphasis::Italic),
+                ("co*de*", Emphasis::Code),
+            ]
+        );
+    }
+
+    #[test]
+    fn unclosed_and_spaced_markers_stay_literal() {
+        assert_eq!(inline("2 * 3 * 4").0, "2 * 3 * 4");
+        assert_eq!(inline("**open").0, "**open");
+        assert_eq!(inline("snake_case_name").0, "snake_case_name");
+        assert_eq!(inline(r"\*not italic\*").0, "*not italic*");
+        assert!(inline("snake_case_name").1.is_empty());
+    }
+
+    #[test]
+    fn headings_and_list_markers() {
+        assert_eq!(heading("## Title "), Some("Title"));
+        assert_eq!(heading("#hashtag"), None);
+        assert_eq!(heading("####### seven"), None);
+        assert_eq!(list_item("- item"), Some((BULLET.to_string(), "item")));
+        assert_eq!(
+            list_item("12) twelve"),
+            Some(("12. ".to_string(), "twelve"))
+        );
+        assert_eq!(list_item("-item"), None);
+        assert_eq!(list_item("1.5 pounds"), None);
+    }
+
+    #[test]
+    fn list_items_hang_under_their_text() {
+        assert_eq!(
+            lines("- one two three four\n  - nested item here", 16),
+            [
+                "> • one two",
+                "    three four",
+                "    • nested",
+                "      item here",
+            ]
+        );
+    }
+
+    #[test]
+    fn wide_characters_wrap_by_display_width() {
+        for line in lines("漢字漢字漢字 漢字漢字漢字", 10) {
+            assert!(display_width(&line) <= 10, "{:?}", line);
+        }
+    }
+
+    #[test]
+    fn fenced_code_is_framed_and_cut_not_wrapped() {
+        let out = render(
+            "```rust\nlet  x = 1234567890;\n```\nafter",
+            "> ",
+            2,
+            14,
+            true,
+        );
+        let text: Vec<&str> = out.iter().map(|(l, _, _)| l.as_str()).collect();
+        assert_eq!(
+            text,
+            [
+                "> ╭─ rust",
+                "  │ 1 let  x =",
+                "  │    1234567",
+                "  │   890;",
+                "  ╰─",
+                "  after",
+            ]
+        );
+        assert_eq!(
+            styled(&out[1]),
+            [("│ 1 ", Emphasis::Fence), ("let  x =", Emphasis::Code)]
+        );
+        // Each row maps back to its source line
+        let sources: Vec<usize> = out.iter().map(|(_, _, s)| *s).collect();
+        assert_eq!(sources, [0, 1, 1, 1, 2, 3]);
+    }
+
+    #[test]
+    fn unclosed_fence_runs_to_the_end() {
+        assert_eq!(
+            lines("```\ncode\n# not a heading", 40),
+            ["> ╭─", "  │ code", "  │ # not a heading"]
+        );
+    }
+
+    #[test]
+    fn styles_continue_across_wrapped_words() {
+        let out = render("**bold words here**", "", 0, 10, false);
+        let got: Vec<_> = out.iter().flat_map(styled).collect();
+        assert_eq!(
+            got,
+            [("bold words", Emphasis::Bold), ("here", Emphasis::Bold)]
+        );
+    }
+
+    #[test]
+    fn empty_content_still_has_the_header_line() {
+        assert_eq!(lines("", 20), ["> "]);
+    }
+}
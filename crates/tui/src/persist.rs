@@ -1,14 +1,23 @@
-use std::{fs, io::Write, path::PathBuf};
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+    sync::mpsc,
+};
 
 use anyhow::{Context, Result};
 use directories::BaseDirs;
 use serde::{Deserialize, Serialize};
+use tracing::debug;
 
 use crate::app::{App, Message};
 
 #[derive(Debug, Serialize, Deserialize, Default)]
 pub struct SavedState {
     pub sessions: Vec<String>,
+    // Parallel to `sessions`; None in state files written before auto-titling
+    pub session_auto_named: Option<Vec<bool>>,
     pub current_session: usize,
     pub show_sidebar: bool,
     pub sidebar_scroll: u16,
@@ -18,12 +27,26 @@ pub struct SavedState {
     pub temperature: Option<f32>,
     pub top_p: Option<f32>,
     pub max_tokens: Option<u32>,
+    pub search_smart_case: Option<bool>,
+    pub search_whole_word: Option<bool>,
+    pub show_timestamps: Option<bool>,
+    pub show_reasoning: Option<bool>,
+    // Theme preset name ("dark" | "light")
+    pub theme: Option<String>,
+    // Only after /collapse; otherwise config.toml decides
+    pub collapse_preview_lines: Option<usize>,
+    pub collapse_threshold_lines: Option<usize>,
+    // Unix seconds of the last release check
+    pub update_last_checked: Option<i64>,
+    // Active `[profile.<name>]`; None for the top-level config
+    pub profile: Option<String>,
 }
 
 impl From<&App> for SavedState {
     fn from(a: &App) -> Self {
         SavedState {
             sessions: a.sessions.clone(),
+            session_auto_named: Some(a.session_auto_named.clone()),
             current_session: a.current_session,
             show_sidebar: a.show_sidebar,
             sidebar_scroll: a.sidebar_scroll,
@@ -32,14 +55,77 @@ impl From<&App> for SavedState {
             temperature: a.temperature,
             top_p: a.top_p,
             max_tokens: a.max_tokens,
+            search_smart_case: Some(a.search_smart_case),
+            search_whole_word: Some(a.search_whole_word),
+            show_timestamps: Some(a.show_timestamps),
+            show_reasoning: Some(a.show_reasoning),
+            theme: Some(a.theme_name.clone()),
+            collapse_preview_lines: a.collapse_overridden.then_some(a.collapse_preview_lines),
+            collapse_threshold_lines: a.collapse_overridden.then_some(a.collapse_threshold_lines),
+            update_last_checked: a.update_last_checked,
+            profile: providers::openai::config::active_profile(),
         }
     }
 }
 
+pub const LOG_FILE: &str = "fast-tui.log";
+
+pub fn log_dir() -> PathBuf {
+    if let Some(root) = test_root() {
+        return root.join("log");
+    }
+    match BaseDirs::new() {
+        Some(base) if cfg!(windows) => base.home_dir().join(".fast").join("log"),
+        Some(base) => base.config_dir().join("fast").join("log"),
+        None => PathBuf::from("./log"),
+    }
+}
+
+// `~/...` expanded against the home dir; other paths are used as given.
+pub fn expand_user_path(p: &str) -> PathBuf {
+    let p = p.trim();
+    if let Some(rest) = p.strip_prefix("~/").or_else(|| p.strip_prefix("~\\")) {
+        if let Some(base) = BaseDirs::new() {
+            return base.home_dir().join(rest);
+        }
+    }
+    PathBuf::from(p)
+}
+
+// `~/.config/fast` and `~/.local/share/fast` (or the platform equivalents).
+// Unit tests swap both for a throwaway dir so they never touch real files.
+fn config_dir() -> Option<PathBuf> {
+    if let Some(root) = test_root() {
+        return Some(root.join("config"));
+    }
+    Some(BaseDirs::new()?.config_dir().join("fast"))
+}
+
+fn data_dir() -> Option<PathBuf> {
+    if let Some(root) = test_root() {
+        return Some(root.join("data"));
+    }
+    Some(BaseDirs::new()?.data_dir().join("fast"))
+}
+
+#[cfg(test)]
+thread_local! {
+    // Set by `test_support::TempRoot` for the current test thread
+    pub static TEST_ROOT: std::cell::RefCell<Option<PathBuf>> = const { std::cell::RefCell::new(None) };
+}
+
+#[cfg(test)]
+fn test_root() -> Option<PathBuf> {
+    TEST_ROOT.with(|r| r.borrow().clone())
+}
+
+#[cfg(not(test))]
+fn test_root() -> Option<PathBuf> {
+    None
+}
+
 pub fn state_path() -> Option<PathBuf> {
-    let base = BaseDirs::new()?;
-    let dir = base.config_dir().join("fast");
-    Some(dir.join("ui_state.json"))
+    Some(config_dir()?.join("ui_state.json"))
 }
 
 pub fn load_state() -> Result<Option<SavedState>> {
@@ -75,10 +161,94 @@ pub fn save_state(app: &App) -> Result<()> {
     Ok(())
 }
 
+// Input history entries kept across runs
+pub const HISTORY_MAX: usize = 1000;
+
+fn history_path() -> Option<PathBuf> {
+    Some(config_dir()?.join("history"))
+}
+
+// The newest HISTORY_MAX entries, oldest first, consecutive repeats merged.
+// A file that grew well past the cap (appends from several windows) is
+// rewritten with what was kept.
+pub fn load_history() -> Vec<String> {
+    let Some(path) = history_path() else {
+        return Vec::new();
+    };
+    let Ok(data) = fs::read_to_string(&path) else {
+        return Vec::new();
+    };
+    let lines: Vec<&str> = data.lines().filter(|l| !l.is_empty()).collect();
+    let mut out: Vec<String> = Vec::new();
+    for line in &lines[lines.len().saturating_sub(HISTORY_MAX)..] {
+        let entry = unescape_history(line);
+        if out.last() != Some(&entry) {
+            out.push(entry);
+        }
+    }
+    if lines.len() > HISTORY_MAX * 2 {
+        let mut text = String::new();
+        for e in &out {
+            text.push_str(&escape_history(e));
+            text.push('\n');
+        }
+        if let Err(e) = fs::write(&path, text) {
+            debug!(target: "tui", "history compaction failed: {}", e);
+        }
+    }
+    out
+}
+
+// Appended, not rewritten, so concurrent windows don't drop each other's entries.
+pub fn append_history(entry: &str) -> Result<()> {
+    let Some(path) = history_path() else {
+        return Ok(());
+    };
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).ok();
+    }
+    let mut f = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .with_context(|| format!("open history: {}", path.display()))?;
+    f.write_all(format!("{}\n", escape_history(entry)).as_bytes())?;
+    Ok(())
+}
+
+// One entry per line: newlines and backslashes are escaped.
+fn escape_history(entry: &str) -> String {
+    entry
+        .replace('\\', "\\\\")
+        .replace('\n', "\\n")
+        .replace('\r', "")
+}
+
+fn unescape_history(line: &str) -> String {
+    let mut out = String::with_capacity(line.len());
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        match (c, chars.clone().next()) {
+            ('\\', Some('n')) => {
+                out.push('\n');
+                chars.next();
+            }
+            ('\\', Some('\\')) => {
+                out.push('\\');
+                chars.next();
+            }
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
 fn session_dir() -> Option<PathBuf> {
-    let base = BaseDirs::new()?;
-    let dir = base.data_dir().join("fast").join("sessions");
-    Some(dir)
+    Some(data_dir()?.join("sessions"))
+}
+
+pub fn attachment_dir(session: &str) -> Option<PathBuf> {
+    Some(data_dir()?.join("attachments").join(sanitize(session)))
 }
 
 fn sanitize(name: &str) -> String {
@@ -115,9 +285,29 @@ pub fn load_session(name: &str) -> Result<Vec<Message>> {
             out.push(m);
         }
     }
+    debug!(target: "tui", "load_session name={} msgs={} last={}", name, out.len(), last_preview(&out));
     Ok(out)
 }
 
+// Time of the newest timestamped message in a session.
+pub fn session_last_activity(name: &str) -> Option<i64> {
+    load_session(name)
+        .ok()?
+        .iter()
+        .filter_map(|m| m.timestamp)
+        .max()
+}
+
+// Hash of a session file's bytes, None when it does not exist. Compared
+// against the value seen at load/save to spot changes made elsewhere.
+pub fn session_fingerprint(name: &str) -> Option<u64> {
+    use std::hash::{Hash, Hasher};
+    let data = fs::read(session_path_for(name)?).ok()?;
+    let mut h = std::collections::hash_map::DefaultHasher::new();
+    data.hash(&mut h);
+    Some(h.finish())
+}
+
 pub fn save_session(name: &str, msgs: &[Message]) -> Result<()> {
     let Some(dir) = session_dir() else {
         return Ok(());
@@ -139,10 +329,274 @@ pub fn save_session(name: &str, msgs: &[Message]) -> Result<()> {
         f.flush()?;
     }
     fs::rename(tmp, &path).with_context(|| format!("persist session to {}", path.display()))?;
+    debug!(target: "tui", "save_session name={} msgs={} last={}", name, msgs.len(), last_preview(msgs));
     Ok(())
 }
 
+fn last_preview(msgs: &[Message]) -> String {
+    msgs.last()
+        .map(|m| crate::redact::preview(&m.content))
+        .unwrap_or_default()
+}
+
+// Tag and version written into exported session files
+const EXPORT_FORMAT: &str = "fast-session";
+const EXPORT_VERSION: u32 = 1;
+
+// Single-object session file for sharing between machines.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SessionExport {
+    pub format: String,
+    pub version: u32,
+    pub name: String,
+    pub model: Option<String>,
+    pub wire_api: Option<String>,
+    // Unix seconds
+    pub exported_at: Option<i64>,
+    pub created_at: Option<i64>,
+    pub updated_at: Option<i64>,
+    pub messages: Vec<Message>,
+}
+
+pub fn export_session_json(
+    name: &str,
+    path: &Path,
+    model: &str,
+    wire: &str,
+    overwrite: bool,
+) -> Result<()> {
+    let messages = load_session(name)?;
+    let export = SessionExport {
+        format: EXPORT_FORMAT.to_string(),
+        version: EXPORT_VERSION,
+        name: name.to_string(),
+        model: Some(model.to_string()),
+        wire_api: Some(wire.to_string()),
+        exported_at: Some(crate::app::now_unix()),
+        created_at: messages.iter().filter_map(|m| m.timestamp).min(),
+        updated_at: messages.iter().filter_map(|m| m.timestamp).max(),
+        messages,
+    };
+    let data = serde_json::to_vec_pretty(&export)?;
+    write_export(path, &data, overwrite)
+}
+
+// Written to a temp file and renamed, like `save_state`; an existing file is
+// an error unless `overwrite`.
+pub fn write_export(path: &Path, data: &[u8], overwrite: bool) -> Result<()> {
+    if !overwrite && path.exists() {
+        anyhow::bail!(
+            "{} exists (end the command with ! to overwrite)",
+            path.display()
+        );
+    }
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent).ok();
+    }
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let tmp = PathBuf::from(tmp);
+    {
+        let mut f =
+            fs::File::create(&tmp).with_context(|| format!("create tmp: {}", tmp.display()))?;
+        f.write_all(data)?;
+        f.flush()?;
+    }
+    fs::rename(&tmp, path).with_context(|| format!("write export: {}", path.display()))?;
+    Ok(())
+}
+
+// `/export` without a path: "<session>-<date>.<ext>" in the downloads dir,
+// else the documents dir, else home.
+pub fn default_export_path(session: &str, ext: &str) -> PathBuf {
+    let dir = directories::UserDirs::new()
+        .map(|u| {
+            u.download_dir()
+                .or(u.document_dir())
+                .unwrap_or(u.home_dir())
+                .to_path_buf()
+        })
+        .unwrap_or_default();
+    let date = chrono::Local::now().format("%Y-%m-%d");
+    dir.join(format!("{}-{}.{}", sanitize(session), date, ext))
+}
+
+// Import an exported session under a fresh name (suffixed when `existing` or a
+// session file already uses it). Returns the name it was saved as.
+pub fn import_session_json(path: &Path, existing: &[String]) -> Result<String> {
+    let data = fs::read(path).with_context(|| format!("read import: {}", path.display()))?;
+    let export: SessionExport =
+        serde_json::from_slice(&data).with_context(|| "parse session json")?;
+    if export.format != EXPORT_FORMAT {
+        anyhow::bail!("not a fast session export (format '{}')", export.format);
+    }
+    let name = unused_session_name(&export.name, existing);
+    save_session(&name, &export.messages)?;
+    Ok(name)
+}
+
+// Sanitized `base`, suffixed "-2", "-3", ... while `existing` or a session file uses it.
+pub fn unused_session_name(base: &str, existing: &[String]) -> String {
+    let base = sanitize(base);
+    let taken = |n: &str| {
+        existing.iter().any(|e| e == n) || session_path_for(n).map(|p| p.exists()).unwrap_or(false)
+    };
+    let mut name = base.clone();
+    let mut n = 2;
+    while taken(&name) {
+        name = format!("{}-{}", base, n);
+        n += 1;
+    }
+    name
+}
+
+#[derive(Clone, Debug)]
+pub struct GlobalHit {
+    pub session: String,
+    pub msg_idx: usize,
+    pub snippet: String,
+}
+
+// Scan the saved transcripts of `sessions` for `query` on a background
+// thread, one hit per matching message. Sends each session's hits (or the
+// error that kept it from loading) in order; the channel closes when done.
+pub fn spawn_search_all(
+    sessions: Vec<String>,
+    query: String,
+    case_insensitive: bool,
+    whole_word: bool,
+) -> mpsc::Receiver<Result<Vec<GlobalHit>>> {
+    let (tx, rx) = mpsc::channel();
+    std::thread::spawn(move || {
+        for name in &sessions {
+            let res = load_session(name)
+                .map(|msgs| search_messages(name, &msgs, &query, case_insensitive, whole_word));
+            // The popup was closed
+            if tx.send(res).is_err() {
+                return;
+            }
+        }
+    });
+    rx
+}
+
+// First match per message, with the same matching rules as the chat search.
+fn search_messages(
+    session: &str,
+    msgs: &[Message],
+    query: &str,
+    case_insensitive: bool,
+    whole_word: bool,
+) -> Vec<GlobalHit> {
+    let mut out = Vec::new();
+    for (i, m) in msgs.iter().enumerate() {
+        let found =
+            crate::app::search::find_matches(&m.content, query, case_insensitive, whole_word);
+        if let Some(&(s, e)) = found.first() {
+            // Char offsets keep the snippet aligned with the original text
+            let at = m.content[..s].chars().count();
+            out.push(GlobalHit {
+                session: session.to_string(),
+                msg_idx: i,
+                snippet: snippet_around(&m.content, at, m.content[s..e].chars().count()),
+            });
+        }
+    }
+    out
+}
+
+// Snippet of `text` around a match found at char offset `at` (of `len` chars).
+fn snippet_around(text: &str, at: usize, len: usize) -> String {
+    const CONTEXT: usize = 30;
+    let total = text.chars().count();
+    let start = at.saturating_sub(CONTEXT);
+    let end = (at + len + CONTEXT).min(total);
+    let window: String = text.chars().skip(start).take(end - start).collect();
+    let body = window.split_whitespace().collect::<Vec<_>>().join(" ");
+    format!(
+        "{}{}{}",
+        if start > 0 { "..." } else { "" },
+        body,
+        if end < total { "..." } else { "" }
+    )
+}
+
+// Per-session `[env]` entries live next to the session file.
+fn session_env_path(name: &str) -> Option<PathBuf> {
+    let dir = session_dir()?;
+    Some(dir.join(format!("{}.env.json", sanitize(name))))
+}
+
+pub fn load_session_env(name: &str) -> BTreeMap<String, String> {
+    session_env_path(name)
+        .and_then(|p| fs::read(p).ok())
+        .and_then(|data| serde_json::from_slice(&data).ok())
+        .unwrap_or_default()
+}
+
+pub fn save_session_env(name: &str, env: &BTreeMap<String, String>) -> Result<()> {
+    let Some(path) = session_env_path(name) else {
+        return Ok(());
+    };
+    if env.is_empty() {
+        let _ = fs::remove_file(path);
+        return Ok(());
+    }
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).ok();
+    }
+    fs::write(&path, serde_json::to_vec_pretty(env)?)
+        .with_context(|| format!("write {}", path.display()))
+}
+
+// View toggles set for one session (`/markdown`, `/wrap`); unset ones
+// follow config.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SessionView {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub markdown: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wrap: Option<bool>,
+}
+
+fn session_view_path(name: &str) -> Option<PathBuf> {
+    let dir = session_dir()?;
+    Some(dir.join(format!("{}.view.json", sanitize(name))))
+}
+
+pub fn load_session_view(name: &str) -> SessionView {
+    session_view_path(name)
+        .and_then(|p| fs::read(p).ok())
+        .and_then(|data| serde_json::from_slice(&data).ok())
+        .unwrap_or_default()
+}
+
+pub fn save_session_view(name: &str, view: &SessionView) -> Result<()> {
+    let Some(path) = session_view_path(name) else {
+        return Ok(());
+    };
+    if *view == SessionView::default() {
+        let _ = fs::remove_file(path);
+        return Ok(());
+    }
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).ok();
+    }
+    fs::write(&path, serde_json::to_vec_pretty(view)?)
+        .with_context(|| format!("write {}", path.display()))
+}
+
 pub fn rename_session(old: &str, new: &str) -> Result<()> {
+    for (from, to) in [
+        (session_env_path(old), session_env_path(new)),
+        (session_view_path(old), session_view_path(new)),
+    ] {
+        if let (Some(from), Some(to)) = (from, to) {
+            if from.exists() {
+                let _ = fs::rename(from, to);
+            }
+        }
+    }
     let Some(old_path) = session_path_for(old) else {
         return Ok(());
     };
@@ -164,11 +618,238 @@ pub fn rename_session(old: &str, new: &str) -> Result<()> {
     Ok(())
 }
 
-pub fn delete_session(name: &str) -> Result<()> {
-    if let Some(path) = session_path_for(name) {
-        if path.exists() {
-            let _ = fs::remove_file(path);
+// Deleted sessions go to `sessions/trash/`, each file under its own name
+// plus `.<ms timestamp>` of the delete, so deleting a name twice keeps both.
+// Only the files of the newest `TRASH_KEEP` deletes are kept.
+const TRASH_KEEP: usize = 20;
+
+fn trash_dir() -> Option<PathBuf> {
+    Some(session_dir()?.join("trash"))
+}
+
+fn session_files(name: &str) -> Vec<PathBuf> {
+    [
+        session_path_for(name),
+        session_env_path(name),
+        session_view_path(name),
+    ]
+    .into_iter()
+    .flatten()
+    .collect()
+}
+
+fn trashed_path(trash: &Path, path: &Path, stamp: i64) -> Option<PathBuf> {
+    let file = path.file_name()?.to_str()?;
+    Some(trash.join(format!("{}.{}", file, stamp)))
+}
+
+// Rename each `(from, to)`; on a failure the ones already moved go back,
+// so a session is never left half in the trash.
+fn move_all(moves: &[(PathBuf, PathBuf)], what: &str) -> Result<()> {
+    for (i, (from, to)) in moves.iter().enumerate() {
+        if let Err(e) = fs::rename(from, to) {
+            for (from, to) in moves[..i].iter().rev() {
+                let _ = fs::rename(to, from);
+            }
+            return Err(e).with_context(|| format!("{} {}", what, from.display()));
         }
     }
     Ok(())
 }
+
+// Returns the stamp the files were trashed under, for `restore_session`.
+pub fn delete_session(name: &str) -> Result<i64> {
+    let Some(trash) = trash_dir() else {
+        return Ok(0);
+    };
+    fs::create_dir_all(&trash).with_context(|| format!("create {}", trash.display()))?;
+    let files: Vec<PathBuf> = session_files(name)
+        .into_iter()
+        .filter(|p| p.exists())
+        .collect();
+    // One stamp per delete, so the trash can be capped by deletes
+    let taken = trash_stamps(&trash);
+    let mut stamp = chrono::Utc::now().timestamp_millis();
+    while taken.contains(&stamp) {
+        stamp += 1;
+    }
+    let moves: Vec<_> = files
+        .into_iter()
+        .filter_map(|p| {
+            let to = trashed_path(&trash, &p, stamp)?;
+            Some((p, to))
+        })
+        .collect();
+    move_all(&moves, "trash")?;
+    purge_trash(&trash);
+    Ok(stamp)
+}
+
+// Move the files trashed under `stamp` back. A session that was never saved
+// has none and comes back empty.
+pub fn restore_session(name: &str, stamp: i64) -> Result<()> {
+    let Some(trash) = trash_dir() else {
+        return Ok(());
+    };
+    let mut moves = Vec::new();
+    for path in session_files(name) {
+        let Some(from) = trashed_path(&trash, &path, stamp).filter(|f| f.exists()) else {
+            continue;
+        };
+        if path.exists() {
+            anyhow::bail!("{} exists", path.display());
+        }
+        moves.push((from, path));
+    }
+    move_all(&moves, "restore")
+}
+
+fn stamp_of(path: &Path) -> Option<i64> {
+    path.extension()?.to_str()?.parse().ok()
+}
+
+fn trash_stamps(trash: &Path) -> Vec<i64> {
+    let Ok(entries) = fs::read_dir(trash) else {
+        return Vec::new();
+    };
+    let mut stamps: Vec<i64> = entries
+        .flatten()
+        .filter_map(|e| stamp_of(&e.path()))
+        .collect();
+    stamps.sort_unstable();
+    stamps.dedup();
+    stamps
+}
+
+// Drop trashed files older than the newest `TRASH_KEEP` deletes, and any
+// without a stamp (left by older versions).
+fn purge_trash(trash: &Path) {
+    let stamps = trash_stamps(trash);
+    let oldest_kept = stamps
+        .len()
+        .checked_sub(TRASH_KEEP)
+        .map_or(i64::MIN, |i| stamps[i]);
+    let Ok(entries) = fs::read_dir(trash) else {
+        return;
+    };
+    for path in entries.flatten().map(|e| e.path()) {
+        if stamp_of(&path).map_or(true, |s| s < oldest_kept) {
+            let _ = fs::remove_file(&path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn search_messages_honors_whole_word() {
+        let msgs = vec![
+            Message::user("pick a category"),
+            Message::assistant("the Cat sat"),
+        ];
+        let hits = search_messages("s", &msgs, "cat", true, true);
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].msg_idx, 1);
+        assert_eq!(search_messages("s", &msgs, "cat", true, false).len(), 2);
+        assert!(search_messages("s", &msgs, "cat", false, true).is_empty());
+    }
+
+    #[test]
+    fn search_messages_snippet_keeps_wide_text() {
+        let msgs = vec![Message::user("前文 文字化け 後文")];
+        let hits = search_messages("s", &msgs, "文字化け", true, true);
+        assert_eq!(hits.len(), 1);
+        assert!(hits[0].snippet.contains("文字化け"));
+    }
+
+    #[test]
+    fn collapse_lines_are_saved_only_after_collapse_command() {
+        let _root = crate::test_support::TempRoot::new();
+        let mut app = crate::test_support::app_with(Vec::new());
+        let state = SavedState::from(&app);
+        assert_eq!(state.collapse_preview_lines, None);
+        assert_eq!(state.collapse_threshold_lines, None);
+        app.collapse_command("preview 3");
+        let state = SavedState::from(&app);
+        assert_eq!(state.collapse_preview_lines, Some(3));
+        assert_eq!(
+            state.collapse_threshold_lines,
+            Some(app.collapse_threshold_lines)
+        );
+    }
+
+    fn texts(name: &str) -> Vec<String> {
+        load_session(name)
+            .unwrap()
+            .into_iter()
+            .map(|m| m.content)
+            .collect()
+    }
+
+    #[test]
+    fn deleting_a_name_twice_keeps_both_in_the_trash() {
+        let _root = crate::test_support::TempRoot::new();
+        save_session("s", &[Message::user("first")]).unwrap();
+        save_session_view(
+            "s",
+            &SessionView {
+                markdown: Some(true),
+                wrap: None,
+            },
+        )
+        .unwrap();
+        let first = delete_session("s").unwrap();
+        save_session("s", &[Message::user("second")]).unwrap();
+        let second = delete_session("s").unwrap();
+        assert_ne!(first, second);
+        assert!(!session_path_for("s").unwrap().exists());
+        restore_session("s", first).unwrap();
+        assert_eq!(texts("s"), ["first"]);
+        assert_eq!(load_session_view("s").markdown, Some(true));
+        // Restoring over a live session is refused
+        assert!(restore_session("s", second).is_err());
+        delete_session("s").unwrap();
+        restore_session("s", second).unwrap();
+        assert_eq!(texts("s"), ["second"]);
+    }
+
+    #[test]
+    fn trash_keeps_only_the_newest_deletes() {
+        let _root = crate::test_support::TempRoot::new();
+        let trash = trash_dir().unwrap();
+        fs::create_dir_all(&trash).unwrap();
+        fs::write(trash.join("legacy.json"), "[]").unwrap();
+        let mut stamps = Vec::new();
+        for i in 0..TRASH_KEEP + 3 {
+            let name = format!("s{}", i);
+            save_session(&name, &[Message::user("x")]).unwrap();
+            stamps.push(delete_session(&name).unwrap());
+        }
+        let left = fs::read_dir(&trash).unwrap().count();
+        assert_eq!(left, TRASH_KEEP);
+        assert!(!trash.join("legacy.json").exists());
+        restore_session("s0", stamps[0]).unwrap();
+        assert!(!session_path_for("s0").unwrap().exists(), "purged");
+        let last = TRASH_KEEP + 2;
+        restore_session(&format!("s{}", last), stamps[last]).unwrap();
+        assert_eq!(texts(&format!("s{}", last)), ["x"]);
+    }
+
+    #[test]
+    fn a_failed_move_puts_the_moved_files_back() {
+        let _root = crate::test_support::TempRoot::new();
+        let dir = session_dir().unwrap();
+        fs::create_dir_all(&dir).unwrap();
+        let (a, a_to) = (dir.join("a"), dir.join("a.moved"));
+        fs::write(&a, "a").unwrap();
+        let moves = [
+            (a.clone(), a_to.clone()),
+            (dir.join("missing"), dir.join("m")),
+        ];
+        assert!(move_all(&moves, "move").is_err());
+        assert!(a.exists());
+        assert!(!a_to.exists());
+    }
+}
@@ -0,0 +1,185 @@
+use std::io::{self, Write};
+use std::sync::RwLock;
+
+use once_cell::sync::OnceCell;
+use providers::openai::httplog;
+use tracing_subscriber::fmt::MakeWriter;
+
+// Chars kept by the "truncated" policy
+const PREVIEW_CHARS: usize = 120;
+
+// How much conversation text may appear in tracing events (config `log_content`).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LogContent {
+    Off,
+    Truncated,
+    Full,
+}
+
+impl LogContent {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "off" => Some(LogContent::Off),
+            "truncated" => Some(LogContent::Truncated),
+            "full" => Some(LogContent::Full),
+            _ => None,
+        }
+    }
+}
+
+static POLICY: OnceCell<LogContent> = OnceCell::new();
+
+// First call wins; later calls are ignored.
+pub fn set_policy(p: LogContent) {
+    let _ = POLICY.set(p);
+}
+
+pub fn policy() -> LogContent {
+    POLICY.get().copied().unwrap_or(LogContent::Off)
+}
+
+// The only way message text reaches a log line: every call site formats through here.
+pub fn preview(text: &str) -> String {
+    preview_with(policy(), text)
+}
+
+fn preview_with(p: LogContent, text: &str) -> String {
+    match p {
+        LogContent::Off => format!("<{} chars>", text.chars().count()),
+        LogContent::Truncated => {
+            let mut out: String = text
+                .chars()
+                .take(PREVIEW_CHARS)
+                .collect::<String>()
+                .escape_debug()
+                .to_string();
+            if text.chars().count() > PREVIEW_CHARS {
+                out.push_str("...");
+            }
+            format!("\"{}\"", out)
+        }
+        LogContent::Full => format!("\"{}\"", text.escape_debug()),
+    }
+}
+
+// Every key configured during this run: the one at startup and those of
+// profiles switched to since. Earlier keys stay, as late errors may echo them.
+static API_KEYS: RwLock<Vec<String>> = RwLock::new(Vec::new());
+
+pub fn add_api_key(key: &str) {
+    if key.is_empty() {
+        return;
+    }
+    if let Ok(mut keys) = API_KEYS.write() {
+        if !keys.iter().any(|k| k == key) {
+            keys.push(key.to_string());
+        }
+    }
+}
+
+fn scrub(text: &str) -> String {
+    let keys = API_KEYS.read().map(|k| k.clone()).unwrap_or_default();
+    match keys.split_first() {
+        Some((first, rest)) => rest
+            .iter()
+            .fold(httplog::redact(text, first), |t, k| httplog::redact(&t, k)),
+        None => httplog::redact(text, ""),
+    }
+}
+
+// Log file writer that scrubs keys from every formatted event: the keys
+// given to `add_api_key` plus `sk-`/`gsk_` keys and `Bearer` tokens (see
+// `httplog::redact`). The fmt layer writes each event in one call, so a key
+// is never split.
+pub struct Redacting<M> {
+    inner: M,
+}
+
+impl<M> Redacting<M> {
+    pub fn new(inner: M) -> Self {
+        Self { inner }
+    }
+}
+
+impl<'a, M: MakeWriter<'a>> MakeWriter<'a> for Redacting<M> {
+    type Writer = RedactingWriter<M::Writer>;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        RedactingWriter {
+            inner: self.inner.make_writer(),
+        }
+    }
+}
+
+pub struct RedactingWriter<W> {
+    inner: W,
+}
+
+impl<W: Write> Write for RedactingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let text = String::from_utf8_lossy(buf);
+        self.inner.write_all(scrub(&text).as_bytes())?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn off_logs_only_the_length() {
+        assert_eq!(preview_with(LogContent::Off, "héllo\nworld"), "<11 chars>");
+        assert_eq!(preview_with(LogContent::Off, ""), "<0 chars>");
+    }
+
+    #[test]
+    fn truncated_keeps_a_quoted_escaped_prefix() {
+        assert_eq!(
+            preview_with(LogContent::Truncated, "line \"one\"\ntwo"),
+            "\"line \\\"one\\\"\\ntwo\""
+        );
+        // Cut at PREVIEW_CHARS chars, not bytes
+        let long = "é".repeat(PREVIEW_CHARS + 5);
+        let out = preview_with(LogContent::Truncated, &long);
+        assert_eq!(out, format!("\"{}...\"", "é".repeat(PREVIEW_CHARS)));
+        let exact = "x".repeat(PREVIEW_CHARS);
+        assert_eq!(
+            preview_with(LogContent::Truncated, &exact),
+            format!("\"{}\"", exact)
+        );
+    }
+
+    #[test]
+    fn full_keeps_everything_escaped() {
+        let long = "y".repeat(PREVIEW_CHARS * 2);
+        assert_eq!(
+            preview_with(LogContent::Full, &long),
+            format!("\"{}\"", long)
+        );
+        assert_eq!(preview_with(LogContent::Full, "a\tb"), "\"a\\tb\"");
+    }
+
+    #[test]
+    fn log_content_parses_case_insensitively() {
+        assert_eq!(LogContent::parse(" Full "), Some(LogContent::Full));
+        assert_eq!(LogContent::parse("truncated"), Some(LogContent::Truncated));
+        assert_eq!(LogContent::parse("OFF"), Some(LogContent::Off));
+        assert_eq!(LogContent::parse("redacted"), None);
+    }
+
+    #[test]
+    fn keys_added_later_are_scrubbed_too() {
+        add_api_key("startup-key-0123456789");
+        add_api_key("profile-key-9876543210");
+        add_api_key("");
+        let out = scrub("a startup-key-0123456789 b profile-key-9876543210 c");
+        assert!(!out.contains("key-0123456789"), "{}", out);
+        assert!(!out.contains("key-9876543210"), "{}", out);
+        assert!(out.starts_with("a ") && out.ends_with(" c"));
+    }
+}
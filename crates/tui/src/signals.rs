@@ -0,0 +1,60 @@
+// SIGINT, SIGTERM and SIGHUP from outside (kill, a closed terminal) end the
+// event loop through its normal exit, so state and a streaming reply are
+// saved. In raw mode Ctrl+C is a key press, not a signal, so the UI's own
+// Ctrl+C handling never reaches here.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use once_cell::sync::Lazy;
+use tracing::warn;
+
+// One flag per signal, so SIGINT can be discarded on its own
+static INTERRUPT: Lazy<Arc<AtomicBool>> = Lazy::new(|| Arc::new(AtomicBool::new(false)));
+static TERMINATE: Lazy<Arc<AtomicBool>> = Lazy::new(|| Arc::new(AtomicBool::new(false)));
+static HANGUP: Lazy<Arc<AtomicBool>> = Lazy::new(|| Arc::new(AtomicBool::new(false)));
+
+pub fn install() {
+    #[cfg(unix)]
+    for (sig, flag) in [
+        (signal_hook::consts::SIGINT, &INTERRUPT),
+        (signal_hook::consts::SIGTERM, &TERMINATE),
+        (signal_hook::consts::SIGHUP, &HANGUP),
+    ] {
+        if let Err(e) = signal_hook::flag::register(sig, Arc::clone(flag)) {
+            warn!(target: "tui", "signal {} handler: {}", sig, e);
+        }
+    }
+}
+
+// Whether any of the signals arrived since the last call.
+pub fn take() -> bool {
+    let int = INTERRUPT.swap(false, Ordering::Relaxed);
+    let term = TERMINATE.swap(false, Ordering::Relaxed);
+    let hup = HANGUP.swap(false, Ordering::Relaxed);
+    int || term || hup
+}
+
+// Forget a pending SIGINT only; SIGTERM and SIGHUP still end the loop.
+pub fn discard_interrupt() {
+    INTERRUPT.store(false, Ordering::Relaxed);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn discarding_sigint_keeps_sigterm_and_sighup() {
+        for flag in [&TERMINATE, &HANGUP] {
+            INTERRUPT.store(true, Ordering::Relaxed);
+            flag.store(true, Ordering::Relaxed);
+            discard_interrupt();
+            assert!(take());
+            assert!(!take(), "taken once");
+        }
+        INTERRUPT.store(true, Ordering::Relaxed);
+        discard_interrupt();
+        assert!(!take());
+    }
+}
@@ -1,172 +1,341 @@
 // Centralized UI strings and labels. ASCII-friendly by default.
 
-use unicode_width::UnicodeWidthStr;
-
 // Minimal, space‑efficient role prefixes (ASCII)
 // User messages: blue '|' prefix (render color applied in UI)
 pub const PREFIX_USER: &str = "| ";
 // Assistant messages: '>' prefix
 pub const PREFIX_ASSISTANT: &str = "> ";
-
-pub const INPUT_HINT: &str = "Type message, Enter to send / Shift+Enter for newline";
+// System prompts stored in the session: '~' prefix
+pub const PREFIX_SYSTEM: &str = "~ ";
+// Header marker for bookmarked messages (rendered after the role prefix)
+pub const BOOKMARK_MARKER: &str = "⚑ ";
 
 // UI block titles (keep surrounding spaces for visual padding)
 pub const TITLE_SESSIONS: &str = " Sessions ";
 pub const TITLE_CHAT: &str = " Chat ";
 pub const TITLE_INPUT: &str = " Input ";
+pub const TITLE_INPUT_READ_ONLY: &str = " Input (read-only) ";
 pub const TITLE_HELP: &str = " Help / Shortcuts ";
+pub const TITLE_CHANGELOG: &str = " Changelog ";
+pub const TITLE_LOG: &str = " Log ";
 pub const TITLE_SEARCH: &str = " Search ";
 pub const TITLE_RENAME: &str = " Rename Session ";
 pub const TITLE_CONFIRM: &str = " Confirm ";
+// Help on a terminal too small for the popup
+pub const INLINE_HELP_TOO_SMALL: &str = "Help needs a larger terminal (F1/Esc close)";
 pub const TITLE_CONTEXT: &str = " Context ";
+// Chat border note while new text is below a scrolled-up view (End jumps)
+pub const NEW_BELOW: &str = "↓ new";
 
-// Confirm messages
-pub fn confirm_delete_session_message(name: &str) -> String {
-    format!(
-        "Delete session \"{}\"? Press Y to confirm, N/Esc to cancel.",
-        name
-    )
+// Chat footer while lines are not wrapped
+pub fn no_wrap_label(hscroll: u16) -> String {
+    format!("no wrap · col {} · ←/→", hscroll as usize + 1)
+}
+// Chat footer for the selected message, e.g. "#12 · 1234 chars · ~310 tokens"
+pub fn message_stats_label(idx: usize, chars: usize, tokens: usize) -> String {
+    format!("#{} · {} chars · ~{} tokens", idx + 1, chars, tokens)
+}
+// Chat footer during a `v` selection
+pub fn visual_label(lines: usize) -> String {
+    let unit = if lines == 1 { "line" } else { "lines" };
+    format!("visual · {} {} · y copy · Esc cancel", lines, unit)
+}
+// Reasoning block header (expanded) and one-line stand-in (collapsed)
+pub const REASONING_HEADER: &str = "▾ thinking";
+pub fn reasoning_summary(chars: usize) -> String {
+    format!("▸ thinking ({} chars, palette: Toggle reasoning)", chars)
+}
+pub const TITLE_BOOKMARKS: &str = " Bookmarks ";
+pub const TITLE_TEMPLATES: &str = " Templates ";
+pub const TITLE_PROFILES: &str = " Profiles ";
+// Model (and wire) tag in the header of an assistant reply, with notes when
+// earlier context was left out of its request or it came from the cache
+pub fn model_label(
+    model: &str,
+    wire: Option<&str>,
+    dropped: Option<usize>,
+    cached: bool,
+) -> String {
+    let mut tag = match wire {
+        Some(w) => format!("[{} · {}", model, w),
+        None => format!("[{}", model),
+    };
+    if let Some(n) = dropped {
+        tag.push_str(&format!(" · {} dropped", n));
+    }
+    if cached {
+        tag.push_str(" · cached");
+    }
+    tag.push_str("] ");
+    tag
 }
 
-// Collapse/expand indicators for long messages
-pub fn indicator_expand(remaining: usize) -> String {
-    // Example: "Expand (12 more lines)"
-    format!("Expand ({} more lines)", remaining)
+// Search popup title with active match options, e.g. " Search [Aa] [Word] "
+pub const TITLE_SEARCH_ALL: &str = " Search all sessions ";
+pub const TITLE_CONFLICT: &str = " Session changed on disk ";
+// Conflict popup choices, in `ConflictChoice::ALL` order
+pub const CONFLICT_CHOICES: [(&str, &str); 3] = [
+    (
+        "[k] Keep mine",
+        "overwrite the file with this window's messages",
+    ),
+    ("[t] Take theirs", "reload from disk"),
+    ("[m] Merge", "keep messages from both, ordered by time"),
+];
+pub fn search_title(smart_case: bool, whole_word: bool, all_sessions: bool) -> String {
+    let mut s = if all_sessions {
+        TITLE_SEARCH_ALL
+    } else {
+        TITLE_SEARCH
+    }
+    .to_string();
+    s.push_str(if smart_case {
+        "[smart case] "
+    } else {
+        "[exact case] "
+    });
+    if whole_word {
+        s.push_str("[Word] ");
+    }
+    s
 }
 
-pub fn indicator_collapse(total: usize) -> String {
-    // Example: "Collapse (120 total lines)"
-    format!("Collapse ({} total lines)", total)
+// Rate-limit headroom, e.g. "RL:142req/90k tok"; None when nothing was reported.
+// Input-box notice for a newer release, e.g. "v0.3.0 available: /changelog".
+pub fn update_notice_label(version: &str) -> String {
+    format!("v{} available: /changelog", version)
 }
 
-// Status bar stick label
-pub const STICK_BOTTOM: &str = "Bottom";
+// Live output rate while a reply streams, e.g. "~42 tok/s"
+pub fn throughput_label(tokens_per_sec: f64) -> String {
+    format!("~{:.0} tok/s", tokens_per_sec)
+}
 
-pub fn stick_lines(n: u16) -> String {
-    // ASCII-friendly label; swap to Unicode variant if desired in future
-    format!("+{} lines", n)
+// Input border while a failed request is sent again, e.g. "retrying 2/5…"
+pub fn retry_label(attempt: u32, max_attempts: u32) -> String {
+    format!("retrying {}/{}…", attempt, max_attempts)
 }
 
-pub fn build_stick_label(scroll: u16) -> String {
-    if scroll == 0 {
-        STICK_BOTTOM.to_string()
-    } else {
-        stick_lines(scroll)
+pub fn rate_limit_label(requests: Option<u32>, tokens: Option<u32>) -> Option<String> {
+    let tok = tokens.map(|t| {
+        if t >= 1000 {
+            format!("{}k tok", t / 1000)
+        } else {
+            format!("{} tok", t)
+        }
+    });
+    match (requests, tok) {
+        (Some(r), Some(t)) => Some(format!("RL:{}req/{}", r, t)),
+        (Some(r), None) => Some(format!("RL:{}req", r)),
+        (None, Some(t)) => Some(format!("RL:{}", t)),
+        (None, None) => None,
     }
 }
 
-// Build the status bar line with width-aware compaction.
-// - stick: e.g., "Bottom" or "^12 lines"
-// - focus: e.g., "Input" or "Sessions"
-// - line_disp/col_disp: caret location (1-based display)
-// - history_len: input history length
-// - search_info: Some((query, current_index_1_based, total_hits))
-// - max_width: available width for the status text
-#[allow(clippy::too_many_arguments)]
-#[allow(clippy::too_many_arguments)]
-pub fn build_status_line(
-    stick: &str,
-    focus: &str,
-    line_disp: u16,
-    col_disp: u16,
-    history_len: usize,
-    context_len: usize,
-    provider: Option<(&str, &str, &str)>,
-    search_info: Option<(String, usize, usize)>,
-    max_width: u16,
-    usage: Option<(u32, u32)>,
-    temp: Option<f32>,
-    top_p: Option<f32>,
-    max_tokens: Option<u32>,
-) -> String {
-    let mut segments: Vec<String> = Vec::new();
-    // Put provider info first for higher visibility on narrow terminals
-    if let Some((prov, model, wire)) = provider {
-        segments.push(format!("[{}][{}][{}]", prov, model, wire));
-    }
-    segments.push(format!(
-        "[{}][{}] L{} C{}",
-        stick, focus, line_disp, col_disp
-    ));
-    segments.push(format!("Hist:{}", history_len));
-    segments.push(format!("Ctx:{}", context_len));
-    if let Some(t) = temp {
-        segments.push(format!("T:{:.1}", t));
+// Local time for message headers: "14:05" today, "2024-03-01 14:05" otherwise.
+pub fn format_timestamp(ts: i64) -> String {
+    use chrono::{Local, TimeZone};
+    let Some(t) = Local.timestamp_opt(ts, 0).single() else {
+        return String::new();
+    };
+    if t.date_naive() == Local::now().date_naive() {
+        t.format("%H:%M").to_string()
+    } else {
+        t.format("%Y-%m-%d %H:%M").to_string()
     }
-    if let Some(p) = top_p {
-        segments.push(format!("P:{:.1}", p));
+}
+
+// Age of `ts` at `now`: "now", "5m", "3h", "2d"; the date after 30 days.
+pub fn format_age(ts: i64, now: i64) -> String {
+    let secs = now.saturating_sub(ts).max(0);
+    match secs {
+        s if s < 60 => "now".to_string(),
+        s if s < 3600 => format!("{}m", s / 60),
+        s if s < 86_400 => format!("{}h", s / 3600),
+        s if s < 30 * 86_400 => format!("{}d", s / 86_400),
+        _ => {
+            use chrono::{Local, TimeZone};
+            Local
+                .timestamp_opt(ts, 0)
+                .single()
+                .map(|t| t.format("%Y-%m-%d").to_string())
+                .unwrap_or_default()
+        }
     }
-    if let Some(m) = max_tokens {
-        segments.push(format!("Max:{}", m));
+}
+
+// Message header time, config `timestamp_style = "relative"`: "2h ago".
+pub fn format_timestamp_relative(ts: i64, now: i64) -> String {
+    match format_age(ts, now) {
+        a if a == "now" => "just now".to_string(),
+        a if a.contains('-') => a,
+        a => format!("{} ago", a),
     }
-    if let Some((p, c)) = usage {
-        let t = p.saturating_add(c);
-        segments.push(format!("Tok:{}/{}/{}", p, c, t));
+}
+
+pub const STREAM_CANCELED: &str = "stream canceled · F5 to retry";
+pub const CONFIRM_QUIT: &str = "unsent input: press Esc again to quit";
+
+// Ctrl+R prompt, shell style: "(reverse-i-search)`foo': matched entry"
+pub fn history_search_prompt(query: &str, entry: Option<&str>) -> String {
+    match entry {
+        Some(e) => {
+            let first = e.lines().next().unwrap_or("");
+            let more = if e.contains('\n') { " …" } else { "" };
+            format!("(reverse-i-search)`{}': {}{}", query, first, more)
+        }
+        None => format!("(failed reverse-i-search)`{}': ", query),
     }
-    if let Some((q, cur, total)) = search_info {
-        segments.push(if total > 0 {
-            format!("Search:{} ({}/{})", q, cur, total)
-        } else {
-            format!("Search:{} (0/0)", q)
-        });
+}
+
+// Dimmed line under the input while a follow-up waits for the reply
+pub fn queued_line(text: &str) -> String {
+    let first = text.lines().next().unwrap_or("");
+    let more = if text.contains('\n') { " …" } else { "" };
+    format!("queued: {}{} · Esc to edit", first, more)
+}
+
+// Row under the input for a held paste, e.g.
+// "[pasted: 2,013 lines, ~8.1k tokens] — press Ctrl+D to drop"
+pub fn pasted_chip(lines: usize, tokens: usize) -> String {
+    let tokens = if tokens >= 1000 {
+        format!("{:.1}k", tokens as f64 / 1000.0)
+    } else {
+        tokens.to_string()
+    };
+    format!(
+        "[pasted: {} {}, ~{} tokens] — press Ctrl+D to drop",
+        group_digits(lines),
+        if lines == 1 { "line" } else { "lines" },
+        tokens
+    )
+}
+
+// 2013 -> "2,013"
+fn group_digits(n: usize) -> String {
+    let digits = n.to_string();
+    let mut out = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i) % 3 == 0 {
+            out.push(',');
+        }
+        out.push(c);
     }
-    // Hints ordered by importance; will be appended if space allows.
-    let hints: [&str; 7] = [
-        "Enter: send; Shift+Enter: newline",
-        "PgUp/PgDn: scroll; Shift+Pg: fast",
-        "Ctrl+Arrow: fine",
-        "F2: sessions",
-        "History: Up/Down",
-        "Ctrl+F: search; F3/Shift+F3: next/prev",
-        "?: help",
-    ];
-    for h in hints {
-        segments.push(h.to_string());
+    out
+}
+
+pub const QUEUED_RESTORED: &str = "the reply failed: queued message moved back to the input";
+
+// Notice for a failed reply: the error, a hint for its kind, how to retry.
+pub fn reply_error_notice(e: &fast_core::llm::ChatError) -> String {
+    match error_hint(e) {
+        Some(hint) => format!("{} · F5 to retry · {}", e, hint),
+        None => format!("{} · F5 to retry", e),
     }
+}
 
-    let sep = "  |  ";
-    let mut out = String::new();
-    let mut used = 0usize;
-    for (i, seg) in segments.iter().enumerate() {
-        let segw = UnicodeWidthStr::width(seg.as_str());
-        let addw = segw
-            + if i == 0 {
-                0
-            } else {
-                UnicodeWidthStr::width(sep)
-            };
-        if used + addw > max_width as usize {
-            break;
-        }
-        if i > 0 {
-            out.push_str(sep);
-            used += UnicodeWidthStr::width(sep);
+fn error_hint(e: &fast_core::llm::ChatError) -> Option<&'static str> {
+    use fast_core::llm::ChatError;
+    match e {
+        ChatError::Auth(_) => Some("check OPENAI_API_KEY (or the provider's key) and its access"),
+        ChatError::RateLimit(_) => Some("slow down and retry later, or raise the plan's limits"),
+        ChatError::Timeout(_) => Some("raise timeout_ms in config.toml if this repeats"),
+        ChatError::Network(_) => Some("check the connection and base_url"),
+        ChatError::Decode(_) | ChatError::Protocol(_) => {
+            Some("the endpoint may not support this wire; try /wire chat")
         }
-        out.push_str(seg);
-        used += segw;
+        ChatError::Canceled | ChatError::Other(_) => None,
     }
-    out
+}
+
+// Errors worth retrying as-is (shown as warnings, others as errors).
+pub fn error_is_transient(e: &fast_core::llm::ChatError) -> bool {
+    use fast_core::llm::ChatError;
+    matches!(
+        e,
+        ChatError::RateLimit(_)
+            | ChatError::Timeout(_)
+            | ChatError::Network(_)
+            | ChatError::Canceled
+    )
+}
+
+// Confirm messages
+pub fn confirm_delete_session_message(name: &str) -> String {
+    format!(
+        "Delete session \"{}\"? Press Y to confirm, N/Esc to cancel.",
+        name
+    )
+}
+
+pub fn confirm_large_paste_message(bytes: usize, lines: usize) -> String {
+    format!(
+        "Paste {} KB ({} lines) into the input? Press Y to insert, N/Esc to discard.",
+        bytes.div_ceil(1024),
+        lines
+    )
+}
+
+// Collapse/expand indicators for long messages
+pub fn indicator_expand(remaining: usize) -> String {
+    // Example: "Expand (12 more lines)"
+    format!("Expand ({} more lines)", remaining)
+}
+
+pub fn indicator_collapse(total: usize) -> String {
+    // Example: "Collapse (120 total lines)"
+    format!("Collapse ({} total lines)", total)
 }
 
 // ASCII help lines content; UI maps to styled lines.
-#[allow(dead_code)]
 pub fn help_lines_ascii() -> &'static [&'static str] {
     &[
         "Basic",
-        "  Enter: Send    Shift+Enter: Newline    Esc/Ctrl-C: Quit",
+        "  Enter: Send (queued while a reply streams)    Shift+Enter: Newline",
+        "  Esc: Edit the queued message, else stop the reply or quit when idle    Ctrl-C: Stop or quit",
         "Input Editing",
         "  Arrow: Move cursor    Backspace/Delete: Delete prev/next char",
-        "  Home/End: Line start/end    Ctrl+A/E: Line start/end",
-        "  Ctrl+Arrow: Word move    Ctrl+W: Delete prev word",
-        "  Ctrl+U/K: Kill to line start/end",
+        "  Home/End or Ctrl+A/E: Line start/end",
+        "  Ctrl+Arrow or Alt+B/F: Word move    Ctrl+W, Alt+Backspace or Ctrl+Backspace: Delete prev word    Alt+D: Delete next word",
+        "  Ctrl+U/K: Kill to line start/end    Ctrl+V: Paste clipboard (images become attachments)",
+        "  Very long pastes wait under the input and go out with the next message    Ctrl+D: Drop it",
+        "  Ctrl+Z or Ctrl+_: Undo input edit    Ctrl+Y or Ctrl+Shift+Z: Redo",
+        "  Ctrl+X (no selection): Edit the input in $VISUAL/$EDITOR; saving and quitting puts it back",
+        "  Shift+Arrow/Home/End: Select (Ctrl+Shift+Arrow by word)    Ctrl+C/X/V: Copy/cut/paste over it",
         "Chat Scrolling",
         "  Mouse wheel: Scroll    PgUp/PgDn: Page    Shift+PgUp/PgDn: Fast page    Ctrl+Arrow: Fine scroll    Click indicator: Expand/collapse",
-        "  Ctrl+Home/End: Top/bottom    Stick to bottom: Auto when at bottom",
+        "  Ctrl+Home/End: Top/bottom    End (chat focused): Jump to latest    Stick to bottom: Auto when at bottom    F7/F8: Expand/collapse all",
+        "  Tab to chat: Up/Down or j/k select message, Enter expand/collapse it, y copy it",
+        "  In chat: b or /branch: New session with the conversation up to the selected message",
+        "  In chat: Space/z fold or unfold the selected (or top) message, Z all of them",
+        "  In chat: v select lines (Up/Down/PgUp/PgDn extend, y copy, Esc cancel)",
+        "  Alt+Up/Down: Previous/next message to the top    Alt+U: Previous user message",
+        "  F9: Copy last code block    Shift+F9: Copy last assistant message",
+        "  F12: Tail of fast-tui.log (Up/Down/PgUp/PgDn scroll, End follow, Esc close)",
         "Sessions & Others",
-        "  F2: Show/hide sessions    Up/Down: Input history    Mouse click sidebar: Switch session",
-        "  Sidebar focus: N new / R rename / D or Delete remove",
+        "  F2: Show/hide sessions    Up/Down: Input history    Ctrl+R: Search input history    Mouse click sidebar: Switch session",
+        "  In chat: m bookmark the selected message    ': Bookmark list    /bookmarks: List as text",
+        "  Sidebar focus: N new / R rename / D or Delete remove / Alt+Up/Down move",
+        "  /export [md|json] [path][!]: Save session (default ~/Downloads/<session>-<date>.md; paths not ending in .md get JSON in the /import format; ! overwrites)    /import <path>: Load a JSON session",
+        "  /attach <path>: Attach a PNG/JPEG to the next message (needs a vision model)",
+        "  /template [name]: Insert a prompt from ~/.config/fast/templates/ ({{selection}} filled in)",
+        "  /profile [name]: Switch to a [profile.<name>] of config.toml (endpoint, key, model, wire); default: top level",
+        "  /bugreport [full|structure|none] <path.zip>: Bundle config/state/log (content stripped by default)",
+        "  /cache clear: Wipe cached replies (config cache = true reuses replies to identical requests)",
+        "  /compare m1,m2 <prompt>: Send one prompt to several models side by side",
+        "  F5 or /retry: Re-send a failed reply, else regenerate the last one    /undo: Restore the replaced reply",
+        "  /theme <dark|light>: Switch the color preset",
+        "  /dryrun [on|off]: Show the JSON each prompt would send instead of sending it",
+        "  /markdown on|off: Render Markdown in assistant replies (this session)",
+        "  /lineno on|off: Line numbers in fenced code blocks (with /markdown on)",
+        "  /wrap on|off: Wrap chat lines, or clip them (Left/Right scroll; this session)",
+        "  /collapse [preview|threshold <n>]: Tune folding of long messages",
+        "  /env [set KEY=VALUE | unset KEY]: Show or edit env for spawned processes",
+        "  /changelog: Notes of an available update (config update_check = true; d dismisses)",
         "Search",
         "  Ctrl+F: Search    F3: Next match    Shift+F3: Prev match",
+        "  In search: case is ignored unless the query has capitals    Alt+C exact case    Alt+W whole word",
+        "  Ctrl+Shift+F or palette \"Search all sessions\": Search saved sessions, Enter opens the hit",
         "Help",
         "  ?: Open/close this panel    F1: Open/close this panel",
     ]
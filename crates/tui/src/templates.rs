@@ -0,0 +1,79 @@
+// Prompt templates: one file per template in ~/.config/fast/templates/,
+// named by the file stem (`review.md` is `/template review`). The body is
+// inserted into the input with `{{selection}}` and `{{clipboard}}` filled in.
+
+use std::{fs, path::PathBuf};
+
+use directories::BaseDirs;
+use tracing::warn;
+
+#[derive(Clone, Debug)]
+pub struct Template {
+    pub name: String,
+    pub body: String,
+}
+
+pub fn templates_dir() -> Option<PathBuf> {
+    let base = BaseDirs::new()?;
+    Some(base.config_dir().join("fast").join("templates"))
+}
+
+// Every readable template, sorted by name. Hidden files and
+// subdirectories are skipped; a missing directory means no templates.
+pub fn load_all() -> Vec<Template> {
+    let Some(dir) = templates_dir() else {
+        return Vec::new();
+    };
+    let Ok(entries) = fs::read_dir(&dir) else {
+        return Vec::new();
+    };
+    let mut out = Vec::new();
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if !path.is_file() {
+            continue;
+        }
+        let Some(name) = path.file_stem().and_then(|s| s.to_str()) else {
+            continue;
+        };
+        if name.is_empty() || name.starts_with('.') {
+            continue;
+        }
+        match fs::read_to_string(&path) {
+            Ok(body) => out.push(Template {
+                name: name.to_string(),
+                // A trailing newline would end the input on an empty line
+                body: body.trim_end_matches(['\n', '\r']).to_string(),
+            }),
+            Err(e) => warn!(target: "tui", "template {} unreadable: {}", path.display(), e),
+        }
+    }
+    out.sort_by(|a, b| a.name.cmp(&b.name));
+    out
+}
+
+pub fn find(name: &str) -> Option<Template> {
+    load_all().into_iter().find(|t| t.name == name)
+}
+
+// Replace `{{var}}` placeholders with what `value` returns for them;
+// unknown names and unclosed braces stay as written.
+pub fn expand(body: &str, mut value: impl FnMut(&str) -> Option<String>) -> String {
+    let mut out = String::with_capacity(body.len());
+    let mut rest = body;
+    while let Some(open) = rest.find("{{") {
+        out.push_str(&rest[..open]);
+        let after = &rest[open + 2..];
+        let Some(close) = after.find("}}") else {
+            out.push_str(&rest[open..]);
+            return out;
+        };
+        match value(after[..close].trim()) {
+            Some(v) => out.push_str(&v),
+            None => out.push_str(&rest[open..open + 2 + close + 2]),
+        }
+        rest = &after[close + 2..];
+    }
+    out.push_str(rest);
+    out
+}
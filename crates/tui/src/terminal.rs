@@ -1,9 +1,10 @@
-use std::io::stdout;
+use std::io::{stdout, Write};
 
 use anyhow::Result;
+use base64::Engine;
 use crossterm::{
-    cursor::SetCursorStyle,
-    event::{DisableMouseCapture, EnableMouseCapture},
+    cursor::{SetCursorStyle, Show},
+    event::{DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste, EnableMouseCapture},
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
@@ -15,15 +16,8 @@ pub struct TerminalGuard {
 
 impl TerminalGuard {
     pub fn new() -> Result<Self> {
-        enable_raw_mode()?;
-        let mut out = stdout();
-        execute!(
-            out,
-            EnterAlternateScreen,
-            EnableMouseCapture,
-            SetCursorStyle::SteadyBar
-        )?;
-        let backend = CrosstermBackend::new(out);
+        resume()?;
+        let backend = CrosstermBackend::new(stdout());
         let terminal = Terminal::new(backend)?;
         Ok(Self { terminal })
     }
@@ -31,13 +25,71 @@ impl TerminalGuard {
 
 impl Drop for TerminalGuard {
     fn drop(&mut self) {
-        let _ = execute!(
-            self.terminal.backend_mut(),
-            LeaveAlternateScreen,
-            DisableMouseCapture,
-            SetCursorStyle::DefaultUserShape
-        );
-        let _ = self.terminal.show_cursor();
-        let _ = disable_raw_mode();
+        let _ = suspend();
     }
 }
+
+// Restore the terminal before the default hook prints a panic, so the
+// message and backtrace land on the normal screen in cooked mode. The
+// guard's `Drop` still runs while unwinding, as a backstop. Only panics on
+// the thread that installed the hook (the UI's) do this: a worker that
+// panics does not end the UI, which would go on drawing in cooked mode.
+pub fn install_panic_hook() {
+    let main_thread = std::thread::current().id();
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        if std::thread::current().id() == main_thread {
+            let _ = suspend();
+        }
+        default_hook(info);
+    }));
+}
+
+// Hand the terminal back as the shell had it: on exit, and while an
+// external editor runs.
+pub fn suspend() -> Result<()> {
+    execute!(
+        stdout(),
+        LeaveAlternateScreen,
+        DisableMouseCapture,
+        DisableBracketedPaste,
+        SetCursorStyle::DefaultUserShape,
+        Show
+    )?;
+    disable_raw_mode()?;
+    Ok(())
+}
+
+// Undo `suspend`; the caller redraws everything afterwards.
+pub fn resume() -> Result<()> {
+    enable_raw_mode()?;
+    execute!(
+        stdout(),
+        EnterAlternateScreen,
+        EnableMouseCapture,
+        // Pastes arrive as one Event::Paste instead of key presses, so
+        // pasted newlines cannot trigger a submit
+        EnableBracketedPaste,
+        SetCursorStyle::SteadyBar
+    )?;
+    Ok(())
+}
+
+// Terminals commonly drop OSC 52 sequences longer than this
+pub const OSC52_MAX_BYTES: usize = 100_000;
+
+// Set the terminal's clipboard with OSC 52 (ESC ] 52 ; c ; <base64> BEL);
+// works over SSH, where no display clipboard exists. Text whose encoding
+// would pass OSC52_MAX_BYTES is cut at a char boundary; returns the number
+// of bytes sent.
+pub fn osc52_copy(text: &str) -> std::io::Result<usize> {
+    let mut end = text.len().min(OSC52_MAX_BYTES / 4 * 3);
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    let data = base64::engine::general_purpose::STANDARD.encode(&text[..end]);
+    let mut out = stdout();
+    write!(out, "\x1b]52;c;{}\x07", data)?;
+    out.flush()?;
+    Ok(end)
+}
@@ -0,0 +1,154 @@
+// Shared fixtures for unit tests: a throwaway config/data dir per test
+// thread, App construction without the user's files, and TestBackend
+// rendering compared against golden files under `ui/snapshots/`.
+//
+// Snapshots are plain text, one line per terminal row with trailing blanks
+// trimmed. Run with `UPDATE_SNAPSHOTS=1` to write new or changed ones.
+
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use ratatui::{backend::TestBackend, buffer::Buffer, Terminal};
+use unicode_width::UnicodeWidthStr;
+
+use crate::app::{App, Message};
+
+// Points the persist paths of the current thread at a fresh temp dir until
+// dropped, so code under test can save sessions and state freely.
+pub struct TempRoot {
+    path: PathBuf,
+}
+
+impl TempRoot {
+    pub fn new() -> Self {
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+        let path = std::env::temp_dir().join(format!(
+            "fast-test-{}-{}",
+            std::process::id(),
+            NEXT.fetch_add(1, Ordering::Relaxed)
+        ));
+        let _ = std::fs::remove_dir_all(&path);
+        std::fs::create_dir_all(&path).expect("create test root");
+        crate::persist::TEST_ROOT.with(|r| *r.borrow_mut() = Some(path.clone()));
+        Self { path }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for TempRoot {
+    fn drop(&mut self) {
+        crate::persist::TEST_ROOT.with(|r| *r.borrow_mut() = None);
+        let _ = std::fs::remove_dir_all(&self.path);
+    }
+}
+
+// `App::blank` showing `messages` in a session named "test".
+pub fn app_with(messages: Vec<Message>) -> App {
+    let mut app = App::blank();
+    app.sessions = vec!["test".to_string()];
+    app.session_auto_named = vec![false];
+    app.messages = messages;
+    app
+}
+
+// Mixed-script conversation used by the rendering snapshots.
+pub fn sample_messages() -> Vec<Message> {
+    vec![
+        Message::user("Explain 文字化け and why 😀 takes two columns"),
+        Message::assistant(
+            "Mojibake (文字化け) is text decoded with the wrong charset.\n\n\
+             Emoji such as 😀 and CJK like 漢字 are double width:\n\n\
+             ```rust\nfn width(s: &str) -> usize {\n    s.width()\n}\n```\n\n\
+             Combining marks (e\u{301}) take no column of their own.",
+        ),
+        Message::user("Thanks!"),
+    ]
+}
+
+// Draw `app` once on a `width` x `height` TestBackend.
+pub fn render(app: &mut App, width: u16, height: u16) -> Buffer {
+    let mut terminal = Terminal::new(TestBackend::new(width, height)).expect("terminal");
+    terminal
+        .draw(|f| crate::ui::draw(f, app))
+        .expect("draw frame");
+    terminal.backend().buffer().clone()
+}
+
+// Rows of `buf` as text. Wide graphemes fill two cells; the blank cell after
+// them is skipped so every row reads as it looks on screen.
+pub fn buffer_text(buf: &Buffer) -> String {
+    let mut out = String::new();
+    for y in 0..buf.area.height {
+        let mut row = String::new();
+        let mut skip = 0usize;
+        for x in 0..buf.area.width {
+            let symbol = buf[(x, y)].symbol();
+            if skip > 0 {
+                skip -= 1;
+                continue;
+            }
+            row.push_str(symbol);
+            skip = UnicodeWidthStr::width(symbol).saturating_sub(1);
+        }
+        out.push_str(row.trim_end());
+        out.push('\n');
+    }
+    out
+}
+
+// Mask clock times ("12:34") and dates ("2025-01-31"), which depend on the
+// local time zone and the day the test runs.
+pub fn normalize(text: &str) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let digits = |at: usize, n: usize| {
+        at + n <= chars.len() && chars[at..at + n].iter().all(|c| c.is_ascii_digit())
+    };
+    let dash = |at: usize| chars.get(at) == Some(&'-');
+    let mut out = String::with_capacity(text.len());
+    let mut i = 0;
+    while i < chars.len() {
+        if digits(i, 4) && dash(i + 4) && digits(i + 5, 2) && dash(i + 7) && digits(i + 8, 2) {
+            out.push_str("YYYY-MM-DD");
+            i += 10;
+            continue;
+        }
+        if digits(i, 2) && chars.get(i + 2) == Some(&':') && digits(i + 3, 2) {
+            out.push_str("HH:MM");
+            i += 5;
+            continue;
+        }
+        out.push(chars[i]);
+        i += 1;
+    }
+    out
+}
+
+// Compare `actual` against `ui/snapshots/<name>.txt`.
+pub fn assert_snapshot(name: &str, actual: &str) {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("src/ui/snapshots")
+        .join(format!("{}.txt", name));
+    let actual = normalize(actual);
+    if std::env::var_os("UPDATE_SNAPSHOTS").is_some() {
+        std::fs::create_dir_all(path.parent().unwrap()).expect("create snapshot dir");
+        std::fs::write(&path, &actual).expect("write snapshot");
+        return;
+    }
+    let expected = std::fs::read_to_string(&path).unwrap_or_else(|_| {
+        panic!(
+            "missing snapshot {}; run with UPDATE_SNAPSHOTS=1 to create it\n{}",
+            path.display(),
+            actual
+        )
+    });
+    assert!(
+        expected == actual,
+        "snapshot {} changed; run with UPDATE_SNAPSHOTS=1 to accept\n--- expected\n{}--- actual\n{}",
+        name,
+        expected,
+        actual
+    );
+}
@@ -1,17 +1,150 @@
+use std::{fs, path::PathBuf, str::FromStr};
+
 use ratatui::style::Color;
+use serde::Deserialize;
+use tracing::warn;
 
+#[derive(Clone, Copy, Debug)]
 pub struct Theme {
     pub border_focus: Color,
     pub border_inactive: Color,
     pub chat_border: Color,
     pub sidebar_selected_fg: Color,
     pub sidebar_selected_bg: Color,
+    // Popup titles and inline prompts
+    pub popup_title: Color,
+    // Hints, descriptions and other secondary text
+    pub muted: Color,
+    // Current search match
+    pub highlight_fg: Color,
+    pub highlight_bg: Color,
+    // Inline and fenced code in rendered Markdown
+    pub code: Color,
+    // Background of the selected message (chat focus)
+    pub selection_bg: Color,
+    // Failed replies: auth/protocol errors, and retryable ones (rate limit,
+    // timeout, network)
+    pub error: Color,
+    pub warning: Color,
 }
 
-pub const THEME: Theme = Theme {
+// Tuned for dark backgrounds; theme.toml overrides individual fields.
+pub const DEFAULT_THEME: Theme = Theme {
     border_focus: Color::Cyan,
     border_inactive: Color::DarkGray,
     chat_border: Color::DarkGray,
     sidebar_selected_fg: Color::Black,
     sidebar_selected_bg: Color::Cyan,
+    popup_title: Color::Yellow,
+    muted: Color::DarkGray,
+    highlight_fg: Color::Black,
+    highlight_bg: Color::Yellow,
+    code: Color::LightGreen,
+    selection_bg: Color::Indexed(236),
+    error: Color::LightRed,
+    warning: Color::Yellow,
+};
+
+// For bright terminals: darker accents, mid grays that stay readable on white.
+pub const LIGHT_THEME: Theme = Theme {
+    border_focus: Color::Blue,
+    border_inactive: Color::Indexed(246),
+    chat_border: Color::Indexed(246),
+    sidebar_selected_fg: Color::White,
+    sidebar_selected_bg: Color::Blue,
+    popup_title: Color::Magenta,
+    muted: Color::Indexed(242),
+    highlight_fg: Color::Black,
+    highlight_bg: Color::LightYellow,
+    code: Color::Red,
+    selection_bg: Color::Indexed(254),
+    error: Color::Red,
+    warning: Color::Indexed(130),
 };
+
+// Built-in presets, selectable with `/theme`
+pub const PRESETS: [&str; 2] = ["dark", "light"];
+
+pub fn preset(name: &str) -> Option<Theme> {
+    match name.trim().to_lowercase().as_str() {
+        "dark" => Some(DEFAULT_THEME),
+        "light" => Some(LIGHT_THEME),
+        _ => None,
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        DEFAULT_THEME
+    }
+}
+
+// theme.toml: every field optional; values are color names ("dark gray",
+// "lightblue"), "#rrggbb" hex or 0-255 palette indices.
+#[derive(Default, Deserialize)]
+struct ThemeFile {
+    border_focus: Option<String>,
+    border_inactive: Option<String>,
+    chat_border: Option<String>,
+    sidebar_selected_fg: Option<String>,
+    sidebar_selected_bg: Option<String>,
+    popup_title: Option<String>,
+    muted: Option<String>,
+    highlight_fg: Option<String>,
+    highlight_bg: Option<String>,
+    code: Option<String>,
+    selection_bg: Option<String>,
+    error: Option<String>,
+    warning: Option<String>,
+}
+
+impl Theme {
+    // The named preset (dark when unknown) with theme.toml, next to
+    // config.toml, on top. Unknown colors are logged and keep the preset's.
+    pub fn load(preset_name: &str) -> Self {
+        let base = preset(preset_name).unwrap_or(DEFAULT_THEME);
+        let Some(text) = theme_path().and_then(|p| fs::read_to_string(p).ok()) else {
+            return base;
+        };
+        match toml::from_str::<ThemeFile>(&text) {
+            Ok(file) => Self::from_file(file, base),
+            Err(e) => {
+                warn!(target: "tui", "theme.toml ignored: {}", e);
+                base
+            }
+        }
+    }
+
+    fn from_file(file: ThemeFile, d: Theme) -> Self {
+        Self {
+            border_focus: color(file.border_focus, d.border_focus),
+            border_inactive: color(file.border_inactive, d.border_inactive),
+            chat_border: color(file.chat_border, d.chat_border),
+            sidebar_selected_fg: color(file.sidebar_selected_fg, d.sidebar_selected_fg),
+            sidebar_selected_bg: color(file.sidebar_selected_bg, d.sidebar_selected_bg),
+            popup_title: color(file.popup_title, d.popup_title),
+            muted: color(file.muted, d.muted),
+            highlight_fg: color(file.highlight_fg, d.highlight_fg),
+            highlight_bg: color(file.highlight_bg, d.highlight_bg),
+            code: color(file.code, d.code),
+            selection_bg: color(file.selection_bg, d.selection_bg),
+            error: color(file.error, d.error),
+            warning: color(file.warning, d.warning),
+        }
+    }
+}
+
+pub fn theme_path() -> Option<PathBuf> {
+    let config = providers::openai::config::OpenAiConfig::config_path()?;
+    Some(config.parent()?.join("theme.toml"))
+}
+
+fn color(value: Option<String>, default: Color) -> Color {
+    let Some(v) = value else {
+        return default;
+    };
+    Color::from_str(v.trim()).unwrap_or_else(|_| {
+        warn!(target: "tui", "theme.toml: unknown color '{}'", v);
+        default
+    })
+}
@@ -1,32 +1,55 @@
 use ratatui::{
     layout::{Constraint, Direction, Layout, Position, Rect},
     style::{Color, Modifier, Style},
-    text::{Line, Span},
+    text::{Line, Span, Text},
     widgets::{Block, Borders, Paragraph, Scrollbar, ScrollbarOrientation, ScrollbarState, Wrap},
     Frame,
 };
-use textwrap::wrap;
 use unicode_segmentation::UnicodeSegmentation;
-use unicode_width::UnicodeWidthStr;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
 
-use crate::app::{App, Role};
+use crate::app::search::grapheme_span;
+use crate::app::{App, NoticeLevel, Role};
+use crate::chat_layout;
+use crate::markdown::Emphasis;
 use crate::strings::{
-    build_status_line, build_stick_label, confirm_delete_session_message, help_lines_ascii,
-    indicator_collapse, indicator_expand, INPUT_HINT, PREFIX_ASSISTANT, PREFIX_USER, TITLE_CHAT,
-    TITLE_CONFIRM, TITLE_CONTEXT, TITLE_HELP, TITLE_INPUT, TITLE_RENAME, TITLE_SEARCH,
-    TITLE_SESSIONS,
+    confirm_delete_session_message, confirm_large_paste_message, format_age, format_timestamp,
+    format_timestamp_relative, help_lines_ascii, history_search_prompt, indicator_collapse,
+    indicator_expand, message_stats_label, no_wrap_label, rate_limit_label, retry_label,
+    search_title, throughput_label, update_notice_label, visual_label, BOOKMARK_MARKER,
+    CONFLICT_CHOICES, INLINE_HELP_TOO_SMALL, NEW_BELOW, PREFIX_ASSISTANT, PREFIX_SYSTEM,
+    PREFIX_USER, TITLE_BOOKMARKS, TITLE_CHANGELOG, TITLE_CHAT, TITLE_CONFIRM, TITLE_CONFLICT,
+    TITLE_CONTEXT, TITLE_HELP, TITLE_INPUT, TITLE_INPUT_READ_ONLY, TITLE_LOG, TITLE_PROFILES,
+    TITLE_RENAME, TITLE_SEARCH_ALL, TITLE_SESSIONS, TITLE_TEMPLATES,
 };
-use crate::theme::THEME;
+use crate::theme::Theme;
+
+const SIDEBAR_WIDTH: u16 = 26;
+const CONTEXT_WIDTH: u16 = 28;
 
 pub fn draw(f: &mut Frame, app: &mut App) {
-    // Layout: optional left sidebar (26), main, optional right context (28)
+    app.update_compact(f.area().width);
+    if app.compact {
+        draw_compact(f, app);
+    } else {
+        draw_columns(f, app);
+    }
+    draw_overlays(f, app);
+    let theme = app.theme;
+    if let Some(state) = &mut app.log_view {
+        draw_log_view(f, &theme, f.area(), state);
+    }
+}
+
+// Layout: optional left sidebar (26), main, optional right context (28)
+fn draw_columns(f: &mut Frame, app: &mut App) {
     let mut constraints: Vec<Constraint> = Vec::new();
     if app.show_sidebar {
-        constraints.push(Constraint::Length(26));
+        constraints.push(Constraint::Length(SIDEBAR_WIDTH));
     }
     constraints.push(Constraint::Min(10));
     if app.show_context {
-        constraints.push(Constraint::Length(28));
+        constraints.push(Constraint::Length(CONTEXT_WIDTH));
     }
     let chunks = Layout::default()
         .direction(Direction::Horizontal)
@@ -52,45 +75,116 @@ pub fn draw(f: &mut Frame, app: &mut App) {
     } else {
         app.context_area = None;
     }
+}
+
+// Narrow terminals: main takes the full width; sidebar and context open as
+// drawers over it while they have focus (Tab).
+fn draw_compact(f: &mut Frame, app: &mut App) {
+    let area = f.area();
+    draw_main(f, area, app);
+    app.sidebar_area = None;
+    app.context_area = None;
+    match app.focus {
+        crate::app::Focus::Sidebar if app.show_sidebar => {
+            let r = drawer_rect(area, SIDEBAR_WIDTH, false);
+            f.render_widget(Clear, r);
+            app.sidebar_area = Some(r);
+            draw_sidebar(f, r, app);
+        }
+        crate::app::Focus::Context if app.show_context => {
+            let r = drawer_rect(area, CONTEXT_WIDTH, true);
+            f.render_widget(Clear, r);
+            app.context_area = Some(r);
+            draw_context(f, r, app);
+        }
+        _ => {}
+    }
+}
+
+// Full-height strip along one edge, leaving a quarter of the chat visible.
+fn drawer_rect(area: Rect, width: u16, right: bool) -> Rect {
+    let w = width.min(area.width.saturating_mul(3) / 4).max(1);
+    Rect {
+        x: if right {
+            area.right().saturating_sub(w)
+        } else {
+            area.x
+        },
+        y: area.y,
+        width: w,
+        height: area.height,
+    }
+}
+
+fn panel_borders(app: &App) -> Borders {
+    if app.compact {
+        Borders::TOP
+    } else {
+        Borders::ALL
+    }
+}
 
+fn draw_overlays(f: &mut Frame, app: &App) {
     if let Some(state) = &app.rename {
-        draw_rename(f, f.area(), state);
+        draw_rename(f, &app.theme, f.area(), state);
     }
     if let Some(confirm) = &app.confirm {
         draw_confirm(f, f.area(), confirm, app);
     }
     if let Some(state) = &app.search_input {
-        draw_search(f, f.area(), state);
+        draw_search(f, f.area(), state, app);
+    }
+    if let (Some(state), Some(area)) = (&app.history_search, app.chat_area) {
+        draw_history_search(f, area, state, app);
     }
     if let Some(state) = &app.palette {
-        draw_palette(f, f.area(), state);
+        draw_palette(f, &app.theme, f.area(), state);
     }
     if let Some(state) = &app.model_picker {
-        draw_model_picker(f, f.area(), state);
+        draw_model_picker(f, &app.theme, f.area(), state);
     }
     if let Some(state) = &app.wire_picker {
-        draw_wire_picker(f, f.area(), state);
+        draw_wire_picker(f, &app.theme, f.area(), state);
     }
     if let Some(state) = &app.slash_picker {
-        draw_slash_picker(f, f.area(), state);
+        draw_slash_picker(f, &app.theme, f.area(), state);
+    }
+    if let Some(state) = &app.bookmark_picker {
+        draw_bookmark_picker(f, f.area(), state, app);
+    }
+    if let Some(state) = &app.template_picker {
+        draw_template_picker(f, &app.theme, f.area(), state);
+    }
+    if let Some(state) = &app.profile_picker {
+        draw_profile_picker(f, &app.theme, f.area(), state);
+    }
+    if let Some(state) = &app.global_search {
+        draw_global_search(f, &app.theme, f.area(), state);
+    }
+    if let Some(state) = &app.conflict {
+        draw_conflict(f, f.area(), state, app);
+    }
+    if let Some(state) = &app.changelog {
+        draw_changelog(f, f.area(), state, app);
     }
     if app.show_help {
-        draw_help(f, f.area());
+        draw_help(f, &app.theme, f.area());
     }
 }
 
 fn draw_sidebar(f: &mut Frame, area: Rect, app: &App) {
+    let theme = app.theme;
     let focused = matches!(app.focus, crate::app::Focus::Sidebar);
     let title = Span::styled(
         TITLE_SESSIONS,
         Style::default()
-            .fg(Color::Yellow)
+            .fg(theme.popup_title)
             .add_modifier(Modifier::BOLD),
     );
     let border_style = if focused {
-        Style::default().fg(THEME.border_focus)
+        Style::default().fg(theme.border_focus)
     } else {
-        Style::default().fg(THEME.border_inactive)
+        Style::default().fg(theme.border_inactive)
     };
     let block = Block::default()
         .title(title)
@@ -99,23 +193,43 @@ fn draw_sidebar(f: &mut Frame, area: Rect, app: &App) {
     let inner_h = area.height.saturating_sub(2) as usize;
     let start = app.sidebar_scroll as usize;
     let mut lines: Vec<Line> = Vec::new();
+    let now = crate::app::now_unix();
+    // Columns left of the scrollbar, when there is one
+    let text_width = area.width.saturating_sub(2) as usize
+        - usize::from(app.sessions.len() > inner_h && area.width > 2);
     for (i, s) in app.sessions.iter().enumerate().skip(start).take(inner_h) {
         let prefix = if i == app.current_session { "> " } else { "  " };
         let style = if i == app.current_session {
             if focused {
                 Style::default()
-                    .fg(THEME.sidebar_selected_fg)
-                    .bg(THEME.sidebar_selected_bg)
+                    .fg(theme.sidebar_selected_fg)
+                    .bg(theme.sidebar_selected_bg)
                     .add_modifier(Modifier::BOLD)
             } else {
                 Style::default()
-                    .fg(THEME.border_focus)
+                    .fg(theme.border_focus)
                     .add_modifier(Modifier::BOLD)
             }
         } else {
             Style::default()
         };
-        lines.push(Line::from(Span::styled(format!("{}{}", prefix, s), style)));
+        let name = format!("{}{}", prefix, s);
+        let mut spans = vec![Span::styled(name.clone(), style)];
+        // Last activity, right-aligned, when timestamps are shown and it fits
+        if let Some(ts) = app.session_activity.get(s).filter(|_| app.show_timestamps) {
+            let age = format_age(*ts, now);
+            let used = name.width() + age.width();
+            if used < text_width {
+                spans.push(Span::styled(" ".repeat(text_width - used), style));
+                let age_style = if i == app.current_session && focused {
+                    style
+                } else {
+                    style.fg(theme.muted)
+                };
+                spans.push(Span::styled(age, age_style));
+            }
+        }
+        lines.push(Line::from(spans));
     }
     if start >= app.sessions.len() {
         lines.clear();
@@ -139,8 +253,9 @@ fn draw_sidebar(f: &mut Frame, area: Rect, app: &App) {
 }
 
 fn draw_main(f: &mut Frame, area: Rect, app: &mut App) {
-    // Compute input visible lines based on available width (bordered input: inner width is area.width - 2)
-    let inner_width = area.width.saturating_sub(2) as usize;
+    // Compute input visible lines based on the input block's inner width
+    let (chrome_w, chrome_h) = app.panel_chrome();
+    let inner_width = area.width.saturating_sub(chrome_w) as usize;
     let input_total_lines = measure_total_lines(&app.input, inner_width as u16).max(1) as u16;
     let target_lines = input_total_lines.min(app.input_max_lines);
     let current = app.input_visible_lines.max(1);
@@ -152,36 +267,93 @@ fn draw_main(f: &mut Frame, area: Rect, app: &mut App) {
         current
     };
     // Ensure total height fits: only input border box (no extra status line)
-    let needed = new_visible + 2; // input border box height
+    let needed = new_visible + chrome_h; // input border box height
     if needed > area.height {
-        let clamped = area.height.max(3); // keep borders
-        new_visible = clamped.saturating_sub(2).max(1);
+        let clamped = area.height.max(chrome_h + 1); // keep borders
+        new_visible = clamped.saturating_sub(chrome_h).max(1);
     }
     app.input_visible_lines = new_visible;
-    let input_height = app.input_visible_lines + 2; // include borders
+    let input_height = app.input_visible_lines + chrome_h; // include borders
 
+    // The notice banner takes a row between chat and input while it lasts
+    let notice_height = u16::from(app.active_notice().is_some());
+    // and a held paste and the queued follow-up a row each under the input
+    let paste_height = u16::from(app.pending_paste.is_some());
+    let queued_height = u16::from(app.queued_input.is_some());
     let main_chunks = Layout::default()
         .direction(Direction::Vertical)
-        .constraints([Constraint::Min(0), Constraint::Length(input_height)])
+        .constraints([
+            Constraint::Min(0),
+            Constraint::Length(notice_height),
+            Constraint::Length(input_height),
+            Constraint::Length(paste_height),
+            Constraint::Length(queued_height),
+        ])
         .split(area);
 
     app.chat_area = Some(main_chunks[0]);
     draw_chat(f, main_chunks[0], app);
+    draw_notice(f, main_chunks[1], app);
     draw_input(
         f,
-        main_chunks[1],
+        main_chunks[2],
         app,
         app.input_visible_lines,
         inner_width as u16,
     );
+    draw_pasted(f, main_chunks[3], app);
+    draw_queued(f, main_chunks[4], app);
+}
+
+fn draw_pasted(f: &mut Frame, area: Rect, app: &App) {
+    let Some(p) = &app.pending_paste else {
+        return;
+    };
+    f.render_widget(
+        Paragraph::new(format!(
+            " {}",
+            crate::strings::pasted_chip(p.lines, p.tokens)
+        ))
+        .style(Style::default().fg(app.theme.border_focus)),
+        area,
+    );
+}
+
+fn draw_queued(f: &mut Frame, area: Rect, app: &App) {
+    let Some(text) = &app.queued_input else {
+        return;
+    };
+    f.render_widget(
+        Paragraph::new(format!(" {}", crate::strings::queued_line(text)))
+            .style(Style::default().fg(app.theme.muted)),
+        area,
+    );
+}
+
+fn draw_notice(f: &mut Frame, area: Rect, app: &App) {
+    let Some(notice) = app.active_notice() else {
+        return;
+    };
+    let theme = app.theme;
+    let color = match notice.level {
+        NoticeLevel::Info => theme.popup_title,
+        NoticeLevel::Warning => theme.warning,
+        NoticeLevel::Error => theme.error,
+    };
+    f.render_widget(
+        Paragraph::new(format!(" {}", notice.text))
+            .style(Style::default().fg(color).add_modifier(Modifier::REVERSED)),
+        area,
+    );
 }
 
 fn draw_context(f: &mut Frame, area: Rect, app: &mut App) {
+    let theme = app.theme;
     let focused = matches!(app.focus, crate::app::Focus::Context);
     let border_style = if focused {
-        Style::default().fg(THEME.border_focus)
+        Style::default().fg(theme.border_focus)
     } else {
-        Style::default().fg(THEME.border_inactive)
+        Style::default().fg(theme.border_inactive)
     };
     let block = Block::default()
         .title(TITLE_CONTEXT)
@@ -201,12 +373,12 @@ fn draw_context(f: &mut Frame, area: Rect, app: &mut App) {
         let style = if i == app.context_current {
             if focused {
                 Style::default()
-                    .fg(THEME.sidebar_selected_fg)
-                    .bg(THEME.sidebar_selected_bg)
+                    .fg(theme.sidebar_selected_fg)
+                    .bg(theme.sidebar_selected_bg)
                     .add_modifier(Modifier::BOLD)
             } else {
                 Style::default()
-                    .fg(THEME.border_focus)
+                    .fg(theme.border_focus)
                     .add_modifier(Modifier::BOLD)
             }
         } else {
@@ -236,172 +408,347 @@ fn draw_context(f: &mut Frame, area: Rect, app: &mut App) {
 }
 
 fn draw_chat(f: &mut Frame, area: Rect, app: &mut App) {
-    let block = Block::default()
+    let theme = app.theme;
+    let focused = matches!(app.focus, crate::app::Focus::Chat);
+    let border = if focused {
+        theme.border_focus
+    } else {
+        theme.chat_border
+    };
+    let mut block = Block::default()
         .title(TITLE_CHAT)
-        .borders(Borders::ALL)
-        .border_style(Style::default().fg(THEME.chat_border));
+        .borders(panel_borders(app))
+        .border_style(Style::default().fg(border));
+    app.selected_message = app.selected_message.filter(|i| *i < app.messages.len());
+    let selected = app.selected_message.filter(|_| focused);
+    let selected_style = Style::default().bg(theme.selection_bg);
 
-    let inner_width = area.width.saturating_sub(2);
-    let inner_height = area.height.saturating_sub(2);
+    let inner = app.panel_inner(area);
+    let (inner_width, inner_height) = (inner.width, inner.height);
     app.ensure_chat_wrapped(inner_width);
+    app.new_below &= !app.stick_to_bottom;
+    if app.new_below {
+        block = block.title_bottom(
+            Line::from(Span::styled(
+                format!(" {} ", NEW_BELOW),
+                Style::default().fg(theme.popup_title),
+            ))
+            .right_aligned(),
+        );
+    }
+    if !app.wrap_lines {
+        block = block.title_bottom(Line::from(Span::styled(
+            format!(" {} ", no_wrap_label(app.chat_hscroll)),
+            Style::default().fg(theme.muted),
+        )));
+    }
+    let visual = app.visual_range();
+    if let Some((first, last)) = visual {
+        block = block.title_bottom(Line::from(Span::styled(
+            format!(" {} ", visual_label(last - first + 1)),
+            Style::default().fg(theme.popup_title),
+        )));
+    } else if let Some(idx) = selected {
+        if let Some((chars, tokens)) = app.selected_message_stats() {
+            block = block.title_bottom(Line::from(Span::styled(
+                format!(" {} ", message_stats_label(idx, chars, tokens)),
+                Style::default().fg(theme.muted),
+            )));
+        }
+    }
+    let hscroll = if app.wrap_lines {
+        0
+    } else {
+        app.chat_hscroll as usize
+    };
 
-    let (viewport, _max_scroll, start_offset, _effective_total) =
-        app.compute_chat_layout(inner_height);
-    app.chat_viewport = viewport as u16;
-    let mut y_offset = start_offset;
+    // Scrolled up: keep the top line in place when the pane changes height
+    // (notice banner, growing input); the offset counts from the bottom
+    let viewport = inner_height.max(1) as usize;
+    if !app.stick_to_bottom && app.chat_viewport > 0 && viewport != app.chat_viewport {
+        app.chat_scroll = (app.chat_scroll + app.chat_viewport).saturating_sub(viewport);
+    }
+    let win = app.compute_chat_layout(inner_height);
+    app.chat_viewport = win.viewport;
+    let layout = app.chat_lines();
 
     let mut vis_lines: Vec<Line> = Vec::new();
-    let mut remaining = viewport;
 
     let current_hit = if app.search_hits.is_empty() {
         None
     } else {
         Some(app.search_hits[app.search_current].clone())
     };
-    for (idx, cached) in app.chat_cache.iter().enumerate() {
+    let now = crate::app::now_unix();
+    // Columns left of the scrollbar, when there is one
+    let text_width = inner_width as usize
+        - usize::from(chat_layout::total(&layout) > inner_height as usize && inner_width > 0);
+    for (row_idx, pos) in chat_layout::visible(&layout, win.start, win.viewport)
+        .into_iter()
+        .enumerate()
+    {
+        let idx = pos.msg;
+        let in_visual = visual.is_some_and(|(a, b)| (a..=b).contains(&(win.start + row_idx)));
+        let cached = &app.chat_cache[idx];
+        if pos.indicator {
+            let base = cached.lines.len();
+            let text = if app.collapsed.get(idx).copied().unwrap_or(false) {
+                indicator_expand(base - layout[idx].shown)
+            } else {
+                indicator_collapse(base)
+            };
+            let mut row = Line::from(Span::styled(text, Style::default().fg(theme.muted)));
+            if selected == Some(idx) {
+                row = row.patch_style(selected_style);
+            }
+            if in_visual {
+                row = row.patch_style(Style::default().add_modifier(Modifier::REVERSED));
+            }
+            vis_lines.push(row);
+            continue;
+        }
+        let i = pos.line;
+        let line = &cached.lines[i];
         let prefix = match cached.role {
             Role::User => PREFIX_USER,
             Role::Assistant => PREFIX_ASSISTANT,
+            Role::System => PREFIX_SYSTEM,
         };
         let header_style = match cached.role {
             Role::User => Style::default()
-                .fg(THEME.border_focus)
+                .fg(theme.border_focus)
                 .add_modifier(Modifier::BOLD),
             // Assistant: prefix uses default style (no special color or bold)
             Role::Assistant => Style::default(),
+            Role::System => Style::default()
+                .fg(theme.warning)
+                .add_modifier(Modifier::BOLD),
         };
         let body_style = match cached.role {
-            Role::User => Style::default().fg(THEME.border_focus),
+            Role::User => Style::default().fg(theme.border_focus),
             Role::Assistant => Style::default(),
+            // Instructions rather than conversation: dimmed
+            Role::System => Style::default().fg(theme.muted),
         };
-        let base = cached.lines.len();
-        let collapsed = app.collapsed.get(idx).copied().unwrap_or(false);
-        let preview = app.collapse_preview_lines;
-        let threshold = app.collapse_threshold_lines;
-        let (display_count, indicator): (usize, Option<String>) = if collapsed && base > preview {
-            (preview, Some(indicator_expand(base - preview)))
-        } else if !collapsed && base > threshold {
-            (base, Some(indicator_collapse(base)))
-        } else {
-            (base, None)
-        };
-        let effective = display_count + indicator.as_ref().map(|_| 1).unwrap_or(0);
-        if y_offset >= effective {
-            y_offset -= effective;
-            continue;
-        }
-        let start_i = y_offset.min(display_count);
-        for (i, line) in cached.lines.iter().enumerate().skip(start_i) {
-            if i >= display_count || remaining == 0 {
-                break;
-            }
 
-            let mut spans: Vec<Span> = Vec::new();
-            let (hl_start, hl_end) = if let Some(h) = &current_hit {
-                if h.msg_idx == idx && h.line_idx == i {
-                    (Some(h.start), Some(h.end))
-                } else {
-                    (None, None)
-                }
+        let mut spans: Vec<Span> = Vec::new();
+        let (hl_start, hl_end) = if let Some(h) = &current_hit {
+            if h.msg_idx == idx && h.line_idx == i {
+                let (s, e) = grapheme_span(line, h.start, h.end);
+                (Some(s), Some(e))
             } else {
                 (None, None)
-            };
-
-            let hb = if i == 0 {
-                // Use display width for header prefix boundary to support Unicode widths
-                UnicodeWidthStr::width(prefix).min(line.len())
+            }
+        } else {
+            (None, None)
+        };
+        let hb = if i == 0 {
+            // Header covers the role prefix plus the bookmark marker (byte offsets)
+            let marker = if cached.bookmarked {
+                BOOKMARK_MARKER
             } else {
-                0
+                ""
             };
-            let mut cuts = vec![0usize, line.len()];
-            if hb > 0 {
-                cuts.push(hb);
-            }
-            if let (Some(s), Some(e)) = (hl_start, hl_end) {
-                cuts.push(s.min(line.len()));
-                cuts.push(e.min(line.len()));
+            (prefix.len() + marker.len()).min(line.len())
+        } else {
+            0
+        };
+        // Reasoning block lines are dimmed
+        let body_style = if i < cached.reasoning_lines {
+            Style::default()
+                .fg(theme.muted)
+                .add_modifier(Modifier::ITALIC)
+        } else {
+            body_style
+        };
+        let md = cached.styles.get(i).map(Vec::as_slice).unwrap_or(&[]);
+        let mut cuts = vec![0usize, line.len()];
+        // Model/wire tag right after the header
+        let meta_end = if i == 0 {
+            (hb + cached.meta_len).min(line.len())
+        } else {
+            0
+        };
+        cuts.extend([hb, meta_end]);
+        for (s, e, _) in md {
+            cuts.push(*s);
+            cuts.push(*e);
+        }
+        if let (Some(s), Some(e)) = (hl_start, hl_end) {
+            cuts.extend([s, e]);
+        }
+        // Every slice must start and end between graphemes
+        for c in &mut cuts {
+            *c = grapheme_span(line, *c, *c).0;
+        }
+        cuts.sort_unstable();
+        cuts.dedup();
+        for w in cuts.windows(2) {
+            let a = w[0];
+            let b = w[1];
+            if a >= b {
+                continue;
             }
-            cuts.sort_unstable();
-            cuts.dedup();
-            for w in cuts.windows(2) {
-                let a = w[0];
-                let b = w[1];
-                if a >= b {
-                    continue;
-                }
-                let seg = &line[a..b];
-                let style = if let (Some(s), Some(e)) = (hl_start, hl_end) {
-                    if a < e && b > s {
-                        Style::default()
-                            .fg(Color::Black)
-                            .bg(Color::Yellow)
-                            .add_modifier(Modifier::BOLD)
-                    } else if a < hb {
-                        header_style
-                    } else {
-                        body_style
-                    }
-                } else if a < hb {
-                    header_style
+            let seg = &line[a..b];
+            let highlighted = matches!((hl_start, hl_end), (Some(s), Some(e)) if a < e && b > s);
+            let style = if highlighted {
+                Style::default()
+                    .fg(theme.highlight_fg)
+                    .bg(theme.highlight_bg)
+                    .add_modifier(Modifier::BOLD)
+            } else if a < hb {
+                header_style
+            } else if a < meta_end {
+                Style::default().fg(theme.muted)
+            } else {
+                markdown_style(body_style, md, a, &theme)
+            };
+            spans.push(Span::styled(seg.to_string(), style));
+        }
+        if i == 0 && app.show_timestamps {
+            if let Some(ts) = cached.timestamp {
+                let stamp = if app.relative_timestamps {
+                    format_timestamp_relative(ts, now)
                 } else {
-                    body_style
+                    format_timestamp(ts)
                 };
-                spans.push(Span::styled(seg.to_string(), style));
-            }
-            vis_lines.push(Line::from(spans));
-            remaining -= 1;
-            if remaining == 0 {
-                break;
+                // Right-aligned when the header leaves room, else appended
+                let used: usize = spans.iter().map(|s| s.content.width()).sum();
+                let gap = text_width.saturating_sub(used + stamp.width()).max(2);
+                spans.push(Span::styled(
+                    format!("{}{}", " ".repeat(gap), stamp),
+                    Style::default().fg(theme.muted),
+                ));
             }
         }
-        if remaining > 0 {
-            if let Some(text) = indicator.as_ref() {
-                if y_offset >= display_count
-                    || (display_count <= cached.lines.len()
-                        && start_i + (display_count - start_i) == display_count)
-                {
-                    vis_lines.push(Line::from(Span::styled(
-                        text.clone(),
-                        Style::default().fg(Color::DarkGray),
-                    )));
-                    remaining = remaining.saturating_sub(1);
-                }
-            }
+        if hscroll > 0 {
+            spans = skip_columns(spans, hscroll);
+        }
+        let mut row = Line::from(spans);
+        if selected == Some(idx) {
+            row = row.patch_style(selected_style);
         }
-        if remaining == 0 {
-            break;
+        if in_visual {
+            row = row.patch_style(Style::default().add_modifier(Modifier::REVERSED));
         }
-        y_offset = 0;
+        vis_lines.push(row);
     }
 
     let para = Paragraph::new(vis_lines).block(block);
     f.render_widget(para, area);
 
-    let inner = Rect {
-        x: area.x.saturating_add(1),
-        y: area.y.saturating_add(1),
-        width: area.width.saturating_sub(2),
-        height: area.height.saturating_sub(2),
-    };
-    let effective_total = app.effective_total_lines();
+    let effective_total = chat_layout::total(&layout);
     if effective_total > inner.height as usize {
-        let mut sb_state = ScrollbarState::new(effective_total).position(start_offset);
+        let mut sb_state = ScrollbarState::new(effective_total).position(win.start);
         let sb = Scrollbar::default().orientation(ScrollbarOrientation::VerticalRight);
         f.render_stateful_widget(sb, inner, &mut sb_state);
     }
 }
 
+// Drop a row's first `cols` display columns (unwrapped chat scrolled right);
+// a wide character cut in half leaves a blank in its place.
+fn skip_columns(spans: Vec<Span<'_>>, cols: usize) -> Vec<Span<'_>> {
+    let mut skip = cols;
+    let mut out = Vec::with_capacity(spans.len());
+    for span in spans {
+        let w = span.content.width();
+        if skip == 0 {
+            out.push(span);
+        } else if w <= skip {
+            skip -= w;
+        } else {
+            let mut text = String::new();
+            for ch in span.content.chars() {
+                let cw = ch.width().unwrap_or(0);
+                if skip == 0 {
+                    text.push(ch);
+                } else if cw > skip {
+                    // Keeps the rest of the row in its columns
+                    text.extend(std::iter::repeat(' ').take(cw - skip));
+                    skip = 0;
+                } else {
+                    skip -= cw;
+                }
+            }
+            out.push(Span::styled(text, span.style));
+        }
+    }
+    out
+}
+
+// Body style with the Markdown emphasis covering byte `at`, if any.
+fn markdown_style(base: Style, md: &[(usize, usize, Emphasis)], at: usize, theme: &Theme) -> Style {
+    let Some((_, _, e)) = md.iter().find(|(s, e, _)| at >= *s && at < *e) else {
+        return base;
+    };
+    match e {
+        Emphasis::Bold => base.add_modifier(Modifier::BOLD),
+        Emphasis::Italic => base.add_modifier(Modifier::ITALIC),
+        Emphasis::Code => base.fg(theme.code),
+        Emphasis::Heading => base.fg(theme.border_focus).add_modifier(Modifier::BOLD),
+        Emphasis::Fence => base.fg(theme.muted),
+    }
+}
+
 fn draw_input(f: &mut Frame, area: Rect, app: &App, input_visible_lines: u16, inner_width: u16) {
-    let focused = matches!(app.focus, crate::app::Focus::Input);
+    let theme = app.theme;
+    let focused = matches!(app.focus, crate::app::Focus::Input) && !app.read_only;
     let border_style = if focused {
-        Style::default().fg(THEME.border_focus)
+        Style::default().fg(theme.border_focus)
     } else {
-        Style::default().fg(THEME.border_inactive)
+        Style::default().fg(theme.border_inactive)
     };
-    let block = Block::default()
-        .title(TITLE_INPUT)
-        .borders(Borders::ALL)
+    let title = if app.read_only {
+        TITLE_INPUT_READ_ONLY.to_string()
+    } else if app.pending_attachments.is_empty() {
+        TITLE_INPUT.to_string()
+    } else {
+        format!("{}{} ", TITLE_INPUT, app.attachment_chips())
+    };
+    let mut block = Block::default()
+        .title(title)
+        .borders(panel_borders(app))
         .border_style(border_style);
+    if let Some((_, attempt, max)) = app.stream_retry {
+        block = block.title(
+            Line::from(Span::styled(
+                format!(" {} ", retry_label(attempt, max)),
+                Style::default().fg(theme.warning),
+            ))
+            .right_aligned(),
+        );
+    }
+    if let Some(rate) = app.stream_rate() {
+        block = block.title(
+            Line::from(Span::styled(
+                format!(" {} ", throughput_label(rate)),
+                Style::default().fg(theme.muted),
+            ))
+            .right_aligned(),
+        );
+    }
+    // Rate-limit headroom from the last turn, right side of the input border
+    if let Some(rl) = app
+        .rate_info
+        .and_then(|r| rate_limit_label(r.remaining_requests, r.remaining_tokens))
+    {
+        block = block.title(
+            Line::from(Span::styled(
+                format!(" {} ", rl),
+                Style::default().fg(theme.muted),
+            ))
+            .right_aligned(),
+        );
+    }
+    if let Some(rel) = &app.update_notice {
+        block = block.title_bottom(
+            Line::from(Span::styled(
+                format!(" {} ", update_notice_label(&rel.version)),
+                Style::default().fg(theme.popup_title),
+            ))
+            .right_aligned(),
+        );
+    }
     let graphemes: Vec<&str> = app.input.graphemes(true).collect();
     let upto = app.input_cursor.min(graphemes.len());
     let cursor_line_idx = measure_prefix_line(&graphemes, upto, inner_width) as u16;
@@ -414,77 +761,179 @@ fn draw_input(f: &mut Frame, area: Rect, app: &App, input_visible_lines: u16, in
             .wrap(Wrap { trim: false })
             .scroll((0, 0))
     } else {
-        Paragraph::new(app.input.clone())
+        let text_style = if app.read_only {
+            Style::default().fg(theme.muted)
+        } else {
+            Style::default()
+        };
+        let text = match app.input_selection() {
+            Some(sel) => selection_text(&graphemes, sel),
+            None => Text::from(app.input.clone()),
+        };
+        Paragraph::new(text)
+            .style(text_style)
             .block(block)
             .wrap(Wrap { trim: false })
             .scroll((offset_y, 0))
     };
     f.render_widget(para, area);
 
-    let x0 = area.x + 1;
-    let y0 = area.y + 1;
+    let inner = app.panel_inner(area);
     let (line_idx, col_width) = measure_prefix_line_col(&graphemes, upto, inner_width);
     if focused {
-        let cursor_x = x0 + col_width;
-        let cursor_y = y0 + line_idx.saturating_sub(offset_y);
-        f.set_cursor_position(Position::new(cursor_x, cursor_y));
+        let cursor_x = inner.x.saturating_add(col_width);
+        let cursor_y = inner.y.saturating_add(line_idx.saturating_sub(offset_y));
+        set_cursor_within(f, inner, cursor_x, cursor_y);
     }
 }
 
-fn draw_status(f: &mut Frame, area: Rect, app: &App, _input_visible_lines: u16, inner_width: u16) {
-    let stick = build_stick_label(app.chat_scroll);
-
-    let graphemes: Vec<&str> = app.input.graphemes(true).collect();
-    let upto = app.input_cursor.min(graphemes.len());
-    let prefix: String = graphemes[..upto].concat();
-    let wrapped = wrap(&prefix, inner_width.max(1) as usize);
-    let (line_idx, col_width) = if wrapped.is_empty() {
-        (0u16, 0u16)
+// The input with graphemes start..end in reverse video, split into lines so
+// the selection survives wrapping.
+fn selection_text(graphemes: &[&str], (start, end): (usize, usize)) -> Text<'static> {
+    let selected = Style::default().add_modifier(Modifier::REVERSED);
+    let mut lines = Vec::new();
+    let mut spans: Vec<Span<'static>> = Vec::new();
+    let mut run = String::new();
+    let mut run_selected = false;
+    for (i, g) in graphemes.iter().enumerate() {
+        let in_sel = (start..end).contains(&i);
+        if in_sel != run_selected && !run.is_empty() {
+            let style = if run_selected {
+                selected
+            } else {
+                Style::default()
+            };
+            spans.push(Span::styled(std::mem::take(&mut run), style));
+        }
+        run_selected = in_sel;
+        if *g == "\n" {
+            let style = if run_selected {
+                selected
+            } else {
+                Style::default()
+            };
+            spans.push(Span::styled(std::mem::take(&mut run), style));
+            lines.push(Line::from(std::mem::take(&mut spans)));
+        } else {
+            run.push_str(g);
+        }
+    }
+    let style = if run_selected {
+        selected
     } else {
-        let last = wrapped.last().unwrap().as_ref();
-        let w = UnicodeWidthStr::width(last) as u16;
-        ((wrapped.len() - 1) as u16, w)
-    };
-    let line_disp = line_idx + 1;
-    let col_disp = col_width + 1;
-
-    let focus = match app.focus {
-        crate::app::Focus::Input => "Input",
-        crate::app::Focus::Sidebar => "Sessions",
-        crate::app::Focus::Context => "Context",
-    };
-    let tips = build_status_line(
-        &stick,
-        focus,
-        line_disp,
-        col_disp,
-        app.history.len(),
-        app.context_items.len(),
-        Some(("OpenAI", &app.model_label, &app.wire_label)),
-        app.search_query
-            .as_ref()
-            .map(|q| (q.clone(), app.search_current + 1, app.search_hits.len())),
-        area.width.saturating_sub(2),
-        app.usage_prompt_tokens.zip(app.usage_completion_tokens),
-        app.temperature,
-        app.top_p,
-        app.max_tokens,
-    );
-    let help = Span::styled(tips, Style::default().fg(Color::DarkGray));
-    let info = Line::from(vec![help]);
-    let para = Paragraph::new(info);
-    f.render_widget(para, area);
+        Style::default()
+    };
+    spans.push(Span::styled(run, style));
+    lines.push(Line::from(spans));
+    Text::from(lines)
 }
 
 use ratatui::widgets::Clear;
 
-fn draw_help(f: &mut Frame, area: Rect) {
-    let popup_area = centered_rect(70, 70, area);
+fn draw_changelog(f: &mut Frame, area: Rect, state: &crate::app::ChangelogState, app: &App) {
+    let theme = app.theme;
+    let Some(rel) = &app.update_notice else {
+        return;
+    };
+    let Some(popup_area) = popup_rect(area, 70, 70, (40, 8)) else {
+        let text = format!("Update v{} available (Esc close)", rel.version);
+        return draw_inline_prompt(f, &theme, area, &text, None);
+    };
+    let block = Block::default()
+        .title(Span::styled(
+            TITLE_CHANGELOG,
+            Style::default()
+                .fg(theme.popup_title)
+                .add_modifier(Modifier::BOLD),
+        ))
+        .title_bottom(Line::from(" Esc close  d dismiss notice ").right_aligned())
+        .borders(Borders::ALL);
+    let mut lines = vec![
+        Line::from(Span::styled(
+            format!(
+                "v{} (running v{})",
+                rel.version,
+                crate::update::CURRENT_VERSION
+            ),
+            Style::default().add_modifier(Modifier::BOLD),
+        )),
+        Line::from(""),
+    ];
+    lines.extend(rel.notes.lines().map(|l| Line::from(l.to_string())));
+    let para = Paragraph::new(lines)
+        .block(block)
+        .wrap(Wrap { trim: false })
+        .scroll((state.scroll, 0));
+    f.render_widget(Clear, popup_area);
+    f.render_widget(para, popup_area);
+}
+
+// Over everything else; records its height so paging matches what is shown.
+fn draw_log_view(
+    f: &mut Frame,
+    theme: &Theme,
+    area: Rect,
+    state: &mut crate::app::logview::LogViewState,
+) {
+    let Some(popup_area) = popup_rect(area, 90, 80, (40, 8)) else {
+        state.viewport = 1;
+        let last = state.lines.last().map_or("", |l| l.as_str());
+        return draw_inline_prompt(f, theme, area, last, None);
+    };
+    state.viewport = popup_area.height.saturating_sub(2) as usize;
+    let top = state.top();
+    let follow = if state.scroll.is_none() {
+        " following "
+    } else {
+        " End follow "
+    };
+    let block = Block::default()
+        .title(Span::styled(
+            TITLE_LOG,
+            Style::default()
+                .fg(theme.popup_title)
+                .add_modifier(Modifier::BOLD),
+        ))
+        .title_bottom(Line::from(format!("{} Esc close ", follow)).right_aligned())
+        .borders(Borders::ALL);
+    let lines: Vec<Line> = match &state.error {
+        Some(e) => vec![Line::from(Span::styled(
+            e.clone(),
+            Style::default().fg(theme.muted),
+        ))],
+        None => state
+            .lines
+            .iter()
+            .skip(top)
+            .take(state.viewport)
+            .map(|l| Line::from(l.clone()))
+            .collect(),
+    };
+    f.render_widget(Clear, popup_area);
+    f.render_widget(Paragraph::new(lines).block(block), popup_area);
+    let inner = Rect {
+        x: popup_area.x,
+        y: popup_area.y.saturating_add(1),
+        width: popup_area.width,
+        height: popup_area.height.saturating_sub(2),
+    };
+    if state.lines.len() > state.viewport {
+        let mut sb_state =
+            ScrollbarState::new(state.lines.len().saturating_sub(state.viewport)).position(top);
+        let sb = Scrollbar::default().orientation(ScrollbarOrientation::VerticalRight);
+        f.render_stateful_widget(sb, inner, &mut sb_state);
+    }
+}
+
+fn draw_help(f: &mut Frame, theme: &Theme, area: Rect) {
+    let Some(popup_area) = popup_rect(area, 70, 70, (40, 10)) else {
+        return draw_inline_prompt(f, theme, area, INLINE_HELP_TOO_SMALL, None);
+    };
     let block = Block::default()
         .title(Span::styled(
             TITLE_HELP,
             Style::default()
-                .fg(Color::Yellow)
+                .fg(theme.popup_title)
                 .add_modifier(Modifier::BOLD),
         ))
         .borders(Borders::ALL);
@@ -520,14 +969,17 @@ fn draw_help(f: &mut Frame, area: Rect) {
     f.render_widget(para, popup_area);
 }
 
-fn draw_palette(f: &mut Frame, area: Rect, state: &crate::app::PaletteState) {
-    use unicode_width::UnicodeWidthStr;
-    let popup_area = centered_rect(60, 60, area);
+fn draw_palette(f: &mut Frame, theme: &Theme, area: Rect, state: &crate::app::PaletteState) {
+    let Some(popup_area) = popup_rect(area, 60, 60, (30, 8)) else {
+        let text = format!(">> {}", state.buffer);
+        let col = 3 + buffer_width(&state.buffer, state.cursor);
+        return draw_inline_prompt(f, theme, area, &text, Some(col));
+    };
     let block = Block::default()
         .title(Span::styled(
             " Command Palette ",
             Style::default()
-                .fg(Color::Yellow)
+                .fg(theme.popup_title)
                 .add_modifier(Modifier::BOLD),
         ))
         .borders(Borders::ALL);
@@ -539,8 +991,8 @@ fn draw_palette(f: &mut Frame, area: Rect, state: &crate::app::PaletteState) {
         let sel = i == state.selected;
         let style = if sel {
             Style::default()
-                .fg(THEME.sidebar_selected_fg)
-                .bg(THEME.sidebar_selected_bg)
+                .fg(theme.sidebar_selected_fg)
+                .bg(theme.sidebar_selected_bg)
                 .add_modifier(Modifier::BOLD)
         } else {
             Style::default()
@@ -553,28 +1005,28 @@ fn draw_palette(f: &mut Frame, area: Rect, state: &crate::app::PaletteState) {
     f.render_widget(Clear, popup_area);
     f.render_widget(para, popup_area);
     // place cursor after prompt
-    let cursor_x = popup_area.x
-        + 3
-        + UnicodeWidthStr::width(
-            state
-                .buffer
-                .graphemes(true)
-                .take(state.cursor)
-                .collect::<String>()
-                .as_str(),
-        ) as u16;
-    let cursor_y = popup_area.y + 1;
-    f.set_cursor_position(Position::new(cursor_x, cursor_y));
-}
-
-fn draw_model_picker(f: &mut Frame, area: Rect, state: &crate::app::ModelPickerState) {
-    use unicode_width::UnicodeWidthStr;
-    let popup_area = centered_rect(60, 60, area);
+    let cursor_x = popup_area
+        .x
+        .saturating_add(4)
+        .saturating_add(buffer_width(&state.buffer, state.cursor));
+    set_cursor_in_block(f, popup_area, cursor_x, popup_area.y + 1);
+}
+
+fn draw_model_picker(
+    f: &mut Frame,
+    theme: &Theme,
+    area: Rect,
+    state: &crate::app::ModelPickerState,
+) {
+    let Some(popup_area) = popup_rect(area, 60, 60, (30, 8)) else {
+        let text = picker_inline_text("model", &state.buffer, state.filtered.get(state.selected));
+        return draw_inline_prompt(f, theme, area, &text, None);
+    };
     let block = Block::default()
         .title(Span::styled(
             " Select Model ",
             Style::default()
-                .fg(Color::Yellow)
+                .fg(theme.popup_title)
                 .add_modifier(Modifier::BOLD),
         ))
         .borders(Borders::ALL);
@@ -586,8 +1038,8 @@ fn draw_model_picker(f: &mut Frame, area: Rect, state: &crate::app::ModelPickerS
         let sel = i == state.selected;
         let style = if sel {
             Style::default()
-                .fg(THEME.sidebar_selected_fg)
-                .bg(THEME.sidebar_selected_bg)
+                .fg(theme.sidebar_selected_fg)
+                .bg(theme.sidebar_selected_bg)
                 .add_modifier(Modifier::BOLD)
         } else {
             Style::default()
@@ -603,13 +1055,16 @@ fn draw_model_picker(f: &mut Frame, area: Rect, state: &crate::app::ModelPickerS
     f.render_widget(para, popup_area);
 }
 
-fn draw_wire_picker(f: &mut Frame, area: Rect, state: &crate::app::WirePickerState) {
-    let popup_area = centered_rect(40, 40, area);
+fn draw_wire_picker(f: &mut Frame, theme: &Theme, area: Rect, state: &crate::app::WirePickerState) {
+    let Some(popup_area) = popup_rect(area, 40, 40, (24, 7)) else {
+        let text = picker_inline_text("wire", &state.buffer, state.filtered.get(state.selected));
+        return draw_inline_prompt(f, theme, area, &text, None);
+    };
     let block = Block::default()
         .title(Span::styled(
             " Select Wire ",
             Style::default()
-                .fg(Color::Yellow)
+                .fg(theme.popup_title)
                 .add_modifier(Modifier::BOLD),
         ))
         .borders(Borders::ALL);
@@ -620,8 +1075,8 @@ fn draw_wire_picker(f: &mut Frame, area: Rect, state: &crate::app::WirePickerSta
         let sel = i == state.selected;
         let style = if sel {
             Style::default()
-                .fg(THEME.sidebar_selected_fg)
-                .bg(THEME.sidebar_selected_bg)
+                .fg(theme.sidebar_selected_fg)
+                .bg(theme.sidebar_selected_bg)
                 .add_modifier(Modifier::BOLD)
         } else {
             Style::default()
@@ -636,14 +1091,22 @@ fn draw_wire_picker(f: &mut Frame, area: Rect, state: &crate::app::WirePickerSta
     f.render_widget(para, popup_area);
 }
 
-fn draw_slash_picker(f: &mut Frame, area: Rect, state: &crate::app::SlashPickerState) {
-    use unicode_width::UnicodeWidthStr;
-    let popup_area = centered_rect(60, 40, area);
+fn draw_slash_picker(
+    f: &mut Frame,
+    theme: &Theme,
+    area: Rect,
+    state: &crate::app::SlashPickerState,
+) {
+    let Some(popup_area) = popup_rect(area, 60, 40, (30, 6)) else {
+        let text = format!("/{}", state.buffer);
+        let col = 1 + buffer_width(&state.buffer, state.cursor);
+        return draw_inline_prompt(f, theme, area, &text, Some(col));
+    };
     let block = Block::default()
         .title(Span::styled(
             " Commands ",
             Style::default()
-                .fg(Color::Yellow)
+                .fg(theme.popup_title)
                 .add_modifier(Modifier::BOLD),
         ))
         .borders(Borders::ALL);
@@ -654,8 +1117,8 @@ fn draw_slash_picker(f: &mut Frame, area: Rect, state: &crate::app::SlashPickerS
         let sel = i == state.selected;
         let style = if sel {
             Style::default()
-                .fg(THEME.sidebar_selected_fg)
-                .bg(THEME.sidebar_selected_bg)
+                .fg(theme.sidebar_selected_fg)
+                .bg(theme.sidebar_selected_bg)
                 .add_modifier(Modifier::BOLD)
         } else {
             Style::default()
@@ -663,7 +1126,7 @@ fn draw_slash_picker(f: &mut Frame, area: Rect, state: &crate::app::SlashPickerS
         lines.push(Line::from(vec![
             Span::styled(format!("/{}", cmd), style),
             Span::raw("  "),
-            Span::styled(desc.clone(), Style::default().fg(Color::DarkGray)),
+            Span::styled(desc.clone(), Style::default().fg(theme.muted)),
         ]));
     }
     let para = Paragraph::new(lines)
@@ -672,62 +1135,393 @@ fn draw_slash_picker(f: &mut Frame, area: Rect, state: &crate::app::SlashPickerS
     f.render_widget(Clear, popup_area);
     f.render_widget(para, popup_area);
     // place cursor after "/"
-    let cursor_x = popup_area.x
-        + 1
-        + UnicodeWidthStr::width(
-            state
-                .buffer
-                .graphemes(true)
-                .take(state.cursor)
-                .collect::<String>()
-                .as_str(),
-        ) as u16;
-    let cursor_y = popup_area.y + 1;
-    f.set_cursor_position(Position::new(cursor_x, cursor_y));
+    let cursor_x = popup_area
+        .x
+        .saturating_add(2)
+        .saturating_add(buffer_width(&state.buffer, state.cursor));
+    set_cursor_in_block(f, popup_area, cursor_x, popup_area.y + 1);
+}
+
+fn draw_bookmark_picker(
+    f: &mut Frame,
+    area: Rect,
+    state: &crate::app::BookmarkPickerState,
+    app: &App,
+) {
+    let theme = app.theme;
+    let Some(popup_area) = popup_rect(area, 70, 50, (40, 8)) else {
+        let label = state
+            .items
+            .get(state.selected)
+            .map(|i| app.bookmark_label(*i));
+        let text = picker_inline_text("bookmark", "", label.as_ref());
+        return draw_inline_prompt(f, &theme, area, &text, None);
+    };
+    let block = Block::default()
+        .title(Span::styled(
+            TITLE_BOOKMARKS,
+            Style::default()
+                .fg(theme.popup_title)
+                .add_modifier(Modifier::BOLD),
+        ))
+        .borders(Borders::ALL);
+    let mut lines: Vec<Line> = Vec::new();
+    if state.items.is_empty() {
+        lines.push(Line::from(Span::styled(
+            "No bookmarks. Ctrl+B bookmarks the message at the top of the chat.",
+            Style::default().fg(theme.muted),
+        )));
+    }
+    let max_list = popup_area.height.saturating_sub(2) as usize;
+    let skip = state.selected.saturating_sub(max_list.saturating_sub(1));
+    for (i, idx) in state.items.iter().enumerate().skip(skip).take(max_list) {
+        let sel = i == state.selected;
+        let style = if sel {
+            Style::default()
+                .fg(theme.sidebar_selected_fg)
+                .bg(theme.sidebar_selected_bg)
+                .add_modifier(Modifier::BOLD)
+        } else {
+            Style::default()
+        };
+        lines.push(Line::from(Span::styled(
+            format!(
+                "{} {}",
+                if sel { ">" } else { " " },
+                app.bookmark_label(*idx)
+            ),
+            style,
+        )));
+    }
+    let para = Paragraph::new(lines).block(block);
+    f.render_widget(Clear, popup_area);
+    f.render_widget(para, popup_area);
+}
+
+// Template names with the first line of each body, dimmed.
+fn draw_template_picker(
+    f: &mut Frame,
+    theme: &Theme,
+    area: Rect,
+    state: &crate::app::templates::TemplatePickerState,
+) {
+    let Some(popup_area) = popup_rect(area, 70, 50, (40, 8)) else {
+        let name = state.current().map(|t| t.name.clone());
+        let text = picker_inline_text("template", &state.buffer, name.as_ref());
+        return draw_inline_prompt(f, theme, area, &text, None);
+    };
+    let block = Block::default()
+        .title(Span::styled(
+            TITLE_TEMPLATES,
+            Style::default()
+                .fg(theme.popup_title)
+                .add_modifier(Modifier::BOLD),
+        ))
+        .borders(Borders::ALL);
+    let mut lines: Vec<Line> = Vec::new();
+    lines.push(Line::from(format!(">> {}", state.buffer)));
+    let max_list = popup_area.height.saturating_sub(3) as usize;
+    let skip = state.selected.saturating_sub(max_list.saturating_sub(1));
+    for (i, idx) in state.filtered.iter().enumerate().skip(skip).take(max_list) {
+        let t = &state.all[*idx];
+        let sel = i == state.selected;
+        let style = if sel {
+            Style::default()
+                .fg(theme.sidebar_selected_fg)
+                .bg(theme.sidebar_selected_bg)
+                .add_modifier(Modifier::BOLD)
+        } else {
+            Style::default()
+        };
+        let preview = t.body.lines().next().unwrap_or("");
+        lines.push(Line::from(vec![
+            Span::styled(format!("{} {}", if sel { ">" } else { " " }, t.name), style),
+            Span::styled(format!("  {}", preview), Style::default().fg(theme.muted)),
+        ]));
+    }
+    let para = Paragraph::new(lines).block(block);
+    f.render_widget(Clear, popup_area);
+    f.render_widget(para, popup_area);
+}
+
+// Profile names; the active one is marked.
+fn draw_profile_picker(
+    f: &mut Frame,
+    theme: &Theme,
+    area: Rect,
+    state: &crate::app::profiles::ProfilePickerState,
+) {
+    let Some(popup_area) = popup_rect(area, 50, 40, (30, 6)) else {
+        let text = picker_inline_text("profile", &state.buffer, state.current());
+        return draw_inline_prompt(f, theme, area, &text, None);
+    };
+    let block = Block::default()
+        .title(Span::styled(
+            TITLE_PROFILES,
+            Style::default()
+                .fg(theme.popup_title)
+                .add_modifier(Modifier::BOLD),
+        ))
+        .borders(Borders::ALL);
+    let active = providers::openai::config::active_profile()
+        .unwrap_or_else(|| crate::app::profiles::DEFAULT_PROFILE.to_string());
+    let mut lines: Vec<Line> = Vec::new();
+    lines.push(Line::from(format!(">> {}", state.buffer)));
+    let max_list = popup_area.height.saturating_sub(3) as usize;
+    let skip = state.selected.saturating_sub(max_list.saturating_sub(1));
+    for (i, idx) in state.filtered.iter().enumerate().skip(skip).take(max_list) {
+        let name = &state.all[*idx];
+        let sel = i == state.selected;
+        let style = if sel {
+            Style::default()
+                .fg(theme.sidebar_selected_fg)
+                .bg(theme.sidebar_selected_bg)
+                .add_modifier(Modifier::BOLD)
+        } else {
+            Style::default()
+        };
+        let mut spans = vec![Span::styled(
+            format!("{} {}", if sel { ">" } else { " " }, name),
+            style,
+        )];
+        if *name == active {
+            spans.push(Span::styled("  (active)", Style::default().fg(theme.muted)));
+        }
+        lines.push(Line::from(spans));
+    }
+    let para = Paragraph::new(lines).block(block);
+    f.render_widget(Clear, popup_area);
+    f.render_widget(para, popup_area);
+}
+
+fn draw_conflict(f: &mut Frame, area: Rect, state: &crate::app::ConflictState, app: &App) {
+    let theme = app.theme;
+    // Previews of messages "take theirs" would drop
+    const LOST_PREVIEW: usize = 5;
+    let Some(popup_area) = popup_rect(area, 70, 50, (44, 10)) else {
+        let choice = CONFLICT_CHOICES
+            .get(state.selected)
+            .map(|(l, _)| l.to_string());
+        let text = picker_inline_text("session changed on disk", "", choice.as_ref());
+        return draw_inline_prompt(f, &theme, area, &text, None);
+    };
+    let block = Block::default()
+        .title(Span::styled(
+            TITLE_CONFLICT,
+            Style::default()
+                .fg(theme.popup_title)
+                .add_modifier(Modifier::BOLD),
+        ))
+        .title_bottom(Line::from(" Enter choose  Esc later ").right_aligned())
+        .borders(Borders::ALL);
+    let inner_w = popup_area.width.saturating_sub(4) as usize;
+    let mut lines = vec![
+        Line::from(format!(
+            "'{}' was modified outside this window.",
+            state.session
+        )),
+        Line::from(format!(
+            "Disk: {} messages ({} not here)   Here: {} messages ({} not on disk)",
+            state.disk.len(),
+            state.theirs_only,
+            app.messages.len(),
+            state.mine_only.len()
+        )),
+        Line::from(""),
+    ];
+    for (i, (label, desc)) in CONFLICT_CHOICES.iter().enumerate() {
+        let sel = i == state.selected;
+        let style = if sel {
+            Style::default()
+                .fg(theme.sidebar_selected_fg)
+                .bg(theme.sidebar_selected_bg)
+                .add_modifier(Modifier::BOLD)
+        } else {
+            Style::default()
+        };
+        lines.push(Line::from(vec![
+            Span::styled(
+                format!("{} {:<16}", if sel { ">" } else { " " }, label),
+                style,
+            ),
+            Span::styled(format!(" {}", desc), Style::default().fg(theme.muted)),
+        ]));
+    }
+    if !state.mine_only.is_empty() {
+        lines.push(Line::from(""));
+        lines.push(Line::from(Span::styled(
+            "Take theirs would drop:",
+            Style::default().fg(Color::Red),
+        )));
+        for m in state.mine_only.iter().take(LOST_PREVIEW) {
+            let prefix = match m.role {
+                Role::User => PREFIX_USER,
+                Role::Assistant => PREFIX_ASSISTANT,
+                Role::System => PREFIX_SYSTEM,
+            };
+            let flat = m.content.split_whitespace().collect::<Vec<_>>().join(" ");
+            let snippet: String = flat.chars().take(inner_w.saturating_sub(4)).collect();
+            lines.push(Line::from(format!("  {}{}", prefix, snippet)));
+        }
+        if state.mine_only.len() > LOST_PREVIEW {
+            lines.push(Line::from(format!(
+                "  ... and {} more",
+                state.mine_only.len() - LOST_PREVIEW
+            )));
+        }
+    }
+    let para = Paragraph::new(lines).block(block);
+    f.render_widget(Clear, popup_area);
+    f.render_widget(para, popup_area);
+}
+
+// Hits grouped by session: a header line per session, then one line per message.
+fn draw_global_search(
+    f: &mut Frame,
+    theme: &Theme,
+    area: Rect,
+    state: &crate::app::GlobalSearchState,
+) {
+    let Some(popup_area) = popup_rect(area, 80, 60, (40, 8)) else {
+        let hit = state
+            .hits
+            .get(state.selected)
+            .map(|h| format!("{} #{}", h.session, h.msg_idx + 1));
+        let text = picker_inline_text(&state.query, "", hit.as_ref());
+        return draw_inline_prompt(f, theme, area, &text, None);
+    };
+    let block = Block::default()
+        .title(Span::styled(
+            format!(
+                "{}\"{}\" ({}) ",
+                TITLE_SEARCH_ALL,
+                state.query,
+                state.hits.len()
+            ),
+            Style::default()
+                .fg(theme.popup_title)
+                .add_modifier(Modifier::BOLD),
+        ))
+        .borders(Borders::ALL);
+    let mut lines: Vec<Line> = Vec::new();
+    let mut selected_line = 0usize;
+    let mut last_session: Option<&str> = None;
+    for (i, hit) in state.hits.iter().enumerate() {
+        if last_session != Some(hit.session.as_str()) {
+            lines.push(Line::from(Span::styled(
+                hit.session.clone(),
+                Style::default()
+                    .fg(theme.border_focus)
+                    .add_modifier(Modifier::BOLD),
+            )));
+            last_session = Some(hit.session.as_str());
+        }
+        let sel = i == state.selected;
+        if sel {
+            selected_line = lines.len();
+        }
+        let style = if sel {
+            Style::default()
+                .fg(theme.sidebar_selected_fg)
+                .bg(theme.sidebar_selected_bg)
+                .add_modifier(Modifier::BOLD)
+        } else {
+            Style::default()
+        };
+        lines.push(Line::from(Span::styled(
+            format!(
+                "{}  #{} {}",
+                if sel { ">" } else { " " },
+                hit.msg_idx + 1,
+                hit.snippet
+            ),
+            style,
+        )));
+    }
+    let searching = state.searched < state.total;
+    if lines.is_empty() && !searching {
+        lines.push(Line::from(Span::styled(
+            "No matches in saved sessions.",
+            Style::default().fg(theme.muted),
+        )));
+    }
+    // Progress while scanning, then how many sessions could not be read
+    let mut footer = Vec::new();
+    if searching {
+        footer.push(format!(
+            "searching {}/{} sessions...",
+            state.searched, state.total
+        ));
+    }
+    if state.failed > 0 {
+        footer.push(format!(
+            "{} session{} skipped (unreadable)",
+            state.failed,
+            if state.failed == 1 { "" } else { "s" }
+        ));
+    }
+    let max_list = (popup_area.height.saturating_sub(2) as usize)
+        .saturating_sub(usize::from(!footer.is_empty()))
+        .max(1);
+    let skip = selected_line.saturating_sub(max_list.saturating_sub(1));
+    let mut lines: Vec<Line> = lines.into_iter().skip(skip).take(max_list).collect();
+    if !footer.is_empty() {
+        lines.push(Line::from(Span::styled(
+            footer.join(" · "),
+            Style::default().fg(theme.muted),
+        )));
+    }
+    let para = Paragraph::new(lines).block(block);
+    f.render_widget(Clear, popup_area);
+    f.render_widget(para, popup_area);
 }
 
 // no toast: usage info is rendered persistently in the status line above input
 
-fn draw_search(f: &mut Frame, area: Rect, state: &crate::app::SearchInput) {
-    use unicode_width::UnicodeWidthStr;
-    let popup_area = centered_rect(60, 20, area);
+fn draw_search(f: &mut Frame, area: Rect, state: &crate::app::SearchInput, app: &App) {
+    let theme = app.theme;
+    let Some(popup_area) = popup_rect(area, 60, 20, (30, 4)) else {
+        let text = format!("find>> {}", state.buffer);
+        let col = 7 + buffer_width(&state.buffer, state.cursor);
+        return draw_inline_prompt(f, &theme, area, &text, Some(col));
+    };
     let block = Block::default()
         .title(Span::styled(
-            TITLE_SEARCH,
+            search_title(
+                app.search_smart_case,
+                app.search_whole_word,
+                state.all_sessions,
+            ),
             Style::default()
-                .fg(Color::Yellow)
+                .fg(theme.popup_title)
                 .add_modifier(Modifier::BOLD),
         ))
         .borders(Borders::ALL);
     let lines = vec![
-        Line::from("Enter keywords, Enter to confirm, Esc to cancel:"),
+        Line::from("Enter keywords, Enter to confirm, Esc to cancel (Alt+C case, Alt+W word):"),
         Line::from(format!(">> {}", state.buffer)),
     ];
     let para = Paragraph::new(lines).block(block);
     f.render_widget(Clear, popup_area);
     f.render_widget(para, popup_area);
-    let cursor_x = popup_area.x
-        + 3
-        + UnicodeWidthStr::width(
-            state
-                .buffer
-                .graphemes(true)
-                .take(state.cursor)
-                .collect::<String>()
-                .as_str(),
-        ) as u16;
-    let cursor_y = popup_area.y + 2;
-    f.set_cursor_position(Position::new(cursor_x, cursor_y));
-}
-
-fn draw_rename(f: &mut Frame, area: Rect, state: &crate::app::RenameState) {
-    use unicode_width::UnicodeWidthStr;
-    let popup_area = centered_rect(60, 30, area);
+    let cursor_x = popup_area
+        .x
+        .saturating_add(4)
+        .saturating_add(buffer_width(&state.buffer, state.cursor));
+    set_cursor_in_block(f, popup_area, cursor_x, popup_area.y + 2);
+}
+
+fn draw_rename(f: &mut Frame, theme: &Theme, area: Rect, state: &crate::app::RenameState) {
+    let Some(popup_area) = popup_rect(area, 60, 30, (30, 4)) else {
+        let text = format!("rename>> {}", state.buffer);
+        let col = 9 + buffer_width(&state.buffer, state.cursor);
+        return draw_inline_prompt(f, theme, area, &text, Some(col));
+    };
     let block = Block::default()
         .title(Span::styled(
             TITLE_RENAME,
             Style::default()
-                .fg(Color::Yellow)
+                .fg(theme.popup_title)
                 .add_modifier(Modifier::BOLD),
         ))
         .borders(Borders::ALL);
@@ -738,60 +1532,148 @@ fn draw_rename(f: &mut Frame, area: Rect, state: &crate::app::RenameState) {
     let para = Paragraph::new(lines).block(block);
     f.render_widget(Clear, popup_area);
     f.render_widget(para, popup_area);
-    let cursor_x = popup_area.x
-        + 3
-        + UnicodeWidthStr::width(
-            state
-                .buffer
-                .graphemes(true)
-                .take(state.cursor)
-                .collect::<String>()
-                .as_str(),
-        ) as u16;
-    let cursor_y = popup_area.y + 2;
-    f.set_cursor_position(Position::new(cursor_x, cursor_y));
+    let cursor_x = popup_area
+        .x
+        .saturating_add(4)
+        .saturating_add(buffer_width(&state.buffer, state.cursor));
+    set_cursor_in_block(f, popup_area, cursor_x, popup_area.y + 2);
 }
 
 fn draw_confirm(f: &mut Frame, area: Rect, confirm: &crate::app::ConfirmState, app: &App) {
-    let popup_area = centered_rect(60, 30, area);
+    let theme = app.theme;
+    let popup_area = popup_rect(area, 60, 30, (30, 5));
     let block = Block::default()
         .title(Span::styled(
             TITLE_CONFIRM,
             Style::default()
-                .fg(Color::Yellow)
+                .fg(theme.popup_title)
                 .add_modifier(Modifier::BOLD),
         ))
         .borders(Borders::ALL);
-    let mut lines = Vec::new();
-    match confirm.action {
+    let message = match confirm.action {
         crate::app::ConfirmAction::DeleteSession(idx) => {
             let name = app.sessions.get(idx).cloned().unwrap_or_default();
-            lines.push(Line::from(confirm_delete_session_message(&name)));
+            confirm_delete_session_message(&name)
         }
-    }
-    let para = Paragraph::new(lines).block(block);
+        crate::app::ConfirmAction::LargePaste(ref text) => {
+            confirm_large_paste_message(text.len(), text.lines().count())
+        }
+    };
+    let Some(popup_area) = popup_area else {
+        return draw_inline_prompt(f, &theme, area, &message, None);
+    };
+    let para = Paragraph::new(Line::from(message))
+        .block(block)
+        .wrap(Wrap { trim: false });
     f.render_widget(Clear, popup_area);
     f.render_widget(para, popup_area);
 }
 
-fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
-    let vert = Layout::default()
-        .direction(Direction::Vertical)
-        .constraints([
-            Constraint::Percentage((100 - percent_y) / 2),
-            Constraint::Percentage(percent_y),
-            Constraint::Percentage((100 - percent_y) / 2),
-        ])
-        .split(r);
-    let horiz = Layout::default()
-        .direction(Direction::Horizontal)
-        .constraints([
-            Constraint::Percentage((100 - percent_x) / 2),
-            Constraint::Percentage(percent_x),
-            Constraint::Percentage((100 - percent_x) / 2),
-        ])
-        .split(vert[1]);
-    horiz[1]
+// Smallest area that still fits a bordered popup; below it popups collapse
+// to a one-line prompt.
+const POPUP_FLOOR: (u16, u16) = (12, 3);
+
+// `percent` of `r`, grown to at least `min` (w, h) and centered. Falls back to
+// all of `r` when `r` is smaller than `min`, and to None when not even a
+// bordered box fits.
+fn popup_rect(r: Rect, percent_x: u16, percent_y: u16, min: (u16, u16)) -> Option<Rect> {
+    if r.width < POPUP_FLOOR.0 || r.height < POPUP_FLOOR.1 {
+        return None;
+    }
+    let w = ((r.width as u32 * percent_x as u32 / 100) as u16)
+        .max(min.0)
+        .min(r.width);
+    let h = ((r.height as u32 * percent_y as u32 / 100) as u16)
+        .max(min.1)
+        .min(r.height);
+    Some(Rect {
+        x: r.x + (r.width - w) / 2,
+        y: r.y + (r.height - h) / 2,
+        width: w,
+        height: h,
+    })
+}
+
+// One-line stand-in for a popup, drawn over the bottom (status) row.
+fn draw_inline_prompt(
+    f: &mut Frame,
+    theme: &Theme,
+    area: Rect,
+    text: &str,
+    cursor_col: Option<u16>,
+) {
+    if area.height == 0 || area.width == 0 {
+        return;
+    }
+    let row = Rect {
+        x: area.x,
+        y: area.y + area.height - 1,
+        width: area.width,
+        height: 1,
+    };
+    let line = Line::from(Span::styled(
+        text.to_string(),
+        Style::default()
+            .fg(theme.popup_title)
+            .add_modifier(Modifier::BOLD),
+    ));
+    f.render_widget(Clear, row);
+    f.render_widget(Paragraph::new(line), row);
+    if let Some(col) = cursor_col {
+        set_cursor_within(f, row, row.x.saturating_add(col), row.y);
+    }
+}
+
+// Over the row just above the input
+fn draw_history_search(
+    f: &mut Frame,
+    chat_area: Rect,
+    state: &crate::app::history::HistorySearch,
+    app: &App,
+) {
+    let text = history_search_prompt(&state.buffer, state.current(&app.history));
+    // Cursor sits in the quoted query
+    let quote = text.find('`').map_or(0, |i| i + 1) as u16;
+    let col = quote + buffer_width(&state.buffer, state.cursor);
+    draw_inline_prompt(f, &app.theme, chat_area, &text, Some(col));
+}
+
+// "label: buffer -> selected" for pickers collapsed to one line.
+fn picker_inline_text(label: &str, buffer: &str, selected: Option<&String>) -> String {
+    let mut out = label.to_string();
+    if !buffer.is_empty() {
+        out.push_str(&format!(" [{}]", buffer));
+    }
+    out.push_str(&format!(
+        ": {} (Enter/Esc)",
+        selected.map(String::as_str).unwrap_or("-")
+    ));
+    out
+}
+
+// Display width of the first `cursor` graphemes of a prompt buffer.
+fn buffer_width(buffer: &str, cursor: usize) -> u16 {
+    let w: usize = buffer
+        .graphemes(true)
+        .take(cursor)
+        .map(UnicodeWidthStr::width)
+        .sum();
+    w.min(u16::MAX as usize) as u16
+}
+
+// Cursor inside a bordered block, never on or past its border.
+fn set_cursor_in_block(f: &mut Frame, popup: Rect, x: u16, y: u16) {
+    let inner = Block::default().borders(Borders::ALL).inner(popup);
+    set_cursor_within(f, inner, x, y);
+}
+
+fn set_cursor_within(f: &mut Frame, bounds: Rect, x: u16, y: u16) {
+    if bounds.width == 0 || bounds.height == 0 {
+        return;
+    }
+    let x = x.clamp(bounds.x, bounds.right() - 1);
+    let y = y.clamp(bounds.y, bounds.bottom() - 1);
+    f.set_cursor_position(Position::new(x, y));
 }
 
 fn measure_total_lines(s: &str, width: u16) -> usize {
@@ -897,3 +1779,6 @@ mod tests {
     }
 }
 */
+
+#[cfg(test)]
+mod tests;
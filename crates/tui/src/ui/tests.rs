@@ -0,0 +1,500 @@
+use crate::app::{
+    App, BookmarkPickerState, ChangelogState, ConfirmAction, ConfirmState, ConflictState,
+    GlobalSearchState, Message, ModelPickerState, RenameState, SlashPickerState, WirePickerState,
+};
+use crate::persist::GlobalHit;
+use crate::test_support::{
+    app_with, assert_snapshot, buffer_text, render, sample_messages, TempRoot,
+};
+
+fn snapshot(name: &str, app: &mut App, width: u16, height: u16) {
+    let text = buffer_text(&render(app, width, height));
+    assert_snapshot(name, &text);
+}
+
+#[test]
+fn chat_at_several_sizes() {
+    let _root = TempRoot::new();
+    for (w, h) in [(100, 30), (60, 20), (40, 14)] {
+        let mut app = app_with(sample_messages());
+        snapshot(&format!("chat_{}x{}", w, h), &mut app, w, h);
+    }
+}
+
+#[test]
+fn chat_with_sidebar_and_timestamps() {
+    let _root = TempRoot::new();
+    let mut app = app_with(
+        sample_messages()
+            .into_iter()
+            .map(|m| m.with_timestamp(1_700_000_000))
+            .collect(),
+    );
+    app.sessions = vec!["test".into(), "notes 笔记".into()];
+    app.session_auto_named = vec![false, false];
+    app.show_sidebar = true;
+    app.show_timestamps = true;
+    snapshot("chat_sidebar_timestamps", &mut app, 120, 24);
+}
+
+#[test]
+fn chat_with_active_search() {
+    let _root = TempRoot::new();
+    let mut app = app_with(sample_messages());
+    app.open_search();
+    app.search_input.as_mut().unwrap().buffer = "文字".into();
+    app.commit_search();
+    let buf = render(&mut app, 80, 24);
+    assert_snapshot("chat_search", &buffer_text(&buf));
+    assert_eq!(app.search_hits.len(), 2);
+    // Only the current hit gets the highlight colors
+    let bg = app.theme.highlight_bg;
+    let highlighted = (0..buf.area.height)
+        .flat_map(|y| (0..buf.area.width).map(move |x| (x, y)))
+        .filter(|&p| buf[p].bg == bg)
+        .map(|p| buf[p].symbol().to_string())
+        .collect::<String>();
+    assert_eq!(highlighted, "文字");
+}
+
+#[test]
+fn chat_with_collapsed_message() {
+    let _root = TempRoot::new();
+    let long = (1..=60)
+        .map(|i| format!("line {}", i))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let mut app = app_with(vec![Message::user("dump"), Message::assistant(long)]);
+    snapshot("chat_collapsed", &mut app, 60, 24);
+    assert!(app.collapsed[1]);
+}
+
+#[test]
+fn status_titles_truncate_on_narrow_input() {
+    let _root = TempRoot::new();
+    for w in [100, 44] {
+        let mut app = app_with(sample_messages());
+        app.stream_retry = Some((1, 2, 5));
+        app.rate_info = Some(fast_core::llm::RateInfo {
+            remaining_requests: Some(12),
+            remaining_tokens: Some(34_000),
+        });
+        app.update_notice = Some(crate::update::ReleaseInfo {
+            version: "9.9.9".into(),
+            notes: String::new(),
+        });
+        snapshot(&format!("status_{}", w), &mut app, w, 12);
+    }
+}
+
+// Each popup over the same conversation at 80x24
+fn popup(name: &str, open: impl FnOnce(&mut App)) {
+    let _root = TempRoot::new();
+    let mut app = app_with(sample_messages());
+    open(&mut app);
+    snapshot(&format!("popup_{}", name), &mut app, 80, 24);
+}
+
+fn open_help(a: &mut App) {
+    a.show_help = true;
+}
+
+fn open_rename(a: &mut App) {
+    a.rename = Some(RenameState {
+        index: 0,
+        buffer: "新しい名前".into(),
+        cursor: 2,
+    })
+}
+
+fn open_confirm(a: &mut App) {
+    a.confirm = Some(ConfirmState {
+        action: ConfirmAction::DeleteSession(0),
+    })
+}
+
+fn open_search(a: &mut App) {
+    a.open_search();
+    a.search_input.as_mut().unwrap().buffer = "emoji".into();
+}
+
+fn open_history_search(a: &mut App) {
+    a.history = vec!["first prompt".into(), "second prompt".into()];
+    a.open_history_search();
+}
+
+fn open_palette(a: &mut App) {
+    a.open_palette();
+}
+
+fn open_wire_picker(a: &mut App) {
+    a.wire_picker = Some(WirePickerState {
+        buffer: String::new(),
+        cursor: 0,
+        filtered: vec!["responses".into(), "chat".into(), "auto".into()],
+        selected: 0,
+    })
+}
+
+fn open_slash_picker(a: &mut App) {
+    a.slash_picker = Some(SlashPickerState {
+        buffer: "ex".into(),
+        cursor: 2,
+        filtered: vec![("export".into(), "write the session to a file".into())],
+        selected: 0,
+    })
+}
+
+fn open_bookmark_picker(a: &mut App) {
+    a.messages[1].bookmarked = true;
+    a.bookmark_picker = Some(BookmarkPickerState {
+        items: vec![1],
+        selected: 0,
+    })
+}
+
+fn open_template_picker(a: &mut App) {
+    a.template_picker = Some(crate::app::templates::TemplatePickerState {
+        buffer: String::new(),
+        cursor: 0,
+        all: vec![crate::templates::Template {
+            name: "review".into(),
+            body: "Review this diff:".into(),
+        }],
+        filtered: vec![0],
+        selected: 0,
+    })
+}
+
+fn open_profile_picker(a: &mut App) {
+    a.profile_picker = Some(crate::app::profiles::ProfilePickerState {
+        buffer: String::new(),
+        cursor: 0,
+        all: vec!["default".into(), "work".into()],
+        filtered: vec![0, 1],
+        selected: 1,
+    })
+}
+
+fn open_global_search(a: &mut App) {
+    a.global_search = Some(GlobalSearchState {
+        query: "charset".into(),
+        hits: vec![GlobalHit {
+            session: "test".into(),
+            msg_idx: 1,
+            snippet: "...decoded with the wrong charset...".into(),
+        }],
+        selected: 0,
+        searched: 1,
+        total: 1,
+        failed: 0,
+    })
+}
+
+fn open_conflict(a: &mut App) {
+    a.conflict = Some(ConflictState {
+        session: "test".into(),
+        disk: vec![Message::user("edited elsewhere")],
+        mine_only: vec![Message::user("Thanks!")],
+        theirs_only: 1,
+        selected: 0,
+    })
+}
+
+// Needs a TempRoot: the log is read from its log dir
+fn open_log_view(a: &mut App) {
+    let dir = crate::persist::log_dir();
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(
+        dir.join(crate::persist::LOG_FILE),
+        "INFO tui: started\nWARN tui: config: unknown key\n",
+    )
+    .unwrap();
+    a.open_log_view();
+}
+
+#[test]
+fn popup_help() {
+    popup("help", open_help);
+}
+
+#[test]
+fn popup_rename() {
+    popup("rename", open_rename);
+}
+
+#[test]
+fn popup_confirm() {
+    popup("confirm", open_confirm);
+}
+
+#[test]
+fn popup_search() {
+    popup("search", open_search);
+}
+
+#[test]
+fn popup_history_search() {
+    popup("history_search", open_history_search);
+}
+
+#[test]
+fn popup_palette() {
+    popup("palette", open_palette);
+}
+
+#[test]
+fn popup_model_picker() {
+    popup("model_picker", |a| {
+        a.model_picker = Some(ModelPickerState {
+            buffer: String::new(),
+            cursor: 0,
+            filtered: vec!["gpt-5".into(), "gpt-5-mini".into()],
+            selected: 1,
+        })
+    });
+}
+
+#[test]
+fn popup_wire_picker() {
+    popup("wire_picker", open_wire_picker);
+}
+
+#[test]
+fn popup_slash_picker() {
+    popup("slash_picker", open_slash_picker);
+}
+
+#[test]
+fn popup_bookmark_picker() {
+    popup("bookmark_picker", open_bookmark_picker);
+}
+
+#[test]
+fn popup_template_picker() {
+    popup("template_picker", open_template_picker);
+}
+
+#[test]
+fn popup_profile_picker() {
+    popup("profile_picker", open_profile_picker);
+}
+
+#[test]
+fn popup_global_search() {
+    popup("global_search", open_global_search);
+}
+
+#[test]
+fn popup_conflict() {
+    popup("conflict", open_conflict);
+}
+
+#[test]
+fn popup_changelog() {
+    popup("changelog", |a| {
+        a.update_notice = Some(crate::update::ReleaseInfo {
+            version: "9.9.9".into(),
+            notes: "- Faster startup\n- 修复了一个错误".into(),
+        });
+        a.changelog = Some(ChangelogState { scroll: 0 });
+    });
+}
+
+#[test]
+fn popup_log_view() {
+    popup("log_view", open_log_view);
+}
+
+// Small terminals: at 20x6 and 11x8 popups fill the screen or collapse to a
+// one-line prompt; 40x10 and 40x2 test the clamped width and height.
+fn tiny_popup(name: &str, open: impl Fn(&mut App)) {
+    let _root = TempRoot::new();
+    for (w, h) in [(11, 8), (40, 2), (20, 6), (40, 10)] {
+        let mut app = app_with(sample_messages());
+        open(&mut app);
+        snapshot(&format!("tiny_{}_{}x{}", name, w, h), &mut app, w, h);
+    }
+}
+
+#[test]
+fn tiny_popup_confirm() {
+    tiny_popup("confirm", open_confirm);
+}
+
+#[test]
+fn tiny_popup_rename() {
+    tiny_popup("rename", open_rename);
+}
+
+#[test]
+fn tiny_popup_model_picker() {
+    tiny_popup("model_picker", |a| {
+        a.model_picker = Some(ModelPickerState {
+            buffer: "gpt".into(),
+            cursor: 3,
+            filtered: vec!["gpt-5".into(), "gpt-5-mini".into()],
+            selected: 1,
+        })
+    });
+}
+
+#[test]
+fn tiny_popup_changelog() {
+    tiny_popup("changelog", |a| {
+        a.update_notice = Some(crate::update::ReleaseInfo {
+            version: "9.9.9".into(),
+            notes: "- Faster startup".into(),
+        });
+        a.changelog = Some(ChangelogState { scroll: 0 });
+    });
+}
+
+#[test]
+fn tiny_popup_help() {
+    tiny_popup("help", open_help);
+}
+
+#[test]
+fn tiny_popup_search() {
+    tiny_popup("search", open_search);
+}
+
+#[test]
+fn tiny_popup_history_search() {
+    tiny_popup("history_search", open_history_search);
+}
+
+#[test]
+fn tiny_popup_palette() {
+    tiny_popup("palette", open_palette);
+}
+
+#[test]
+fn tiny_popup_wire_picker() {
+    tiny_popup("wire_picker", open_wire_picker);
+}
+
+#[test]
+fn tiny_popup_slash_picker() {
+    tiny_popup("slash_picker", open_slash_picker);
+}
+
+#[test]
+fn tiny_popup_bookmark_picker() {
+    tiny_popup("bookmark_picker", open_bookmark_picker);
+}
+
+#[test]
+fn tiny_popup_template_picker() {
+    tiny_popup("template_picker", open_template_picker);
+}
+
+#[test]
+fn tiny_popup_profile_picker() {
+    tiny_popup("profile_picker", open_profile_picker);
+}
+
+#[test]
+fn tiny_popup_global_search() {
+    tiny_popup("global_search", open_global_search);
+}
+
+#[test]
+fn tiny_popup_conflict() {
+    tiny_popup("conflict", open_conflict);
+}
+
+#[test]
+fn tiny_popup_log_view() {
+    tiny_popup("log_view", open_log_view);
+}
+
+#[test]
+fn cached_reply_is_tagged_in_the_header() {
+    let _root = TempRoot::new();
+    let mut reply = Message::assistant("from the cache");
+    reply.model = Some("gpt-5".into());
+    reply.cached = true;
+    let mut app = app_with(vec![Message::user("again"), reply]);
+    let text = buffer_text(&render(&mut app, 60, 12));
+    assert!(text.contains("[gpt-5 · cached] from the cache"), "{}", text);
+    assert!(!app.messages[1].content.contains("[cached]"));
+    let json = serde_json::to_string(&app.messages[1]).unwrap();
+    assert!(json.contains("\"cached\":true"));
+    let plain: Message = serde_json::from_str(r#"{"role":"User","content":"x"}"#).unwrap();
+    assert!(!plain.cached);
+}
+
+#[test]
+fn compact_layout_on_narrow_terminals() {
+    let _root = TempRoot::new();
+    for (w, h) in [(60, 20), (45, 15)] {
+        let mut app = app_with(sample_messages());
+        app.show_sidebar = true;
+        snapshot(&format!("compact_{}x{}", w, h), &mut app, w, h);
+        assert!(app.compact);
+        assert!(
+            app.sidebar_area.is_none(),
+            "sidebar is a drawer, not a column"
+        );
+    }
+}
+
+#[test]
+fn compact_sidebar_opens_as_a_drawer() {
+    let _root = TempRoot::new();
+    let mut app = app_with(sample_messages());
+    app.show_sidebar = true;
+    app.focus = crate::app::Focus::Sidebar;
+    snapshot("compact_sidebar_drawer", &mut app, 45, 15);
+    let drawer = app.sidebar_area.expect("drawer drawn");
+    assert_eq!((drawer.x, drawer.height), (0, 15));
+    assert!(drawer.width < 45);
+}
+
+#[test]
+fn sideways_scroll_pads_a_cut_wide_char() {
+    use ratatui::text::Span;
+    let spans = vec![Span::raw("a漢字"), Span::raw("b")];
+    let text = |cols| {
+        super::skip_columns(spans.clone(), cols)
+            .iter()
+            .map(|s| s.content.to_string())
+            .collect::<String>()
+    };
+    assert_eq!(text(1), "漢字b");
+    // Column 2 is the right half of 漢
+    assert_eq!(text(2), " 字b");
+    assert_eq!(text(4), " b");
+    assert_eq!(text(5), "b");
+}
+
+#[test]
+fn scrollbar_tracks_positions_past_u16_max() {
+    let _root = TempRoot::new();
+    let text = vec!["line"; 100_000].join("\n");
+    let mut app = app_with(vec![Message::assistant(text)]);
+    app.collapse_threshold_lines = usize::MAX;
+    // Rows of the scrollbar thumb in the chat pane's right column
+    let thumb = |app: &mut App| {
+        let buf = render(app, 40, 16);
+        let rows: Vec<u16> = (0..16)
+            .filter(|&y| (30..40).any(|x| buf[(x, y)].symbol() == "█"))
+            .collect();
+        assert!(!rows.is_empty(), "no thumb:\n{}", buffer_text(&buf));
+        rows
+    };
+    let bottom = thumb(&mut app);
+    app.scroll_to_top();
+    let top = thumb(&mut app);
+    app.chat_scroll /= 2;
+    let middle = thumb(&mut app);
+    assert!(
+        top[0] < middle[0] && middle[0] < bottom[0],
+        "{:?} {:?} {:?}",
+        top,
+        middle,
+        bottom
+    );
+}
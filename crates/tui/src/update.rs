@@ -0,0 +1,208 @@
+use std::{cmp::Ordering, sync::mpsc, time::Duration};
+
+use tracing::{debug, info};
+
+// Minimum gap between release checks
+pub const CHECK_INTERVAL_SECS: i64 = 24 * 60 * 60;
+
+pub const CURRENT_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+#[derive(Clone, Debug)]
+pub struct ReleaseInfo {
+    pub version: String,
+    pub notes: String,
+}
+
+// Whether a check is due given the last check time (unix seconds).
+pub fn check_due(last_checked: Option<i64>, now: i64) -> bool {
+    match last_checked {
+        Some(t) => now.saturating_sub(t) >= CHECK_INTERVAL_SECS || now < t,
+        None => true,
+    }
+}
+
+// Fetch the latest release on a background thread. A successful fetch sends
+// `Some` for a newer release and `None` otherwise; network and parse failures
+// are logged at debug level and close the channel without sending, so the
+// check is retried next start. Nothing is downloaded besides the release
+// metadata.
+pub fn spawn_check(url: String) -> mpsc::Receiver<Option<ReleaseInfo>> {
+    let (tx, rx) = mpsc::channel();
+    std::thread::spawn(move || {
+        let Ok(rt) = tokio::runtime::Runtime::new() else {
+            return;
+        };
+        rt.block_on(async move {
+            match fetch_latest(&url).await {
+                Ok(rel) if is_newer(&rel.version, CURRENT_VERSION) => {
+                    info!(target: "tui", "update available: {} (running {})", rel.version, CURRENT_VERSION);
+                    let _ = tx.send(Some(rel));
+                }
+                Ok(rel) => {
+                    debug!(target: "tui", "update check: latest {} is not newer", rel.version);
+                    let _ = tx.send(None);
+                }
+                Err(e) => debug!(target: "tui", "update check failed: {:#}", e),
+            }
+        });
+    });
+    rx
+}
+
+async fn fetch_latest(url: &str) -> anyhow::Result<ReleaseInfo> {
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(10))
+        .user_agent(concat!("fast-tui/", env!("CARGO_PKG_VERSION")))
+        .build()?;
+    let body = client
+        .get(url)
+        .header("Accept", "application/vnd.github+json")
+        .send()
+        .await?
+        .error_for_status()?
+        .text()
+        .await?;
+    let v: serde_json::Value = serde_json::from_str(&body)?;
+    let tag = v["tag_name"]
+        .as_str()
+        .ok_or_else(|| anyhow::anyhow!("no tag_name in release"))?;
+    Ok(ReleaseInfo {
+        version: tag.trim_start_matches('v').to_string(),
+        notes: v["body"].as_str().unwrap_or("").to_string(),
+    })
+}
+
+// Semver precedence: `latest` > `current`. Unparsable versions are never newer.
+pub fn is_newer(latest: &str, current: &str) -> bool {
+    match (Version::parse(latest), Version::parse(current)) {
+        (Some(l), Some(c)) => l.cmp(&c) == Ordering::Greater,
+        _ => false,
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct Version {
+    core: [u64; 3],
+    // Dot-separated pre-release identifiers; empty for a release
+    pre: Vec<String>,
+}
+
+impl Version {
+    fn parse(s: &str) -> Option<Self> {
+        let s = s.trim().trim_start_matches('v');
+        // Build metadata does not affect precedence
+        let s = s.split('+').next()?;
+        let (core, pre) = match s.split_once('-') {
+            Some((c, p)) => (c, p.split('.').map(str::to_string).collect()),
+            None => (s, Vec::new()),
+        };
+        let mut nums = core.split('.').map(|n| n.parse::<u64>().ok());
+        let major = nums.next()??;
+        let minor = nums.next().unwrap_or(Some(0))?;
+        let patch = nums.next().unwrap_or(Some(0))?;
+        if nums.next().is_some() {
+            return None;
+        }
+        Some(Version {
+            core: [major, minor, patch],
+            pre,
+        })
+    }
+}
+
+impl Ord for Version {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.core.cmp(&other.core).then_with(|| {
+            // A release ranks above any of its pre-releases
+            match (self.pre.is_empty(), other.pre.is_empty()) {
+                (true, true) => Ordering::Equal,
+                (true, false) => Ordering::Greater,
+                (false, true) => Ordering::Less,
+                (false, false) => cmp_pre(&self.pre, &other.pre),
+            }
+        })
+    }
+}
+
+impl PartialOrd for Version {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+// Numeric identifiers compare numerically and rank below alphanumeric ones;
+// a shorter list ranks lower when all shared identifiers are equal.
+fn cmp_pre(a: &[String], b: &[String]) -> Ordering {
+    for (x, y) in a.iter().zip(b.iter()) {
+        let ord = match (x.parse::<u64>(), y.parse::<u64>()) {
+            (Ok(m), Ok(n)) => m.cmp(&n),
+            (Ok(_), Err(_)) => Ordering::Less,
+            (Err(_), Ok(_)) => Ordering::Greater,
+            (Err(_), Err(_)) => x.cmp(y),
+        };
+        if ord != Ordering::Equal {
+            return ord;
+        }
+    }
+    a.len().cmp(&b.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn release_ranks_above_its_pre_releases() {
+        assert!(is_newer("1.2.0", "1.2.0-rc.1"));
+        assert!(!is_newer("1.2.0-rc.1", "1.2.0"));
+        assert!(is_newer("1.2.1-alpha", "1.2.0"));
+        assert!(!is_newer("1.2.0", "1.2.0"));
+    }
+
+    #[test]
+    fn semver_precedence_chain() {
+        // The ordering example from the semver spec
+        let chain = [
+            "1.0.0-alpha",
+            "1.0.0-alpha.1",
+            "1.0.0-alpha.beta",
+            "1.0.0-beta",
+            "1.0.0-beta.2",
+            "1.0.0-beta.11",
+            "1.0.0-rc.1",
+            "1.0.0",
+        ];
+        for pair in chain.windows(2) {
+            assert!(is_newer(pair[1], pair[0]), "{} > {}", pair[1], pair[0]);
+            assert!(!is_newer(pair[0], pair[1]), "{} < {}", pair[0], pair[1]);
+        }
+    }
+
+    #[test]
+    fn core_numbers_compare_numerically() {
+        assert!(is_newer("0.10.0", "0.9.9"));
+        assert!(is_newer("2.0.0", "1.99.99"));
+        assert!(is_newer("v1.3", "1.2.9"));
+        // Build metadata and a leading "v" do not count
+        assert!(!is_newer("v1.2.0+build.7", "1.2.0"));
+        assert_eq!(Version::parse("1"), Version::parse("1.0.0"));
+    }
+
+    #[test]
+    fn unparsable_versions_are_never_newer() {
+        for bad in ["", "latest", "1.2.x", "1.2.3.4", "-rc.1"] {
+            assert!(!is_newer(bad, "0.0.1"), "{:?}", bad);
+            assert!(!is_newer("9.9.9", bad), "{:?}", bad);
+        }
+    }
+
+    #[test]
+    fn check_is_due_once_a_day() {
+        let now = 1_000_000;
+        assert!(check_due(None, now));
+        assert!(!check_due(Some(now - CHECK_INTERVAL_SECS + 1), now));
+        assert!(check_due(Some(now - CHECK_INTERVAL_SECS), now));
+        // A clock set back is treated as due
+        assert!(check_due(Some(now + 60), now));
+    }
+}